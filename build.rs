@@ -0,0 +1,34 @@
+//! Embed build information for `SHOW VERSION`
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let sha = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok());
+    if let Some(sha) = sha {
+        println!("cargo:rustc-env=ARTA_GIT_SHA={}", sha.trim());
+    }
+
+    // Honour reproducible builds, otherwise use the current time
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs())
+        });
+    if let Some(timestamp) = timestamp {
+        println!("cargo:rustc-env=ARTA_BUILD_TIMESTAMP={}", timestamp);
+    }
+}
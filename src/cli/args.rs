@@ -1,7 +1,13 @@
 //! CLI argument parsing
 
-use clap::{Parser, Subcommand};
+use crate::cli::duration::{parse_duration, parse_interval};
+use crate::error::Result;
+use crate::output::format_util::DEFAULT_PRECISION;
+use crate::output::{DisplayZone, FormatterRegistry, OutputFormat};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "arta")]
@@ -25,6 +31,104 @@ pub struct Args {
     /// Verbose output
     #[arg(short, long, global = true)]
     pub verbose: bool,
+
+    /// With --dry-run, show which WHERE conditions each item matched
+    #[arg(long, global = true)]
+    pub explain_matches: bool,
+
+    /// Output format: human, json, props, csv, tsv or custom:<name> (overrides --json)
+    #[arg(long, global = true, value_name = "FORMAT")]
+    pub format: Option<String>,
+
+    /// Prefix output lines with an ISO-8601 timestamp (adds a `timestamp` field to JSON)
+    #[arg(long, global = true)]
+    pub timestamps: bool,
+
+    /// Render timestamps in utc, local or a named zone such as Europe/Berlin
+    /// (default: local for human output, utc for JSON)
+    #[arg(long, global = true, value_name = "ZONE", value_parser = DisplayZone::from_str)]
+    pub timezone: Option<DisplayZone>,
+
+    /// Emit JSON sizes and percentages as {"value": ..., "unit": ...} objects
+    #[arg(long, global = true)]
+    pub json_units: bool,
+
+    /// Fail on unknown fields in PROCESS, FILES, SERVICES and NETWORK WHERE
+    /// clauses instead of ignoring them (DELETE and KILL always do)
+    #[arg(long, global = true)]
+    pub strict_fields: bool,
+
+    /// List pseudo filesystems (tmpfs, proc, overlay, ...) in DISK queries
+    #[arg(long, global = true)]
+    pub all_mounts: bool,
+
+    /// Record every DELETE, KILL and WRITE as a JSON line in this file
+    /// (read back with SHOW AUDIT)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub audit_log: Option<PathBuf>,
+
+    /// Decimal places of percentages in human and properties output
+    /// (JSON keeps full precision)
+    #[arg(long, global = true, value_name = "N", default_value_t = DEFAULT_PRECISION)]
+    pub precision: usize,
+
+    /// After the output, print a one-line summary for scripts to read
+    #[arg(long, global = true, value_name = "FORMAT")]
+    pub trailer: Option<TrailerFormat>,
+
+    /// Log parsing, validation, queries and actions to stderr at this level
+    #[arg(long, global = true, value_name = "LEVEL")]
+    pub log_level: Option<LogLevel>,
+
+    /// Run queries on a remote host over SSH, e.g. user@server
+    #[cfg(feature = "ssh")]
+    #[arg(long, global = true, value_name = "USER@HOST")]
+    pub host: Option<String>,
+
+    /// Private key for --host connections
+    #[cfg(feature = "ssh")]
+    #[arg(long, global = true, value_name = "PATH", requires = "host")]
+    pub ssh_key: Option<PathBuf>,
+}
+
+impl Args {
+    /// The output format selected by `--format` or `--json`
+    pub fn output_format(&self, formatters: &FormatterRegistry) -> Result<OutputFormat> {
+        match self.format {
+            Some(ref spec) => formatters.resolve(spec),
+            None if self.json => Ok(OutputFormat::Json),
+            None => Ok(OutputFormat::Human),
+        }
+    }
+}
+
+/// Format of the `--trailer` summary line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TrailerFormat {
+    /// Compact JSON: {"target", "count", "success"}
+    Json,
+}
+
+/// Verbosity of the internal log written to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for tracing::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => tracing::Level::ERROR,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Trace => tracing::Level::TRACE,
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -33,6 +137,22 @@ pub enum SubCommand {
     Query {
         /// The SQL-like query to execute
         query: String,
+
+        /// Stream list results as a compact JSON array (with --json)
+        #[arg(long)]
+        stream: bool,
+
+        /// Run the query this many times
+        #[arg(long, default_value_t = 1, value_name = "N")]
+        repeat: u32,
+
+        /// Seconds to wait between repeated runs
+        #[arg(long, default_value_t = 1.0, value_name = "SECS")]
+        delay: f64,
+
+        /// For a condition: print an alert and exit 2 when it holds, stay silent otherwise
+        #[arg(long)]
+        alert: bool,
     },
 
     /// Run an Arta script file (.arta)
@@ -44,9 +164,41 @@ pub enum SubCommand {
         #[arg(long = "arg", value_name = "KEY=VALUE")]
         args: Vec<String>,
 
-        /// Run the script in a specific container
-        #[arg(long)]
+        /// Size arguments, e.g. max=100MB
+        #[arg(long = "arg-size", value_name = "KEY=SIZE")]
+        size_args: Vec<String>,
+
+        /// Numeric arguments, e.g. threshold=80
+        #[arg(long = "arg-num", value_name = "KEY=NUMBER")]
+        num_args: Vec<String>,
+
+        /// Path arguments, e.g. dir=/tmp
+        #[arg(long = "arg-path", value_name = "KEY=PATH")]
+        path_args: Vec<String>,
+
+        /// String arguments, taken verbatim
+        #[arg(long = "arg-str", value_name = "KEY=STRING")]
+        str_args: Vec<String>,
+
+        /// Run the script in this container's context and permissions
+        #[arg(long, requires = "containers")]
         container: Option<String>,
+
+        /// Script defining the containers, as written by EXPORT SESSION
+        #[arg(long, value_name = "FILE")]
+        containers: Option<PathBuf>,
+
+        /// Roll back context changes (variables, folders) if the script fails
+        #[arg(long)]
+        transactional: bool,
+
+        /// Run only this LABEL section (use parent.child for nested sections)
+        #[arg(long, value_name = "LABEL")]
+        only: Option<String>,
+
+        /// Refuse to run a script that has validation warnings
+        #[arg(long)]
+        fail_on_warning: bool,
     },
 
     /// Start live monitoring mode
@@ -54,9 +206,13 @@ pub enum SubCommand {
         /// What to monitor (battery, cpu, memory, disk, network, processes)
         target: String,
 
-        /// Polling interval in seconds (default: 1)
-        #[arg(long, short, default_value = "1")]
-        interval: u64,
+        /// Polling interval, e.g. 500ms, 2s or 1m (minimum 100ms)
+        #[arg(long, short, default_value = "1s", value_parser = parse_interval)]
+        interval: Duration,
+
+        /// Stop once nothing has changed for this long, e.g. 60s or 5m
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        idle: Option<Duration>,
     },
 
     /// Explain a script or query without executing
@@ -65,6 +221,16 @@ pub enum SubCommand {
         input: String,
     },
 
+    /// Check a script for problems without executing it
+    Validate {
+        /// Path to the .arta script file
+        file: PathBuf,
+
+        /// Treat warnings as failures
+        #[arg(long)]
+        strict: bool,
+    },
+
     /// Start interactive REPL mode
     Repl {
         /// Start REPL in a specific container
@@ -0,0 +1,78 @@
+//! Duration literals for CLI options, e.g. `500ms`, `2s`, `1m`
+
+use std::time::Duration;
+
+use crate::error::{ArtaError, Result};
+
+/// Shortest polling interval accepted, so monitors cannot busy-loop
+pub const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Parse a duration such as `500ms`, `2s`, `1.5m` or `1h`
+///
+/// A bare number is taken as seconds, matching the old `--interval 2` form.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let invalid = || {
+        ArtaError::ParseError(format!(
+            "invalid duration '{}': expected e.g. 500ms, 2s or 1m",
+            input
+        ))
+    };
+    let value: f64 = number.parse().map_err(|_| invalid())?;
+    let seconds = match unit.trim().to_lowercase().as_str() {
+        "ms" => value / 1000.0,
+        "" | "s" | "sec" | "secs" => value,
+        "m" | "min" | "mins" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => return Err(invalid()),
+    };
+
+    Duration::try_from_secs_f64(seconds).map_err(|_| invalid())
+}
+
+/// Parse a polling interval, rejecting anything below `MIN_INTERVAL`
+pub fn parse_interval(input: &str) -> Result<Duration> {
+    let interval = parse_duration(input)?;
+    if interval < MIN_INTERVAL {
+        return Err(ArtaError::ParseError(format!(
+            "interval '{}' is too short, the minimum is {:?}",
+            input.trim(),
+            MIN_INTERVAL
+        )));
+    }
+    Ok(interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_duration("2").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_duration("1.5m").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("1H").unwrap(), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_garbage() {
+        for input in ["", "ms", "2x", "-1s", "1.2.3s"] {
+            assert!(parse_duration(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_interval_floor() {
+        assert_eq!(parse_interval("100ms").unwrap(), MIN_INTERVAL);
+        let err = parse_interval("0").unwrap_err();
+        assert!(err.to_string().contains("minimum is 100ms"));
+        assert!(parse_interval("50ms").is_err());
+    }
+}
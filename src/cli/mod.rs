@@ -1,4 +1,5 @@
 //! CLI module
 
 pub mod args;
-pub use args::{Args, SubCommand};
+pub mod duration;
+pub use args::{Args, LogLevel, SubCommand, TrailerFormat};
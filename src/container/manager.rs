@@ -7,9 +7,20 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+use tracing::instrument;
+
 use super::types::Container;
-use crate::error::{ArtaError, Result};
-use crate::parser::ContainerOptions;
+use crate::context::VariableValue;
+use crate::engine::executor::{
+    execute_command_with_context, ContainerInfo, ContainerResultInfo, ExecutionContext,
+};
+use crate::engine::filter;
+use crate::engine::filter::{compare_bools, compare_strings};
+use crate::error::{did_you_mean, ArtaError, Result};
+use crate::parser::{
+    parse_script, Command, Condition, ContainerCommand, ContainerOptions, ContextCommand, Value,
+    WhereClause,
+};
 
 /// Default container name
 pub const DEFAULT_CONTAINER: &str = "default";
@@ -39,6 +50,7 @@ impl ContainerManager {
     }
 
     /// Create a new container
+    #[instrument(level = "debug", skip(self, options))]
     pub fn create(&mut self, name: &str, options: ContainerOptions) -> Result<&mut Container> {
         if self.containers.contains_key(name) {
             return Err(ArtaError::ExecutionError(format!(
@@ -54,6 +66,7 @@ impl ContainerManager {
     }
 
     /// Switch to a different container
+    #[instrument(level = "debug", skip(self))]
     pub fn switch(&mut self, name: &str) -> Result<()> {
         if !self.containers.contains_key(name) {
             return Err(ArtaError::ExecutionError(format!(
@@ -67,6 +80,7 @@ impl ContainerManager {
     }
 
     /// Destroy a container (cannot destroy the default container)
+    #[instrument(level = "debug", skip(self))]
     pub fn destroy(&mut self, name: &str) -> Result<()> {
         if name == DEFAULT_CONTAINER {
             return Err(ArtaError::ExecutionError(
@@ -95,6 +109,32 @@ impl ContainerManager {
         self.containers.keys().map(|s| s.as_str()).collect()
     }
 
+    /// Describe containers (sorted by name) as a LIST result, optionally filtered
+    pub fn list_info(&self, where_clause: Option<&WhereClause>) -> Result<ContainerResultInfo> {
+        let mut names = self.list();
+        names.sort_unstable();
+
+        let containers = names
+            .into_iter()
+            .map(|name| {
+                let container = &self.containers[name];
+                ContainerInfo {
+                    name: name.to_string(),
+                    allow_actions: container.allow_actions,
+                    readonly: container.readonly,
+                    is_active: self.active == name,
+                }
+            })
+            .collect();
+
+        Ok(ContainerResultInfo {
+            operation: "LIST".to_string(),
+            container_name: None,
+            containers: Some(filter_containers(containers, where_clause)?),
+            message: "Container list".to_string(),
+        })
+    }
+
     /// Get the active container
     pub fn active(&self) -> &Container {
         self.containers.get(&self.active).unwrap()
@@ -126,10 +166,15 @@ impl ContainerManager {
     }
 
     /// Export a container to a script file
-    pub fn export(&self, name: &str, path: &Path) -> Result<()> {
+    ///
+    /// The target is checked before anything is written: its folder must
+    /// exist and be writable, and an existing file that is not an `.arta`
+    /// script is only replaced with `force`.
+    pub fn export(&self, name: &str, path: &Path, force: bool) -> Result<()> {
         let container = self.containers.get(name).ok_or_else(|| {
             ArtaError::ExecutionError(format!("Container '{}' does not exist", name))
         })?;
+        check_export_target(path, force)?;
 
         // Generate script content
         let mut script = String::new();
@@ -156,10 +201,215 @@ impl ContainerManager {
         Ok(())
     }
 
+    /// Export every non-default container to one script file
+    ///
+    /// Replaying the script recreates each container, oldest first, with its
+    /// options, variables and current folder, then switches back to the
+    /// active container. The target is checked like for `export`.
+    pub fn export_all(&self, path: &Path, force: bool) -> Result<()> {
+        check_export_target(path, force)?;
+        fs::write(path, self.session_script()).map_err(ArtaError::IoError)
+    }
+
+    /// Script recreating the session, as written by `export_all`
+    pub fn session_script(&self) -> String {
+        let mut containers: Vec<&Container> = self
+            .containers
+            .values()
+            .filter(|c| c.name != DEFAULT_CONTAINER)
+            .collect();
+        containers.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.name.cmp(&b.name)));
+
+        let mut script = format!("-- Exported session: {} container(s)\n", containers.len());
+        for container in containers {
+            let mut options = Vec::new();
+            if container.allow_actions {
+                options.push("ALLOW ACTIONS");
+            }
+            if container.readonly {
+                options.push("READONLY");
+            }
+            let with = if options.is_empty() {
+                String::new()
+            } else {
+                format!(" WITH {}", options.join(", "))
+            };
+
+            script.push_str(&format!(
+                "\nCREATE CONTAINER \"{}\"{} DO\n",
+                container.name, with
+            ));
+            let mut variables: Vec<_> = container.context.variables().iter().collect();
+            variables.sort_by(|a, b| a.0.cmp(b.0));
+            for (key, value) in variables {
+                script.push_str(&format!("    LET {} = {};\n", key, let_literal(value)));
+            }
+            script.push_str(&format!(
+                "    ENTER FOLDER {};\n",
+                crate::context::path_literal(container.context.current_folder())
+            ));
+            script.push_str("END CONTAINER\n");
+        }
+
+        if self.active != DEFAULT_CONTAINER {
+            script.push_str(&format!("\nSWITCH CONTAINER \"{}\"\n", self.active));
+        }
+        script
+    }
+
     /// Get the number of containers
     pub fn count(&self) -> usize {
         self.containers.len()
     }
+
+    /// Recreate the containers of a session script, as written by EXPORT SESSION
+    ///
+    /// Each CREATE CONTAINER body runs in the new container's context, with
+    /// `ctx` narrowed to the container's permissions. SWITCH CONTAINER makes
+    /// a container active. Bodies may only hold LET and ENTER FOLDER; the
+    /// whole file is checked before any of it runs, and anything else is an
+    /// error.
+    pub fn load_session(path: &Path, ctx: &ExecutionContext) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            ArtaError::ExecutionError(format!(
+                "Cannot read containers file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        let statements = parse_script(&content)?.statements;
+        for cmd in &statements {
+            let body = match cmd {
+                Command::Container(ContainerCommand::Create(create)) => create.body.as_slice(),
+                Command::Container(ContainerCommand::Switch(_)) => &[],
+                _ => {
+                    return Err(ArtaError::ExecutionError(format!(
+                        "Containers file {} may only contain CREATE CONTAINER and SWITCH CONTAINER",
+                        path.display()
+                    )))
+                }
+            };
+            let restorable = |cmd: &Command| {
+                matches!(
+                    cmd,
+                    Command::Let(_) | Command::Context(ContextCommand::EnterFolder(_))
+                )
+            };
+            if !body.iter().all(restorable) {
+                return Err(ArtaError::ExecutionError(format!(
+                    "Containers in {} may only contain LET and ENTER FOLDER",
+                    path.display()
+                )));
+            }
+        }
+
+        let mut manager = Self::new();
+        for cmd in statements {
+            match cmd {
+                Command::Container(ContainerCommand::Create(create)) => {
+                    let container = manager.create(&create.name, create.options)?;
+                    let mut container_ctx = ctx.clone();
+                    container.restrict(&mut container_ctx);
+                    for body_cmd in &create.body {
+                        execute_command_with_context(
+                            body_cmd,
+                            &container_ctx,
+                            &mut container.context,
+                        )?;
+                    }
+                }
+                Command::Container(ContainerCommand::Switch(name)) => manager.switch(&name)?,
+                _ => {
+                    return Err(ArtaError::ExecutionError(format!(
+                        "Containers file {} may only contain CREATE CONTAINER and SWITCH CONTAINER",
+                        path.display()
+                    )))
+                }
+            }
+        }
+        Ok(manager)
+    }
+
+    /// Hand over a container, e.g. to run a script in its context
+    ///
+    /// An empty container with the same options is left in its place.
+    pub fn take(&mut self, name: &str) -> Result<Container> {
+        let mut known = self.list();
+        known.sort_unstable();
+        let unknown = ArtaError::ExecutionError(format!(
+            "Container '{}' does not exist{}",
+            name,
+            did_you_mean(name, &known)
+        ));
+
+        let container = self.containers.get_mut(name).ok_or(unknown)?;
+        let fresh = container.clone();
+        Ok(std::mem::replace(container, fresh))
+    }
+}
+
+/// Check that an export can write `path`, without touching it
+fn check_export_target(path: &Path, force: bool) -> Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if !parent.is_dir() {
+        return Err(ArtaError::PathNotFound(format!(
+            "{} (folder for export to {})",
+            parent.display(),
+            path.display()
+        )));
+    }
+    if !is_writable(parent) {
+        return Err(ArtaError::PermissionDenied(format!(
+            "cannot write to {} (export to {})",
+            parent.display(),
+            path.display()
+        )));
+    }
+
+    if path.is_dir() {
+        return Err(ArtaError::ExecutionError(format!(
+            "Cannot export to {}: it is a directory",
+            path.display()
+        )));
+    }
+    let is_script = path.extension().is_some_and(|ext| ext == "arta");
+    if path.exists() && !is_script && !force {
+        return Err(ArtaError::ExecutionError(format!(
+            "Refusing to overwrite {}, which is not an .arta script; add FORCE to replace it",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_writable(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `c_path` is a valid NUL-terminated string for the whole call
+    unsafe { libc::access(c_path.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_writable(dir: &Path) -> bool {
+    fs::metadata(dir).is_ok_and(|m| !m.permissions().readonly())
+}
+
+/// A variable value as it is written in a LET statement
+fn let_literal(value: &VariableValue) -> String {
+    match value {
+        VariableValue::Size(bytes) => format!("{}B", bytes),
+        VariableValue::Path(path) => crate::context::path_literal(path),
+        VariableValue::String(s) => format!("\"{}\"", crate::parser::escape(s)),
+        other => other.to_string(),
+    }
 }
 
 impl Default for ContainerManager {
@@ -168,6 +418,60 @@ impl Default for ContainerManager {
     }
 }
 
+/// Container attributes usable in `LIST CONTAINERS WHERE`
+const CONTAINER_FIELDS: &[&str] = &["name", "allow_actions", "readonly", "active", "is_active"];
+
+/// Keep the containers matching a WHERE clause
+///
+/// Unknown fields are an error rather than matching everything, since a typo
+/// would otherwise list every container.
+pub fn filter_containers(
+    mut containers: Vec<ContainerInfo>,
+    where_clause: Option<&WhereClause>,
+) -> Result<Vec<ContainerInfo>> {
+    let Some(wc) = where_clause else {
+        return Ok(containers);
+    };
+    filter::check_patterns(wc)?;
+
+    for expr in &wc.conditions {
+        let mut node = Some(expr);
+        while let Some(current) = node {
+            let field = current.condition.field.to_lowercase();
+            if !CONTAINER_FIELDS.contains(&field.as_str()) {
+                return Err(ArtaError::ExecutionError(format!(
+                    "Unknown container field '{}' (expected name, allow_actions, readonly or active)",
+                    current.condition.field
+                )));
+            }
+            node = current.next.as_ref().map(|(_, next)| next.as_ref());
+        }
+    }
+
+    containers.retain(|c| filter::matches(wc, |cond| matches_container_condition(c, cond)));
+    Ok(containers)
+}
+
+fn matches_container_condition(container: &ContainerInfo, condition: &Condition) -> bool {
+    let flag = match condition.field.to_lowercase().as_str() {
+        "name" => {
+            return match &condition.value {
+                Value::String(s) | Value::Identifier(s) => {
+                    compare_strings(&container.name, s, &condition.operator)
+                }
+                _ => false,
+            }
+        }
+        "allow_actions" => container.allow_actions,
+        "readonly" => container.readonly,
+        _ => container.is_active,
+    };
+    match &condition.value {
+        Value::Boolean(b) => compare_bools(flag, *b, &condition.operator),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -180,6 +484,147 @@ mod tests {
         assert_eq!(manager.active_name(), DEFAULT_CONTAINER);
     }
 
+    #[test]
+    fn test_list_info_where() {
+        let mut manager = ContainerManager::new();
+        for (name, allow_actions, readonly) in [("ops", true, false), ("audit", false, true)] {
+            manager
+                .create(
+                    name,
+                    ContainerOptions {
+                        allow_actions,
+                        readonly,
+                    },
+                )
+                .unwrap();
+        }
+        let names = |query: &str| {
+            let wc = match crate::parser::parse_command(query).unwrap() {
+                crate::parser::Command::Container(crate::parser::ContainerCommand::List(wc)) => wc,
+                other => panic!("Expected LIST CONTAINERS, got {:?}", other),
+            };
+            manager.list_info(wc.as_ref()).map(|info| {
+                info.containers
+                    .unwrap()
+                    .into_iter()
+                    .map(|c| c.name)
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            names("LIST CONTAINERS").unwrap(),
+            ["audit", "default", "ops"]
+        );
+        assert_eq!(
+            names("LIST CONTAINERS WHERE allow_actions = true").unwrap(),
+            ["ops"]
+        );
+        assert_eq!(
+            names("LIST CONTAINERS WHERE readonly = false").unwrap(),
+            ["default", "ops"]
+        );
+        assert_eq!(
+            names("LIST CONTAINERS WHERE active = true").unwrap(),
+            ["default"]
+        );
+        assert_eq!(
+            names("LIST CONTAINERS WHERE name LIKE \"a%\" OR allow_actions = true").unwrap(),
+            ["audit", "ops"]
+        );
+        assert!(names("LIST CONTAINERS WHERE writable = true").is_err());
+    }
+
+    #[test]
+    fn test_load_session() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("session.arta");
+        fs::write(
+            &path,
+            format!(
+                "CREATE CONTAINER \"audit\" WITH READONLY DO\n\
+                 \x20   LET who = \"auditor\";\n\
+                 \x20   ENTER FOLDER \"{}\";\n\
+                 END CONTAINER\n\
+                 CREATE CONTAINER \"ops\" WITH ALLOW ACTIONS DO\n\
+                 END CONTAINER\n\
+                 SWITCH CONTAINER \"ops\"\n",
+                dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let mut manager =
+            ContainerManager::load_session(&path, &ExecutionContext::default()).unwrap();
+        assert_eq!(manager.active_name(), "ops");
+
+        let audit = manager.take("audit").unwrap();
+        assert!(audit.is_readonly());
+        assert!(audit.context().get_variable("who").is_some());
+        assert_eq!(audit.context().current_folder(), dir.path());
+        // The name stays taken, with an empty context
+        assert!(manager
+            .get("audit")
+            .unwrap()
+            .context()
+            .get_variable("who")
+            .is_none());
+
+        let err = manager.take("opps").unwrap_err().to_string();
+        assert!(err.contains("did you mean ops?"), "{}", err);
+
+        fs::write(&path, "SELECT CPU *\n").unwrap();
+        assert!(ContainerManager::load_session(&path, &ExecutionContext::default()).is_err());
+
+        // Nothing in the file runs when any body holds more than LET / ENTER FOLDER
+        let victim = dir.path().join("victim.log");
+        fs::write(&victim, "").unwrap();
+        fs::write(
+            &path,
+            format!(
+                "CREATE CONTAINER \"a\" WITH ALLOW ACTIONS DO LET x = 1 END CONTAINER\n\
+                 CREATE CONTAINER \"b\" WITH ALLOW ACTIONS DO\n\
+                 \x20   DELETE FILES FROM \"{}\" WHERE extension = \"log\";\n\
+                 END CONTAINER\n",
+                dir.path().display()
+            ),
+        )
+        .unwrap();
+        let ctx = ExecutionContext {
+            allow_actions: true,
+            ..Default::default()
+        };
+        let err = ContainerManager::load_session(&path, &ctx).unwrap_err();
+        assert!(err.to_string().contains("LET and ENTER FOLDER"), "{}", err);
+        assert!(victim.exists());
+    }
+
+    #[test]
+    fn test_list_info_json() {
+        let mut manager = ContainerManager::new();
+        manager
+            .create(
+                "sandbox",
+                ContainerOptions {
+                    allow_actions: true,
+                    readonly: true,
+                },
+            )
+            .unwrap();
+        manager.switch("sandbox").unwrap();
+
+        let json = serde_json::to_value(manager.list_info(None).unwrap()).unwrap();
+        let containers = json["containers"].as_array().unwrap();
+
+        assert_eq!(containers.len(), 2);
+        assert_eq!(containers[0]["name"], "default");
+        assert_eq!(containers[0]["is_active"], false);
+        assert_eq!(containers[1]["name"], "sandbox");
+        assert_eq!(containers[1]["allow_actions"], true);
+        assert_eq!(containers[1]["readonly"], true);
+        assert_eq!(containers[1]["is_active"], true);
+    }
+
     #[test]
     fn test_manager_create() {
         let mut manager = ContainerManager::new();
@@ -237,6 +682,80 @@ mod tests {
         assert_eq!(manager.active_name(), DEFAULT_CONTAINER);
     }
 
+    #[test]
+    fn test_export_session_round_trip() {
+        use crate::parser::{parse_script, Command, ContainerCommand, LetValue};
+
+        let mut manager = ContainerManager::new();
+        let build = manager
+            .create(
+                "build",
+                ContainerOptions {
+                    allow_actions: true,
+                    readonly: false,
+                },
+            )
+            .unwrap();
+        build
+            .context_mut()
+            .set_variable("max".to_string(), VariableValue::Size(100 * 1024 * 1024));
+        build.context_mut().set_variable(
+            "out".to_string(),
+            VariableValue::Path("/tmp/my \"out\"".into()),
+        );
+        let review = manager
+            .create(
+                "review",
+                ContainerOptions {
+                    allow_actions: false,
+                    readonly: true,
+                },
+            )
+            .unwrap();
+        review.context_mut().set_variable(
+            "owner".to_string(),
+            VariableValue::String(r#"ops "on call" \ nights"#.to_string()),
+        );
+        manager.switch("review").unwrap();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("session.arta");
+        manager.export_all(&path, false).unwrap();
+        let script = parse_script(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        let creates: Vec<_> = script
+            .statements
+            .iter()
+            .filter_map(|cmd| match cmd {
+                Command::Container(ContainerCommand::Create(c)) => Some(c),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(creates.len(), 2);
+        assert_eq!(creates[0].name, "build");
+        assert!(creates[0].options.allow_actions && !creates[0].options.readonly);
+        // Two LETs and the ENTER FOLDER
+        assert_eq!(creates[0].body.len(), 3);
+        assert_eq!(creates[1].name, "review");
+        assert!(!creates[1].options.allow_actions && creates[1].options.readonly);
+        assert_eq!(creates[1].body.len(), 2);
+        // Quotes and backslashes survive the round trip
+        let lets: Vec<_> = creates
+            .iter()
+            .flat_map(|c| &c.body)
+            .filter_map(|cmd| match cmd {
+                Command::Let(statement) => Some(&statement.value),
+                _ => None,
+            })
+            .collect();
+        assert!(matches!(lets[1], LetValue::Path(p) if p == "/tmp/my \"out\""));
+        assert!(matches!(lets[2], LetValue::String(s) if s == r#"ops "on call" \ nights"#));
+        assert!(matches!(
+            script.statements.last(),
+            Some(Command::Container(ContainerCommand::Switch(name))) if name == "review"
+        ));
+    }
+
     #[test]
     fn test_manager_list() {
         let mut manager = ContainerManager::new();
@@ -252,4 +771,59 @@ mod tests {
         assert!(list.contains(&"test1"));
         assert!(list.contains(&"test2"));
     }
+
+    #[test]
+    fn test_export_missing_parent() {
+        let manager = ContainerManager::new();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("missing").join("default.arta");
+
+        let err = manager.export(DEFAULT_CONTAINER, &path, false).unwrap_err();
+        assert!(matches!(err, ArtaError::PathNotFound(_)), "{}", err);
+        assert!(err.to_string().contains("default.arta"), "{}", err);
+    }
+
+    #[test]
+    fn test_export_refuses_to_overwrite_other_files() {
+        let manager = ContainerManager::new();
+        let dir = tempfile::TempDir::new().unwrap();
+        let notes = dir.path().join("notes.txt");
+        fs::write(&notes, "keep me").unwrap();
+
+        let err = manager
+            .export(DEFAULT_CONTAINER, &notes, false)
+            .unwrap_err();
+        assert!(err.to_string().contains("FORCE"), "{}", err);
+        assert_eq!(fs::read_to_string(&notes).unwrap(), "keep me");
+
+        manager.export(DEFAULT_CONTAINER, &notes, true).unwrap();
+        assert!(fs::read_to_string(&notes)
+            .unwrap()
+            .starts_with("-- Exported container"));
+
+        // Re-exporting over a script is fine without FORCE
+        let script = dir.path().join("default.arta");
+        manager.export(DEFAULT_CONTAINER, &script, false).unwrap();
+        manager.export(DEFAULT_CONTAINER, &script, false).unwrap();
+    }
+
+    #[test]
+    fn test_export_session_checks_target() {
+        let manager = ContainerManager::new();
+        let dir = tempfile::TempDir::new().unwrap();
+        let notes = dir.path().join("notes.txt");
+        fs::write(&notes, "keep me").unwrap();
+
+        let err = manager.export_all(&notes, false).unwrap_err();
+        assert!(err.to_string().contains("FORCE"), "{}", err);
+        assert_eq!(fs::read_to_string(&notes).unwrap(), "keep me");
+        manager.export_all(&notes, true).unwrap();
+        assert!(fs::read_to_string(&notes)
+            .unwrap()
+            .starts_with("-- Exported session"));
+
+        let missing = dir.path().join("missing").join("session.arta");
+        let err = manager.export_all(&missing, false).unwrap_err();
+        assert!(matches!(err, ArtaError::PathNotFound(_)), "{}", err);
+    }
 }
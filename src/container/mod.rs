@@ -8,5 +8,5 @@
 mod manager;
 mod types;
 
-pub use manager::ContainerManager;
+pub use manager::{filter_containers, ContainerManager};
 pub use types::Container;
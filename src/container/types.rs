@@ -4,6 +4,7 @@
 //! context, variables, and configuration.
 
 use crate::context::Context;
+use crate::engine::ExecutionContext;
 use crate::parser::ContainerOptions;
 use chrono::{DateTime, Utc};
 
@@ -58,6 +59,29 @@ impl Container {
     pub fn is_readonly(&self) -> bool {
         self.readonly
     }
+
+    /// Narrow `ctx` to this container's permissions
+    ///
+    /// A container only takes permissions away: actions need both
+    /// `--allow-actions` and the container's ALLOW ACTIONS option, and never
+    /// run in a read-only container.
+    pub fn restrict(&self, ctx: &mut ExecutionContext) {
+        ctx.allow_actions = ctx.allow_actions && self.allow_actions && !self.readonly;
+    }
+
+    /// Why actions are refused in this container, if they are
+    pub fn action_denial(&self) -> Option<String> {
+        if self.readonly {
+            Some(format!("Container '{}' is read-only", self.name))
+        } else if !self.allow_actions {
+            Some(format!(
+                "Container '{}' was created without ALLOW ACTIONS",
+                self.name
+            ))
+        } else {
+            None
+        }
+    }
 }
 
 impl Clone for Container {
@@ -96,10 +120,35 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::overly_complex_bool_expr)]
     fn test_container_context() {
         let mut container = Container::new_default("test".to_string());
         let ctx = container.context_mut();
         // Should be able to modify context
         assert!(ctx.current_folder().exists() || true); // Just check it's accessible
     }
+
+    #[test]
+    fn test_restrict_only_removes_permissions() {
+        let allowed = |allow_actions, readonly, cli: bool| {
+            let container = Container::new(
+                "test".to_string(),
+                ContainerOptions {
+                    allow_actions,
+                    readonly,
+                },
+            );
+            let mut ctx = ExecutionContext {
+                allow_actions: cli,
+                ..Default::default()
+            };
+            container.restrict(&mut ctx);
+            ctx.allow_actions
+        };
+
+        assert!(allowed(true, false, true));
+        assert!(!allowed(true, false, false));
+        assert!(!allowed(false, false, true));
+        assert!(!allowed(true, true, true));
+    }
 }
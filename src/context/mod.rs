@@ -3,6 +3,8 @@
 //! The context system maintains stateful information across commands,
 //! such as the current working directory and file being inspected.
 
+use crate::engine::provider::{SystemHandle, SystemProvider};
+use crate::engine::queries::memory::PressureThresholds;
 use crate::error::{ArtaError, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -22,6 +24,14 @@ pub struct Context {
 
     /// History of entered paths
     history: Vec<ContextHistoryEntry>,
+
+    /// Shared sysinfo provider reused by queries in this session
+    #[serde(skip)]
+    system: SystemHandle,
+
+    /// Thresholds for the MEMORY `pressure` field
+    #[serde(skip)]
+    memory_pressure: PressureThresholds,
 }
 
 /// Variable value types
@@ -39,7 +49,7 @@ impl std::fmt::Display for VariableValue {
         match self {
             VariableValue::String(s) => write!(f, "\"{}\"", s),
             VariableValue::Number(n) => write!(f, "{}", n),
-            VariableValue::Size(s) => write!(f, "{}", bytesize::ByteSize(*s)),
+            VariableValue::Size(s) => write!(f, "{}", crate::output::format_util::fmt_size(*s)),
             VariableValue::Boolean(b) => write!(f, "{}", b),
             VariableValue::Path(p) => write!(f, "{}", p.display()),
         }
@@ -61,6 +71,8 @@ impl Default for Context {
             current_file: None,
             variables: HashMap::new(),
             history: Vec::new(),
+            system: SystemHandle::default(),
+            memory_pressure: PressureThresholds::default(),
         }
     }
 }
@@ -71,6 +83,28 @@ impl Context {
         Self::default()
     }
 
+    /// Use a custom system provider for queries run in this context
+    pub fn with_system_provider(mut self, provider: impl SystemProvider + 'static) -> Self {
+        self.system = SystemHandle::new(provider);
+        self
+    }
+
+    /// Classify MEMORY `pressure` with custom thresholds
+    pub fn with_memory_pressure(mut self, thresholds: PressureThresholds) -> Self {
+        self.memory_pressure = thresholds;
+        self
+    }
+
+    /// Get the thresholds for the MEMORY `pressure` field
+    pub fn memory_pressure(&self) -> &PressureThresholds {
+        &self.memory_pressure
+    }
+
+    /// Get the shared system provider
+    pub fn system(&self) -> &SystemHandle {
+        &self.system
+    }
+
     /// Get the current working directory
     pub fn current_folder(&self) -> &Path {
         self.folder_stack
@@ -89,20 +123,15 @@ impl Context {
         self.folder_stack.len()
     }
 
+    /// Get the entered folders, from the initial folder to the current one
+    pub fn folder_stack_paths(&self) -> &[PathBuf] {
+        &self.folder_stack
+    }
+
     /// Enter a folder context
     pub fn enter_folder(&mut self, path: &str) -> Result<()> {
         let path = self.resolve_path(path)?;
-
-        if !path.exists() {
-            return Err(ArtaError::PathNotFound(path.to_string_lossy().to_string()));
-        }
-
-        if !path.is_dir() {
-            return Err(ArtaError::ExecutionError(format!(
-                "'{}' is not a directory",
-                path.display()
-            )));
-        }
+        ensure_folder(&path)?;
 
         // Canonicalize the path
         let canonical = path.canonicalize().map_err(ArtaError::IoError)?;
@@ -122,17 +151,7 @@ impl Context {
     /// Enter a file context for content inspection
     pub fn enter_file(&mut self, path: &str) -> Result<()> {
         let path = self.resolve_path(path)?;
-
-        if !path.exists() {
-            return Err(ArtaError::PathNotFound(path.to_string_lossy().to_string()));
-        }
-
-        if !path.is_file() {
-            return Err(ArtaError::ExecutionError(format!(
-                "'{}' is not a file",
-                path.display()
-            )));
-        }
+        ensure_file(&path)?;
 
         let canonical = path.canonicalize().map_err(ArtaError::IoError)?;
 
@@ -176,6 +195,25 @@ impl Context {
         ))
     }
 
+    /// Pop the folder stack, clearing any file context along the way
+    pub fn up(&mut self) -> Result<()> {
+        if self.folder_stack.len() <= 1 {
+            return Err(ArtaError::ExecutionError(
+                "Already at root context, cannot go up further".to_string(),
+            ));
+        }
+
+        self.current_file = None;
+        let exited = self.folder_stack.pop();
+        self.history.push(ContextHistoryEntry {
+            action: "UP".to_string(),
+            path: exited,
+            timestamp: chrono::Utc::now(),
+        });
+
+        Ok(())
+    }
+
     /// Reset context to initial state
     pub fn reset(&mut self) {
         let initial_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
@@ -189,6 +227,24 @@ impl Context {
         });
     }
 
+    /// Remove all variables, keeping folders and history; returns how many were set
+    pub fn clear_variables(&mut self) -> usize {
+        let count = self.variables.len();
+        self.variables.clear();
+
+        self.history.push(ContextHistoryEntry {
+            action: "RESET VARIABLES".to_string(),
+            path: None,
+            timestamp: chrono::Utc::now(),
+        });
+        count
+    }
+
+    /// Remove all history entries, keeping folders and variables; returns how many there were
+    pub fn clear_history(&mut self) -> usize {
+        std::mem::take(&mut self.history).len()
+    }
+
     /// Resolve a path relative to current context
     pub fn resolve_path(&self, path: &str) -> Result<PathBuf> {
         let path = Path::new(path);
@@ -283,6 +339,57 @@ mod dirs {
     }
 }
 
+/// Check that `path` exists and is a file, suggesting FILES for a directory
+pub fn ensure_file(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Err(ArtaError::PathNotFound(path.display().to_string()));
+    }
+    if path.is_dir() {
+        return Err(ArtaError::ExecutionError(format!(
+            "Expected a file but '{}' is a directory; did you mean SELECT FILES * FROM {}?",
+            path.display(),
+            path_literal(path)
+        )));
+    }
+    if !path.is_file() {
+        return Err(ArtaError::ExecutionError(format!(
+            "'{}' is not a regular file",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Check that `path` exists and is a directory, suggesting CONTENT for a file
+pub fn ensure_folder(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Err(ArtaError::PathNotFound(path.display().to_string()));
+    }
+    if !path.is_dir() {
+        return Err(ArtaError::ExecutionError(format!(
+            "Expected a folder but '{}' is a file; did you mean SELECT CONTENT * FROM {}?",
+            path.display(),
+            path_literal(path)
+        )));
+    }
+    Ok(())
+}
+
+/// Write a path the way a script would, quoting (and escaping) it unless it
+/// parses bare
+pub fn path_literal(path: &Path) -> String {
+    let path = path.display().to_string();
+    let bare = path.starts_with('/')
+        && path
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/_-.".contains(c));
+    if bare {
+        path
+    } else {
+        format!("\"{}\"", crate::parser::escape(&path))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -297,6 +404,35 @@ mod tests {
         assert_eq!(ctx.folder_depth(), 1);
     }
 
+    #[test]
+    fn test_clear_variables_keeps_folders() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut ctx = Context::new();
+        ctx.enter_folder(temp_dir.path().to_str().unwrap()).unwrap();
+        ctx.set_variable("x".to_string(), VariableValue::Number(1.0));
+        ctx.set_variable("y".to_string(), VariableValue::Boolean(true));
+
+        assert_eq!(ctx.clear_variables(), 2);
+        assert!(ctx.variables().is_empty());
+        assert_eq!(ctx.folder_depth(), 2);
+        assert_eq!(ctx.current_folder(), temp_dir.path());
+        assert_eq!(ctx.history().last().unwrap().action, "RESET VARIABLES");
+    }
+
+    #[test]
+    fn test_clear_history_keeps_folders_and_variables() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut ctx = Context::new();
+        ctx.enter_folder(temp_dir.path().to_str().unwrap()).unwrap();
+        ctx.set_variable("x".to_string(), VariableValue::Number(1.0));
+        assert!(!ctx.history().is_empty());
+
+        ctx.clear_history();
+        assert!(ctx.history().is_empty());
+        assert_eq!(ctx.folder_depth(), 2);
+        assert!(ctx.get_variable("x").is_some());
+    }
+
     #[test]
     fn test_enter_folder() {
         let temp_dir = TempDir::new().unwrap();
@@ -326,6 +462,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_enter_directory_as_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut ctx = Context::new();
+
+        let err = ctx
+            .enter_file(temp_dir.path().to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Expected a file but"));
+        assert!(err.contains("did you mean SELECT FILES * FROM"));
+        assert!(ctx.current_file().is_none());
+    }
+
+    #[test]
+    fn test_enter_file_as_folder() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("notes.txt");
+        File::create(&file_path).unwrap();
+        let mut ctx = Context::new();
+
+        let err = ctx
+            .enter_folder(file_path.to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Expected a folder but"));
+        assert!(err.contains(&format!(
+            "did you mean SELECT CONTENT * FROM {}?",
+            path_literal(&file_path)
+        )));
+        assert_eq!(ctx.folder_depth(), 1);
+    }
+
+    #[test]
+    fn test_path_literal_quotes_when_needed() {
+        assert_eq!(path_literal(Path::new("/var/log")), "/var/log");
+        assert_eq!(path_literal(Path::new("/tmp/my dir")), "\"/tmp/my dir\"");
+        assert_eq!(
+            path_literal(Path::new(r#"/tmp/a "b" \c"#)),
+            r#""/tmp/a \"b\" \\c""#
+        );
+    }
+
     #[test]
     fn test_exit_context() {
         let temp_dir = TempDir::new().unwrap();
@@ -360,6 +539,29 @@ mod tests {
         assert_eq!(ctx.folder_depth(), 1);
     }
 
+    #[test]
+    fn test_up_pops_folder_with_file_entered() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test.txt");
+        File::create(&file_path).unwrap();
+
+        let mut ctx = Context::new();
+        ctx.enter_folder(temp_dir.path().to_str().unwrap()).unwrap();
+        ctx.enter_file(file_path.to_str().unwrap()).unwrap();
+
+        // A single UP clears the file and pops the folder
+        ctx.up().unwrap();
+        assert!(ctx.current_file().is_none());
+        assert_eq!(ctx.folder_depth(), 1);
+    }
+
+    #[test]
+    fn test_up_at_root_fails() {
+        let mut ctx = Context::new();
+        assert!(ctx.up().is_err());
+        assert_eq!(ctx.folder_depth(), 1);
+    }
+
     #[test]
     fn test_reset_context() {
         let temp_dir = TempDir::new().unwrap();
@@ -1,70 +1,99 @@
-//! File deletion action
+//! File actions: deletion and writing
 
 use crate::engine::actions::ActionResult;
+use crate::engine::executor::{file_condition, list_files, FileEntry, OWNERSHIP_FIELDS};
+use crate::engine::fields::known_fields;
+use crate::engine::filter;
 use crate::error::{ArtaError, Result};
-use crate::parser::{CompareOp, Value, WhereClause};
+use crate::parser::{QueryTarget, WhereClause};
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
 
 const MAX_FILES_PER_OPERATION: usize = 100;
 
 pub fn delete_files(
-    path: &str,
+    base_path: &Path,
     where_clause: Option<&WhereClause>,
     dry_run: bool,
+    explain_matches: bool,
 ) -> Result<ActionResult> {
-    let base_path = Path::new(path);
-
-    if !base_path.exists() {
-        return Err(ArtaError::PathNotFound(path.to_string()));
-    }
-
-    if !base_path.is_dir() {
-        return Err(ArtaError::ExecutionError(format!(
-            "{} is not a directory",
-            path
-        )));
-    }
+    crate::context::ensure_folder(base_path)?;
 
     // Security check: require WHERE clause
-    if where_clause.is_none() {
+    let Some(where_clause) = where_clause else {
         return Err(ArtaError::SecurityError(
             "DELETE without WHERE clause is too dangerous. Add a WHERE clause to filter files."
                 .to_string(),
         ));
-    }
+    };
+    filter::check_patterns(where_clause)?;
+    filter::check_fields(
+        where_clause,
+        "DELETE FILES",
+        known_fields(QueryTarget::Files),
+    )?;
+    filter::check_times(where_clause, QueryTarget::Files, "modified")?;
 
-    let mut matched_files: Vec<FileInfo> = Vec::new();
-
-    // Scan directory (non-recursive for safety)
-    for entry in fs::read_dir(base_path).map_err(ArtaError::IoError)? {
-        let entry = entry.map_err(ArtaError::IoError)?;
-        let file_path = entry.path();
-
-        if file_path.is_file() {
-            let metadata = fs::metadata(&file_path).map_err(ArtaError::IoError)?;
-
-            let file_info = FileInfo {
-                path: file_path.to_string_lossy().to_string(),
-                name: file_path
-                    .file_name()
-                    .map(|n| n.to_string_lossy().to_string())
-                    .unwrap_or_default(),
-                size: metadata.len(),
-                extension: file_path
-                    .extension()
-                    .map(|e| e.to_string_lossy().to_string())
-                    .unwrap_or_default(),
-            };
+    let references =
+        |field: &str| filter::references_field(where_clause, QueryTarget::Files, field);
+    let files = list_files(
+        base_path,
+        references("dir_size"),
+        OWNERSHIP_FIELDS.iter().any(|field| references(field)),
+        None,
+    )?;
 
-            if let Some(wc) = where_clause {
-                if matches_file_where_clause(&file_info, wc) {
-                    matched_files.push(file_info);
-                }
-            }
+    // Matched files with the conditions that selected them; the scan is not
+    // recursive and directories are never deleted
+    let mut matched_files: Vec<(FileEntry, Vec<String>)> = Vec::new();
+    for file in files.into_iter().filter(|file| !file.is_dir) {
+        // Every field was checked above, so the matcher knows them all
+        let trace =
+            filter::evaluate_with_trace(where_clause, |c| file_condition(&file, c) == Some(true));
+        if trace.matched {
+            let reasons = trace.satisfied.iter().map(|c| c.to_string()).collect();
+            matched_files.push((file, reasons));
         }
     }
 
+    remove_matched(&matched_files, dry_run, explain_matches)
+}
+
+/// Delete the files a FILES query returned (`SELECT FILES ... | DELETE`)
+///
+/// Directories are skipped, symlinks are removed rather than their targets,
+/// and the DELETE FILES limit on matches applies. `explain` is the query's
+/// WHERE clause; when given, a dry run lists the conditions each file satisfied.
+pub fn delete_listed(
+    files: &[FileEntry],
+    explain: Option<&WhereClause>,
+    dry_run: bool,
+) -> Result<ActionResult> {
+    let matched_files: Vec<(FileEntry, Vec<String>)> = files
+        .iter()
+        .filter(|file| !file.is_dir)
+        .map(|file| {
+            let reasons = explain
+                .map(|wc| {
+                    filter::evaluate_with_trace(wc, |c| file_condition(file, c) == Some(true))
+                        .satisfied
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            (file.clone(), reasons)
+        })
+        .collect();
+    remove_matched(&matched_files, dry_run, explain.is_some())
+}
+
+fn remove_matched(
+    matched_files: &[(FileEntry, Vec<String>)],
+    dry_run: bool,
+    explain_matches: bool,
+) -> Result<ActionResult> {
     // Safety limit
     if matched_files.len() > MAX_FILES_PER_OPERATION {
         return Err(ArtaError::SecurityError(format!(
@@ -76,15 +105,24 @@ pub fn delete_files(
 
     let mut details = Vec::new();
     let mut deleted_count = 0;
+    let mut deleted_bytes = 0;
 
-    for file in &matched_files {
-        if dry_run {
+    for (file, reasons) in matched_files {
+        if dry_run && explain_matches {
+            details.push(format!(
+                "Would delete: {} ({} bytes), matched: {}",
+                file.path,
+                file.size,
+                reasons.join(", ")
+            ));
+        } else if dry_run {
             details.push(format!("Would delete: {} ({} bytes)", file.path, file.size));
         } else {
             match fs::remove_file(&file.path) {
                 Ok(_) => {
                     details.push(format!("Deleted: {}", file.path));
                     deleted_count += 1;
+                    deleted_bytes += file.size;
                 }
                 Err(e) => {
                     details.push(format!("Failed to delete {}: {}", file.path, e));
@@ -100,92 +138,159 @@ pub fn delete_files(
         } else {
             deleted_count
         },
+        affected_bytes: if dry_run {
+            matched_files.iter().map(|(f, _)| f.size).sum()
+        } else {
+            deleted_bytes
+        },
         dry_run,
         details,
     })
 }
 
-#[derive(Debug)]
-struct FileInfo {
-    path: String,
-    name: String,
-    size: u64,
-    extension: String,
+/// Write `content` to a file, replacing it or appending to it
+pub fn write_file(path: &Path, content: &str, append: bool, dry_run: bool) -> Result<ActionResult> {
+    if is_protected_path(path) {
+        return Err(ArtaError::SecurityError(format!(
+            "Refusing to write to system path: {}",
+            path.display()
+        )));
+    }
+    if path.is_dir() {
+        return Err(ArtaError::ExecutionError(format!(
+            "Cannot write to '{}': it is a directory",
+            path.display()
+        )));
+    }
+
+    let bytes = content.len();
+    let verb = if append { "append" } else { "write" };
+    let detail = if dry_run {
+        format!("Would {} {} bytes to {}", verb, bytes, path.display())
+    } else {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .map_err(ArtaError::IoError)?;
+        file.write_all(content.as_bytes())
+            .map_err(ArtaError::IoError)?;
+        format!("Wrote {} bytes to {}", bytes, path.display())
+    };
+
+    Ok(ActionResult {
+        action_type: "WRITE FILE".to_string(),
+        affected_count: 1,
+        affected_bytes: bytes as u64,
+        dry_run,
+        details: vec![detail],
+    })
 }
 
-fn matches_file_where_clause(file: &FileInfo, where_clause: &WhereClause) -> bool {
-    for condition_expr in &where_clause.conditions {
-        if !matches_file_condition(file, &condition_expr.condition) {
-            return false;
-        }
-    }
-    true
+/// System directories that WRITE refuses to touch
+///
+/// The path is normalized first, so `/tmp/../etc/hosts` or a symlink into
+/// `/etc` is caught, and compared component by component.
+pub fn is_protected_path(path: &Path) -> bool {
+    let protected = [
+        "/bin", "/boot", "/dev", "/etc", "/lib", "/proc", "/sbin", "/sys", "/usr",
+    ];
+    // The roots are normalized too, as some systems link /etc elsewhere
+    let path = normalize_path(path);
+    protected
+        .iter()
+        .any(|p| path.starts_with(p) || path.starts_with(normalize_path(Path::new(p))))
 }
 
-fn matches_file_condition(file: &FileInfo, condition: &crate::parser::Condition) -> bool {
-    let field = condition.field.to_lowercase();
+/// Absolute form of `path` with symlinks and `.`/`..` resolved
+///
+/// The deepest ancestor that exists is canonicalized; the components below
+/// it (which don't exist yet) are resolved lexically.
+fn normalize_path(path: &Path) -> PathBuf {
+    let absolute = match std::env::current_dir() {
+        Ok(cwd) if path.is_relative() => cwd.join(path),
+        _ => path.to_path_buf(),
+    };
 
-    match field.as_str() {
-        "size" => {
-            let target = match &condition.value {
-                Value::Number(n) => *n as u64,
-                Value::Size(s) => *s,
-                _ => return false,
-            };
-            compare_numbers(file.size as f64, target as f64, &condition.operator)
-        }
-        "name" => {
-            if let Value::String(s) = &condition.value {
-                compare_strings(&file.name, s, &condition.operator)
-            } else {
-                false
-            }
+    let mut existing = absolute.as_path();
+    let mut missing = Vec::new();
+    let mut normalized = loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            break canonical;
         }
-        "extension" | "ext" => {
-            if let Value::String(s) = &condition.value {
-                compare_strings(&file.extension, s, &condition.operator)
-            } else {
-                false
+        match (existing.parent(), existing.components().next_back()) {
+            (Some(parent), Some(last)) => {
+                missing.push(last);
+                existing = parent;
             }
+            _ => break PathBuf::new(),
         }
-        _ => true,
-    }
-}
+    };
 
-fn compare_numbers(left: f64, right: f64, op: &CompareOp) -> bool {
-    match op {
-        CompareOp::Equal => (left - right).abs() < f64::EPSILON,
-        CompareOp::NotEqual => (left - right).abs() >= f64::EPSILON,
-        CompareOp::GreaterThan => left > right,
-        CompareOp::GreaterThanOrEqual => left >= right,
-        CompareOp::LessThan => left < right,
-        CompareOp::LessThanOrEqual => left <= right,
-        _ => false,
-    }
-}
-
-fn compare_strings(left: &str, right: &str, op: &CompareOp) -> bool {
-    match op {
-        CompareOp::Equal => left.eq_ignore_ascii_case(right),
-        CompareOp::NotEqual => !left.eq_ignore_ascii_case(right),
-        CompareOp::Like => {
-            let pattern = right.replace('%', ".*");
-            regex::Regex::new(&format!("(?i)^{}$", pattern))
-                .map(|r| r.is_match(left))
-                .unwrap_or(false)
+    for component in missing.into_iter().rev() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
         }
-        CompareOp::Contains => left.to_lowercase().contains(&right.to_lowercase()),
-        _ => false,
     }
+    normalized
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::{CompareOp, Value};
     use std::fs::File;
-    use std::io::Write;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_protected_path_is_normalized() {
+        assert!(is_protected_path(Path::new("/etc/hosts")));
+        assert!(is_protected_path(Path::new("/tmp/../etc/arta-new")));
+        assert!(is_protected_path(Path::new("/tmp/missing/../../usr/x")));
+        assert!(!is_protected_path(Path::new("/etcetera/x")));
+        assert!(!is_protected_path(Path::new("/usr/../tmp/x")));
+
+        #[cfg(unix)]
+        {
+            let temp_dir = TempDir::new().unwrap();
+            let link = temp_dir.path().join("config");
+            std::os::unix::fs::symlink("/etc", &link).unwrap();
+            assert!(is_protected_path(&link.join("arta-new")));
+            assert!(write_file(&link.join("arta-new"), "x", false, true).is_err());
+        }
+    }
+
+    #[test]
+    fn test_write_file_dry_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("flag");
+
+        let result = write_file(&path, "done\n", false, true).unwrap();
+        assert!(result.dry_run);
+        assert_eq!(result.affected_bytes, 5);
+        assert!(result.details[0].starts_with("Would write 5 bytes"));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_write_file_truncates_and_appends() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("flag");
+        fs::write(&path, "old contents\n").unwrap();
+
+        write_file(&path, "one\n", false, false).unwrap();
+        write_file(&path, "two\n", true, false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+
+        assert!(write_file(Path::new("/etc/arta-test"), "x", false, true).is_err());
+    }
+
     #[test]
     fn test_delete_files_dry_run() {
         let temp_dir = TempDir::new().unwrap();
@@ -207,9 +312,10 @@ mod tests {
         };
 
         let result = delete_files(
-            temp_dir.path().to_str().unwrap(),
+            temp_dir.path(),
             Some(&where_clause),
             true, // dry_run
+            false,
         )
         .unwrap();
 
@@ -220,16 +326,114 @@ mod tests {
         assert!(file_path.exists());
     }
 
+    #[test]
+    fn test_delete_files_path_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        File::create(temp_dir.path().join("cache_a.tmp")).unwrap();
+        File::create(temp_dir.path().join("keep.tmp")).unwrap();
+
+        let where_clause = WhereClause {
+            conditions: vec![crate::parser::ConditionExpr {
+                condition: crate::parser::Condition {
+                    field: "path".to_string(),
+                    operator: CompareOp::Contains,
+                    value: Value::String("cache".to_string()),
+                },
+                next: None,
+            }],
+        };
+
+        let result = delete_files(temp_dir.path(), Some(&where_clause), true, false).unwrap();
+        assert_eq!(result.affected_count, 1);
+        assert!(result.details[0].contains("cache_a.tmp"));
+    }
+
+    #[test]
+    fn test_delete_files_extension_filters() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["a.log", "b.LOG", "c.logs", "d.txt"] {
+            File::create(temp_dir.path().join(name)).unwrap();
+        }
+        let delete = |operator: CompareOp, pattern: &str| {
+            let where_clause = WhereClause {
+                conditions: vec![crate::parser::ConditionExpr {
+                    condition: crate::parser::Condition {
+                        field: "extension".to_string(),
+                        operator,
+                        value: Value::String(pattern.to_string()),
+                    },
+                    next: None,
+                }],
+            };
+            delete_files(temp_dir.path(), Some(&where_clause), true, false)
+                .unwrap()
+                .affected_count
+        };
+
+        assert_eq!(delete(CompareOp::Like, "log"), 2);
+        assert_eq!(delete(CompareOp::Like, "log%"), 3);
+        assert_eq!(delete(CompareOp::Contains, "OG"), 3);
+        assert_eq!(delete(CompareOp::Matches, "^log$"), 1);
+    }
+
+    #[test]
+    fn test_delete_rejects_unknown_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("keep.txt");
+        File::create(&file_path).unwrap();
+
+        let where_clause = WhereClause {
+            conditions: vec![crate::parser::ConditionExpr {
+                condition: crate::parser::Condition {
+                    field: "sise".to_string(),
+                    operator: CompareOp::GreaterThan,
+                    value: Value::Number(0.0),
+                },
+                next: None,
+            }],
+        };
+
+        let err = delete_files(temp_dir.path(), Some(&where_clause), false, false).unwrap_err();
+        assert!(err.to_string().contains("sise (did you mean size?)"));
+        assert!(file_path.exists());
+    }
+
     #[test]
     fn test_delete_requires_where_clause() {
         let temp_dir = TempDir::new().unwrap();
 
         let result = delete_files(
-            temp_dir.path().to_str().unwrap(),
+            temp_dir.path(),
             None, // No WHERE clause
             false,
+            false,
         );
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_delete_files_explain_matches() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut file = File::create(temp_dir.path().join("data.bin")).unwrap();
+        writeln!(file, "payload").unwrap();
+
+        let where_clause = match crate::parser::parse_command(&format!(
+            "DELETE FILES FROM {} WHERE size > 0 OR extension = \"x\"",
+            temp_dir.path().display()
+        ))
+        .unwrap()
+        {
+            crate::parser::Command::Action(crate::parser::ActionCommand::DeleteFiles(cmd)) => {
+                cmd.where_clause.unwrap()
+            }
+            _ => panic!("Expected DELETE FILES command"),
+        };
+
+        let result = delete_files(temp_dir.path(), Some(&where_clause), true, true).unwrap();
+
+        assert_eq!(result.affected_count, 1);
+        assert!(result.details[0].contains("matched: size > 0"));
+        assert!(!result.details[0].contains("extension"));
+    }
 }
@@ -3,15 +3,84 @@
 pub mod files;
 pub mod process;
 
-pub use files::delete_files;
-pub use process::kill_processes;
+pub use files::{delete_files, delete_listed, write_file};
+pub use process::{kill_listed, kill_processes, kill_processes_with};
 
+use crate::output::format_util::fmt_size;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActionResult {
     pub action_type: String,
     pub affected_count: usize,
+    /// Total size of the affected files (0 for non-file actions)
+    #[serde(default)]
+    pub affected_bytes: u64,
     pub dry_run: bool,
     pub details: Vec<String>,
 }
+
+/// Totals across several action results, e.g. all actions of a dry run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActionSummary {
+    pub files: usize,
+    pub bytes: u64,
+    pub processes: usize,
+    pub writes: usize,
+    pub written_bytes: u64,
+}
+
+impl ActionSummary {
+    pub fn add(&mut self, action: &ActionResult) {
+        match action.action_type.as_str() {
+            "DELETE FILES" => {
+                self.files += action.affected_count;
+                self.bytes += action.affected_bytes;
+            }
+            "KILL PROCESS" => self.processes += action.affected_count,
+            "WRITE FILE" => {
+                self.writes += action.affected_count;
+                self.written_bytes += action.affected_bytes;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files == 0 && self.processes == 0 && self.writes == 0
+    }
+}
+
+/// e.g. "Would delete 12 files (3.4 GB), would kill 2 processes"
+impl std::fmt::Display for ActionSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural =
+            |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+
+        let mut parts = Vec::new();
+        if self.files > 0 {
+            parts.push(format!(
+                "delete {} ({})",
+                plural(self.files, "file", "files"),
+                fmt_size(self.bytes)
+            ));
+        }
+        if self.processes > 0 {
+            parts.push(format!(
+                "kill {}",
+                plural(self.processes, "process", "processes")
+            ));
+        }
+        if self.writes > 0 {
+            parts.push(format!(
+                "write {} to {}",
+                fmt_size(self.written_bytes),
+                plural(self.writes, "file", "files")
+            ));
+        }
+        if parts.is_empty() {
+            return write!(f, "No changes would be made");
+        }
+        write!(f, "Would {}", parts.join(", would "))
+    }
+}
@@ -1,36 +1,87 @@
 //! Process kill action
 
 use crate::engine::actions::ActionResult;
+use crate::engine::confirm::Confirmer;
+use crate::engine::fields::known_fields;
+use crate::engine::filter;
+use crate::engine::provider::{SysinfoProvider, SystemProvider};
+use crate::engine::queries::{query_processes_with, ProcessInfo};
 use crate::error::{ArtaError, Result};
-use crate::parser::{CompareOp, Value, WhereClause};
+use crate::output::format_util::{fmt_percent, fmt_size};
+use crate::parser::{FieldList, QueryTarget, WhereClause};
 use sysinfo::{Pid, Signal, System};
 
 const MAX_PROCESSES_PER_OPERATION: usize = 10;
 
-pub fn kill_processes(where_clause: &WhereClause, dry_run: bool) -> Result<ActionResult> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
+/// Kill processes matching a WHERE clause
+///
+/// With `full_names`, `name` conditions also match the untruncated name
+/// (see `full_process_name`). With `confirm`, each match is asked about
+/// before it is killed and declined ones are skipped; dry runs never ask.
+pub fn kill_processes(
+    where_clause: &WhereClause,
+    dry_run: bool,
+    full_names: bool,
+    confirm: Option<&Confirmer>,
+) -> Result<ActionResult> {
+    kill_processes_with(
+        &mut SysinfoProvider::default(),
+        where_clause,
+        dry_run,
+        full_names,
+        confirm,
+    )
+}
 
-    let mut matched_processes: Vec<ProcessMatch> = Vec::new();
+/// [`kill_processes`] with the processes read from a (possibly shared) provider
+///
+/// Matches are found exactly as `SELECT PROCESS * WHERE ...` finds them, after
+/// every WHERE field has been checked against the PROCESS fields.
+pub fn kill_processes_with(
+    provider: &mut dyn SystemProvider,
+    where_clause: &WhereClause,
+    dry_run: bool,
+    full_names: bool,
+    confirm: Option<&Confirmer>,
+) -> Result<ActionResult> {
+    filter::check_patterns(where_clause)?;
+    filter::check_fields(
+        where_clause,
+        "KILL PROCESS",
+        known_fields(QueryTarget::Process),
+    )?;
 
-    for (pid, process) in sys.processes() {
-        let proc_info = ProcessMatch {
-            pid: pid.as_u32(),
-            name: process.name().to_string(),
-            cpu: process.cpu_usage(),
-            memory: process.memory(),
-        };
+    let processes = query_processes_with(
+        provider,
+        &FieldList::All,
+        Some(where_clause),
+        full_names,
+        None,
+        None,
+    )?;
+    kill_listed(&processes, dry_run, confirm)
+}
 
-        if matches_process_where_clause(&proc_info, where_clause) {
-            // Don't allow killing system-critical processes
-            if is_protected_process(&proc_info.name) {
-                continue;
-            }
-            matched_processes.push(proc_info);
-        }
-    }
+/// Kill the given processes, e.g. the rows of `SELECT PROCESS ... | KILL`
+///
+/// Protected processes are skipped under either name, and the KILL PROCESS
+/// limit applies. `confirm` works as for [`kill_processes`].
+pub fn kill_listed(
+    processes: &[ProcessInfo],
+    dry_run: bool,
+    confirm: Option<&Confirmer>,
+) -> Result<ActionResult> {
+    // Don't allow killing system-critical processes
+    let matched_processes: Vec<&ProcessInfo> = processes
+        .iter()
+        .filter(|process| !is_protected(process))
+        .collect();
+
+    check_kill_limit(&matched_processes)?;
+    Ok(kill_matched(&matched_processes, dry_run, confirm))
+}
 
-    // Safety limit
+fn check_kill_limit(matched_processes: &[&ProcessInfo]) -> Result<()> {
     if matched_processes.len() > MAX_PROCESSES_PER_OPERATION {
         return Err(ArtaError::SecurityError(format!(
             "Too many processes to kill ({} > {}). Please use a more specific WHERE clause.",
@@ -38,13 +89,25 @@ pub fn kill_processes(where_clause: &WhereClause, dry_run: bool) -> Result<Actio
             MAX_PROCESSES_PER_OPERATION
         )));
     }
+    Ok(())
+}
 
+fn kill_matched(
+    matched_processes: &[&ProcessInfo],
+    dry_run: bool,
+    confirm: Option<&Confirmer>,
+) -> ActionResult {
     let mut details = Vec::new();
     let mut killed_count = 0;
 
-    for proc in &matched_processes {
+    for proc in matched_processes {
         if dry_run {
             details.push(format!("Would kill: {} (PID {})", proc.name, proc.pid));
+        } else if confirm.is_some_and(|confirm| !confirm.ask(&kill_question(proc))) {
+            details.push(format!(
+                "Skipped (declined): {} (PID {})",
+                proc.name, proc.pid
+            ));
         } else {
             // Re-get the process from a fresh system snapshot
             let mut fresh_sys = System::new_all();
@@ -70,24 +133,37 @@ pub fn kill_processes(where_clause: &WhereClause, dry_run: bool) -> Result<Actio
         details.push("No matching processes found".to_string());
     }
 
-    Ok(ActionResult {
+    ActionResult {
         action_type: "KILL PROCESS".to_string(),
         affected_count: if dry_run {
             matched_processes.len()
         } else {
             killed_count
         },
+        affected_bytes: 0,
         dry_run,
         details,
-    })
+    }
+}
+
+/// Question asked about a match under `CONFIRM EACH`
+fn kill_question(proc: &ProcessInfo) -> String {
+    format!(
+        "Kill {} (PID {}, {} CPU, {})?",
+        proc.name,
+        proc.pid,
+        fmt_percent(proc.cpu.into()),
+        fmt_size(proc.memory)
+    )
 }
 
-#[derive(Debug)]
-struct ProcessMatch {
-    pid: u32,
-    name: String,
-    cpu: f32,
-    memory: u64,
+/// System-critical under either name
+fn is_protected(process: &ProcessInfo) -> bool {
+    is_protected_process(&process.name)
+        || process
+            .name_full
+            .as_deref()
+            .is_some_and(is_protected_process)
 }
 
 fn is_protected_process(name: &str) -> bool {
@@ -107,82 +183,11 @@ fn is_protected_process(name: &str) -> bool {
         .any(|p| name.to_lowercase().contains(&p.to_lowercase()))
 }
 
-fn matches_process_where_clause(proc: &ProcessMatch, where_clause: &WhereClause) -> bool {
-    for condition_expr in &where_clause.conditions {
-        if !matches_process_condition(proc, &condition_expr.condition) {
-            return false;
-        }
-    }
-    true
-}
-
-fn matches_process_condition(proc: &ProcessMatch, condition: &crate::parser::Condition) -> bool {
-    let field = condition.field.to_lowercase();
-
-    match field.as_str() {
-        "pid" => {
-            if let Value::Number(n) = &condition.value {
-                compare_numbers(proc.pid as f64, *n, &condition.operator)
-            } else {
-                false
-            }
-        }
-        "name" => {
-            if let Value::String(s) = &condition.value {
-                compare_strings(&proc.name, s, &condition.operator)
-            } else {
-                false
-            }
-        }
-        "cpu" => {
-            if let Value::Number(n) = &condition.value {
-                compare_numbers(proc.cpu as f64, *n, &condition.operator)
-            } else {
-                false
-            }
-        }
-        "memory" => {
-            let target = match &condition.value {
-                Value::Number(n) => *n as u64,
-                Value::Size(s) => *s,
-                _ => return false,
-            };
-            compare_numbers(proc.memory as f64, target as f64, &condition.operator)
-        }
-        _ => true,
-    }
-}
-
-fn compare_numbers(left: f64, right: f64, op: &CompareOp) -> bool {
-    match op {
-        CompareOp::Equal => (left - right).abs() < f64::EPSILON,
-        CompareOp::NotEqual => (left - right).abs() >= f64::EPSILON,
-        CompareOp::GreaterThan => left > right,
-        CompareOp::GreaterThanOrEqual => left >= right,
-        CompareOp::LessThan => left < right,
-        CompareOp::LessThanOrEqual => left <= right,
-        _ => false,
-    }
-}
-
-fn compare_strings(left: &str, right: &str, op: &CompareOp) -> bool {
-    match op {
-        CompareOp::Equal => left.eq_ignore_ascii_case(right),
-        CompareOp::NotEqual => !left.eq_ignore_ascii_case(right),
-        CompareOp::Like => {
-            let pattern = right.replace('%', ".*");
-            regex::Regex::new(&format!("(?i)^{}$", pattern))
-                .map(|r| r.is_match(left))
-                .unwrap_or(false)
-        }
-        CompareOp::Contains => left.to_lowercase().contains(&right.to_lowercase()),
-        _ => false,
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::engine::queries::process::matches_condition;
+    use crate::parser::{CompareOp, Value};
 
     #[test]
     fn test_scan_processes_with_filter() {
@@ -198,11 +203,31 @@ mod tests {
             }],
         };
 
-        let result = kill_processes(&where_clause, true).unwrap();
+        let result = kill_processes(&where_clause, true, true, None).unwrap();
         assert!(result.dry_run);
         assert_eq!(result.affected_count, 0);
     }
 
+    #[test]
+    fn test_kill_rejects_unknown_fields() {
+        let where_clause = WhereClause {
+            conditions: vec![crate::parser::ConditionExpr {
+                condition: crate::parser::Condition {
+                    field: "cpy".to_string(),
+                    operator: CompareOp::GreaterThan,
+                    value: Value::Number(10.0),
+                },
+                next: None,
+            }],
+        };
+
+        // Even a dry run refuses instead of listing every process
+        let err = kill_processes(&where_clause, true, true, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unknown KILL PROCESS field in WHERE: cpy"));
+    }
+
     #[test]
     fn test_kill_dry_run_no_matches() {
         let where_clause = WhereClause {
@@ -216,10 +241,92 @@ mod tests {
             }],
         };
 
-        let result = kill_processes(&where_clause, true).unwrap();
+        let result = kill_processes(&where_clause, true, true, None).unwrap();
         assert_eq!(result.affected_count, 0);
     }
 
+    fn process(pid: u32, name: &str, name_full: Option<&str>) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: name.to_string(),
+            name_full: name_full.map(str::to_string),
+            cpu: 0.0,
+            memory: 0,
+            status: "running".to_string(),
+            threads: None,
+            open_files: None,
+            user: None,
+            uid: None,
+        }
+    }
+
+    #[test]
+    fn test_kill_matches_full_name() {
+        let proc = process(42, "my-long-service", Some("my-long-service-worker"));
+        let condition = crate::parser::Condition {
+            field: "name".to_string(),
+            operator: CompareOp::Equal,
+            value: Value::String("My-Long-Service-Worker".to_string()),
+        };
+
+        assert_eq!(matches_condition(&proc, &condition, true), Some(true));
+        assert_eq!(matches_condition(&proc, &condition, false), Some(false));
+    }
+
+    #[cfg(unix)]
+    fn sleeper() -> std::process::Child {
+        std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap()
+    }
+
+    #[cfg(unix)]
+    fn sleeper_match(child: &std::process::Child) -> ProcessInfo {
+        process(child.id(), "sleep", None)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_confirm_each() {
+        let mut approved = sleeper();
+        let mut declined = sleeper();
+        let approved_pid = approved.id();
+        let sleepers = [sleeper_match(&approved), sleeper_match(&declined)];
+        let matches: Vec<&ProcessInfo> = sleepers.iter().collect();
+
+        let asked = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let confirm = {
+            let asked = asked.clone();
+            Confirmer::new(move |question| {
+                asked.lock().unwrap().push(question.to_string());
+                question.contains(&format!("PID {},", approved_pid))
+            })
+        };
+
+        let result = kill_matched(&matches, false, Some(&confirm));
+        assert_eq!(result.affected_count, 1);
+        assert_eq!(
+            result.details,
+            [
+                format!("Killed: sleep (PID {})", approved.id()),
+                format!("Skipped (declined): sleep (PID {})", declined.id()),
+            ]
+        );
+        assert_eq!(asked.lock().unwrap().len(), 2);
+
+        // The approved process got SIGTERM, the declined one is still running
+        assert!(!approved.wait().unwrap().success());
+        assert!(declined.try_wait().unwrap().is_none());
+        declined.kill().unwrap();
+        declined.wait().unwrap();
+
+        // Dry runs list every match without asking
+        let result = kill_matched(&matches, true, Some(&confirm));
+        assert_eq!(result.affected_count, 2);
+        assert_eq!(asked.lock().unwrap().len(), 2);
+    }
+
     #[test]
     fn test_protected_processes() {
         assert!(is_protected_process("systemd"));
@@ -0,0 +1,231 @@
+//! Audit log of executed actions (`--audit-log`)
+//!
+//! Every DELETE, KILL and WRITE appends one JSON line to the log, dry runs
+//! and failed actions included. The log is opened before the action runs, so
+//! an action that cannot be audited never happens. `SHOW AUDIT [n]` reads the
+//! last entries back (default
+//! [`DEFAULT_AUDIT_ENTRIES`](crate::parser::ast::DEFAULT_AUDIT_ENTRIES)).
+
+use crate::engine::actions::ActionResult;
+use crate::error::{ArtaError, Result};
+use crate::output::DisplayZone;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One line of the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// ISO-8601; written in UTC, shown in the display zone
+    pub timestamp: String,
+    pub action: String,
+    pub affected_count: usize,
+    #[serde(default)]
+    pub affected_bytes: u64,
+    pub dry_run: bool,
+    #[serde(default)]
+    pub details: Vec<String>,
+    /// Why the action failed; whatever it did before failing is not known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn from_result(result: &ActionResult) -> Self {
+        Self {
+            timestamp: DisplayZone::Utc.rfc3339(Utc::now()),
+            action: result.action_type.clone(),
+            affected_count: result.affected_count,
+            affected_bytes: result.affected_bytes,
+            dry_run: result.dry_run,
+            details: result.details.clone(),
+            error: None,
+        }
+    }
+
+    /// Entry for an action that returned an error instead of a result
+    pub fn failed(action: &str, dry_run: bool, error: &ArtaError) -> Self {
+        Self {
+            timestamp: DisplayZone::Utc.rfc3339(Utc::now()),
+            action: action.to_string(),
+            affected_count: 0,
+            affected_bytes: 0,
+            dry_run,
+            details: Vec::new(),
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+/// An audit log opened for appending
+#[derive(Debug)]
+pub struct AuditWriter {
+    path: PathBuf,
+    file: File,
+}
+
+impl AuditWriter {
+    /// Open the log at `path`, creating the file if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| write_error(path, e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    /// Append `entry` as one JSON line
+    pub fn record(&mut self, entry: &AuditEntry) -> Result<()> {
+        let line =
+            serde_json::to_string(entry).map_err(|e| ArtaError::ExecutionError(e.to_string()))?;
+        writeln!(self.file, "{}", line).map_err(|e| write_error(&self.path, e))
+    }
+}
+
+fn write_error(path: &Path, e: std::io::Error) -> ArtaError {
+    ArtaError::ExecutionError(format!("Cannot write audit log {}: {}", path.display(), e))
+}
+
+/// The last entries of an audit log, oldest first, for SHOW AUDIT
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditLog {
+    pub path: String,
+    pub entries: Vec<AuditEntry>,
+}
+
+/// Append `entry` to the log at `path`, creating the file if needed
+pub fn append_entry(path: &Path, entry: &AuditEntry) -> Result<()> {
+    AuditWriter::open(path)?.record(entry)
+}
+
+/// The last `count` entries of the log at `path`, with timestamps in `zone`
+///
+/// A missing log has no entries.
+pub fn read_last(path: &Path, count: usize, zone: DisplayZone) -> Result<AuditLog> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(ArtaError::ExecutionError(format!(
+                "Cannot read audit log {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    };
+
+    let lines: Vec<(usize, &str)> = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .collect();
+    let entries = lines[lines.len().saturating_sub(count)..]
+        .iter()
+        .map(|(i, line)| {
+            let mut entry: AuditEntry = serde_json::from_str(line).map_err(|e| {
+                ArtaError::ExecutionError(format!(
+                    "Invalid audit log entry at {}:{}: {}",
+                    path.display(),
+                    i + 1,
+                    e
+                ))
+            })?;
+            if let Ok(instant) = DateTime::parse_from_rfc3339(&entry.timestamp) {
+                entry.timestamp = zone.rfc3339(instant.with_timezone(&Utc));
+            }
+            Ok(entry)
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(AuditLog {
+        path: path.display().to_string(),
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn action(action_type: &str, affected_count: usize) -> ActionResult {
+        ActionResult {
+            action_type: action_type.to_string(),
+            affected_count,
+            affected_bytes: 0,
+            dry_run: false,
+            details: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_append_and_read_last() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.ndjson");
+
+        for (i, name) in ["DELETE FILES", "KILL PROCESS", "WRITE FILE"]
+            .iter()
+            .enumerate()
+        {
+            append_entry(&path, &AuditEntry::from_result(&action(name, i))).unwrap();
+        }
+
+        let log = read_last(&path, 2, DisplayZone::Utc).unwrap();
+        let actions: Vec<&str> = log.entries.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, ["KILL PROCESS", "WRITE FILE"]);
+        assert_eq!(log.entries[1].affected_count, 2);
+        assert!(log.entries[1].timestamp.ends_with("+00:00"));
+        assert_eq!(
+            read_last(&path, 10, DisplayZone::Utc)
+                .unwrap()
+                .entries
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_failed_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.ndjson");
+
+        let error = ArtaError::SecurityError("Too many files".to_string());
+        let mut writer = AuditWriter::open(&path).unwrap();
+        writer
+            .record(&AuditEntry::failed("DELETE FILES", false, &error))
+            .unwrap();
+
+        let log = read_last(&path, 1, DisplayZone::Utc).unwrap();
+        assert_eq!(log.entries[0].action, "DELETE FILES");
+        assert!(log.entries[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Too many files"));
+
+        // An unwritable log is reported before anything is recorded
+        assert!(AuditWriter::open(temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_missing_and_invalid_logs() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.ndjson");
+        assert!(read_last(&path, 5, DisplayZone::Utc)
+            .unwrap()
+            .entries
+            .is_empty());
+
+        std::fs::write(&path, "not json\n").unwrap();
+        let err = read_last(&path, 5, DisplayZone::Utc)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("audit.ndjson:1"), "{}", err);
+    }
+}
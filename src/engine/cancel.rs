@@ -0,0 +1,106 @@
+//! Cooperative cancellation for long-running queries
+//!
+//! Loops that can take a while (directory scans, repeated sampling) check a
+//! `CancellationToken` and stop with a "cancelled" error once it is set.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use crate::error::{ArtaError, Result};
+
+/// Shared flag that asks running work to stop
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process-wide token that Ctrl+C cancels
+    ///
+    /// `ctrlc` accepts only one handler per process, so every caller shares
+    /// this token; the handler is installed on first use. Each call resets
+    /// the token, so an earlier Ctrl+C does not cancel new work.
+    pub fn ctrl_c() -> Result<Self> {
+        // Initialized exactly once, even when several threads ask at the same time
+        static TOKEN: OnceLock<std::result::Result<CancellationToken, String>> = OnceLock::new();
+
+        let token = TOKEN
+            .get_or_init(|| {
+                let token = CancellationToken::new();
+                let handler_token = token.clone();
+                ctrlc::set_handler(move || handler_token.cancel())
+                    .map(|_| token)
+                    .map_err(|e| e.to_string())
+            })
+            .clone()
+            .map_err(|e| {
+                ArtaError::ExecutionError(format!("Failed to set Ctrl+C handler: {}", e))
+            })?;
+        token.reset();
+        Ok(token)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Fail with a "cancelled" error if the token has been set
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(ArtaError::ExecutionError("cancelled".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Check an optional token, for functions where cancellation is opt-in
+pub fn check_cancelled(token: Option<&CancellationToken>) -> Result<()> {
+    token.map_or(Ok(()), CancellationToken::check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_state() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(check_cancelled(Some(&token)).is_err());
+        assert!(check_cancelled(None).is_ok());
+
+        token.reset();
+        assert!(!clone.is_cancelled());
+    }
+
+    #[test]
+    fn test_ctrl_c_handler_installed_once() {
+        // The token is shared by the whole test process, so it is compared
+        // rather than cancelled
+        let tokens: Vec<CancellationToken> = (0..4)
+            .map(|_| std::thread::spawn(CancellationToken::ctrl_c))
+            .map(|handle| handle.join().unwrap().unwrap())
+            .collect();
+
+        for token in &tokens[1..] {
+            assert!(Arc::ptr_eq(&token.cancelled, &tokens[0].cancelled));
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Yes/no prompts for actions that ask before each item (`CONFIRM EACH`)
+//!
+//! The default asks on the terminal; embedders and tests can answer with a
+//! callback instead via `ExecutionContext::with_confirm`.
+
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+/// Function answering a yes/no question
+pub type ConfirmFn = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Answers per-item confirmation questions
+#[derive(Clone)]
+pub struct Confirmer {
+    ask: ConfirmFn,
+}
+
+impl Confirmer {
+    pub fn new<F>(ask: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        Self { ask: Arc::new(ask) }
+    }
+
+    /// Ask `question` on stderr and read the answer from stdin
+    pub fn stdin() -> Self {
+        Self::new(prompt_stdin)
+    }
+
+    pub fn ask(&self, question: &str) -> bool {
+        (self.ask)(question)
+    }
+}
+
+impl Default for Confirmer {
+    fn default() -> Self {
+        Self::stdin()
+    }
+}
+
+impl std::fmt::Debug for Confirmer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Confirmer")
+    }
+}
+
+/// Prompt with `[y/N]`; anything but y/yes, including end of input, is a no
+fn prompt_stdin(question: &str) -> bool {
+    let mut stderr = std::io::stderr();
+    let _ = write!(stderr, "{} [y/N] ", question);
+    let _ = stderr.flush();
+
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    is_yes(&answer)
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answers() {
+        assert!(is_yes("y\n"));
+        assert!(is_yes(" YES "));
+        assert!(!is_yes(""));
+        assert!(!is_yes("n"));
+        assert!(!is_yes("yep"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Line diffs between two files, for `DIFF <old> <new>`
+//!
+//! Hunks follow the unified diff layout: each covers a run of changes plus up
+//! to `CONTEXT_LINES` unchanged lines on either side. A changed line shows up
+//! as a removal followed by an addition.
+
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+/// Unchanged lines kept around each change
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffInfo {
+    pub old_path: String,
+    pub new_path: String,
+    /// Lines only in the new file
+    pub added: usize,
+    /// Lines only in the old file
+    pub removed: usize,
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl DiffInfo {
+    /// Both files have the same lines
+    pub fn is_identical(&self) -> bool {
+        self.hunks.is_empty()
+    }
+}
+
+/// A run of changes, with line ranges as in a `@@ -a,b +c,d @@` header
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+impl DiffLineKind {
+    /// Prefix of the line in unified diff output
+    pub fn marker(self) -> char {
+        match self {
+            DiffLineKind::Context => ' ',
+            DiffLineKind::Added => '+',
+            DiffLineKind::Removed => '-',
+        }
+    }
+}
+
+/// Diff two texts line by line
+pub fn diff_text(old_path: &str, new_path: &str, old: &str, new: &str) -> DiffInfo {
+    let diff = TextDiff::from_lines(old, new);
+    let mut info = DiffInfo {
+        old_path: old_path.to_string(),
+        new_path: new_path.to_string(),
+        added: 0,
+        removed: 0,
+        hunks: Vec::new(),
+    };
+
+    for group in diff.grouped_ops(CONTEXT_LINES) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+
+        let mut lines = Vec::new();
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let kind = match change.tag() {
+                    ChangeTag::Equal => DiffLineKind::Context,
+                    ChangeTag::Insert => {
+                        info.added += 1;
+                        DiffLineKind::Added
+                    }
+                    ChangeTag::Delete => {
+                        info.removed += 1;
+                        DiffLineKind::Removed
+                    }
+                };
+                let text = change.value();
+                let text = text.strip_suffix('\n').unwrap_or(text);
+                let text = text.strip_suffix('\r').unwrap_or(text);
+                lines.push(DiffLine {
+                    kind,
+                    text: text.to_string(),
+                });
+            }
+        }
+
+        info.hunks.push(DiffHunk {
+            old_start: hunk_start(&old_range),
+            old_lines: old_range.len(),
+            new_start: hunk_start(&new_range),
+            new_lines: new_range.len(),
+            lines,
+        });
+    }
+
+    info
+}
+
+/// 1-based first line of a range; an empty range names the line before it
+fn hunk_start(range: &std::ops::Range<usize>) -> usize {
+    if range.is_empty() {
+        range.start
+    } else {
+        range.start + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_text() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nb\nC\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        let info = diff_text("old", "new", old, new);
+
+        assert_eq!((info.added, info.removed), (2, 1));
+        assert_eq!(info.hunks.len(), 2);
+
+        let first = &info.hunks[0];
+        assert_eq!(
+            (
+                first.old_start,
+                first.old_lines,
+                first.new_start,
+                first.new_lines
+            ),
+            (1, 6, 1, 6)
+        );
+        let changed: Vec<_> = first
+            .lines
+            .iter()
+            .filter(|l| l.kind != DiffLineKind::Context)
+            .map(|l| (l.kind.marker(), l.text.as_str()))
+            .collect();
+        assert_eq!(changed, [('-', "c"), ('+', "C")]);
+
+        let second = &info.hunks[1];
+        assert_eq!((second.old_start, second.old_lines), (8, 3));
+        assert_eq!(second.lines.last().unwrap().text, "k");
+    }
+
+    #[test]
+    fn test_identical_texts() {
+        let info = diff_text("a", "b", "same\n", "same\n");
+        assert!(info.is_identical());
+        assert_eq!((info.added, info.removed), (0, 0));
+    }
+}
@@ -2,14 +2,33 @@
 
 use crate::context::Context;
 use crate::engine::actions::*;
+use crate::engine::audit::{read_last, AuditEntry, AuditLog, AuditWriter};
+use crate::engine::cancel::{check_cancelled, CancellationToken};
+use crate::engine::confirm::Confirmer;
+use crate::engine::diff::{diff_text, DiffInfo};
+use crate::engine::fields::{
+    canonical_field, field_value, known_fields, sort_by_field, FieldSource, FieldValue,
+    ProjectedRow, Projection,
+};
+use crate::engine::filter::{self, check_fields, percent_of, resolve_percentages};
+use crate::engine::follow::FileTail;
+use crate::engine::ownership::{OwnerNames, Ownership};
+use crate::engine::queries::disk::{is_pseudo_filesystem, DiskEntry};
 use crate::engine::queries::*;
-use crate::error::{ArtaError, Result};
-use crate::output::OutputFormat;
+use crate::engine::registry::TargetRegistry;
+use crate::engine::repeat::{run_repeated, RepeatPlan};
+use crate::error::{did_you_mean, ArtaError, Result};
+use crate::output::format_util::fmt_size;
+use crate::output::json::format_json_compact;
+use crate::output::{DisplayZone, FormatterRegistry, OutputFormat, OutputSink, SharedWriter};
 use crate::parser::{
-    ActionCommand, Command, CompareOp, ContainerCommand, ContextCommand, ForLoop, IfCondition,
-    IfStatement, LetStatement, LetValue, LifeMonitor, PrintCommand, PrintExpr, QueryCommand,
-    QueryTarget, ShowTarget, Value,
+    ActionCommand, Command, CompareOp, ContainerCommand, ContextCommand, DiffCommand, ForLoop,
+    IfCondition, IfStatement, LabelBlock, LetStatement, LetValue, LifeMonitor, PipeSink,
+    PipelineCommand, PrintCommand, PrintExpr, Quantifier, QueryCommand, QueryTarget, ResetTarget,
+    ShowTarget, Value,
 };
+use chrono::{DateTime, Utc};
+use tracing::instrument;
 
 /// Execution context containing runtime configuration
 #[derive(Debug, Clone)]
@@ -18,6 +37,55 @@ pub struct ExecutionContext {
     pub allow_actions: bool,
     pub output_format: OutputFormat,
     pub verbose: bool,
+    /// Annotate dry-run action results with the conditions each item matched
+    pub explain_matches: bool,
+    /// Query targets registered by the embedding application
+    pub targets: TargetRegistry,
+    /// Read limits for CONTENT queries
+    pub content_limits: ContentLimits,
+    /// Stamp printed output with the wall-clock time
+    pub timestamps: bool,
+    /// Output formatters registered by the embedding application
+    pub formatters: FormatterRegistry,
+    /// Let process `name` conditions also match the untruncated `name_full`
+    pub full_process_names: bool,
+    /// Checked by long-running queries, which stop with a "cancelled" error once set
+    pub cancellation: Option<CancellationToken>,
+    /// Zone timestamps are rendered in; `None` picks one from the output format
+    pub timezone: Option<DisplayZone>,
+    /// Emit JSON sizes and percentages as `{"value": ..., "unit": ...}` objects
+    pub json_units: bool,
+    /// Fail on unknown fields in PROCESS, FILES, SERVICES and NETWORK WHERE
+    /// clauses instead of ignoring them (actions always do)
+    pub strict_fields: bool,
+    /// List pseudo filesystems (tmpfs, proc, overlay, ...) in DISK queries
+    pub all_mounts: bool,
+    /// NDJSON file every action is recorded in, read back by SHOW AUDIT
+    pub audit_log: Option<std::path::PathBuf>,
+    /// Decimal places of percentages in human and properties output
+    pub precision: usize,
+    /// Answers `CONFIRM EACH` questions; asks on the terminal by default
+    pub confirm: Confirmer,
+    /// Where EVERY, FOLLOW, scripts and LIFE print as they run; stdout by default
+    pub output: SharedWriter,
+}
+
+/// Read limits that keep CONTENT queries on pathological files bounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentLimits {
+    /// Longer lines are cut and marked `[truncated]`
+    pub max_line_bytes: usize,
+    /// Stop reading after this many bytes; the line count is then a lower bound
+    pub max_scan_bytes: u64,
+}
+
+impl Default for ContentLimits {
+    fn default() -> Self {
+        Self {
+            max_line_bytes: 64 * 1024,
+            max_scan_bytes: 1024 * 1024 * 1024,
+        }
+    }
 }
 
 impl Default for ExecutionContext {
@@ -27,15 +95,114 @@ impl Default for ExecutionContext {
             allow_actions: false,
             output_format: OutputFormat::Human,
             verbose: false,
+            explain_matches: false,
+            targets: TargetRegistry::default(),
+            content_limits: ContentLimits::default(),
+            timestamps: false,
+            formatters: FormatterRegistry::default(),
+            full_process_names: true,
+            cancellation: None,
+            timezone: None,
+            json_units: false,
+            strict_fields: false,
+            all_mounts: false,
+            audit_log: None,
+            precision: crate::output::format_util::DEFAULT_PRECISION,
+            confirm: Confirmer::default(),
+            output: SharedWriter::default(),
         }
     }
 }
 
+impl ExecutionContext {
+    /// Register a custom query target, e.g. `SELECT GPU *`
+    pub fn with_target<F>(mut self, name: &str, query: F) -> Self
+    where
+        F: Fn() -> Result<serde_json::Value> + Send + Sync + 'static,
+    {
+        self.targets.register(name, query);
+        self
+    }
+
+    /// Register a custom output formatter, selected with `--format custom:<name>`
+    pub fn with_formatter<F>(mut self, name: &str, format: F) -> Self
+    where
+        F: Fn(&ExecutionResult) -> String + Send + Sync + 'static,
+    {
+        self.formatters.register(name, format);
+        self
+    }
+
+    /// Record every action in an NDJSON audit log at `path`
+    pub fn with_audit_log(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.audit_log = Some(path.into());
+        self
+    }
+
+    /// Let long-running queries be stopped from another thread
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Answer `CONFIRM EACH` questions with a callback instead of a terminal prompt
+    pub fn with_confirm<F>(mut self, ask: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.confirm = Confirmer::new(ask);
+        self
+    }
+
+    /// Send output printed while commands run to `writer` instead of stdout
+    pub fn with_output<W: std::io::Write + Send + 'static>(mut self, writer: W) -> Self {
+        self.output = SharedWriter::new(writer);
+        self
+    }
+
+    /// Render every timestamp in `zone`, whatever the output format
+    pub fn with_timezone(mut self, zone: DisplayZone) -> Self {
+        self.timezone = Some(zone);
+        self
+    }
+
+    /// Zone timestamps are rendered in: the override, else UTC for JSON and local time otherwise
+    pub fn display_zone(&self) -> DisplayZone {
+        self.timezone
+            .unwrap_or_else(|| DisplayZone::default_for(&self.output_format))
+    }
+}
+
 /// Result of command execution
 #[derive(Debug, Clone)]
 pub struct ExecutionResult {
     pub data: ResultData,
     pub message: Option<String>,
+    /// LIMIT of the query that produced the rows; human output then lists
+    /// every row instead of cutting long PROCESS and FILES lists short
+    pub limit: Option<usize>,
+}
+
+impl ExecutionResult {
+    pub fn new(data: ResultData) -> Self {
+        Self {
+            data,
+            message: None,
+            limit: None,
+        }
+    }
+
+    /// Attach a note shown alongside the data
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Record the query's LIMIT, see `limit`
+    pub fn with_limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -47,12 +214,32 @@ pub enum ResultData {
     System(SystemInfo),
     Battery(BatteryInfo),
     Processes(Vec<ProcessInfo>),
+    SelfProc(SelfInfo),
     Files(Vec<FileEntry>),
+    Services(Vec<ServiceInfo>),
+    /// SELECT with an explicit field list
+    Projection(Projection),
     Content(ContentInfo),
+    /// Counts from SELECT CONTENT COUNT
+    ContentStats(ContentStats),
+    /// Line diff from DIFF
+    Diff(DiffInfo),
+    /// Result of a registered custom target
+    Custom(CustomResult),
     ActionResult(ActionResult),
     ContextInfo(ContextInfo),
+    /// Build information from SHOW VERSION
+    Version(VersionInfo),
+    /// Execution settings from SHOW CONFIG
+    Config(ConfigInfo),
+    /// Recent actions from SHOW AUDIT
+    Audit(AuditLog),
     Explanation(String),
     Message(String),
+    /// PRINT values under JSON output, one per expression
+    Print(Vec<serde_json::Value>),
+    /// Outcome of a bare condition such as `SELECT CPU usage > 90`
+    Boolean(bool),
     /// Container operation result
     ContainerResult(ContainerResultInfo),
     /// Multiple results from loop execution
@@ -69,6 +256,117 @@ pub struct ContextInfo {
     pub folder_depth: usize,
     pub variables: Vec<(String, String)>,
     pub history: Vec<String>,
+    /// Folder stack from SHOW STACK, initial folder first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stack: Vec<String>,
+}
+
+/// Build information for SHOW VERSION
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct VersionInfo {
+    pub version: String,
+    pub git_sha: Option<String>,
+    /// Build date (UTC, `YYYY-MM-DD`)
+    pub build_date: Option<String>,
+    /// Cargo features compiled in
+    pub features: Vec<String>,
+}
+
+/// Settings of the running `ExecutionContext`, for SHOW CONFIG
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ConfigInfo {
+    pub dry_run: bool,
+    pub allow_actions: bool,
+    /// `human`, `json` or `custom:<name>`
+    pub output_format: String,
+    pub verbose: bool,
+    pub explain_matches: bool,
+    pub timestamps: bool,
+    pub full_process_names: bool,
+    /// Zone timestamps are rendered in (`utc`, `local` or an IANA name)
+    pub timezone: String,
+    pub json_units: bool,
+    pub strict_fields: bool,
+    pub all_mounts: bool,
+    pub audit_log: Option<String>,
+    pub precision: usize,
+    pub content_max_line_bytes: usize,
+    pub content_max_scan_bytes: u64,
+    /// Custom query targets, sorted
+    pub targets: Vec<String>,
+    /// Custom output formatters, sorted
+    pub formatters: Vec<String>,
+}
+
+impl ConfigInfo {
+    pub fn from_context(ctx: &ExecutionContext) -> Self {
+        let output_format = match &ctx.output_format {
+            OutputFormat::Human => "human".to_string(),
+            OutputFormat::Json => "json".to_string(),
+            OutputFormat::Properties => "props".to_string(),
+            OutputFormat::Csv => "csv".to_string(),
+            OutputFormat::Tsv => "tsv".to_string(),
+            OutputFormat::Custom(formatter) => format!("custom:{}", formatter.name),
+        };
+
+        Self {
+            dry_run: ctx.dry_run,
+            allow_actions: ctx.allow_actions,
+            output_format,
+            verbose: ctx.verbose,
+            explain_matches: ctx.explain_matches,
+            timestamps: ctx.timestamps,
+            full_process_names: ctx.full_process_names,
+            timezone: ctx.display_zone().to_string(),
+            json_units: ctx.json_units,
+            strict_fields: ctx.strict_fields,
+            all_mounts: ctx.all_mounts,
+            audit_log: ctx.audit_log.as_ref().map(|p| p.display().to_string()),
+            precision: ctx.precision,
+            content_max_line_bytes: ctx.content_limits.max_line_bytes,
+            content_max_scan_bytes: ctx.content_limits.max_scan_bytes,
+            targets: ctx
+                .targets
+                .names()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            formatters: ctx
+                .formatters
+                .names()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+}
+
+impl VersionInfo {
+    /// Information about the running build
+    pub fn current() -> Self {
+        let build_date = option_env!("ARTA_BUILD_TIMESTAMP")
+            .and_then(|ts| ts.parse::<i64>().ok())
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map(|dt| dt.format("%Y-%m-%d").to_string());
+
+        let mut features = Vec::new();
+        if cfg!(feature = "repl") {
+            features.push("repl".to_string());
+        }
+        if cfg!(feature = "ssh") {
+            features.push("ssh".to_string());
+        }
+        if cfg!(feature = "services") {
+            features.push("services".to_string());
+        }
+
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_sha: option_env!("ARTA_GIT_SHA").map(str::to_string),
+            build_date,
+            features,
+        }
+    }
 }
 
 /// Result of container operations
@@ -94,10 +392,42 @@ pub struct ContainerInfo {
 pub struct FileEntry {
     pub name: String,
     pub path: String,
+    /// For a symlink, the size of the link itself
     pub size: u64,
+    /// False for a symlink, even one pointing at a directory
     pub is_dir: bool,
-    pub modified: Option<String>,
+    #[serde(default)]
+    pub is_symlink: bool,
+    /// Where a symlink points, as stored in the link (the target may not exist)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symlink_target: Option<String>,
+    /// Last modification time; rendered in the display zone by human output
+    #[serde(
+        default,
+        serialize_with = "crate::output::timezone::serialize_rfc3339_opt"
+    )]
+    pub modified: Option<DateTime<Utc>>,
     pub extension: Option<String>,
+    /// Recursive size of a directory's contents (the file size for files),
+    /// only computed `WITH SIZES`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir_size: Option<u64>,
+    /// Owner's user name (Unix), only looked up when the query needs it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Owning group's name (Unix), looked up along with `owner`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Permission bits such as `rwxr-xr-x` (Unix), read along with `owner`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+}
+
+/// Value returned by a custom query target
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CustomResult {
+    pub target: String,
+    pub value: serde_json::Value,
 }
 
 /// Content information for CONTENT query
@@ -107,6 +437,50 @@ pub struct ContentInfo {
     pub lines: Vec<String>,
     pub total_lines: usize,
     pub file_size: u64,
+    /// Reading stopped at the scan limit, so `total_lines` is a lower bound
+    #[serde(default)]
+    pub scan_truncated: bool,
+    /// Line number of each entry of `lines` (not in JSON, where numbered
+    /// lines already show it)
+    #[serde(skip)]
+    pub line_numbers: Vec<usize>,
+    /// Whether `lines` carry a `   N: ` line number prefix
+    #[serde(skip)]
+    pub numbered: bool,
+}
+
+impl ContentInfo {
+    /// Each kept line as `(line_number, text)`, without the number prefix
+    ///
+    /// Results decoded from JSON have no line numbers; their lines are then
+    /// numbered in order.
+    pub fn rows(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.lines.iter().enumerate().map(|(i, line)| {
+            let number = self.line_numbers.get(i).copied().unwrap_or(i + 1);
+            let prefix = format!("{:>4}: ", number);
+            let text = match line.strip_prefix(&prefix) {
+                Some(text) if self.numbered => text,
+                _ => line.as_str(),
+            };
+            (number, text)
+        })
+    }
+}
+
+/// Counts from `SELECT CONTENT COUNT`, like `wc`
+///
+/// With a WHERE filter only matching lines are counted. A last line without
+/// a trailing newline still counts as a line, as in CONTENT's `total_lines`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ContentStats {
+    pub file_path: String,
+    pub lines: u64,
+    pub words: u64,
+    pub chars: u64,
+    pub bytes: u64,
+    /// Reading stopped at the scan limit, so the counts are lower bounds
+    #[serde(default)]
+    pub scan_truncated: bool,
 }
 
 /// Execute a parsed command (stateless - for single queries)
@@ -124,68 +498,261 @@ pub fn execute_command_with_context(
     match cmd {
         Command::Query(query) => execute_query(query, ctx, context),
         Command::Action(action) => execute_action(action, ctx, context),
-        Command::Context(context_cmd) => execute_context_command(context_cmd, context),
-        Command::Let(let_stmt) => execute_let(let_stmt, context),
+        Command::Context(context_cmd) => execute_context_command(context_cmd, ctx, context),
+        Command::Let(let_stmt) => execute_let(let_stmt, ctx, context),
         Command::For(for_loop) => execute_for_loop(for_loop, ctx, context),
         Command::If(if_stmt) => execute_if(if_stmt, ctx, context),
         Command::Life(life_monitor) => execute_life(life_monitor, ctx, context),
-        Command::Print(print_cmd) => execute_print(print_cmd, context),
+        Command::Label(label) => execute_label(label, ctx, context),
+        Command::Print(print_cmd) => execute_print(print_cmd, ctx, context),
         Command::Container(container_cmd) => execute_container_cmd(container_cmd, ctx, context),
         Command::Explain(inner) => execute_explain(inner, ctx),
+        Command::Diff(diff) => execute_diff(diff, ctx, context),
+        Command::Pipeline(pipeline) => execute_pipeline(pipeline, ctx, context),
+        // The script runner expands macros before anything executes
+        Command::Define(_) | Command::RunMacro(_) => Err(ArtaError::ExecutionError(
+            "DEFINE and RUN are only supported in scripts (arta run)".to_string(),
+        )),
+        Command::Check(condition) => Ok(ExecutionResult::new(ResultData::Boolean(
+            evaluate_if_condition(condition, ctx, context)?,
+        ))),
     }
 }
 
+#[instrument(level = "debug", skip_all, fields(target = %query.target))]
 fn execute_query(
     query: &QueryCommand,
-    _ctx: &ExecutionContext,
+    ctx: &ExecutionContext,
     context: &Context,
 ) -> Result<ExecutionResult> {
+    if query.follow {
+        return execute_follow(query, ctx, context);
+    }
+    if let Some(interval) = query.every {
+        return execute_every(query, interval, ctx, context);
+    }
+
+    let rows_supported = matches!(
+        query.target,
+        QueryTarget::Disk | QueryTarget::Files | QueryTarget::Process
+    );
+    if (query.limit.is_some() || query.order_by.is_some()) && !rows_supported {
+        return Err(ArtaError::ExecutionError(format!(
+            "ORDER BY and LIMIT are not supported for {} queries yet",
+            query.target
+        )));
+    }
+
     let data = match query.target {
-        QueryTarget::Cpu => ResultData::Cpu(query_cpu(&query.fields)?),
-        QueryTarget::Memory => ResultData::Memory(query_memory(&query.fields)?),
+        QueryTarget::Cpu => ResultData::Cpu(query_cpu_with(
+            context.system().lock().as_mut(),
+            &query.fields,
+        )?),
+        QueryTarget::Memory => ResultData::Memory(query_memory_with(
+            context.system().lock().as_mut(),
+            &query.fields,
+            context.memory_pressure(),
+        )?),
         QueryTarget::Disk => {
-            ResultData::Disk(query_disk(&query.fields, query.from_path.as_deref())?)
+            let from_path = query
+                .from_path
+                .as_deref()
+                .map(|p| resolve_fs_path(p, context))
+                .transpose()?;
+            let from_path = from_path.as_ref().map(|p| p.to_string_lossy());
+            let mut info = query_disk(
+                &query.fields,
+                from_path.as_deref(),
+                query.include_pseudo || ctx.all_mounts,
+            )?;
+            order_and_limit(&mut info.disks, query)?;
+            ResultData::Disk(info)
+        }
+        QueryTarget::Network => {
+            let where_clause = query
+                .where_clause
+                .as_ref()
+                .map(|wc| resolve_where_variables(wc, context));
+            check_strict_fields(where_clause.as_ref(), QueryTarget::Network, ctx)?;
+            ResultData::Network(query_network_with(
+                context.system().lock().as_mut(),
+                &query.fields,
+                where_clause.as_ref(),
+                query.include_down,
+            )?)
         }
-        QueryTarget::Network => ResultData::Network(query_network(&query.fields)?),
         QueryTarget::System => ResultData::System(query_system(&query.fields)?),
         QueryTarget::Battery => ResultData::Battery(query_battery(&query.fields)?),
         QueryTarget::Process => {
-            ResultData::Processes(query_processes(&query.fields, query.where_clause.as_ref())?)
+            let where_clause = query
+                .where_clause
+                .as_ref()
+                .map(|wc| {
+                    let wc = resolve_where_variables(wc, context);
+                    resolve_percentages(&wc, &["memory"], || memory_total(context))
+                })
+                .transpose()?;
+            check_strict_fields(where_clause.as_ref(), QueryTarget::Process, ctx)?;
+            let processes = query_processes_with(
+                context.system().lock().as_mut(),
+                &query.fields,
+                where_clause.as_ref(),
+                ctx.full_process_names,
+                query.order_by.as_ref(),
+                query.limit,
+            )?;
+            check_cancelled(ctx.cancellation.as_ref())?;
+            ResultData::Processes(processes)
         }
+        QueryTarget::SelfProc => ResultData::SelfProc(query_self_with(
+            context.system().lock().as_mut(),
+            &query.fields,
+        )?),
         QueryTarget::Files => {
             let path = query
                 .from_path
                 .as_deref()
-                .map(|p| {
-                    let resolved = resolve_variable_in_string(p, context);
-                    context.resolve_path(&resolved)
-                })
+                .map(|p| resolve_fs_path(p, context))
                 .transpose()?
                 .unwrap_or_else(|| context.current_folder().to_path_buf());
-            ResultData::Files(query_files(&path, query.where_clause.as_ref())?)
+            let where_clause = query
+                .where_clause
+                .as_ref()
+                .map(|wc| {
+                    let wc = resolve_where_variables(wc, context);
+                    resolve_percentages(&wc, FILE_SIZE_FIELDS, || filesystem_total(&path))
+                })
+                .transpose()?;
+            check_strict_fields(where_clause.as_ref(), QueryTarget::Files, ctx)?;
+            // Directory sizes are only walked when asked for, directly or via
+            // ORDER BY or WHERE
+            let sizes = query.with_sizes
+                || query
+                    .order_by
+                    .as_ref()
+                    .is_some_and(|o| o.field.eq_ignore_ascii_case("dir_size"))
+                || where_clause
+                    .as_ref()
+                    .is_some_and(|wc| filter::references_field(wc, QueryTarget::Files, "dir_size"));
+            let ownership = ctx.verbose || needs_ownership(query);
+            let mut files = query_files(
+                &path,
+                where_clause.as_ref(),
+                sizes,
+                ownership,
+                ctx.cancellation.as_ref(),
+            )?;
+            order_and_limit(&mut files, query)?;
+            ResultData::Files(files)
+        }
+        QueryTarget::Content if query.count => {
+            let file_path = content_path(query, context)?;
+            ResultData::ContentStats(content_stats(
+                &file_path,
+                query.where_clause.as_ref(),
+                &ctx.content_limits,
+            )?)
         }
         QueryTarget::Content => {
-            let file_path = if let Some(ref path) = query.from_path {
-                let resolved = resolve_variable_in_string(path, context);
-                context.resolve_path(&resolved)?
-            } else if let Some(file) = context.current_file() {
-                file.to_path_buf()
-            } else {
-                return Err(ArtaError::ExecutionError(
-                    "No file in context. Use 'ENTER FILE <path>' or 'SELECT CONTENT * FROM <path>'"
-                        .to_string(),
-                ));
-            };
-            ResultData::Content(query_content(&file_path, query.where_clause.as_ref())?)
+            let file_path = content_path(query, context)?;
+            ResultData::Content(query_content(
+                &file_path,
+                query.where_clause.as_ref(),
+                query.line_numbers,
+                &ctx.content_limits,
+            )?)
+        }
+        QueryTarget::Services => {
+            let where_clause = query
+                .where_clause
+                .as_ref()
+                .map(|wc| resolve_where_variables(wc, context));
+            check_strict_fields(where_clause.as_ref(), QueryTarget::Services, ctx)?;
+            ResultData::Services(query_services(&query.fields, where_clause.as_ref())?)
+        }
+        QueryTarget::Custom => {
+            let name = query.custom_target.clone().unwrap_or_default();
+            let target = ctx.targets.get(&name).ok_or_else(|| {
+                let mut known = QueryTarget::NAMES.to_vec();
+                known.extend(ctx.targets.names());
+                ArtaError::InvalidTarget(format!("{}{}", name, did_you_mean(&name, &known)))
+            })?;
+            ResultData::Custom(CustomResult {
+                target: name,
+                value: target()?,
+            })
         }
     };
 
-    Ok(ExecutionResult {
-        data,
-        message: None,
-    })
+    let data = match &query.fields {
+        crate::parser::FieldList::Fields(fields) => project_result(data, fields)?,
+        crate::parser::FieldList::All => data,
+    };
+
+    Ok(ExecutionResult::new(data).with_limit(query.limit))
+}
+
+/// FILES fields that `N%` resolves against the filesystem size for
+const FILE_SIZE_FIELDS: &[&str] = &["size", "dir_size"];
+
+/// FILES fields that need the owner lookup
+pub(crate) const OWNERSHIP_FIELDS: &[&str] = &["owner", "group", "mode"];
+
+/// Whether a FILES query selects, filters or sorts on ownership fields
+fn needs_ownership(query: &QueryCommand) -> bool {
+    let selected = match &query.fields {
+        crate::parser::FieldList::All => false,
+        crate::parser::FieldList::Fields(fields) => fields
+            .iter()
+            .any(|f| OWNERSHIP_FIELDS.contains(&f.to_lowercase().as_str())),
+    };
+    let filtered = query.where_clause.as_ref().is_some_and(|wc| {
+        OWNERSHIP_FIELDS
+            .iter()
+            .any(|field| filter::references_field(wc, QueryTarget::Files, field))
+    });
+    let sorted = query
+        .order_by
+        .as_ref()
+        .is_some_and(|o| OWNERSHIP_FIELDS.contains(&o.field.to_lowercase().as_str()));
+    selected || filtered || sorted
+}
+
+/// Under `--strict-fields`, fail on WHERE fields the target does not have
+fn check_strict_fields(
+    where_clause: Option<&crate::parser::WhereClause>,
+    target: QueryTarget,
+    ctx: &ExecutionContext,
+) -> Result<()> {
+    match where_clause {
+        Some(wc) if ctx.strict_fields => {
+            check_fields(wc, &target.to_string(), known_fields(target))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Size of the filesystem holding `path`
+fn filesystem_total(path: &std::path::Path) -> Result<u64> {
+    // A path under /tmp may well live on tmpfs
+    let info = query_disk(&crate::parser::FieldList::All, None, true)?;
+    let path = path.to_string_lossy();
+    info.mount_for(&path)
+        .map(|disk| disk.total)
+        .ok_or_else(|| ArtaError::ExecutionError(format!("No mount found for '{}'", path)))
 }
 
+/// Total physical memory
+fn memory_total(context: &Context) -> Result<u64> {
+    query_memory_with(
+        context.system().lock().as_mut(),
+        &crate::parser::FieldList::All,
+        context.memory_pressure(),
+    )
+    .map(|info| info.total)
+}
+
+#[instrument(level = "debug", skip_all, fields(dry_run = ctx.dry_run))]
 fn execute_action(
     action: &ActionCommand,
     ctx: &ExecutionContext,
@@ -195,68 +762,226 @@ fn execute_action(
         return Err(ArtaError::ActionsDisabled);
     }
 
-    let result = match action {
+    audited(action.name(), ctx, || match action {
         ActionCommand::DeleteFiles(cmd) => {
-            let resolved_path = resolve_variable_in_string(&cmd.path, context);
-            let path = context.resolve_path(&resolved_path)?;
+            let path = resolve_fs_path(&cmd.path, context)?;
+            let where_clause = cmd
+                .where_clause
+                .as_ref()
+                .map(|wc| {
+                    let wc = resolve_where_variables(wc, context);
+                    resolve_percentages(&wc, FILE_SIZE_FIELDS, || filesystem_total(&path))
+                })
+                .transpose()?;
             delete_files(
-                path.to_str().unwrap_or(&cmd.path),
-                cmd.where_clause.as_ref(),
+                &path,
+                where_clause.as_ref(),
+                ctx.dry_run,
+                ctx.explain_matches,
+            )
+        }
+        ActionCommand::KillProcess(cmd) => {
+            let where_clause = resolve_where_variables(&cmd.where_clause, context);
+            let where_clause =
+                resolve_percentages(&where_clause, &["memory"], || memory_total(context))?;
+            let confirm = cmd.confirm_each.then_some(&ctx.confirm);
+            kill_processes_with(
+                context.system().lock().as_mut(),
+                &where_clause,
                 ctx.dry_run,
-            )?
+                ctx.full_process_names,
+                confirm,
+            )
+        }
+        ActionCommand::WriteFile(cmd) => {
+            let path = resolve_fs_path(&cmd.path, context)?;
+            let content = interpolate_variables(&cmd.content, context)?;
+            write_file(&path, &content, cmd.append, ctx.dry_run)
+        }
+    })
+}
+
+/// Run an action, logging and auditing what it did
+///
+/// The audit log is opened first, so nothing happens when it can't be
+/// written; an action that fails is recorded with its error.
+fn audited(
+    action_name: &str,
+    ctx: &ExecutionContext,
+    run: impl FnOnce() -> Result<ActionResult>,
+) -> Result<ExecutionResult> {
+    let mut audit = ctx
+        .audit_log
+        .as_deref()
+        .map(AuditWriter::open)
+        .transpose()?;
+    let result = match run() {
+        Ok(result) => result,
+        Err(e) => {
+            if let Some(audit) = &mut audit {
+                audit.record(&AuditEntry::failed(action_name, ctx.dry_run, &e))?;
+            }
+            return Err(e);
         }
-        ActionCommand::KillProcess(cmd) => kill_processes(&cmd.where_clause, ctx.dry_run)?,
     };
 
-    Ok(ExecutionResult {
-        data: ResultData::ActionResult(result),
-        message: None,
+    tracing::debug!(
+        action = %result.action_type,
+        affected = result.affected_count,
+        "action finished"
+    );
+    if let Some(audit) = &mut audit {
+        audit.record(&AuditEntry::from_result(&result))?;
+    }
+
+    Ok(ExecutionResult::new(ResultData::ActionResult(result)))
+}
+
+/// Run a pipeline's query and hand its rows to the step after `|`
+///
+/// DELETE and KILL act on exactly the rows the query returned, so they need
+/// actions enabled (or a dry run) and, like DELETE FILES, a WHERE clause whose
+/// fields all exist; the query alone would let unknown fields match.
+fn execute_pipeline(
+    pipeline: &PipelineCommand,
+    ctx: &ExecutionContext,
+    context: &mut Context,
+) -> Result<ExecutionResult> {
+    if pipeline.sink == PipeSink::Count {
+        let result = execute_query(&pipeline.source, ctx, context)?;
+        let count = match &result.data {
+            ResultData::Files(files) => files.len(),
+            ResultData::Processes(processes) => processes.len(),
+            ResultData::Disk(info) => info.disks.len(),
+            ResultData::Network(info) => info.interfaces.len(),
+            ResultData::Battery(info) => info.batteries.len(),
+            ResultData::Services(services) => services.len(),
+            ResultData::Projection(Projection::Rows(rows)) => rows.len(),
+            _ => 1,
+        };
+        let row = ProjectedRow(vec![("count".to_string(), FieldValue::Count(count as u64))]);
+        return Ok(ExecutionResult::new(ResultData::Projection(
+            Projection::Single(row),
+        )));
+    }
+
+    if !ctx.allow_actions && !ctx.dry_run {
+        return Err(ArtaError::ActionsDisabled);
+    }
+    let Some(where_clause) = &pipeline.source.where_clause else {
+        return Err(ArtaError::SecurityError(format!(
+            "Piping every {} row into {} is too dangerous. Add a WHERE clause to the query.",
+            pipeline.source.target, pipeline.sink
+        )));
+    };
+    let action_name = pipeline.sink.action_name().unwrap_or_default();
+    check_fields(
+        where_clause,
+        action_name,
+        known_fields(pipeline.source.target),
+    )?;
+
+    // The rows themselves are needed, not a projection of them
+    let source = QueryCommand {
+        fields: crate::parser::FieldList::All,
+        ..pipeline.source.clone()
+    };
+    let rows = execute_query(&source, ctx, context)?.data;
+    audited(action_name, ctx, || match (rows, pipeline.sink) {
+        (ResultData::Files(files), PipeSink::Delete) => {
+            let where_clause = resolve_where_variables(where_clause, context);
+            let explain = ctx.explain_matches.then_some(&where_clause);
+            delete_listed(&files, explain, ctx.dry_run)
+        }
+        (ResultData::Processes(processes), PipeSink::Kill) => {
+            let confirm = pipeline.confirm_each.then_some(&ctx.confirm);
+            kill_listed(&processes, ctx.dry_run, confirm)
+        }
+        _ => Err(ArtaError::ExecutionError(format!(
+            "| {} cannot take the rows of a {} query",
+            pipeline.sink, pipeline.source.target
+        ))),
     })
 }
 
-fn execute_context_command(cmd: &ContextCommand, context: &mut Context) -> Result<ExecutionResult> {
+fn execute_context_command(
+    cmd: &ContextCommand,
+    ctx: &ExecutionContext,
+    context: &mut Context,
+) -> Result<ExecutionResult> {
     match cmd {
         ContextCommand::EnterFolder(path) => {
             let resolved_path = resolve_variable_in_string(path, context);
             context.enter_folder(&resolved_path)?;
-            Ok(ExecutionResult {
-                data: ResultData::Message(format!(
-                    "Entered folder: {}",
-                    context.current_folder().display()
-                )),
-                message: None,
-            })
+            Ok(ExecutionResult::new(ResultData::Message(format!(
+                "Entered folder: {}",
+                context.current_folder().display()
+            ))))
         }
         ContextCommand::EnterFile(path) => {
             let resolved_path = resolve_variable_in_string(path, context);
             context.enter_file(&resolved_path)?;
-            Ok(ExecutionResult {
-                data: ResultData::Message(format!(
-                    "Entered file: {}",
-                    context.current_file().unwrap().display()
-                )),
-                message: None,
-            })
+            Ok(ExecutionResult::new(ResultData::Message(format!(
+                "Entered file: {}",
+                context.current_file().unwrap().display()
+            ))))
         }
         ContextCommand::Exit => {
             context.exit_context()?;
-            Ok(ExecutionResult {
-                data: ResultData::Message(format!(
-                    "Exited to: {}",
-                    context.current_folder().display()
-                )),
-                message: None,
-            })
+            Ok(ExecutionResult::new(ResultData::Message(format!(
+                "Exited to: {}",
+                context.current_folder().display()
+            ))))
         }
-        ContextCommand::Reset => {
-            context.reset();
-            Ok(ExecutionResult {
-                data: ResultData::Message("Context reset to initial state".to_string()),
-                message: None,
-            })
+        ContextCommand::Up => {
+            context.up()?;
+            Ok(ExecutionResult::new(ResultData::Message(format!(
+                "Moved up to: {}",
+                context.current_folder().display()
+            ))))
+        }
+        ContextCommand::Reset(target) => {
+            let message = match target {
+                ResetTarget::Context => {
+                    context.reset();
+                    "Context reset to initial state".to_string()
+                }
+                ResetTarget::Variables => {
+                    let count = context.clear_variables();
+                    format!("Cleared {} variable(s)", count)
+                }
+                ResetTarget::History => {
+                    let count = context.clear_history();
+                    format!("Cleared {} history entries", count)
+                }
+            };
+            Ok(ExecutionResult::new(ResultData::Message(message)))
         }
         ContextCommand::Show(target) => {
             let info = match target {
+                ShowTarget::Version => {
+                    return Ok(ExecutionResult::new(ResultData::Version(
+                        VersionInfo::current(),
+                    )))
+                }
+                ShowTarget::Config => {
+                    return Ok(ExecutionResult::new(ResultData::Config(
+                        ConfigInfo::from_context(ctx),
+                    )))
+                }
+                ShowTarget::Audit(count) => {
+                    let path = ctx.audit_log.as_ref().ok_or_else(|| {
+                        ArtaError::ExecutionError(
+                            "No audit log configured; start arta with --audit-log <PATH>"
+                                .to_string(),
+                        )
+                    })?;
+                    return Ok(ExecutionResult::new(ResultData::Audit(read_last(
+                        path,
+                        *count,
+                        ctx.display_zone(),
+                    )?)));
+                }
                 ShowTarget::Context => ContextInfo {
                     current_folder: context.current_folder().display().to_string(),
                     current_file: context.current_file().map(|p| p.display().to_string()),
@@ -267,6 +992,7 @@ fn execute_context_command(cmd: &ContextCommand, context: &mut Context) -> Resul
                         .map(|(k, v)| (k.clone(), v.to_string()))
                         .collect(),
                     history: Vec::new(),
+                    stack: Vec::new(),
                 },
                 ShowTarget::Variables => ContextInfo {
                     current_folder: String::new(),
@@ -278,6 +1004,7 @@ fn execute_context_command(cmd: &ContextCommand, context: &mut Context) -> Resul
                         .map(|(k, v)| (k.clone(), v.to_string()))
                         .collect(),
                     history: Vec::new(),
+                    stack: Vec::new(),
                 },
                 ShowTarget::History => ContextInfo {
                     current_folder: String::new(),
@@ -290,23 +1017,176 @@ fn execute_context_command(cmd: &ContextCommand, context: &mut Context) -> Resul
                         .map(|h| {
                             format!(
                                 "{}: {} {:?}",
-                                h.timestamp.format("%H:%M:%S"),
+                                ctx.display_zone().format(h.timestamp, "%H:%M:%S"),
                                 h.action,
                                 h.path.as_ref().map(|p| p.display().to_string())
                             )
                         })
                         .collect(),
+                    stack: Vec::new(),
+                },
+                ShowTarget::Stack => ContextInfo {
+                    current_folder: String::new(),
+                    current_file: None,
+                    folder_depth: context.folder_depth(),
+                    variables: Vec::new(),
+                    history: Vec::new(),
+                    stack: context
+                        .folder_stack_paths()
+                        .iter()
+                        .map(|p| p.display().to_string())
+                        .collect(),
                 },
             };
-            Ok(ExecutionResult {
-                data: ResultData::ContextInfo(info),
-                message: None,
-            })
+            Ok(ExecutionResult::new(ResultData::ContextInfo(info)))
+        }
+    }
+}
+
+/// Print a query's result every `interval` seconds until Ctrl+C or FOR n TIMES
+fn execute_every(
+    query: &QueryCommand,
+    interval: u64,
+    ctx: &ExecutionContext,
+    context: &Context,
+) -> Result<ExecutionResult> {
+    if interval == 0 {
+        return Err(ArtaError::ExecutionError(
+            "EVERY needs an interval of at least 1 second".to_string(),
+        ));
+    }
+
+    let count = query
+        .every_count
+        .map_or(u32::MAX, |n| u32::try_from(n).unwrap_or(u32::MAX));
+    let plan = RepeatPlan::new(count, std::time::Duration::from_secs(interval));
+    let sample = QueryCommand {
+        every: None,
+        every_count: None,
+        ..query.clone()
+    };
+
+    let cancel = match &ctx.cancellation {
+        Some(token) => token.clone(),
+        None => CancellationToken::ctrl_c()?,
+    };
+    let mut sink = OutputSink::for_context(ctx);
+    run_repeated(
+        &plan,
+        &cancel,
+        |_| {
+            let result = execute_query(&sample, ctx, context)?;
+            sink.write_result(&result)?;
+            Ok(())
+        },
+        std::thread::sleep,
+    )?;
+
+    Ok(ExecutionResult::new(ResultData::Empty))
+}
+
+/// Print a file's content, then lines appended to it until Ctrl+C
+///
+/// The content is read up to the length the tail starts from, so nothing
+/// appended in between is lost or printed twice. Truncated or rotated files
+/// are read again from the start, see `FileTail`. Compressed files are
+/// refused, since appended bytes can't be decoded on their own.
+fn execute_follow(
+    query: &QueryCommand,
+    ctx: &ExecutionContext,
+    context: &Context,
+) -> Result<ExecutionResult> {
+    const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+    if query.target != QueryTarget::Content {
+        return Err(ArtaError::ExecutionError(format!(
+            "FOLLOW is only supported for CONTENT queries, not {}",
+            query.target
+        )));
+    }
+    if query.every.is_some() {
+        return Err(ArtaError::ExecutionError(
+            "FOLLOW and EVERY cannot be combined".to_string(),
+        ));
+    }
+
+    let path = content_path(query, context)?;
+    crate::context::ensure_file(&path)?;
+    if let Some(format) = compression_of(&path)? {
+        return Err(ArtaError::ExecutionError(format!(
+            "FOLLOW cannot tail a {} compressed file: {}",
+            format,
+            path.display()
+        )));
+    }
+    let cancel = match &ctx.cancellation {
+        Some(token) => token.clone(),
+        None => CancellationToken::ctrl_c()?,
+    };
+    let mut sink = OutputSink::for_context(ctx);
+
+    let mut tail = FileTail::at_end(&path)?;
+    let info = query_content_until(
+        &path,
+        query.where_clause.as_ref(),
+        query.line_numbers,
+        &ctx.content_limits,
+        Some(tail.offset()),
+    )?;
+    let mut line_number = info.total_lines;
+    sink.write_result(&ExecutionResult::new(ResultData::Content(info)))?;
+
+    let pattern = content_pattern(query.where_clause.as_ref());
+    let numbered = query.line_numbers.unwrap_or(pattern.is_some());
+
+    while !cancel.is_cancelled() {
+        for mut line in tail.read_new_lines()? {
+            line_number += 1;
+            if pattern
+                .as_ref()
+                .is_some_and(|pat| !line.contains(pat.as_str()))
+            {
+                continue;
+            }
+            if line.len() > ctx.content_limits.max_line_bytes {
+                line.truncate(line.floor_char_boundary(ctx.content_limits.max_line_bytes));
+                line.push_str(" [truncated]");
+            }
+
+            let text = match ctx.output_format {
+                OutputFormat::Json => {
+                    serde_json::json!({ "line": line_number, "content": line }).to_string()
+                }
+                _ if numbered => format!("{:>4}: {}", line_number, line),
+                _ => line,
+            };
+            sink.write_line(&text)?;
         }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    Ok(ExecutionResult::new(ResultData::Empty))
+}
+
+/// File a CONTENT query reads: its FROM path, or the entered file
+fn content_path(query: &QueryCommand, context: &Context) -> Result<std::path::PathBuf> {
+    if let Some(ref path) = query.from_path {
+        resolve_fs_path(path, context)
+    } else if let Some(file) = context.current_file() {
+        Ok(file.to_path_buf())
+    } else {
+        Err(ArtaError::ExecutionError(
+            "No file in context. Use 'ENTER FILE <path>' or 'SELECT CONTENT * FROM <path>'"
+                .to_string(),
+        ))
     }
 }
 
-fn execute_let(let_stmt: &LetStatement, context: &mut Context) -> Result<ExecutionResult> {
+fn execute_let(
+    let_stmt: &LetStatement,
+    ctx: &ExecutionContext,
+    context: &mut Context,
+) -> Result<ExecutionResult> {
     use crate::context::VariableValue;
 
     let value = match &let_stmt.value {
@@ -317,16 +1197,15 @@ fn execute_let(let_stmt: &LetStatement, context: &mut Context) -> Result<Executi
         LetValue::Path(p) => VariableValue::Path(std::path::PathBuf::from(p)),
     };
 
-    let display_value = value.to_string();
+    let mut message = format!("Variable '{}' set to {}", let_stmt.name, value);
+    if ctx.verbose {
+        if let Some(previous) = context.get_variable(&let_stmt.name) {
+            message.push_str(&format!(" (overwrote {})", previous));
+        }
+    }
     context.set_variable(let_stmt.name.clone(), value);
 
-    Ok(ExecutionResult {
-        data: ResultData::Message(format!(
-            "Variable '{}' set to {}",
-            let_stmt.name, display_value
-        )),
-        message: None,
-    })
+    Ok(ExecutionResult::new(ResultData::Message(message)))
 }
 
 fn execute_for_loop(
@@ -375,6 +1254,10 @@ fn execute_for_loop(
                     format!("{}.is_dir", for_loop.iterator_var),
                     VariableValue::Boolean(file.is_dir),
                 );
+                context.set_variable(
+                    format!("{}.is_symlink", for_loop.iterator_var),
+                    VariableValue::Boolean(file.is_symlink),
+                );
 
                 // Execute each command in the body
                 for cmd in &for_loop.body {
@@ -408,6 +1291,18 @@ fn execute_for_loop(
                     format!("{}.memory", for_loop.iterator_var),
                     VariableValue::Size(proc.memory),
                 );
+                if let Some(threads) = proc.threads {
+                    context.set_variable(
+                        format!("{}.threads", for_loop.iterator_var),
+                        VariableValue::Number(threads as f64),
+                    );
+                }
+                if let Some(open_files) = proc.open_files {
+                    context.set_variable(
+                        format!("{}.open_files", for_loop.iterator_var),
+                        VariableValue::Number(open_files as f64),
+                    );
+                }
 
                 // Execute each command in the body
                 for cmd in &for_loop.body {
@@ -427,25 +1322,39 @@ fn execute_for_loop(
     // Note: We don't have a remove_variable method, so they persist until context reset
 
     if results.is_empty() {
-        Ok(ExecutionResult {
-            data: ResultData::Message("FOR loop completed (no items)".to_string()),
-            message: None,
-        })
+        Ok(ExecutionResult::new(ResultData::Message(
+            "FOR loop completed (no items)".to_string(),
+        )))
     } else {
-        Ok(ExecutionResult {
-            data: ResultData::Multiple(results),
-            message: Some("FOR loop completed".to_string()),
-        })
+        Ok(ExecutionResult::new(ResultData::Multiple(results)).with_message("FOR loop completed"))
     }
 }
 
+fn execute_label(
+    label: &LabelBlock,
+    ctx: &ExecutionContext,
+    context: &mut Context,
+) -> Result<ExecutionResult> {
+    let mut results = label
+        .body
+        .iter()
+        .map(|cmd| execute_command_with_context(cmd, ctx, context))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(match results.len() {
+        0 => ExecutionResult::new(ResultData::Empty),
+        1 => results.remove(0),
+        _ => ExecutionResult::new(ResultData::Multiple(results)),
+    })
+}
+
 fn execute_if(
     if_stmt: &IfStatement,
     ctx: &ExecutionContext,
     context: &mut Context,
 ) -> Result<ExecutionResult> {
     // Evaluate the condition
-    let condition_met = evaluate_if_condition(&if_stmt.condition, context)?;
+    let condition_met = evaluate_if_condition(&if_stmt.condition, ctx, context)?;
 
     if condition_met {
         // Execute THEN body
@@ -458,10 +1367,7 @@ fn execute_if(
         if results.len() == 1 {
             Ok(results.into_iter().next().unwrap())
         } else {
-            Ok(ExecutionResult {
-                data: ResultData::Multiple(results),
-                message: None,
-            })
+            Ok(ExecutionResult::new(ResultData::Multiple(results)))
         }
     } else if let Some(else_body) = &if_stmt.else_body {
         // Execute ELSE body
@@ -474,44 +1380,79 @@ fn execute_if(
         if results.len() == 1 {
             Ok(results.into_iter().next().unwrap())
         } else {
-            Ok(ExecutionResult {
-                data: ResultData::Multiple(results),
-                message: None,
-            })
+            Ok(ExecutionResult::new(ResultData::Multiple(results)))
         }
     } else {
         // No ELSE and condition was false
-        Ok(ExecutionResult {
-            data: ResultData::Empty,
-            message: Some("IF condition was false".to_string()),
-        })
+        Ok(ExecutionResult::new(ResultData::Empty).with_message("IF condition was false"))
     }
 }
 
-fn evaluate_if_condition(condition: &IfCondition, context: &Context) -> Result<bool> {
+#[instrument(
+    level = "debug",
+    skip_all,
+    fields(target = %condition.target, field = %condition.field)
+)]
+fn evaluate_if_condition(
+    condition: &IfCondition,
+    ctx: &ExecutionContext,
+    context: &Context,
+) -> Result<bool> {
     // Execute a query to get the current value
     // For now, we'll get the system info and compare the field
+    check_target_index(condition.target, condition.index)?;
 
-    match condition.target {
-        QueryTarget::Memory => {
-            let info = query_memory(&crate::parser::FieldList::All)?;
-            let field_value = get_memory_field_value(&info, &condition.field)?;
-            compare_values(field_value, &condition.operator, &condition.value, context)
+    if condition.target != QueryTarget::Disk && condition.quantifier.is_some() {
+        return Err(ArtaError::ExecutionError(format!(
+            "ANY/ALL in IF are only supported for DISK, not {}",
+            condition.target
+        )));
+    }
+    if !matches!(condition.target, QueryTarget::Disk | QueryTarget::Files)
+        && condition.from_path.is_some()
+    {
+        return Err(ArtaError::ExecutionError(format!(
+            "FROM in IF is only supported for DISK and FILES, not {}",
+            condition.target
+        )));
+    }
+
+    match condition.target {
+        QueryTarget::Memory => {
+            let info = query_memory_with(
+                context.system().lock().as_mut(),
+                &crate::parser::FieldList::All,
+                context.memory_pressure(),
+            )?;
+            // Percentages of swap fields refer to total swap
+            let total = match canonical_field(QueryTarget::Memory, &condition.field) {
+                Some("swap_total" | "swap_used") => info.swap_total,
+                _ => info.total,
+            };
+            let value = field_value(&info, &condition.field)?;
+            compare_field(value, Some(total), condition, context)
         }
         QueryTarget::Cpu => {
-            let info = query_cpu(&crate::parser::FieldList::All)?;
-            let field_value = get_cpu_field_value(&info, &condition.field)?;
-            compare_values(field_value, &condition.operator, &condition.value, context)
+            let info = query_cpu_with(
+                context.system().lock().as_mut(),
+                &crate::parser::FieldList::All,
+            )?;
+            let value = field_value(&info, &condition.field)?;
+            compare_field(value, None, condition, context)
         }
         QueryTarget::Disk => {
-            let info = query_disk(&crate::parser::FieldList::All, None)?;
-            let field_value = get_disk_field_value(&info, &condition.field)?;
-            compare_values(field_value, &condition.operator, &condition.value, context)
+            let info = query_disk(&crate::parser::FieldList::All, None, true)?;
+            evaluate_disk_condition(&info, condition, ctx.all_mounts, context)
         }
         QueryTarget::Battery => {
             let info = query_battery(&crate::parser::FieldList::All)?;
-            let field_value = get_battery_field_value(&info, &condition.field)?;
-            compare_values(field_value, &condition.operator, &condition.value, context)
+            // The only numeric battery field is the charge percentage
+            let value = get_battery_field_value(&info, condition.index, &condition.field)?;
+            compare_field(FieldValue::Percent(value), None, condition, context)
+        }
+        QueryTarget::Process | QueryTarget::Files => {
+            let count = count_matching(condition, ctx, context)?;
+            compare_field(FieldValue::Count(count as u64), None, condition, context)
         }
         _ => Err(ArtaError::ExecutionError(format!(
             "IF condition not supported for {} queries yet",
@@ -520,64 +1461,316 @@ fn evaluate_if_condition(condition: &IfCondition, context: &Context) -> Result<b
     }
 }
 
-fn get_memory_field_value(info: &MemoryInfo, field: &str) -> Result<f64> {
-    match field.to_lowercase().as_str() {
-        "total" | "total_bytes" => Ok(info.total as f64),
-        "used" | "used_bytes" => Ok(info.used as f64),
-        "free" | "free_bytes" => Ok(info.free as f64),
-        "available" | "available_bytes" => Ok(info.available as f64),
-        "used_percent" | "percent" | "usage" | "usage_percent" => Ok(info.usage_percent),
-        _ => Err(ArtaError::ExecutionError(format!(
-            "Unknown MEMORY field: {}",
-            field
-        ))),
+/// Number of processes, or files in the FROM folder, matching a condition's WHERE
+///
+/// Unknown WHERE fields are an error, as for actions: a typo would otherwise
+/// make the count silently 0.
+fn count_matching(
+    condition: &IfCondition,
+    ctx: &ExecutionContext,
+    context: &Context,
+) -> Result<usize> {
+    if !condition.field.eq_ignore_ascii_case("count") {
+        return Err(ArtaError::ExecutionError(format!(
+            "{} conditions compare count, e.g. IF SELECT {} count > 5 WHERE ...",
+            condition.target, condition.target
+        )));
     }
-}
 
-fn get_cpu_field_value(info: &CpuInfo, field: &str) -> Result<f64> {
-    match field.to_lowercase().as_str() {
-        "usage" | "percent" | "used_percent" | "usage_percent" => Ok(info.usage as f64),
-        "cores" | "core_count" => Ok(info.cores as f64),
-        "frequency" | "frequency_mhz" => Ok(info.frequency as f64),
-        _ => Err(ArtaError::ExecutionError(format!(
-            "Unknown CPU field: {}",
-            field
-        ))),
+    if condition.target == QueryTarget::Process {
+        let where_clause = condition
+            .where_clause
+            .as_ref()
+            .map(|wc| {
+                let wc = resolve_where_variables(wc, context);
+                resolve_percentages(&wc, &["memory"], || memory_total(context))
+            })
+            .transpose()?;
+        if let Some(wc) = &where_clause {
+            check_fields(wc, "PROCESS", known_fields(QueryTarget::Process))?;
+        }
+        let processes = query_processes_with(
+            context.system().lock().as_mut(),
+            &crate::parser::FieldList::All,
+            where_clause.as_ref(),
+            ctx.full_process_names,
+            None,
+            None,
+        )?;
+        return Ok(processes.len());
     }
+
+    let path = condition
+        .from_path
+        .as_deref()
+        .map(|p| resolve_fs_path(p, context))
+        .transpose()?
+        .unwrap_or_else(|| context.current_folder().to_path_buf());
+    let where_clause = condition
+        .where_clause
+        .as_ref()
+        .map(|wc| {
+            let wc = resolve_where_variables(wc, context);
+            resolve_percentages(&wc, FILE_SIZE_FIELDS, || filesystem_total(&path))
+        })
+        .transpose()?;
+    if let Some(wc) = &where_clause {
+        check_fields(wc, "FILES", known_fields(QueryTarget::Files))?;
+    }
+    let references = |field: &str| {
+        where_clause
+            .as_ref()
+            .is_some_and(|wc| filter::references_field(wc, QueryTarget::Files, field))
+    };
+    let files = query_files(
+        &path,
+        where_clause.as_ref(),
+        references("dir_size"),
+        OWNERSHIP_FIELDS.iter().any(|field| references(field)),
+        None,
+    )?;
+    Ok(files.len())
 }
 
-fn get_disk_field_value(info: &DiskInfo, field: &str) -> Result<f64> {
-    // Use first disk if available
-    if let Some(disk) = info.disks.first() {
-        match field.to_lowercase().as_str() {
-            "total" | "total_bytes" => Ok(disk.total as f64),
-            "used" | "used_bytes" => Ok(disk.used as f64),
-            "free" | "free_bytes" | "available" | "available_bytes" => Ok(disk.free as f64),
-            "used_percent" | "percent" | "usage" => Ok(disk.usage_percent),
-            _ => Err(ArtaError::ExecutionError(format!(
-                "Unknown DISK field: {}",
-                field
-            ))),
+/// Compare a field against an IF condition
+///
+/// An `N%` value means N percent of `total` on byte fields, and is taken as
+/// is on fields that are already percentages. Text fields compare as strings.
+fn compare_field(
+    value: FieldValue,
+    total: Option<u64>,
+    condition: &IfCondition,
+    context: &Context,
+) -> Result<bool> {
+    if let FieldValue::Text(text) = &value {
+        let expected = match &condition.value {
+            Value::String(s) => s.clone(),
+            Value::Identifier(id) => match context.get_variable(id) {
+                Some(crate::context::VariableValue::String(s)) => s.clone(),
+                _ => id.clone(),
+            },
+            _ => {
+                return Err(ArtaError::ExecutionError(format!(
+                    "{} field '{}' is text; compare it with a string",
+                    condition.target, condition.field
+                )))
+            }
+        };
+        return Ok(filter::compare_strings(
+            text,
+            &expected,
+            &condition.operator,
+        ));
+    }
+
+    let actual = value.as_number().ok_or_else(|| {
+        ArtaError::ExecutionError(format!(
+            "{} field '{}' is not numeric",
+            condition.target, condition.field
+        ))
+    })?;
+
+    let expected = match (&condition.value, &value, total) {
+        (Value::Percent(p), FieldValue::Percent(_), _) => Value::Number(*p),
+        (Value::Percent(p), FieldValue::Bytes(_), Some(total)) => {
+            Value::Size(percent_of(total, *p))
         }
-    } else {
-        Err(ArtaError::ExecutionError("No disks found".to_string()))
+        (Value::Percent(p), _, _) => {
+            return Err(ArtaError::ExecutionError(format!(
+                "{}% has no total to refer to for {} field '{}'",
+                p, condition.target, condition.field
+            )))
+        }
+        (other, _, _) => other.clone(),
+    };
+    compare_values(actual, &condition.operator, &expected, context)
+}
+
+/// Evaluate a DISK condition on one mount, or on every mount with ANY/ALL
+///
+/// Without a quantifier the mount containing the FROM path (default `/`) is
+/// checked. With a quantifier, FROM limits the check to mounts below it; ALL
+/// is false when no mount is left.
+fn evaluate_disk_condition(
+    info: &DiskInfo,
+    condition: &IfCondition,
+    all_mounts: bool,
+    context: &Context,
+) -> Result<bool> {
+    let disks = checked_disks(info, condition, all_mounts, context)?;
+    if disks.is_empty() {
+        return Ok(false);
+    }
+
+    let mut results = disks
+        .into_iter()
+        .map(|disk| check_disk(disk, condition, context));
+    match condition.quantifier {
+        None | Some(Quantifier::Any) => results.try_fold(false, |any, r| r.map(|m| any || m)),
+        Some(Quantifier::All) => results.try_fold(true, |all, r| r.map(|m| all && m)),
     }
 }
 
-fn get_battery_field_value(info: &BatteryInfo, field: &str) -> Result<f64> {
-    if let Some(battery) = info.batteries.first() {
-        match field.to_lowercase().as_str() {
-            "percent" | "charge" | "level" | "charge_percent" | "percentage" => {
-                Ok(battery.percentage as f64)
-            }
-            _ => Err(ArtaError::ExecutionError(format!(
-                "Unknown BATTERY field: {}",
-                field
-            ))),
+/// Mounts a DISK condition looks at: the one holding the FROM path (default
+/// `/`), or with ANY/ALL every mount below it
+///
+/// `info` lists every mount, so a root on overlay or a FROM path on tmpfs is
+/// still found; pseudo filesystems are only left out of ANY/ALL, unless
+/// `all_mounts` is set.
+fn checked_disks<'a>(
+    info: &'a DiskInfo,
+    condition: &IfCondition,
+    all_mounts: bool,
+    context: &Context,
+) -> Result<Vec<&'a DiskEntry>> {
+    let from_path = condition
+        .from_path
+        .as_deref()
+        .map(|p| resolve_fs_path(p, context))
+        .transpose()?
+        .map(|p| p.to_string_lossy().into_owned());
+
+    if condition.quantifier.is_none() {
+        let disk = match from_path.as_deref() {
+            Some(path) => info.mount_for(path).ok_or_else(|| {
+                ArtaError::ExecutionError(format!("No mount found for '{}'", path))
+            })?,
+            None => info
+                .primary()
+                .ok_or_else(|| ArtaError::ExecutionError("No disks found".to_string()))?,
+        };
+        return Ok(vec![disk]);
+    }
+
+    Ok(info
+        .disks
+        .iter()
+        .filter(|d| all_mounts || !is_pseudo_filesystem(&d.file_system))
+        .filter(|d| {
+            from_path
+                .as_deref()
+                .is_none_or(|p| std::path::Path::new(&d.mount_point).starts_with(p))
+        })
+        .collect())
+}
+
+fn check_disk(disk: &DiskEntry, condition: &IfCondition, context: &Context) -> Result<bool> {
+    let value = field_value(disk, &condition.field)?;
+    compare_field(value, Some(disk.total), condition, context)
+}
+
+/// Exit code of `arta query --alert` when the condition holds
+pub const ALERT_EXIT_CODE: i32 = 2;
+
+/// Alert text for a condition that holds, or `None` when it does not
+///
+/// DISK alerts name each mount over the threshold with its current value.
+pub fn check_alert(
+    condition: &IfCondition,
+    ctx: &ExecutionContext,
+    context: &Context,
+) -> Result<Option<String>> {
+    if condition.target == QueryTarget::Disk {
+        check_target_index(condition.target, condition.index)?;
+        let info = query_disk(&crate::parser::FieldList::All, None, true)?;
+        return disk_alert(&info, condition, ctx.all_mounts, context);
+    }
+    Ok(evaluate_if_condition(condition, ctx, context)?
+        .then(|| format!("ALERT: {}", describe_condition(condition))))
+}
+
+fn disk_alert(
+    info: &DiskInfo,
+    condition: &IfCondition,
+    all_mounts: bool,
+    context: &Context,
+) -> Result<Option<String>> {
+    if !evaluate_disk_condition(info, condition, all_mounts, context)? {
+        return Ok(None);
+    }
+
+    let mut breached = Vec::new();
+    for disk in checked_disks(info, condition, all_mounts, context)? {
+        if check_disk(disk, condition, context)? {
+            breached.push(format!(
+                "{}: {} = {}",
+                disk.mount_point,
+                condition.field,
+                field_value(disk, &condition.field)?
+            ));
         }
-    } else {
+    }
+    Ok(Some(format!(
+        "ALERT: {} ({})",
+        describe_condition(condition),
+        breached.join("; ")
+    )))
+}
+
+/// A condition as written, e.g. `ANY DISK used_percent > 90 FROM /home`
+fn describe_condition(condition: &IfCondition) -> String {
+    let mut text = match condition.quantifier {
+        Some(q) => format!("{} {}", q, condition.target),
+        None => condition.target.to_string(),
+    };
+    if let Some(index) = condition.index {
+        text.push_str(&format!("[{}]", index));
+    }
+    text.push_str(&format!(
+        " {} {} {}",
+        condition.field, condition.operator, condition.value
+    ));
+    if let Some(path) = &condition.from_path {
+        text.push_str(&format!(" FROM {}", path));
+    }
+    if condition.where_clause.is_some() {
+        text.push_str(" WHERE ...");
+    }
+    text
+}
+
+fn get_battery_field_value(info: &BatteryInfo, index: Option<usize>, field: &str) -> Result<f64> {
+    match battery_field_value(info, index, field)? {
+        Some(value) => value.as_number().ok_or_else(|| {
+            ArtaError::ExecutionError(format!("BATTERY field '{}' is not numeric", field))
+        }),
         // No battery, return 100 (assume desktop/always powered)
-        Ok(100.0)
+        None => Ok(100.0),
+    }
+}
+
+/// Look up a battery field, aggregating across batteries unless an index is given
+///
+/// Returns `None` when the machine has no battery at all.
+fn battery_field_value(
+    info: &BatteryInfo,
+    index: Option<usize>,
+    field: &str,
+) -> Result<Option<FieldValue>> {
+    match index {
+        Some(i) => {
+            let battery = info.batteries.get(i).ok_or_else(|| {
+                ArtaError::ExecutionError(format!(
+                    "BATTERY[{}] not found ({} batteries present)",
+                    i,
+                    info.batteries.len()
+                ))
+            })?;
+            field_value(battery, field).map(Some)
+        }
+        None if info.batteries.is_empty() => Ok(None),
+        None => field_value(info, field).map(Some),
+    }
+}
+
+/// Only BATTERY supports selecting a device by index
+fn check_target_index(target: QueryTarget, index: Option<usize>) -> Result<()> {
+    match index {
+        Some(i) if target != QueryTarget::Battery => Err(ArtaError::ExecutionError(format!(
+            "{}[{}]: indexing is only supported for BATTERY",
+            target, i
+        ))),
+        _ => Ok(()),
     }
 }
 
@@ -632,21 +1825,94 @@ fn compare_values(
     })
 }
 
+/// Resolve a path operand against the current folder
+///
+/// Every FROM path of queries and actions goes through here, so a relative
+/// path means the same folder for `SELECT FILES` and `DELETE FILES`. A bare
+/// variable name is substituted first.
+fn resolve_fs_path(raw: &str, context: &Context) -> Result<std::path::PathBuf> {
+    context.resolve_path(&resolve_variable_in_string(raw, context))
+}
+
 /// Resolve variable references in a string (e.g., path references)
 fn resolve_variable_in_string(input: &str, context: &Context) -> String {
     // Check if the entire input is a variable name
     if let Some(var_value) = context.get_variable(input) {
-        return match var_value {
-            crate::context::VariableValue::String(s) => s.clone(),
-            crate::context::VariableValue::Path(p) => p.display().to_string(),
-            other => other.to_string(),
-        };
+        return variable_text(var_value);
     }
 
-    // Otherwise return as-is (we can add ${var} syntax later)
+    // Otherwise return as-is
     input.to_string()
 }
 
+/// Substitute `${name}` references in text with variable values
+fn interpolate_variables(input: &str, context: &Context) -> Result<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let value = context.get_variable(name).ok_or_else(|| {
+            ArtaError::ExecutionError(format!("Undefined variable '{}' in '{}'", name, input))
+        })?;
+        out.push_str(&rest[..start]);
+        out.push_str(&variable_text(value));
+        rest = &rest[start + 3 + len..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn variable_text(value: &crate::context::VariableValue) -> String {
+    match value {
+        crate::context::VariableValue::String(s) => s.clone(),
+        crate::context::VariableValue::Path(p) => p.display().to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// A variable as a JSON value of its own type, with sizes in bytes
+fn variable_json(value: &crate::context::VariableValue) -> serde_json::Value {
+    use crate::context::VariableValue;
+
+    match value {
+        VariableValue::String(s) => s.as_str().into(),
+        VariableValue::Number(n) => (*n).into(),
+        VariableValue::Size(bytes) => (*bytes).into(),
+        VariableValue::Boolean(b) => (*b).into(),
+        VariableValue::Path(p) => p.display().to_string().into(),
+    }
+}
+
+/// Substitute variable references in WHERE values with their current values
+///
+/// Identifiers that name no variable are left alone, so bare words such as
+/// `status = running` keep working.
+fn resolve_where_variables(
+    where_clause: &crate::parser::WhereClause,
+    context: &Context,
+) -> crate::parser::WhereClause {
+    use crate::context::VariableValue;
+
+    let mut resolved = where_clause.clone();
+    for expr in &mut resolved.conditions {
+        if let Value::Identifier(id) = &expr.condition.value {
+            if let Some(var) = context.get_variable(id) {
+                expr.condition.value = match var {
+                    VariableValue::String(s) => Value::String(s.clone()),
+                    VariableValue::Number(n) => Value::Number(*n),
+                    VariableValue::Size(s) => Value::Size(*s),
+                    VariableValue::Boolean(b) => Value::Boolean(*b),
+                    VariableValue::Path(p) => Value::String(p.display().to_string()),
+                };
+            }
+        }
+    }
+    resolved
+}
+
 fn execute_life(
     life: &LifeMonitor,
     ctx: &ExecutionContext,
@@ -654,15 +1920,63 @@ fn execute_life(
 ) -> Result<ExecutionResult> {
     // For LIFE monitoring in script context, we run synchronously
     // The actual continuous monitoring is handled by the life module
-    crate::life::run_life_block(life.target, &life.body, ctx, context, 1)?;
+    crate::life::run_life_block(life, ctx, context)?;
 
-    Ok(ExecutionResult {
-        data: ResultData::Message("LIFE monitoring completed".to_string()),
-        message: None,
-    })
+    Ok(ExecutionResult::new(ResultData::Message(
+        "LIFE monitoring completed".to_string(),
+    )))
+}
+
+fn execute_diff(
+    diff: &DiffCommand,
+    ctx: &ExecutionContext,
+    context: &Context,
+) -> Result<ExecutionResult> {
+    let old_path = resolve_fs_path(&diff.old, context)?;
+    let new_path = resolve_fs_path(&diff.new, context)?;
+    let old = read_diff_input(&old_path, &ctx.content_limits)?;
+    let new = read_diff_input(&new_path, &ctx.content_limits)?;
+
+    Ok(ExecutionResult::new(ResultData::Diff(diff_text(
+        &old_path.display().to_string(),
+        &new_path.display().to_string(),
+        &old,
+        &new,
+    ))))
+}
+
+/// Read one side of a DIFF, refusing files past the CONTENT scan limit
+///
+/// Unlike CONTENT, a partial read would report a bogus change set, so an
+/// oversized file is an error rather than a truncated result.
+fn read_diff_input(path: &std::path::Path, limits: &ContentLimits) -> Result<String> {
+    use std::io::Read;
+
+    crate::context::ensure_file(path)?;
+    let mut bytes = Vec::new();
+    open_content_reader(path)?
+        .take(limits.max_scan_bytes.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .map_err(ArtaError::IoError)?;
+    if bytes.len() as u64 > limits.max_scan_bytes {
+        return Err(ArtaError::ExecutionError(format!(
+            "Cannot DIFF '{}': larger than the {} read limit",
+            path.display(),
+            fmt_size(limits.max_scan_bytes)
+        )));
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
 }
 
-fn execute_print(print_cmd: &PrintCommand, context: &Context) -> Result<ExecutionResult> {
+fn execute_print(
+    print_cmd: &PrintCommand,
+    ctx: &ExecutionContext,
+    context: &Context,
+) -> Result<ExecutionResult> {
+    if ctx.output_format == OutputFormat::Json {
+        return execute_print_values(print_cmd, ctx, context);
+    }
+
     let mut output_parts = Vec::new();
 
     for expr in &print_cmd.expressions {
@@ -675,20 +1989,61 @@ fn execute_print(print_cmd: &PrintCommand, context: &Context) -> Result<Executio
                     format!("<undefined: {}>", name)
                 }
             }
-            PrintExpr::QueryField { target, field } => {
+            PrintExpr::QueryField {
+                target,
+                index,
+                field,
+            } => {
                 // Query the target and extract the field
-                get_query_field_value(*target, field)?
+                // Percentages use --precision, as in query output
+                match get_query_field_value(*target, *index, field, context)? {
+                    Some(value) => format!("{:.*}", ctx.precision, value),
+                    None => no_device_message(*target).to_string(),
+                }
             }
+            PrintExpr::Query(query) => format_json_compact(&execute_query(query, ctx, context)?),
         };
         output_parts.push(value);
     }
 
     let output = output_parts.join(" ");
 
-    Ok(ExecutionResult {
-        data: ResultData::Message(output),
-        message: None,
-    })
+    Ok(ExecutionResult::new(ResultData::Message(output)))
+}
+
+/// PRINT under JSON output: one typed value per expression
+///
+/// Numbers stay numbers, sizes are bytes and queries are nested objects.
+/// An undefined variable or a missing device is `null`.
+fn execute_print_values(
+    print_cmd: &PrintCommand,
+    ctx: &ExecutionContext,
+    context: &Context,
+) -> Result<ExecutionResult> {
+    let mut values = Vec::new();
+
+    for expr in &print_cmd.expressions {
+        let value = match expr {
+            PrintExpr::String(s) => serde_json::Value::from(s.as_str()),
+            PrintExpr::Variable(name) => context
+                .get_variable(name)
+                .map_or(serde_json::Value::Null, variable_json),
+            PrintExpr::QueryField {
+                target,
+                index,
+                field,
+            } => get_query_field_value(*target, *index, field, context)?
+                .map_or(serde_json::Value::Null, |value| {
+                    serde_json::to_value(value).unwrap_or_default()
+                }),
+            PrintExpr::Query(query) => {
+                crate::output::json::json_value(&execute_query(query, ctx, context)?)
+            }
+        };
+        values.push(value);
+    }
+
+    Ok(ExecutionResult::new(ResultData::Print(values)))
 }
 
 fn execute_container_cmd(
@@ -706,8 +2061,8 @@ fn execute_container_cmd(
                 results.push(result);
             }
 
-            Ok(ExecutionResult {
-                data: ResultData::ContainerResult(ContainerResultInfo {
+            Ok(ExecutionResult::new(ResultData::ContainerResult(
+                ContainerResultInfo {
                     operation: "CREATE".to_string(),
                     container_name: Some(create.name.clone()),
                     containers: None,
@@ -716,174 +2071,221 @@ fn execute_container_cmd(
                         create.name,
                         create.body.len()
                     ),
-                }),
-                message: None,
-            })
+                },
+            )))
         }
-        ContainerCommand::Switch(name) => Ok(ExecutionResult {
-            data: ResultData::ContainerResult(ContainerResultInfo {
+        ContainerCommand::Switch(name) => Ok(ExecutionResult::new(ResultData::ContainerResult(
+            ContainerResultInfo {
                 operation: "SWITCH".to_string(),
                 container_name: Some(name.clone()),
                 containers: None,
                 message: format!("Switched to container '{}'", name),
-            }),
-            message: None,
-        }),
-        ContainerCommand::List => Ok(ExecutionResult {
-            data: ResultData::ContainerResult(ContainerResultInfo {
+            },
+        ))),
+        ContainerCommand::List(where_clause) => Ok(ExecutionResult::new(
+            ResultData::ContainerResult(ContainerResultInfo {
                 operation: "LIST".to_string(),
                 container_name: None,
-                containers: Some(vec![ContainerInfo {
-                    name: "default".to_string(),
-                    allow_actions: ctx.allow_actions,
-                    readonly: false,
-                    is_active: true,
-                }]),
+                containers: Some(crate::container::filter_containers(
+                    vec![ContainerInfo {
+                        name: "default".to_string(),
+                        allow_actions: ctx.allow_actions,
+                        readonly: false,
+                        is_active: true,
+                    }],
+                    where_clause.as_ref(),
+                )?),
                 message: "Container list".to_string(),
             }),
-            message: None,
-        }),
+        )),
         ContainerCommand::Destroy(name) => {
             if name == "default" {
                 return Err(ArtaError::ExecutionError(
                     "Cannot destroy the default container".to_string(),
                 ));
             }
-            Ok(ExecutionResult {
-                data: ResultData::ContainerResult(ContainerResultInfo {
+            Ok(ExecutionResult::new(ResultData::ContainerResult(
+                ContainerResultInfo {
                     operation: "DESTROY".to_string(),
                     container_name: Some(name.clone()),
                     containers: None,
                     message: format!("Container '{}' destroyed", name),
-                }),
-                message: None,
-            })
+                },
+            )))
         }
-        ContainerCommand::Export(export) => Ok(ExecutionResult {
-            data: ResultData::ContainerResult(ContainerResultInfo {
+        ContainerCommand::Export(export) => Ok(ExecutionResult::new(ResultData::ContainerResult(
+            ContainerResultInfo {
                 operation: "EXPORT".to_string(),
                 container_name: Some(export.name.clone()),
                 containers: None,
                 message: format!("Container '{}' exported to '{}'", export.name, export.path),
-            }),
-            message: None,
-        }),
+            },
+        ))),
+        // Only the REPL keeps a session of containers to export
+        ContainerCommand::ExportSession(export) => Err(ArtaError::ExecutionError(format!(
+            "Cannot export the session to '{}': EXPORT SESSION needs a container session, such as the REPL",
+            export.path
+        ))),
     }
 }
 
-fn get_query_field_value(target: QueryTarget, field: &str) -> Result<String> {
-    match target {
+/// Look up one field of a query target for PRINT
+///
+/// `None` means the machine has no such device, see `no_device_message`.
+fn get_query_field_value(
+    target: QueryTarget,
+    index: Option<usize>,
+    field: &str,
+    context: &Context,
+) -> Result<Option<FieldValue>> {
+    check_target_index(target, index)?;
+
+    let value = match target {
         QueryTarget::Battery => {
             let info = query_battery(&crate::parser::FieldList::All)?;
-            if let Some(battery) = info.batteries.first() {
-                match field.to_lowercase().as_str() {
-                    "level" | "percent" | "percentage" | "charge" => {
-                        Ok(format!("{}%", battery.percentage as u32))
-                    }
-                    "state" | "status" => Ok(battery.state.clone()),
-                    "time_to_empty" | "remaining" => Ok(battery
-                        .time_to_empty
-                        .clone()
-                        .unwrap_or_else(|| "N/A".to_string())),
-                    "time_to_full" => Ok(battery
-                        .time_to_full
-                        .clone()
-                        .unwrap_or_else(|| "N/A".to_string())),
-                    _ => Err(ArtaError::ExecutionError(format!(
-                        "Unknown BATTERY field: {}",
-                        field
-                    ))),
-                }
-            } else {
-                Ok("No battery".to_string())
+            match battery_field_value(&info, index, field)? {
+                Some(value) => value,
+                None => return Ok(None),
             }
         }
         QueryTarget::Memory => {
-            let info = query_memory(&crate::parser::FieldList::All)?;
-            match field.to_lowercase().as_str() {
-                "total" => Ok(bytesize::ByteSize(info.total).to_string()),
-                "used" => Ok(bytesize::ByteSize(info.used).to_string()),
-                "free" => Ok(bytesize::ByteSize(info.free).to_string()),
-                "available" => Ok(bytesize::ByteSize(info.available).to_string()),
-                "usage" | "percent" | "used_percent" => Ok(format!("{:.1}%", info.usage_percent)),
-                _ => Err(ArtaError::ExecutionError(format!(
-                    "Unknown MEMORY field: {}",
-                    field
-                ))),
-            }
+            let info = query_memory_with(
+                context.system().lock().as_mut(),
+                &crate::parser::FieldList::All,
+                context.memory_pressure(),
+            )?;
+            field_value(&info, field)?
         }
         QueryTarget::Cpu => {
-            let info = query_cpu(&crate::parser::FieldList::All)?;
-            match field.to_lowercase().as_str() {
-                "usage" | "percent" => Ok(format!("{:.1}%", info.usage)),
-                "cores" => Ok(info.cores.to_string()),
-                "frequency" | "frequency_mhz" => Ok(format!("{} MHz", info.frequency)),
-                "name" | "brand" => Ok(info.brand.clone()),
-                _ => Err(ArtaError::ExecutionError(format!(
-                    "Unknown CPU field: {}",
-                    field
-                ))),
-            }
+            let info = query_cpu_with(
+                context.system().lock().as_mut(),
+                &crate::parser::FieldList::All,
+            )?;
+            field_value(&info, field)?
         }
         QueryTarget::Disk => {
-            let info = query_disk(&crate::parser::FieldList::All, None)?;
-            if let Some(disk) = info.disks.first() {
-                match field.to_lowercase().as_str() {
-                    "total" => Ok(bytesize::ByteSize(disk.total).to_string()),
-                    "used" => Ok(bytesize::ByteSize(disk.used).to_string()),
-                    "free" | "available" => Ok(bytesize::ByteSize(disk.free).to_string()),
-                    "usage" | "percent" | "used_percent" => {
-                        Ok(format!("{:.1}%", disk.usage_percent))
-                    }
-                    "name" | "mount" | "mount_point" => Ok(disk.mount_point.clone()),
-                    _ => Err(ArtaError::ExecutionError(format!(
-                        "Unknown DISK field: {}",
-                        field
-                    ))),
-                }
-            } else {
-                Ok("No disks".to_string())
+            // The root may be on overlay, so look among every mount
+            let info = query_disk(&crate::parser::FieldList::All, None, true)?;
+            match info.primary() {
+                Some(disk) => field_value(disk, field)?,
+                None => return Ok(None),
             }
         }
         QueryTarget::System => {
             let info = query_system(&crate::parser::FieldList::All)?;
-            match field.to_lowercase().as_str() {
-                "hostname" | "name" => Ok(info.hostname.clone()),
-                "os" | "os_name" => Ok(info.os_name.clone()),
-                "os_version" | "version" => Ok(info.os_version.clone()),
-                "kernel" | "kernel_version" => Ok(info.kernel_version.clone()),
-                "uptime" | "uptime_secs" => Ok(format!("{} seconds", info.uptime)),
-                _ => Err(ArtaError::ExecutionError(format!(
-                    "Unknown SYSTEM field: {}",
-                    field
-                ))),
-            }
+            field_value(&info, field)?
+        }
+        QueryTarget::SelfProc => {
+            let info = query_self_with(
+                context.system().lock().as_mut(),
+                &crate::parser::FieldList::All,
+            )?;
+            field_value(&info, field)?
         }
         QueryTarget::Network => {
-            let info = query_network(&crate::parser::FieldList::All)?;
-            if let Some(iface) = info.interfaces.first() {
-                match field.to_lowercase().as_str() {
-                    "name" => Ok(iface.name.clone()),
-                    "sent" | "bytes_sent" | "transmitted" => {
-                        Ok(bytesize::ByteSize(iface.transmitted).to_string())
-                    }
-                    "recv" | "received" | "bytes_recv" => {
-                        Ok(bytesize::ByteSize(iface.received).to_string())
-                    }
-                    _ => Err(ArtaError::ExecutionError(format!(
-                        "Unknown NETWORK field: {}",
-                        field
-                    ))),
-                }
-            } else {
-                Ok("No network interfaces".to_string())
+            let info = query_network(&crate::parser::FieldList::All, None, false)?;
+            match info.interfaces.first() {
+                Some(iface) => field_value(iface, field)?,
+                None => return Ok(None),
             }
         }
-        _ => Err(ArtaError::ExecutionError(format!(
-            "PRINT not supported for {} queries",
-            target
-        ))),
+        _ => {
+            return Err(ArtaError::ExecutionError(format!(
+                "PRINT not supported for {} queries",
+                target
+            )))
+        }
+    };
+
+    Ok(Some(value))
+}
+
+/// Printed in place of a field when the target has no devices
+fn no_device_message(target: QueryTarget) -> &'static str {
+    match target {
+        QueryTarget::Battery => "No battery",
+        QueryTarget::Disk => "No disks",
+        QueryTarget::Network => "No network interfaces",
+        _ => "Not available",
+    }
+}
+
+/// Apply a query's ORDER BY and LIMIT clauses to its rows
+fn order_and_limit<T: FieldSource>(rows: &mut Vec<T>, query: &QueryCommand) -> Result<()> {
+    if let Some(order) = &query.order_by {
+        sort_by_field(rows, order)?;
+    }
+    if let Some(limit) = query.limit {
+        rows.truncate(limit);
     }
+    Ok(())
+}
+
+/// Reduce a query result to the explicitly selected fields
+fn project_result(data: ResultData, fields: &[String]) -> Result<ResultData> {
+    let rows = |rows: Result<Vec<ProjectedRow>>| rows.map(Projection::Rows);
+
+    let projection = match &data {
+        ResultData::Memory(info) => Projection::Single(ProjectedRow::project(info, fields)?),
+        ResultData::Cpu(info) => Projection::Single(ProjectedRow::project(info, fields)?),
+        ResultData::System(info) => Projection::Single(ProjectedRow::project(info, fields)?),
+        ResultData::SelfProc(info) => Projection::Single(ProjectedRow::project(info, fields)?),
+        ResultData::Disk(info) => rows(
+            info.disks
+                .iter()
+                .map(|d| ProjectedRow::project(d, fields))
+                .collect(),
+        )?,
+        ResultData::Battery(info) => rows(
+            info.batteries
+                .iter()
+                .map(|b| ProjectedRow::project(b, fields))
+                .collect(),
+        )?,
+        ResultData::Network(info) => rows(
+            info.interfaces
+                .iter()
+                .map(|i| ProjectedRow::project(i, fields))
+                .collect(),
+        )?,
+        ResultData::Services(services) => rows(
+            services
+                .iter()
+                .map(|s| ProjectedRow::project(s, fields))
+                .collect(),
+        )?,
+        ResultData::Custom(result) => {
+            return project_custom(result, fields).map(ResultData::Custom);
+        }
+        // Other targets have their own listing formats
+        _ => return Ok(data),
+    };
+
+    Ok(ResultData::Projection(projection))
+}
+
+/// Keep only the selected keys of a custom target's object value
+fn project_custom(result: &CustomResult, fields: &[String]) -> Result<CustomResult> {
+    let serde_json::Value::Object(object) = &result.value else {
+        return Ok(result.clone());
+    };
+
+    let projected = fields
+        .iter()
+        .map(|field| {
+            object
+                .get(field)
+                .map(|value| (field.clone(), value.clone()))
+                .ok_or_else(|| {
+                    ArtaError::ExecutionError(format!("Unknown {} field: {}", result.target, field))
+                })
+        })
+        .collect::<Result<serde_json::Map<_, _>>>()?;
+
+    Ok(CustomResult {
+        target: result.target.clone(),
+        value: serde_json::Value::Object(projected),
+    })
 }
 
 fn execute_explain(cmd: &Command, _ctx: &ExecutionContext) -> Result<ExecutionResult> {
@@ -913,14 +2315,36 @@ fn execute_explain(cmd: &Command, _ctx: &ExecutionContext) -> Result<ExecutionRe
                     .unwrap_or("(all files - DANGEROUS!)")
             )
         }
-        Command::Action(ActionCommand::KillProcess(_)) => {
-            "EXPLAIN: Would kill processes matching filter criteria".to_string()
+        Command::Action(ActionCommand::KillProcess(k)) => format!(
+            "EXPLAIN: Would kill processes matching filter criteria{}",
+            if k.confirm_each {
+                ", asking before each"
+            } else {
+                ""
+            }
+        ),
+        Command::Action(ActionCommand::WriteFile(w)) => {
+            format!(
+                "EXPLAIN: Would {} {} bytes to '{}'",
+                if w.append { "append" } else { "write" },
+                w.content.len(),
+                w.path
+            )
         }
         Command::Context(c) => match c {
             ContextCommand::EnterFolder(p) => format!("EXPLAIN: Would enter folder '{}'", p),
             ContextCommand::EnterFile(p) => format!("EXPLAIN: Would enter file '{}'", p),
             ContextCommand::Exit => "EXPLAIN: Would exit current context".to_string(),
-            ContextCommand::Reset => "EXPLAIN: Would reset context to initial state".to_string(),
+            ContextCommand::Up => "EXPLAIN: Would move up to the parent folder".to_string(),
+            ContextCommand::Reset(ResetTarget::Context) => {
+                "EXPLAIN: Would reset context to initial state".to_string()
+            }
+            ContextCommand::Reset(ResetTarget::Variables) => {
+                "EXPLAIN: Would clear all variables, keeping the folder stack".to_string()
+            }
+            ContextCommand::Reset(ResetTarget::History) => {
+                "EXPLAIN: Would clear the navigation history, keeping the folder stack".to_string()
+            }
             ContextCommand::Show(t) => format!("EXPLAIN: Would show {}", t),
         },
         Command::Let(l) => {
@@ -955,14 +2379,33 @@ fn execute_explain(cmd: &Command, _ctx: &ExecutionContext) -> Result<ExecutionRe
         }
         Command::Life(l) => {
             format!(
-                "EXPLAIN: Would start LIFE monitoring for {} and execute {} statement(s) on changes",
+                "EXPLAIN: Would start LIFE monitoring for {} and execute {} statement(s) {}{}",
                 l.target,
+                l.body.len(),
+                l.average_secs
+                    .map(|s| format!("with averages every {}s", s))
+                    .unwrap_or_else(|| "on changes".to_string()),
+                l.idle_secs
+                    .map(|s| format!(", stopping after {}s without a change", s))
+                    .unwrap_or_default()
+            )
+        }
+        Command::Label(l) => {
+            format!(
+                "EXPLAIN: Would run section '{}' with {} statement(s)",
+                l.name,
                 l.body.len()
             )
         }
         Command::Print(p) => {
             format!("EXPLAIN: Would print {} expression(s)", p.expressions.len())
         }
+        Command::Define(d) => format!(
+            "EXPLAIN: Would define macro '{}' with {} statement(s)",
+            d.name,
+            d.body.len()
+        ),
+        Command::RunMacro(name) => format!("EXPLAIN: Would run macro '{}'", name),
         Command::Container(c) => {
             match c {
                 ContainerCommand::Create(create) => {
@@ -977,7 +2420,10 @@ fn execute_explain(cmd: &Command, _ctx: &ExecutionContext) -> Result<ExecutionRe
                 ContainerCommand::Switch(name) => {
                     format!("EXPLAIN: Would switch to container '{}'", name)
                 }
-                ContainerCommand::List => "EXPLAIN: Would list all containers".to_string(),
+                ContainerCommand::List(None) => "EXPLAIN: Would list all containers".to_string(),
+                ContainerCommand::List(Some(_)) => {
+                    "EXPLAIN: Would list containers matching the WHERE clause".to_string()
+                }
                 ContainerCommand::Destroy(name) => {
                     format!("EXPLAIN: Would destroy container '{}'", name)
                 }
@@ -985,109 +2431,407 @@ fn execute_explain(cmd: &Command, _ctx: &ExecutionContext) -> Result<ExecutionRe
                     "EXPLAIN: Would export container '{}' to '{}'",
                     e.name, e.path
                 ),
+                ContainerCommand::ExportSession(e) => {
+                    format!("EXPLAIN: Would export all containers to '{}'", e.path)
+                }
             }
         }
         Command::Explain(_) => "EXPLAIN: Nested EXPLAIN not supported".to_string(),
+        Command::Diff(d) => format!("EXPLAIN: Would diff '{}' against '{}'", d.old, d.new),
+        Command::Pipeline(p) => format!(
+            "EXPLAIN: Would query {} and pass the rows to {}",
+            p.source.target, p.sink
+        ),
+        Command::Check(c) => format!(
+            "EXPLAIN: Would check whether {} {} {} {}",
+            c.target, c.field, c.operator, c.value
+        ),
     };
 
-    Ok(ExecutionResult {
-        data: ResultData::Explanation(explanation),
-        message: None,
-    })
+    Ok(ExecutionResult::new(ResultData::Explanation(explanation)))
 }
 
 // Query helpers for new targets
 
+/// Entries of `path` that match a WHERE clause, sorted by name
+///
+/// Unknown WHERE fields match (see `filter::matches_lenient`); callers that
+/// must not widen a selection check them first.
 fn query_files(
     path: &std::path::Path,
     where_clause: Option<&crate::parser::WhereClause>,
+    sizes: bool,
+    ownership: bool,
+    cancel: Option<&CancellationToken>,
 ) -> Result<Vec<FileEntry>> {
-    use std::fs;
-
-    if !path.exists() {
-        return Err(ArtaError::PathNotFound(path.display().to_string()));
+    if let Some(wc) = where_clause {
+        filter::check_patterns(wc)?;
+        filter::check_times(wc, QueryTarget::Files, "modified")?;
     }
 
-    if !path.is_dir() {
-        return Err(ArtaError::ExecutionError(format!(
-            "'{}' is not a directory",
-            path.display()
-        )));
+    let mut entries = list_files(path, sizes, ownership, cancel)?;
+    if let Some(wc) = where_clause {
+        entries.retain(|entry| filter::matches_lenient(wc, |c| file_condition(entry, c)));
     }
+    Ok(entries)
+}
+
+/// Every entry of a directory, sorted by name
+///
+/// Directory sizes (`sizes`) and owner, group and mode (`ownership`) are only
+/// looked up when asked for.
+pub(crate) fn list_files(
+    path: &std::path::Path,
+    sizes: bool,
+    ownership: bool,
+    cancel: Option<&CancellationToken>,
+) -> Result<Vec<FileEntry>> {
+    use std::fs;
+
+    crate::context::ensure_folder(path)?;
 
     let mut entries = Vec::new();
+    let mut owner_names = OwnerNames::new();
 
     for entry in fs::read_dir(path).map_err(ArtaError::IoError)? {
+        check_cancelled(cancel)?;
         let entry = entry.map_err(ArtaError::IoError)?;
-        let metadata = entry.metadata().map_err(ArtaError::IoError)?;
+        // Describe links themselves, so broken links are listed instead of failing the scan
+        let metadata = fs::symlink_metadata(entry.path()).map_err(ArtaError::IoError)?;
         let file_path = entry.path();
+        let is_symlink = metadata.file_type().is_symlink();
 
-        let modified = metadata.modified().ok().map(|t| {
-            chrono::DateTime::<chrono::Utc>::from(t)
-                .format("%Y-%m-%d %H:%M")
-                .to_string()
-        });
-
-        let file_entry = FileEntry {
+        let mut file_entry = FileEntry {
             name: entry.file_name().to_string_lossy().to_string(),
             path: file_path.display().to_string(),
             size: metadata.len(),
             is_dir: metadata.is_dir(),
-            modified,
+            is_symlink,
+            symlink_target: is_symlink
+                .then(|| fs::read_link(&file_path).ok())
+                .flatten()
+                .map(|target| target.display().to_string()),
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
             extension: file_path
                 .extension()
                 .map(|e| e.to_string_lossy().to_string()),
+            dir_size: None,
+            owner: None,
+            group: None,
+            mode: None,
         };
 
-        // Apply filtering if WHERE clause exists
-        if let Some(wc) = where_clause {
-            if matches_file_filter(&file_entry, wc) {
-                entries.push(file_entry);
-            }
-        } else {
-            entries.push(file_entry);
+        if ownership {
+            let Ownership { owner, group, mode } = owner_names.ownership(&metadata);
+            file_entry.owner = owner;
+            file_entry.group = group;
+            file_entry.mode = mode;
+        }
+
+        if sizes {
+            file_entry.dir_size = Some(if metadata.is_dir() {
+                directory_size(&file_path, cancel)?
+            } else {
+                metadata.len()
+            });
         }
+
+        entries.push(file_entry);
     }
 
-    // Sort by name
     entries.sort_by(|a, b| a.name.cmp(&b.name));
 
     Ok(entries)
 }
 
-fn matches_file_filter(_entry: &FileEntry, _where_clause: &crate::parser::WhereClause) -> bool {
-    // TODO: Implement proper WHERE filtering for files
-    // For now, accept all
-    true
+/// Total size of the files below a directory, like `du -s`
+///
+/// Symlinks are skipped rather than followed, so link cycles cannot recurse
+/// forever. Entries that cannot be read are skipped too.
+fn directory_size(path: &std::path::Path, cancel: Option<&CancellationToken>) -> Result<u64> {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return Ok(0);
+    };
+
+    let mut total = 0;
+    for entry in entries.flatten() {
+        check_cancelled(cancel)?;
+        let Ok(metadata) = std::fs::symlink_metadata(entry.path()) else {
+            continue;
+        };
+        if metadata.file_type().is_symlink() {
+            continue;
+        }
+        total += if metadata.is_dir() {
+            directory_size(&entry.path(), cancel)?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
 }
 
-fn query_content(
-    path: &std::path::Path,
-    where_clause: Option<&crate::parser::WhereClause>,
-) -> Result<ContentInfo> {
-    use std::fs;
-    use std::io::{BufRead, BufReader};
+/// Test one condition against a file; `None` when FILES has no such field
+///
+/// Every field in [`known_fields`] is handled here, through its canonical name.
+pub(crate) fn file_condition(
+    entry: &FileEntry,
+    condition: &crate::parser::Condition,
+) -> Option<bool> {
+    use filter::{compare_bools, compare_numbers, compare_strings, compare_times};
+
+    let field = condition.field.to_lowercase();
+    let (base, measured) = match field.strip_suffix(filter::LENGTH_SUFFIX) {
+        Some(base) => (base, true),
+        None => (field.as_str(), false),
+    };
+    let canonical = canonical_field(QueryTarget::Files, base)?;
+    let text = |value: &Option<String>| value.as_deref().unwrap_or("").to_string();
+    let text_value = match canonical {
+        "name" => Some(entry.name.clone()),
+        "path" => Some(entry.path.clone()),
+        "extension" => Some(text(&entry.extension)),
+        "symlink_target" => entry.symlink_target.clone(),
+        "owner" => entry.owner.clone(),
+        "group" => entry.group.clone(),
+        "mode" => entry.mode.clone(),
+        _ => None,
+    };
 
-    if !path.exists() {
-        return Err(ArtaError::PathNotFound(path.display().to_string()));
+    if measured {
+        return filter::length_condition(condition, |_| text_value.as_deref());
     }
 
-    if !path.is_file() {
-        return Err(ArtaError::ExecutionError(format!(
-            "'{}' is not a file",
-            path.display()
-        )));
-    }
+    let op = &condition.operator;
+    let bytes = |actual: Option<u64>| match (actual, &condition.value) {
+        (Some(actual), Value::Number(n)) => compare_numbers(actual as f64, *n, op),
+        (Some(actual), Value::Size(s)) => compare_numbers(actual as f64, *s as f64, op),
+        _ => false,
+    };
+    let flag = |actual: bool| match &condition.value {
+        Value::Boolean(b) => compare_bools(actual, *b, op),
+        _ => false,
+    };
+    Some(match canonical {
+        "size" => bytes(Some(entry.size)),
+        "dir_size" => bytes(entry.dir_size),
+        "is_dir" => flag(entry.is_dir),
+        "is_symlink" => flag(entry.is_symlink),
+        "modified" => match (entry.modified, &condition.value) {
+            (Some(modified), Value::String(s)) => {
+                filter::parse_time(s).is_some_and(|time| compare_times(modified, time, op))
+            }
+            _ => false,
+        },
+        // A missing link target or owner never matches
+        "name" | "path" | "extension" | "symlink_target" => match (&text_value, &condition.value) {
+            (Some(actual), Value::String(s)) => compare_strings(actual, s, op),
+            _ => false,
+        },
+        "owner" | "group" | "mode" => match (&text_value, &condition.value) {
+            (Some(actual), Value::String(s) | Value::Identifier(s)) => {
+                compare_strings(actual, s, op)
+            }
+            _ => false,
+        },
+        _ => return None,
+    })
+}
+
+/// Read a file's lines, optionally filtered by a `line`/`content` pattern
+///
+/// Lines are numbered when `line_numbers` says so; by default only filtered
+/// output is numbered. Long lines are cut (patterns only see the kept part)
+/// and reading stops at the scan limit, see `ContentLimits`.
+fn query_content(
+    path: &std::path::Path,
+    where_clause: Option<&crate::parser::WhereClause>,
+    line_numbers: Option<bool>,
+    limits: &ContentLimits,
+) -> Result<ContentInfo> {
+    query_content_until(path, where_clause, line_numbers, limits, None)
+}
+
+/// `query_content`, reading no further than byte `end` of the file if given
+fn query_content_until(
+    path: &std::path::Path,
+    where_clause: Option<&crate::parser::WhereClause>,
+    line_numbers: Option<bool>,
+    limits: &ContentLimits,
+    end: Option<u64>,
+) -> Result<ContentInfo> {
+    use std::fs;
+    use std::io::Read;
+
+    crate::context::ensure_file(path)?;
 
     let metadata = fs::metadata(path).map_err(ArtaError::IoError)?;
-    let file = fs::File::open(path).map_err(ArtaError::IoError)?;
-    let reader = BufReader::new(file);
+    let mut reader = open_content_reader(path)?;
+    if let Some(end) = end {
+        reader = Box::new(reader.take(end));
+    }
 
     let mut lines: Vec<String> = Vec::new();
+    let mut numbers = Vec::new();
     let mut total_lines = 0;
+    let mut scanned: u64 = 0;
+    let mut scan_truncated = false;
+    let mut buf = Vec::new();
+
+    let pattern = content_pattern(where_clause);
+
+    let numbered = line_numbers.unwrap_or(pattern.is_some());
+    let render = |number: usize, line: String| {
+        if numbered {
+            format!("{:>4}: {}", number, line)
+        } else {
+            line
+        }
+    };
+
+    loop {
+        if scanned >= limits.max_scan_bytes {
+            scan_truncated = true;
+            break;
+        }
+        let Some((consumed, cut)) = read_bounded_line(&mut reader, limits.max_line_bytes, &mut buf)
+            .map_err(ArtaError::IoError)?
+        else {
+            break;
+        };
+        scanned += consumed;
+        let i = total_lines;
+        total_lines += 1;
+
+        let mut line = String::from_utf8_lossy(&buf).into_owned();
+        if cut {
+            line.push_str(" [truncated]");
+        }
+
+        // Limit to first 100 lines if no filter
+        let keep = match pattern {
+            Some(ref pat) => line.contains(pat),
+            None => lines.len() < 100,
+        };
+        if keep {
+            lines.push(render(i + 1, line));
+            numbers.push(i + 1);
+        }
+    }
+
+    Ok(ContentInfo {
+        file_path: path.display().to_string(),
+        lines,
+        total_lines,
+        file_size: end.unwrap_or(metadata.len()),
+        scan_truncated,
+        line_numbers: numbers,
+        numbered,
+    })
+}
+
+/// Count a file's lines, words, characters and bytes without buffering it
+///
+/// Only the first `max_line_bytes` of each line are kept, for the WHERE
+/// pattern; counting itself sees every byte up to the scan limit. Characters
+/// are counted as UTF-8 sequences and words are split on ASCII whitespace.
+fn content_stats(
+    path: &std::path::Path,
+    where_clause: Option<&crate::parser::WhereClause>,
+    limits: &ContentLimits,
+) -> Result<ContentStats> {
+    crate::context::ensure_file(path)?;
+
+    let mut reader = open_content_reader(path)?;
+    let pattern = content_pattern(where_clause);
+    let keep = if pattern.is_some() {
+        limits.max_line_bytes
+    } else {
+        0
+    };
+
+    let mut stats = ContentStats {
+        file_path: path.display().to_string(),
+        ..Default::default()
+    };
+    let mut line = LineCounter::default();
+    let finish = |line: LineCounter, stats: &mut ContentStats| {
+        let matched = pattern
+            .as_ref()
+            .is_none_or(|p| String::from_utf8_lossy(&line.text).contains(p.as_str()));
+        if matched {
+            stats.lines += 1;
+            stats.words += line.words;
+            stats.chars += line.chars;
+            stats.bytes += line.bytes;
+        }
+    };
 
-    // Check for pattern filter in WHERE clause
-    let pattern = where_clause.and_then(|wc| {
+    let mut scanned: u64 = 0;
+    loop {
+        if scanned >= limits.max_scan_bytes {
+            stats.scan_truncated = true;
+            break;
+        }
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(ArtaError::IoError(e)),
+        };
+        if available.is_empty() {
+            break;
+        }
+
+        let room = usize::try_from(limits.max_scan_bytes - scanned).unwrap_or(usize::MAX);
+        let taken = available.len().min(room);
+        for &byte in &available[..taken] {
+            line.push(byte, keep);
+            if byte == b'\n' {
+                finish(std::mem::take(&mut line), &mut stats);
+            }
+        }
+        reader.consume(taken);
+        scanned += taken as u64;
+    }
+    if line.bytes > 0 {
+        finish(line, &mut stats);
+    }
+
+    Ok(stats)
+}
+
+/// Counts for the line being read by `content_stats`
+#[derive(Default)]
+struct LineCounter {
+    words: u64,
+    chars: u64,
+    bytes: u64,
+    in_word: bool,
+    /// Start of the line, for pattern matching
+    text: Vec<u8>,
+}
+
+impl LineCounter {
+    fn push(&mut self, byte: u8, keep: usize) {
+        self.bytes += 1;
+        // UTF-8 continuation bytes do not start a new character
+        if byte & 0xC0 != 0x80 {
+            self.chars += 1;
+        }
+        let space = byte.is_ascii_whitespace();
+        if !space && !self.in_word {
+            self.words += 1;
+        }
+        self.in_word = !space;
+        if byte != b'\n' && self.text.len() < keep {
+            self.text.push(byte);
+        }
+    }
+}
+
+/// Substring a CONTENT query filters lines on (`WHERE line = "..."`)
+fn content_pattern(where_clause: Option<&crate::parser::WhereClause>) -> Option<String> {
+    where_clause.and_then(|wc| {
         wc.conditions.first().and_then(|c| {
             if c.condition.field.to_lowercase() == "content"
                 || c.condition.field.to_lowercase() == "line"
@@ -1100,28 +2844,1589 @@ fn query_content(
                 None
             }
         })
-    });
+    })
+}
 
-    for (i, line_result) in reader.lines().enumerate() {
-        let line = line_result.map_err(ArtaError::IoError)?;
-        total_lines = i + 1;
+/// Read one line into `buf`, keeping at most `max_bytes` of it
+///
+/// The rest of an overlong line is skipped without being buffered. Returns
+/// the number of bytes consumed and whether the line was cut, or `None` at
+/// end of input.
+fn read_bounded_line(
+    reader: &mut dyn std::io::BufRead,
+    max_bytes: usize,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<Option<(u64, bool)>> {
+    buf.clear();
+    let mut consumed: u64 = 0;
+    let mut cut = false;
 
-        if let Some(ref pat) = pattern {
-            if line.contains(pat) {
-                lines.push(format!("{:>4}: {}", i + 1, line));
-            }
-        } else {
-            // Limit to first 100 lines if no filter
-            if lines.len() < 100 {
-                lines.push(line);
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            if consumed == 0 {
+                return Ok(None);
             }
+            break;
+        }
+
+        let (chunk, done) = match available.iter().position(|&b| b == b'\n') {
+            Some(pos) => (&available[..pos], Some(pos + 1)),
+            None => (available, None),
+        };
+        let room = max_bytes.saturating_sub(buf.len());
+        if chunk.len() > room {
+            cut = true;
+        }
+        buf.extend_from_slice(&chunk[..chunk.len().min(room)]);
+
+        let used = done.unwrap_or(available.len());
+        reader.consume(used);
+        consumed += used as u64;
+        if done.is_some() {
+            break;
         }
     }
 
-    Ok(ContentInfo {
-        file_path: path.display().to_string(),
-        lines,
-        total_lines,
-        file_size: metadata.len(),
+    if buf.last() == Some(&b'\r') {
+        buf.pop();
+    }
+    Ok(Some((consumed, cut)))
+}
+
+/// Open a file for line reading, decompressing gzip, bzip2 and xz transparently
+///
+/// The format is detected from the file's magic bytes, so rotated logs are
+/// handled regardless of their extension.
+fn open_content_reader(path: &std::path::Path) -> Result<Box<dyn std::io::BufRead>> {
+    use std::io::BufReader;
+
+    let file = std::fs::File::open(path).map_err(ArtaError::IoError)?;
+    let reader: Box<dyn std::io::BufRead> = match compression_of(path)? {
+        Some("gzip") => Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file))),
+        Some("bzip2") => Box::new(BufReader::new(bzip2::read::MultiBzDecoder::new(file))),
+        Some(_) => Box::new(BufReader::new(xz2::read::XzDecoder::new_multi_decoder(
+            file,
+        ))),
+        None => Box::new(BufReader::new(file)),
+    };
+
+    Ok(reader)
+}
+
+/// Compression format of a file (`gzip`, `bzip2` or `xz`), from its magic bytes
+fn compression_of(path: &std::path::Path) -> Result<Option<&'static str>> {
+    use std::io::Read;
+
+    const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+    const BZIP2_MAGIC: &[u8] = b"BZh";
+    const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+    let file = std::fs::File::open(path).map_err(ArtaError::IoError)?;
+    let mut magic = Vec::with_capacity(XZ_MAGIC.len());
+    file.take(XZ_MAGIC.len() as u64)
+        .read_to_end(&mut magic)
+        .map_err(ArtaError::IoError)?;
+
+    Ok(if magic.starts_with(GZIP_MAGIC) {
+        Some("gzip")
+    } else if magic.starts_with(BZIP2_MAGIC) {
+        Some("bzip2")
+    } else if magic.starts_with(XZ_MAGIC) {
+        Some("xz")
+    } else {
+        None
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_command;
+    use std::fs::{self, File};
+    use tempfile::TempDir;
+
+    fn where_of(query: &str) -> crate::parser::WhereClause {
+        match parse_command(query).unwrap() {
+            Command::Query(q) => q.where_clause.unwrap(),
+            _ => panic!("Expected Query command"),
+        }
+    }
+
+    #[test]
+    fn test_export_session_needs_container_session() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.arta");
+        let cmd = parse_command(&format!("EXPORT SESSION TO \"{}\"", path.display())).unwrap();
+
+        let err = execute_command(&cmd, &ExecutionContext::default()).unwrap_err();
+        assert!(err.to_string().contains("container session"), "{}", err);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_shared_provider_avoids_full_refreshes() {
+        let ctx = ExecutionContext::default();
+        let mut context = Context::new();
+        let cmd = parse_command("SELECT MEMORY *").unwrap();
+
+        for _ in 0..10 {
+            execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+        }
+        let cmd = parse_command("SELECT CPU *").unwrap();
+        execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+        execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+
+        let stats = context.system().lock().stats();
+        assert_eq!(stats.full_refreshes, 0);
+        assert!(stats.selective_refreshes >= 12);
+    }
+
+    #[test]
+    fn test_full_strategy_refreshes_every_query() {
+        use crate::engine::provider::{RefreshStrategy, SysinfoProvider};
+
+        let ctx = ExecutionContext::default();
+        let mut context =
+            Context::new().with_system_provider(SysinfoProvider::new(RefreshStrategy::Full));
+        let cmd = parse_command("SELECT MEMORY *").unwrap();
+
+        for _ in 0..10 {
+            execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+        }
+
+        assert_eq!(context.system().lock().stats().full_refreshes, 10);
+    }
+
+    #[test]
+    fn test_write_interpolates_variables() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("flag");
+        let ctx = ExecutionContext {
+            allow_actions: true,
+            ..Default::default()
+        };
+        let mut context = Context::new();
+
+        for line in [
+            r#"LET host = "web1""#.to_string(),
+            format!(r#"WRITE "up: ${{host}}\n" TO "{}""#, path.display()),
+            format!(r#"WRITE "checked\n" TO "{}" APPEND"#, path.display()),
+        ] {
+            execute_command_with_context(&parse_command(&line).unwrap(), &ctx, &mut context)
+                .unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "up: web1\nchecked\n");
+
+        let cmd = parse_command(&format!(r#"WRITE "${{missing}}" TO "{}""#, path.display()));
+        assert!(execute_command_with_context(&cmd.unwrap(), &ctx, &mut context).is_err());
+
+        let denied = ExecutionContext::default();
+        let cmd = parse_command(&format!(r#"WRITE "x" TO "{}""#, path.display())).unwrap();
+        assert!(matches!(
+            execute_command_with_context(&cmd, &denied, &mut context),
+            Err(ArtaError::ActionsDisabled)
+        ));
+    }
+
+    #[test]
+    fn test_content_of_directory_suggests_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let query = format!("SELECT CONTENT * FROM \"{}\"", temp_dir.path().display());
+        let err = execute_command(
+            &parse_command(&query).unwrap(),
+            &ExecutionContext::default(),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("Expected a file but"));
+        assert!(err.contains("did you mean SELECT FILES * FROM"));
+    }
+
+    #[test]
+    fn test_relative_delete_resolves_against_entered_folder() {
+        let temp_dir = TempDir::new().unwrap();
+        let logs = temp_dir.path().join("logs");
+        fs::create_dir(&logs).unwrap();
+        fs::write(logs.join("app.log"), "entry").unwrap();
+
+        let ctx = ExecutionContext {
+            dry_run: true,
+            ..Default::default()
+        };
+        let mut context = Context::new();
+        let cmd =
+            parse_command(&format!("ENTER FOLDER \"{}\"", temp_dir.path().display())).unwrap();
+        execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+
+        let cmd = parse_command("DELETE FILES FROM logs WHERE size > 0").unwrap();
+        let result = execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+        let ResultData::ActionResult(action) = &result.data else {
+            panic!("Expected action result");
+        };
+        assert_eq!(action.affected_count, 1);
+        let expected = logs.canonicalize().unwrap().join("app.log");
+        assert!(action.details[0].contains(&expected.display().to_string()));
+        assert!(logs.join("app.log").exists());
+    }
+
+    #[test]
+    fn test_show_stack_lists_entered_folders() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+
+        let ctx = ExecutionContext::default();
+        let mut context = Context::new();
+        for path in [temp_dir.path(), nested.as_path()] {
+            let cmd = parse_command(&format!("ENTER FOLDER \"{}\"", path.display())).unwrap();
+            execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+        }
+
+        let cmd = parse_command("SHOW STACK").unwrap();
+        let result = execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+        let ResultData::ContextInfo(info) = &result.data else {
+            panic!("Expected context info");
+        };
+        assert_eq!(info.stack.len(), 3);
+        assert_eq!(
+            info.stack[1],
+            temp_dir
+                .path()
+                .canonicalize()
+                .unwrap()
+                .display()
+                .to_string()
+        );
+        assert_eq!(
+            info.stack[2],
+            nested.canonicalize().unwrap().display().to_string()
+        );
+
+        let output = crate::output::format_output(&result, &OutputFormat::Human);
+        assert!(output.contains("Folder Stack"));
+        assert!(output.contains("2: ") && output.ends_with("(current)\n"));
+    }
+
+    #[test]
+    fn test_files_with_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        let big = temp_dir.path().join("big");
+        fs::create_dir_all(big.join("nested")).unwrap();
+        fs::write(big.join("a.bin"), vec![0u8; 100]).unwrap();
+        fs::write(big.join("nested").join("b.bin"), vec![0u8; 50]).unwrap();
+        fs::create_dir(temp_dir.path().join("small")).unwrap();
+        fs::write(temp_dir.path().join("small").join("c.bin"), vec![0u8; 10]).unwrap();
+        fs::write(temp_dir.path().join("top.bin"), vec![0u8; 20]).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(temp_dir.path(), big.join("nested").join("loop")).unwrap();
+
+        let plain = query_files(temp_dir.path(), None, false, false, None).unwrap();
+        assert!(plain.iter().all(|f| f.dir_size.is_none()));
+
+        let ctx = ExecutionContext::default();
+        let mut context = Context::new();
+        let cmd = parse_command(&format!(
+            "SELECT FILES * FROM \"{}\" ORDER BY dir_size DESC",
+            temp_dir.path().display()
+        ))
+        .unwrap();
+        let result = execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+        let ResultData::Files(files) = result.data else {
+            panic!("Expected files");
+        };
+        let sizes: Vec<(&str, Option<u64>)> = files
+            .iter()
+            .map(|f| (f.name.as_str(), f.dir_size))
+            .collect();
+        assert_eq!(
+            sizes,
+            [
+                ("big", Some(150)),
+                ("top.bin", Some(20)),
+                ("small", Some(10))
+            ]
+        );
+
+        // Filtering on dir_size walks the directories even without WITH SIZES
+        for query in [
+            "SELECT FILES * FROM \"{}\" WHERE dir_size > 100",
+            "SELECT FILES * FROM \"{}\" WHERE dir_size > 100 WITH SIZES",
+        ] {
+            let cmd = parse_command(&query.replace("{}", &temp_dir.path().display().to_string()))
+                .unwrap();
+            let result = execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+            let names: Vec<String> = match result.data {
+                ResultData::Files(files) => files.into_iter().map(|f| f.name).collect(),
+                other => panic!("Expected files, got {:?}", other),
+            };
+            assert_eq!(names, ["big"], "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_query_files_stops_when_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        for i in 0..5 {
+            File::create(temp_dir.path().join(format!("file{}.txt", i))).unwrap();
+        }
+
+        let token = CancellationToken::new();
+        assert_eq!(
+            query_files(temp_dir.path(), None, false, false, Some(&token))
+                .unwrap()
+                .len(),
+            5
+        );
+
+        token.cancel();
+        let err = query_files(temp_dir.path(), None, false, false, Some(&token)).unwrap_err();
+        assert!(err.to_string().contains("cancelled"));
+
+        let ctx = ExecutionContext::default().with_cancellation(token);
+        let mut context = Context::new();
+        let cmd = parse_command(&format!(
+            "SELECT FILES * FROM \"{}\"",
+            temp_dir.path().display()
+        ))
+        .unwrap();
+        assert!(execute_command_with_context(&cmd, &ctx, &mut context).is_err());
+    }
+
+    #[test]
+    fn test_pipeline_count_and_delete() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["a.log", "b.log", "c.txt"] {
+            File::create(temp_dir.path().join(name)).unwrap();
+        }
+        fs::create_dir(temp_dir.path().join("old.log")).unwrap();
+        let run = |query: &str, ctx: &ExecutionContext| {
+            let query = query.replace("DIR", &temp_dir.path().display().to_string());
+            execute_command_with_context(&parse_command(&query)?, ctx, &mut Context::new())
+        };
+        let count = |query: &str| match run(query, &ExecutionContext::default()).unwrap().data {
+            ResultData::Projection(Projection::Single(row)) => row.0,
+            other => panic!("Expected a count, got {:?}", other),
+        };
+
+        assert_eq!(
+            count(r#"SELECT FILES * FROM "DIR" WHERE extension = "log" | COUNT"#),
+            [("count".to_string(), FieldValue::Count(3))]
+        );
+        assert_eq!(
+            count(r#"SELECT FILES name FROM "DIR" LIMIT 2 | COUNT"#),
+            [("count".to_string(), FieldValue::Count(2))]
+        );
+
+        let delete = r#"SELECT FILES * FROM "DIR" WHERE extension = "log" | DELETE"#;
+        assert!(matches!(
+            run(delete, &ExecutionContext::default()),
+            Err(ArtaError::ActionsDisabled)
+        ));
+        let unfiltered = run(
+            r#"SELECT FILES * FROM "DIR" | DELETE"#,
+            &ExecutionContext {
+                dry_run: true,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(unfiltered, Err(ArtaError::SecurityError(_))));
+
+        let dry_run = ExecutionContext {
+            dry_run: true,
+            explain_matches: true,
+            ..Default::default()
+        };
+        // Unknown fields are refused rather than matching every row
+        let err = run(
+            r#"SELECT FILES * FROM "DIR" WHERE extnsion = "log" | DELETE"#,
+            &dry_run,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("extnsion"), "{}", err);
+        match run(
+            r#"SELECT FILES * FROM "DIR" WHERE modified < "2000-01-01" | DELETE"#,
+            &dry_run,
+        )
+        .unwrap()
+        .data
+        {
+            ResultData::ActionResult(action) => assert_eq!(action.affected_count, 0),
+            other => panic!("Expected an action result, got {:?}", other),
+        }
+        match run(delete, &dry_run).unwrap().data {
+            ResultData::ActionResult(action) => {
+                assert!(action.details[0].contains("matched: extension = \"log\""))
+            }
+            other => panic!("Expected an action result, got {:?}", other),
+        }
+
+        let ctx = ExecutionContext {
+            allow_actions: true,
+            ..Default::default()
+        };
+        match run(delete, &ctx).unwrap().data {
+            // The old.log directory is skipped
+            ResultData::ActionResult(action) => assert_eq!(action.affected_count, 2),
+            other => panic!("Expected an action result, got {:?}", other),
+        }
+        assert!(!temp_dir.path().join("a.log").exists());
+        assert!(temp_dir.path().join("c.txt").exists());
+        assert!(temp_dir.path().join("old.log").is_dir());
+    }
+
+    #[test]
+    fn test_query_files_directories_only() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("subdir")).unwrap();
+        File::create(temp_dir.path().join("file.txt")).unwrap();
+
+        let wc = where_of("SELECT FILES * WHERE is_dir = true");
+        let files = query_files(temp_dir.path(), Some(&wc), false, false, None).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "subdir");
+        assert!(files[0].is_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_query_files_symlinks() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("real")).unwrap();
+        std::os::unix::fs::symlink(temp_dir.path().join("real"), temp_dir.path().join("link"))
+            .unwrap();
+        std::os::unix::fs::symlink("missing.txt", temp_dir.path().join("broken")).unwrap();
+
+        let files = query_files(temp_dir.path(), None, true, false, None).unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["broken", "link", "real"]);
+
+        let broken = &files[0];
+        assert!(broken.is_symlink && !broken.is_dir);
+        assert_eq!(broken.symlink_target.as_deref(), Some("missing.txt"));
+        // A link to a directory is not a directory, and WITH SIZES does not follow it
+        let link = &files[1];
+        assert!(link.is_symlink && !link.is_dir);
+        assert_eq!(link.dir_size, Some(link.size));
+        assert!(files[2].is_dir && !files[2].is_symlink);
+        assert_eq!(files[2].symlink_target, None);
+
+        let wc = where_of("SELECT FILES * WHERE is_symlink = true");
+        let links = query_files(temp_dir.path(), Some(&wc), false, false, None).unwrap();
+        assert_eq!(links.len(), 2);
+
+        let wc = where_of(r#"SELECT FILES * WHERE symlink_target LIKE "%.txt""#);
+        let links = query_files(temp_dir.path(), Some(&wc), false, false, None).unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].name, "broken");
+    }
+
+    #[test]
+    fn test_query_files_path_contains() {
+        let temp_dir = TempDir::new().unwrap();
+        File::create(temp_dir.path().join("app_cache.db")).unwrap();
+        File::create(temp_dir.path().join("notes.txt")).unwrap();
+
+        let wc = where_of("SELECT FILES * WHERE path CONTAINS \"cache\"");
+        let files = query_files(temp_dir.path(), Some(&wc), false, false, None).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "app_cache.db");
+    }
+
+    #[test]
+    fn test_query_files_name_matches() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["python3", "python2", "pip3"] {
+            File::create(temp_dir.path().join(name)).unwrap();
+        }
+
+        let wc = where_of("SELECT FILES * WHERE name MATCHES \"^py.*3$\"");
+        let files = query_files(temp_dir.path(), Some(&wc), false, false, None).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "python3");
+
+        let wc = where_of("SELECT FILES * WHERE name MATCHES \"^py(\"");
+        assert!(query_files(temp_dir.path(), Some(&wc), false, false, None).is_err());
+    }
+
+    #[test]
+    fn test_query_files_name_length() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["a.txt", "report.txt", "a-really-long-file-name.txt", "é.md"] {
+            File::create(temp_dir.path().join(name)).unwrap();
+        }
+
+        let wc = where_of("SELECT FILES * WHERE name.length > 10");
+        let files = query_files(temp_dir.path(), Some(&wc), false, false, None).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "a-really-long-file-name.txt");
+
+        // Characters, not bytes
+        let wc = where_of("SELECT FILES * WHERE name.length <= 4");
+        let files = query_files(temp_dir.path(), Some(&wc), false, false, None).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "é.md");
+
+        let wc = where_of("SELECT FILES * WHERE name.LENGTH = 10 AND extension.length = 3");
+        let files = query_files(temp_dir.path(), Some(&wc), false, false, None).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "report.txt");
+    }
+
+    #[test]
+    fn test_query_content_gzip() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("syslog.1.gz");
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Default::default());
+        encoder
+            .write_all(b"first line\nsecond line\nthird line\n")
+            .unwrap();
+        encoder.finish().unwrap();
+
+        let info = query_content(&path, None, None, &ContentLimits::default()).unwrap();
+        assert_eq!(info.lines, vec!["first line", "second line", "third line"]);
+        assert_eq!(info.total_lines, 3);
+
+        let wc = where_of("SELECT CONTENT * WHERE line CONTAINS \"second\"");
+        let info = query_content(&path, Some(&wc), None, &ContentLimits::default()).unwrap();
+        assert_eq!(info.lines, vec!["   2: second line"]);
+
+        // FOLLOW would tail the compressed bytes
+        let cmd = parse_command(&format!(
+            "SELECT CONTENT * FROM \"{}\" FOLLOW",
+            path.display()
+        ))
+        .unwrap();
+        let ctx = ExecutionContext::default().with_cancellation(CancellationToken::new());
+        let err = execute_command(&cmd, &ctx).unwrap_err().to_string();
+        assert!(
+            err.contains("cannot tail a gzip compressed file"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_query_content_until() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.log");
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+        // Lines past the end FOLLOW starts tailing from are left to the tail
+        let info =
+            query_content_until(&path, None, None, &ContentLimits::default(), Some(8)).unwrap();
+        assert_eq!(info.lines, vec!["one", "two"]);
+        assert_eq!(info.file_size, 8);
+    }
+
+    #[test]
+    fn test_query_content_with_line_numbers() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.txt");
+        std::fs::write(&path, "alpha\nbeta\n").unwrap();
+
+        let info = query_content(&path, None, Some(true), &ContentLimits::default()).unwrap();
+        assert_eq!(info.lines, vec!["   1: alpha", "   2: beta"]);
+    }
+
+    #[test]
+    fn test_query_content_without_line_numbers() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.txt");
+        std::fs::write(&path, "alpha\nbeta\n").unwrap();
+
+        let wc = where_of("SELECT CONTENT * WHERE line CONTAINS \"beta\"");
+        let info = query_content(&path, Some(&wc), Some(false), &ContentLimits::default()).unwrap();
+        assert_eq!(info.lines, vec!["beta"]);
+    }
+
+    #[test]
+    fn test_query_content_truncates_huge_line() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("minified.js");
+        let huge = "x".repeat(5 * 1024 * 1024);
+        std::fs::write(&path, format!("{}\nshort\n", huge)).unwrap();
+
+        let limits = ContentLimits::default();
+        let info = query_content(&path, None, None, &limits).unwrap();
+
+        assert_eq!(info.total_lines, 2);
+        assert!(!info.scan_truncated);
+        assert_eq!(
+            info.lines[0].len(),
+            limits.max_line_bytes + " [truncated]".len()
+        );
+        assert!(info.lines[0].ends_with("[truncated]"));
+        assert_eq!(info.lines[1], "short");
+    }
+
+    #[test]
+    fn test_content_count() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.log");
+        fs::write(&path, "héllo world\nfoo bar  baz\nERROR: disk full\ntail").unwrap();
+
+        let stats = |filter: &str| {
+            let cmd = parse_command(&format!(
+                "SELECT CONTENT COUNT FROM \"{}\" {}",
+                path.display(),
+                filter
+            ))
+            .unwrap();
+            match execute_command(&cmd, &ExecutionContext::default())
+                .unwrap()
+                .data
+            {
+                ResultData::ContentStats(stats) => stats,
+                other => panic!("Expected content stats, got {:?}", other),
+            }
+        };
+
+        // Multi-byte characters count once in chars; the last line has no newline
+        let all = stats("");
+        assert_eq!((all.lines, all.words, all.chars, all.bytes), (4, 9, 46, 47));
+        assert!(!all.scan_truncated);
+
+        let errors = stats(r#"WHERE line = "ERROR""#);
+        assert_eq!(
+            (errors.lines, errors.words, errors.chars, errors.bytes),
+            (1, 3, 17, 17)
+        );
+    }
+
+    #[test]
+    fn test_query_content_scan_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("big.log");
+        std::fs::write(&path, "0123456789\n".repeat(1000)).unwrap();
+
+        // A single huge line hits the scan limit too
+        let huge_path = temp_dir.path().join("one-line.bin");
+        std::fs::write(&huge_path, "y".repeat(2 * 1024 * 1024)).unwrap();
+
+        let limits = ContentLimits {
+            max_line_bytes: 1024,
+            max_scan_bytes: 110,
+        };
+        let info = query_content(&path, None, None, &limits).unwrap();
+        assert!(info.scan_truncated);
+        assert_eq!(info.total_lines, 10);
+
+        let info = query_content(&huge_path, None, None, &limits).unwrap();
+        assert_eq!(info.total_lines, 1);
+        assert_eq!(info.lines[0].len(), 1024 + " [truncated]".len());
+    }
+
+    #[test]
+    fn test_select_newest_and_oldest_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let now = std::time::SystemTime::now();
+        for (name, hours_ago) in [("a.txt", 3), ("b.txt", 2), ("c.txt", 1), ("d.txt", 4)] {
+            let path = temp_dir.path().join(name);
+            let file = fs::File::create(&path).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(hours_ago * 3600))
+                .unwrap();
+        }
+
+        let names = |query: &str| {
+            let cmd = parse_command(&format!("{} FROM \"{}\"", query, temp_dir.path().display()))
+                .unwrap();
+            match execute_command(&cmd, &ExecutionContext::default())
+                .unwrap()
+                .data
+            {
+                ResultData::Files(files) => files.into_iter().map(|f| f.name).collect::<Vec<_>>(),
+                other => panic!("Expected files, got {:?}", other),
+            }
+        };
+
+        assert_eq!(names("SELECT FILES NEWEST 2"), ["c.txt", "b.txt"]);
+        assert_eq!(names("SELECT FILES OLDEST 1"), ["d.txt"]);
+    }
+
+    #[test]
+    fn test_modified_keeps_full_resolution() {
+        let temp_dir = TempDir::new().unwrap();
+        let now = std::time::SystemTime::now();
+        // Seconds apart, so the same minute as far as human output goes
+        for (name, secs_ago) in [("older.txt", 20), ("newer.txt", 10)] {
+            let file = fs::File::create(temp_dir.path().join(name)).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(secs_ago))
+                .unwrap();
+        }
+
+        let cmd = parse_command(&format!(
+            "SELECT FILES NEWEST 1 FROM \"{}\"",
+            temp_dir.path().display()
+        ))
+        .unwrap();
+        let result = execute_command(&cmd, &ExecutionContext::default()).unwrap();
+        let ResultData::Files(files) = &result.data else {
+            panic!("Expected files, got {:?}", result.data);
+        };
+        assert_eq!(files[0].name, "newer.txt");
+
+        let json = crate::output::json::json_value(&result);
+        let modified = json[0]["modified"].as_str().unwrap();
+        assert!(modified.ends_with("+00:00"), "{}", modified);
+        assert!(chrono::DateTime::parse_from_rfc3339(modified).is_ok());
+    }
+
+    #[test]
+    fn test_file_condition_handles_every_field() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::File::create(temp_dir.path().join("a.txt")).unwrap();
+        let files = list_files(temp_dir.path(), true, true, None).unwrap();
+
+        for field in known_fields(QueryTarget::Files) {
+            let condition = crate::parser::Condition {
+                field: field.to_string(),
+                operator: CompareOp::Equal,
+                value: Value::String(String::new()),
+            };
+            assert!(file_condition(&files[0], &condition).is_some(), "{}", field);
+        }
+    }
+
+    #[test]
+    fn test_files_where_modified() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::File::create(temp_dir.path().join("a.txt")).unwrap();
+        let count = |condition: &str| {
+            let cmd = parse_command(&format!(
+                "SELECT FILES * FROM \"{}\" WHERE {}",
+                temp_dir.path().display(),
+                condition
+            ))
+            .unwrap();
+            match execute_command(&cmd, &ExecutionContext::default())
+                .unwrap()
+                .data
+            {
+                ResultData::Files(files) => files.len(),
+                other => panic!("Expected files, got {:?}", other),
+            }
+        };
+
+        assert_eq!(count("modified < \"2000-01-01\""), 0);
+        assert_eq!(count("modified > \"2000-01-01\""), 1);
+
+        let cmd = parse_command(&format!(
+            "SELECT FILES * FROM \"{}\" WHERE modified < \"yesterday\"",
+            temp_dir.path().display()
+        ))
+        .unwrap();
+        let err = execute_command(&cmd, &ExecutionContext::default()).unwrap_err();
+        assert!(err.to_string().contains("modified"), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_files_owner_and_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("audit.txt");
+        fs::write(&path, "x").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        // SAFETY: geteuid has no preconditions
+        let uid = unsafe { libc::geteuid() };
+        let me = crate::engine::ownership::user_name(uid).unwrap_or_else(|| uid.to_string());
+        let files = |clause: &str| {
+            let cmd = parse_command(&format!(
+                "SELECT FILES * FROM \"{}\" {}",
+                temp_dir.path().display(),
+                clause
+            ))
+            .unwrap();
+            match execute_command(&cmd, &ExecutionContext::default())
+                .unwrap()
+                .data
+            {
+                ResultData::Files(files) => files,
+                other => panic!("Expected files, got {:?}", other),
+            }
+        };
+
+        let owned = files(&format!("WHERE owner = \"{}\"", me));
+        assert_eq!(owned.len(), 1);
+        assert_eq!(owned[0].owner.as_deref(), Some(me.as_str()));
+        assert_eq!(owned[0].mode.as_deref(), Some("rw-r-----"));
+        assert!(owned[0].group.is_some());
+
+        assert_eq!(files("WHERE mode CONTAINS \"w\"").len(), 1);
+        assert!(files("WHERE mode = \"rwxrwxrwx\"").is_empty());
+
+        // Plain listings skip the lookup
+        assert!(files("")
+            .iter()
+            .all(|f| f.owner.is_none() && f.mode.is_none()));
+    }
+
+    #[test]
+    fn test_strict_fields_reject_typos() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.log"), "x").unwrap();
+        fs::write(temp_dir.path().join("b.txt"), "y").unwrap();
+        let cmd = parse_command(&format!(
+            "SELECT FILES * FROM \"{}\" WHERE extention = \"log\"",
+            temp_dir.path().display()
+        ))
+        .unwrap();
+
+        // By default the unknown field is ignored and every file matches
+        match execute_command(&cmd, &ExecutionContext::default())
+            .unwrap()
+            .data
+        {
+            ResultData::Files(files) => assert_eq!(files.len(), 2),
+            other => panic!("Expected files, got {:?}", other),
+        }
+
+        let strict = ExecutionContext {
+            strict_fields: true,
+            ..Default::default()
+        };
+        let err = execute_command(&cmd, &strict).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Execution error: Unknown FILES field in WHERE: extention (did you mean extension?)"
+        );
+    }
+
+    #[test]
+    fn test_strict_fields_cover_services_and_network() {
+        let strict = ExecutionContext {
+            strict_fields: true,
+            ..Default::default()
+        };
+        for (query, expected) in [
+            (
+                "SELECT SERVICES * WHERE nmae = \"x\"",
+                "Execution error: Unknown SERVICES field in WHERE: nmae (did you mean name?)",
+            ),
+            (
+                "SELECT NETWORK * WHERE recieved > 0",
+                "Execution error: Unknown NETWORK field in WHERE: recieved (did you mean received?)",
+            ),
+        ] {
+            let cmd = parse_command(query).unwrap();
+            let err = execute_command(&cmd, &strict).unwrap_err();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_diff_files() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("hosts.bak"),
+            "127.0.0.1 localhost\n10.0.0.5 db\n10.0.0.6 cache\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("hosts"),
+            "127.0.0.1 localhost\n10.0.0.7 db\n10.0.0.6 cache\n10.0.0.8 queue\n",
+        )
+        .unwrap();
+
+        let mut ctx = ExecutionContext::default();
+        let mut context = Context::new();
+        let cmd =
+            parse_command(&format!("ENTER FOLDER \"{}\"", temp_dir.path().display())).unwrap();
+        execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+
+        // Relative paths resolve against the entered folder
+        let cmd = parse_command("DIFF \"hosts.bak\" \"hosts\"").unwrap();
+        let result = execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+        let ResultData::Diff(diff) = &result.data else {
+            panic!("Expected diff result, got {:?}", result.data);
+        };
+        assert_eq!((diff.added, diff.removed), (2, 1));
+        assert_eq!(diff.hunks.len(), 1);
+        let changes: Vec<String> = diff.hunks[0]
+            .lines
+            .iter()
+            .filter(|l| l.kind != crate::engine::diff::DiffLineKind::Context)
+            .map(|l| format!("{}{}", l.kind.marker(), l.text))
+            .collect();
+        assert_eq!(changes, ["-10.0.0.5 db", "+10.0.0.7 db", "+10.0.0.8 queue"]);
+
+        ctx.content_limits.max_scan_bytes = 16;
+        let err = execute_command_with_context(&cmd, &ctx, &mut context).unwrap_err();
+        assert!(err.to_string().contains("read limit"));
+    }
+
+    #[test]
+    fn test_used_percent_in_select() {
+        for target in ["MEMORY", "DISK"] {
+            let cmd = parse_command(&format!("SELECT {} used_percent", target)).unwrap();
+            let result = execute_command(&cmd, &ExecutionContext::default()).unwrap();
+            let row = match result.data {
+                ResultData::Projection(Projection::Single(row)) => row,
+                ResultData::Projection(Projection::Rows(rows)) => rows[0].clone(),
+                other => panic!("Expected projection, got {:?}", other),
+            };
+            assert_eq!(row.0.len(), 1);
+            assert_eq!(row.0[0].0, "used_percent");
+        }
+    }
+
+    #[test]
+    fn test_used_percent_in_if() {
+        for target in ["MEMORY", "DISK"] {
+            let cmd = parse_command(&format!(
+                "IF SELECT {} used_percent >= 0 THEN PRINT \"ok\" END IF",
+                target
+            ))
+            .unwrap();
+            let result = execute_command(&cmd, &ExecutionContext::default()).unwrap();
+            assert!(format!("{:?}", result.data).contains("ok"));
+        }
+    }
+
+    #[test]
+    fn test_every_rejects_zero_interval() {
+        let cmd = parse_command("SELECT CPU usage EVERY 0 SECONDS FOR 1 TIMES").unwrap();
+        let ctx = ExecutionContext::default().with_cancellation(CancellationToken::new());
+        assert!(execute_command(&cmd, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_every_writes_to_context_output() {
+        #[derive(Clone, Default)]
+        struct Captured(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let captured = Captured::default();
+        let ctx = ExecutionContext {
+            output_format: OutputFormat::Json,
+            cancellation: Some(CancellationToken::new()),
+            ..Default::default()
+        }
+        .with_output(captured.clone());
+        let cmd = parse_command("SELECT SYSTEM * EVERY 1 SECOND FOR 2 TIMES").unwrap();
+        let result = execute_command(&cmd, &ctx).unwrap();
+        assert!(matches!(result.data, ResultData::Empty));
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let samples = serde_json::Deserializer::from_str(&output)
+            .into_iter::<serde_json::Value>()
+            .count();
+        assert_eq!(samples, 2, "{}", output);
+    }
+
+    #[test]
+    fn test_let_overwrite_noted_when_verbose() {
+        let mut context = Context::new();
+        let run = |cmd: &str, verbose: bool, context: &mut Context| {
+            let ctx = ExecutionContext {
+                verbose,
+                ..Default::default()
+            };
+            match execute_command_with_context(&parse_command(cmd).unwrap(), &ctx, context)
+                .unwrap()
+                .data
+            {
+                ResultData::Message(msg) => msg,
+                other => panic!("Expected message, got {:?}", other),
+            }
+        };
+
+        assert!(!run("LET x = 1", true, &mut context).contains("overwrote"));
+        assert!(!run("LET x = 2", false, &mut context).contains("overwrote"));
+        assert!(run("LET x = 3", true, &mut context).ends_with("(overwrote 2)"));
+    }
+
+    #[test]
+    fn test_print_values_are_typed_under_json() {
+        let ctx = ExecutionContext {
+            output_format: OutputFormat::Json,
+            ..Default::default()
+        };
+        let mut context = Context::new();
+        for cmd in ["LET answer = 42", "LET cap = 2KB", "LET ok = true"] {
+            execute_command_with_context(&parse_command(cmd).unwrap(), &ctx, &mut context).unwrap();
+        }
+
+        let cmd =
+            parse_command(r#"PRINT answer, "status:", cap, ok, missing, MEMORY total"#).unwrap();
+        let result = execute_command_with_context(&cmd, &ctx, &mut context).unwrap();
+        let json = crate::output::json::json_value(&result);
+        let values = json["print"].as_array().unwrap();
+
+        assert_eq!(values[0], serde_json::json!(42.0));
+        assert_eq!(values[1], "status:");
+        assert_eq!(values[2], 2048);
+        assert_eq!(values[3], true);
+        assert!(values[4].is_null());
+        assert!(values[5].as_u64().unwrap() > 0);
+
+        // Human output still joins the values into one line
+        let result =
+            execute_command_with_context(&cmd, &ExecutionContext::default(), &mut context).unwrap();
+        let ResultData::Message(msg) = result.data else {
+            panic!("Expected message");
+        };
+        assert!(
+            msg.starts_with("42 status: 2.0 KB true <undefined: missing>"),
+            "{}",
+            msg
+        );
+    }
+
+    #[test]
+    fn test_used_percent_in_print() {
+        for target in ["MEMORY", "DISK"] {
+            let cmd = parse_command(&format!("PRINT {} used_percent", target)).unwrap();
+            let result = execute_command(&cmd, &ExecutionContext::default()).unwrap();
+            match result.data {
+                ResultData::Message(msg) => assert!(msg.ends_with('%'), "{}", msg),
+                other => panic!("Expected message, got {:?}", other),
+            }
+        }
+
+        // --precision applies as it does to query output
+        let ctx = ExecutionContext {
+            precision: 3,
+            ..Default::default()
+        };
+        let cmd = parse_command("PRINT MEMORY used_percent").unwrap();
+        match execute_command(&cmd, &ctx).unwrap().data {
+            ResultData::Message(msg) => {
+                let decimals = msg.trim_end_matches('%').split_once('.').unwrap().1;
+                assert_eq!(decimals.len(), 3, "{}", msg);
+            }
+            other => panic!("Expected message, got {:?}", other),
+        }
+    }
+
+    fn two_batteries(capacities: [Option<f64>; 2]) -> BatteryInfo {
+        use crate::engine::queries::battery::BatteryEntry;
+
+        let entry = |percentage, capacity_wh| BatteryEntry {
+            state: "Discharging".to_string(),
+            percentage,
+            time_to_empty: None,
+            time_to_full: None,
+            capacity_wh,
+        };
+        BatteryInfo {
+            batteries: vec![entry(80.0, capacities[0]), entry(20.0, capacities[1])],
+        }
+    }
+
+    #[test]
+    fn test_battery_percentage_averages_across_batteries() {
+        let info = two_batteries([None, None]);
+        assert_eq!(get_battery_field_value(&info, None, "level").unwrap(), 50.0);
+
+        // Weighted by capacity: (80 * 30 + 20 * 10) / 40
+        let info = two_batteries([Some(30.0), Some(10.0)]);
+        assert_eq!(get_battery_field_value(&info, None, "level").unwrap(), 65.0);
+    }
+
+    #[test]
+    fn test_battery_index_selects_one_battery() {
+        let info = two_batteries([Some(30.0), Some(10.0)]);
+        assert_eq!(
+            get_battery_field_value(&info, Some(1), "level").unwrap(),
+            20.0
+        );
+        assert_eq!(
+            battery_field_value(&info, Some(0), "percent")
+                .unwrap()
+                .unwrap()
+                .to_string(),
+            "80.0%"
+        );
+        assert!(get_battery_field_value(&info, Some(2), "level").is_err());
+    }
+
+    fn three_disks() -> DiskInfo {
+        use crate::engine::queries::disk::DiskEntry;
+
+        let entry = |mount: &str, usage_percent| DiskEntry {
+            name: mount.to_string(),
+            mount_point: mount.to_string(),
+            total: 100,
+            used: usage_percent as u64,
+            free: 100 - usage_percent as u64,
+            usage_percent,
+            file_system: "ext4".to_string(),
+        };
+        DiskInfo {
+            disks: vec![entry("/boot", 40.0), entry("/", 50.0), entry("/home", 95.0)],
+        }
+    }
+
+    fn disk_condition(if_stmt: &str) -> IfCondition {
+        match parse_command(&format!("{} THEN PRINT \"x\" END IF", if_stmt)).unwrap() {
+            Command::If(i) => i.condition,
+            _ => panic!("Expected If command"),
+        }
+    }
+
+    #[test]
+    fn test_disk_order_by_and_limit() {
+        let query = |s: &str| match parse_command(s).unwrap() {
+            Command::Query(q) => q,
+            _ => panic!("Expected Query command"),
+        };
+
+        let mut disks = three_disks().disks;
+        order_and_limit(
+            &mut disks,
+            &query("SELECT DISK * ORDER BY used_percent DESC LIMIT 1"),
+        )
+        .unwrap();
+        assert_eq!(disks.len(), 1);
+        assert_eq!(disks[0].mount_point, "/home");
+
+        let mut disks = three_disks().disks;
+        order_and_limit(&mut disks, &query("SELECT DISK * ORDER BY mount")).unwrap();
+        let mounts: Vec<_> = disks.iter().map(|d| d.mount_point.as_str()).collect();
+        assert_eq!(mounts, vec!["/", "/boot", "/home"]);
+
+        let mut disks = three_disks().disks;
+        assert!(order_and_limit(&mut disks, &query("SELECT DISK * ORDER BY bogus")).is_err());
+    }
+
+    #[test]
+    fn test_disk_condition_targets_mount() {
+        let info = three_disks();
+        let context = Context::new();
+        let eval =
+            |s: &str| evaluate_disk_condition(&info, &disk_condition(s), false, &context).unwrap();
+
+        // Without FROM the root mount is checked, not the first disk
+        assert!(!eval("IF SELECT DISK used_percent > 90"));
+        assert!(eval("IF SELECT DISK used_percent > 90 FROM /home"));
+        assert!(eval(
+            "IF SELECT DISK used_percent > 90 FROM /home/user/docs"
+        ));
+        assert!(!eval("IF SELECT DISK used_percent > 90 FROM /var"));
+    }
+
+    #[test]
+    fn test_disk_condition_pseudo_mounts() {
+        use crate::engine::queries::disk::DiskEntry;
+
+        let mut info = three_disks();
+        let entry = |mount: &str, usage_percent, file_system: &str| DiskEntry {
+            name: mount.to_string(),
+            mount_point: mount.to_string(),
+            total: 100,
+            used: usage_percent as u64,
+            free: 100 - usage_percent as u64,
+            usage_percent,
+            file_system: file_system.to_string(),
+        };
+        // A container root on overlay, and a full tmpfs
+        info.disks.retain(|d| d.mount_point != "/");
+        info.disks.push(entry("/", 99.0, "overlay"));
+        info.disks.push(entry("/run", 100.0, "tmpfs"));
+        let context = Context::new();
+        let eval = |s: &str, all_mounts| {
+            evaluate_disk_condition(&info, &disk_condition(s), all_mounts, &context).unwrap()
+        };
+
+        assert!(eval("IF SELECT DISK used_percent > 90", false));
+        assert!(eval(
+            "IF SELECT DISK used_percent > 99 FROM /run/user",
+            false
+        ));
+        assert!(!eval("IF ALL DISK used_percent > 90 FROM /run", false));
+        assert!(eval("IF ALL DISK used_percent > 90 FROM /run", true));
+        assert!(!eval("IF ANY DISK used_percent > 96", false));
+        assert!(eval("IF ANY DISK used_percent > 96", true));
+    }
+
+    #[test]
+    fn test_disk_condition_any_all() {
+        let info = three_disks();
+        let context = Context::new();
+        let eval =
+            |s: &str| evaluate_disk_condition(&info, &disk_condition(s), false, &context).unwrap();
+
+        assert!(eval("IF ANY DISK used_percent > 90"));
+        assert!(!eval("IF ALL DISK used_percent > 90"));
+        assert!(eval("IF ALL DISK used_percent > 30"));
+        assert!(!eval("IF ANY DISK used_percent > 90 FROM /boot"));
+        assert!(!eval("IF ALL DISK used_percent > 0 FROM /srv"));
+    }
+
+    #[test]
+    fn test_disk_alert() {
+        let info = three_disks();
+        let context = Context::new();
+        let alert = |s: &str| disk_alert(&info, &disk_condition(s), false, &context).unwrap();
+
+        assert_eq!(
+            alert("IF SELECT DISK used_percent > 90 FROM /home").as_deref(),
+            Some("ALERT: DISK used_percent > 90 FROM /home (/home: used_percent = 95.0%)")
+        );
+        assert_eq!(
+            alert("IF ANY DISK used_percent > 45").as_deref(),
+            Some("ALERT: ANY DISK used_percent > 45 (/: used_percent = 50.0%; /home: used_percent = 95.0%)")
+        );
+
+        // Below the threshold nothing is reported
+        assert_eq!(alert("IF SELECT DISK used_percent > 90 FROM /"), None);
+        assert_eq!(alert("IF ALL DISK used_percent > 45"), None);
+    }
+
+    #[test]
+    fn test_count_condition_with_where() {
+        let context = Context::new();
+        let check = |source: &str| match parse_command(source).unwrap() {
+            Command::Check(condition) => {
+                evaluate_if_condition(&condition, &ExecutionContext::default(), &context)
+            }
+            other => panic!("Expected Check command, got {:?}", other),
+        };
+
+        let pid = std::process::id();
+        assert!(check(&format!("SELECT PROCESS count = 1 WHERE pid = {}", pid)).unwrap());
+        assert!(!check("SELECT PROCESS count > 0 WHERE name = \"no-such-process-xyz\"").unwrap());
+        assert!(check("SELECT PROCESS count > 0").unwrap());
+
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("big.log"), vec![0u8; 4096]).unwrap();
+        std::fs::write(temp_dir.path().join("small.log"), "x").unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), "x").unwrap();
+        let dir = temp_dir.path().display();
+        assert!(check(&format!(
+            "SELECT FILES count = 2 FROM \"{}\" WHERE extension = \"log\"",
+            dir
+        ))
+        .unwrap());
+        assert!(check(&format!(
+            "SELECT FILES count = 1 FROM \"{}\" WHERE size > 1KB",
+            dir
+        ))
+        .unwrap());
+
+        // A misspelt field would otherwise count nothing
+        let err = check("SELECT PROCESS count > 0 WHERE nmae = \"node\"").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unknown PROCESS field in WHERE: nmae"));
+        assert!(check("SELECT PROCESS cpu > 0").is_err());
+    }
+
+    #[test]
+    fn test_where_thresholds_from_variables() {
+        let ctx = ExecutionContext {
+            dry_run: true,
+            ..Default::default()
+        };
+        let mut context = Context::new();
+        let mut run = |line: &str| {
+            execute_command_with_context(&parse_command(line).unwrap(), &ctx, &mut context)
+                .unwrap()
+                .data
+        };
+
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("big.log"), vec![0u8; 4096]).unwrap();
+        std::fs::write(temp_dir.path().join("small.log"), "x").unwrap();
+        let dir = temp_dir.path().display();
+
+        run(&format!("LET me = {}", std::process::id()));
+        run("LET min_size = 1KB");
+
+        match run("SELECT PROCESS * WHERE pid = me") {
+            ResultData::Processes(processes) => assert_eq!(processes.len(), 1),
+            other => panic!("Expected processes, got {:?}", other),
+        }
+        match run("KILL PROCESS WHERE pid = me") {
+            ResultData::ActionResult(action) => assert_eq!(action.affected_count, 1),
+            other => panic!("Expected action result, got {:?}", other),
+        }
+        match run(&format!(
+            "SELECT FILES * FROM \"{}\" WHERE size > min_size",
+            dir
+        )) {
+            ResultData::Files(files) => {
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].name, "big.log");
+            }
+            other => panic!("Expected files, got {:?}", other),
+        }
+        match run(&format!(
+            "DELETE FILES FROM \"{}\" WHERE size > min_size",
+            dir
+        )) {
+            ResultData::ActionResult(action) => assert_eq!(action.affected_count, 1),
+            other => panic!("Expected action result, got {:?}", other),
+        }
+        assert!(matches!(
+            run("SELECT PROCESS count = 1 WHERE pid = me"),
+            ResultData::Boolean(true)
+        ));
+    }
+
+    #[test]
+    fn test_quantifier_rejected_for_other_targets() {
+        let condition = disk_condition("IF ANY MEMORY used_percent > 90");
+        assert!(
+            evaluate_if_condition(&condition, &ExecutionContext::default(), &Context::new())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_custom_target_from_registry() {
+        let ctx = ExecutionContext::default().with_target("custom", || {
+            Ok(serde_json::json!({ "answer": 42, "unit": "x" }))
+        });
+
+        let cmd = parse_command("SELECT CUSTOM *").unwrap();
+        match execute_command(&cmd, &ctx).unwrap().data {
+            ResultData::Custom(result) => {
+                assert_eq!(result.target, "CUSTOM");
+                assert_eq!(result.value["answer"], 42);
+            }
+            other => panic!("Expected custom result, got {:?}", other),
+        }
+
+        let cmd = parse_command("SELECT custom answer").unwrap();
+        match execute_command(&cmd, &ctx).unwrap().data {
+            ResultData::Custom(result) => {
+                assert_eq!(result.value, serde_json::json!({ "answer": 42 }))
+            }
+            other => panic!("Expected custom result, got {:?}", other),
+        }
+
+        let cmd = parse_command("SELECT unknown *").unwrap();
+        assert!(matches!(
+            execute_command(&cmd, &ctx),
+            Err(ArtaError::InvalidTarget(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_target_suggests_closest() {
+        let ctx = ExecutionContext::default().with_target("gpu", || Ok(serde_json::json!({})));
+
+        let err = execute_command(&parse_command("SELECT MEMROY *").unwrap(), &ctx).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid query target: MEMROY (did you mean MEMORY?)"
+        );
+
+        let err = execute_command(&parse_command("SELECT gpus *").unwrap(), &ctx).unwrap_err();
+        assert!(err.to_string().ends_with("(did you mean GPU?)"));
+
+        let err = execute_command(&parse_command("SELECT CPU usge").unwrap(), &ctx).unwrap_err();
+        assert!(err.to_string().ends_with("(did you mean usage?)"));
+    }
+
+    #[test]
+    fn test_show_version() {
+        let cmd = parse_command("SHOW VERSION").unwrap();
+        match execute_command(&cmd, &ExecutionContext::default())
+            .unwrap()
+            .data
+        {
+            ResultData::Version(info) => {
+                assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+                assert_eq!(
+                    info.features.contains(&"repl".to_string()),
+                    cfg!(feature = "repl")
+                );
+            }
+            other => panic!("Expected version info, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_show_config() {
+        let ctx = ExecutionContext {
+            dry_run: true,
+            allow_actions: true,
+            output_format: OutputFormat::Json,
+            strict_fields: true,
+            ..Default::default()
+        }
+        .with_target("gpu", || Ok(serde_json::json!(1)))
+        .with_timezone(DisplayZone::Utc);
+
+        let cmd = parse_command("SHOW CONFIG").unwrap();
+        match execute_command(&cmd, &ctx).unwrap().data {
+            ResultData::Config(info) => {
+                assert_eq!(info, ConfigInfo::from_context(&ctx));
+                assert!(info.dry_run && info.allow_actions && info.strict_fields);
+                assert!(!info.verbose && !info.json_units);
+                assert_eq!(info.output_format, "json");
+                assert_eq!(info.timezone, "utc");
+                assert_eq!(info.targets, vec!["GPU"]);
+                assert!(info.formatters.is_empty());
+            }
+            other => panic!("Expected config info, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_show_audit_returns_last_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let audit_path = temp_dir.path().join("audit.ndjson");
+        let ctx = ExecutionContext {
+            allow_actions: true,
+            ..Default::default()
+        }
+        .with_audit_log(&audit_path)
+        .with_timezone(DisplayZone::Utc);
+
+        for content in ["a", "bb", "ccc"] {
+            let path = temp_dir.path().join(format!("{}.txt", content));
+            let cmd =
+                parse_command(&format!(r#"WRITE "{}" TO "{}""#, content, path.display())).unwrap();
+            execute_command(&cmd, &ctx).unwrap();
+        }
+        assert_eq!(
+            std::fs::read_to_string(&audit_path)
+                .unwrap()
+                .lines()
+                .count(),
+            3
+        );
+
+        let cmd = parse_command("SHOW AUDIT 2").unwrap();
+        match execute_command(&cmd, &ctx).unwrap().data {
+            ResultData::Audit(log) => {
+                let written: Vec<&str> =
+                    log.entries.iter().map(|e| e.details[0].as_str()).collect();
+                assert_eq!(written.len(), 2);
+                assert!(written[0].contains("bb.txt"), "{:?}", written);
+                assert!(written[1].contains("ccc.txt"), "{:?}", written);
+                assert!(log.entries.iter().all(|e| e.action == "WRITE FILE"));
+            }
+            other => panic!("Expected audit log, got {:?}", other),
+        }
+
+        let err = execute_command(&cmd, &ExecutionContext::default()).unwrap_err();
+        assert!(err.to_string().contains("--audit-log"), "{}", err);
+    }
+
+    #[test]
+    fn test_audit_records_failures_and_guards_actions() {
+        let temp_dir = TempDir::new().unwrap();
+        let audit_path = temp_dir.path().join("audit.ndjson");
+        let ctx = ExecutionContext {
+            allow_actions: true,
+            ..Default::default()
+        }
+        .with_audit_log(&audit_path);
+
+        let cmd = parse_command(&format!(
+            "DELETE FILES FROM \"{}\"",
+            temp_dir.path().display()
+        ))
+        .unwrap();
+        assert!(execute_command(&cmd, &ctx).is_err());
+        let log = read_last(&audit_path, 1, DisplayZone::Utc).unwrap();
+        assert_eq!(log.entries[0].action, "DELETE FILES");
+        assert!(log.entries[0].error.is_some());
+
+        // A log that can't be written stops the action before it runs
+        let target = temp_dir.path().join("flag");
+        let ctx = ctx.with_audit_log(temp_dir.path());
+        let cmd = parse_command(&format!(r#"WRITE "x" TO "{}""#, target.display())).unwrap();
+        assert!(execute_command(&cmd, &ctx).is_err());
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn test_percent_values_resolve_against_total() {
+        let context = Context::new();
+        let info = MemoryInfo::new(1000, 600, 400, 400);
+        let check = |query: &str| {
+            let condition = match parse_command(query).unwrap() {
+                Command::Check(c) => c,
+                _ => panic!("Expected Check command"),
+            };
+            let value = field_value(&info, &condition.field).unwrap();
+            compare_field(value, Some(info.total), &condition, &context)
+        };
+
+        assert!(check("SELECT MEMORY used > 50%").unwrap());
+        assert!(!check("SELECT MEMORY used > 60.5%").unwrap());
+        assert!(check("SELECT MEMORY free <= 40%").unwrap());
+        // Already a percentage: 60 is compared with 50 directly
+        assert!(check("SELECT MEMORY used_percent > 50%").unwrap());
+
+        let usage = FieldValue::Percent(30.0);
+        let condition = match parse_command("SELECT CPU usage < 50%").unwrap() {
+            Command::Check(c) => c,
+            _ => panic!("Expected Check command"),
+        };
+        assert!(compare_field(usage.clone(), None, &condition, &context).unwrap());
+        assert!(compare_field(FieldValue::Bytes(1), None, &condition, &context).is_err());
+    }
+
+    #[test]
+    fn test_debug_logging_spans_query_execution() {
+        use std::sync::{Arc, Mutex};
+        use tracing_subscriber::filter::LevelFilter;
+        use tracing_subscriber::layer::{self, SubscriberExt};
+
+        /// Records the name of every span opened
+        struct SpanNames(Arc<Mutex<Vec<String>>>);
+
+        impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanNames {
+            fn on_new_span(
+                &self,
+                attrs: &tracing::span::Attributes<'_>,
+                _id: &tracing::span::Id,
+                _ctx: layer::Context<'_, S>,
+            ) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(attrs.metadata().name().to_string());
+            }
+        }
+
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry()
+            .with(LevelFilter::DEBUG)
+            .with(SpanNames(names.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let cmd = parse_command("SELECT MEMORY *").unwrap();
+            execute_command(&cmd, &ExecutionContext::default()).unwrap();
+        });
+
+        let names = names.lock().unwrap();
+        assert!(names.iter().any(|n| n == "parse_command_with_options"));
+        assert!(names.iter().any(|n| n == "execute_query"));
+    }
+
+    #[test]
+    fn test_no_battery_defaults() {
+        let info = BatteryInfo { batteries: vec![] };
+        assert_eq!(
+            get_battery_field_value(&info, None, "level").unwrap(),
+            100.0
+        );
+        assert!(battery_field_value(&info, None, "level").unwrap().is_none());
+    }
+}
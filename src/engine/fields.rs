@@ -0,0 +1,732 @@
+//! Canonical field names for query results
+//!
+//! SELECT projection, IF conditions and PRINT all resolve field names through
+//! this module, so an alias such as `percent` means the same thing everywhere.
+//!
+//! | Target  | Canonical fields (aliases)                                                    |
+//! |---------|-------------------------------------------------------------------------------|
+//! | MEMORY  | total, used, free, available, used_percent (percent, usage, usage_percent), swap_total, swap_used (swap), pressure |
+//! | CPU     | usage (percent, used_percent, usage_percent), cores, frequency, brand (name)  |
+//! | DISK    | name, mount_point (mount), total, used, free (available), used_percent, file_system |
+//! | BATTERY | percentage (percent, level, charge), state (status), time_to_empty, time_to_full |
+//! | SYSTEM  | hostname (name), os_name (os), os_version (version), kernel_version (kernel), uptime, uptime_human |
+//! | NETWORK | name, received (recv), transmitted (sent), received_delta (recv_delta), transmitted_delta (sent_delta), packets_received, packets_transmitted, up |
+//! | SELF    | pid, name, cpu (usage, percent), memory (rss), virtual_memory (virtual), uptime |
+//! | FILES   | name, path, size, is_dir, modified, extension (ext), dir_size, owner, group, mode |
+//! | SERVICES | name, active_state (state, active), sub_state, enabled, pid, description     |
+//!
+//! Byte fields also accept a `_bytes` suffix (e.g. `used_bytes`).
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::engine::executor::FileEntry;
+use crate::engine::queries::battery::{BatteryEntry, BatteryInfo};
+use crate::engine::queries::disk::DiskEntry;
+use crate::engine::queries::network::NetworkInterface;
+use crate::engine::queries::services::ServiceInfo;
+use crate::engine::queries::{CpuInfo, MemoryInfo, ProcessInfo, SelfInfo, SystemInfo};
+use crate::error::{did_you_mean, ArtaError, Result};
+use crate::output::format_util::{
+    fmt_duration, fmt_file_time, fmt_percent_with, fmt_size, DEFAULT_PRECISION,
+};
+use crate::output::DisplayZone;
+use crate::parser::{OrderBy, QueryTarget, SortDirection};
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+
+/// A field value that knows how to display itself
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    Count(u64),
+    Bytes(u64),
+    Percent(f64),
+    Megahertz(u64),
+    Seconds(u64),
+    Bool(bool),
+    Text(String),
+    /// An instant such as a file's modification time
+    #[serde(serialize_with = "crate::output::timezone::serialize_rfc3339")]
+    Time(DateTime<Utc>),
+}
+
+impl FieldValue {
+    /// Numeric value for comparisons, if the field is numeric
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            FieldValue::Percent(n) => Some(*n),
+            FieldValue::Count(n)
+            | FieldValue::Bytes(n)
+            | FieldValue::Megahertz(n)
+            | FieldValue::Seconds(n) => Some(*n as f64),
+            FieldValue::Bool(_) | FieldValue::Text(_) | FieldValue::Time(_) => None,
+        }
+    }
+
+    /// Unit `--json-units` annotates the value with, for sizes and percentages
+    pub fn unit(&self) -> Option<&'static str> {
+        match self {
+            FieldValue::Bytes(_) => Some("bytes"),
+            FieldValue::Percent(_) => Some("percent"),
+            _ => None,
+        }
+    }
+}
+
+/// Percentages honour a format precision such as `{:.0}`; one decimal otherwise
+impl std::fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldValue::Count(n) => write!(f, "{}", n),
+            FieldValue::Bytes(b) => write!(f, "{}", fmt_size(*b)),
+            FieldValue::Percent(p) => {
+                let precision = f.precision().unwrap_or(DEFAULT_PRECISION);
+                write!(f, "{}", fmt_percent_with(*p, precision))
+            }
+            FieldValue::Megahertz(m) => write!(f, "{} MHz", m),
+            FieldValue::Seconds(s) => write!(f, "{}", fmt_duration(*s)),
+            FieldValue::Bool(b) => write!(f, "{}", b),
+            FieldValue::Text(s) => write!(f, "{}", s),
+            FieldValue::Time(t) => write!(f, "{}", fmt_file_time(*t, DisplayZone::Local)),
+        }
+    }
+}
+
+/// Resolve a field name or alias to its canonical name for a target
+pub fn canonical_field(target: QueryTarget, field: &str) -> Option<&'static str> {
+    let field = field.to_lowercase();
+    let canonical = match (target, field.as_str()) {
+        (QueryTarget::Memory, "total" | "total_bytes") => "total",
+        (QueryTarget::Memory, "used" | "used_bytes") => "used",
+        (QueryTarget::Memory, "free" | "free_bytes") => "free",
+        (QueryTarget::Memory, "available" | "available_bytes") => "available",
+        (QueryTarget::Memory, "used_percent" | "percent" | "usage" | "usage_percent") => {
+            "used_percent"
+        }
+        (QueryTarget::Memory, "swap_total" | "swap_total_bytes") => "swap_total",
+        (QueryTarget::Memory, "swap_used" | "swap_used_bytes" | "swap") => "swap_used",
+        (QueryTarget::Memory, "pressure") => "pressure",
+
+        (QueryTarget::Cpu, "usage" | "percent" | "used_percent" | "usage_percent") => "usage",
+        (QueryTarget::Cpu, "cores" | "core_count") => "cores",
+        (QueryTarget::Cpu, "frequency" | "frequency_mhz") => "frequency",
+        (QueryTarget::Cpu, "brand" | "name") => "brand",
+
+        (QueryTarget::Disk, "name") => "name",
+        (QueryTarget::Disk, "mount_point" | "mount") => "mount_point",
+        (QueryTarget::Disk, "total" | "total_bytes") => "total",
+        (QueryTarget::Disk, "used" | "used_bytes") => "used",
+        (QueryTarget::Disk, "free" | "free_bytes" | "available" | "available_bytes") => "free",
+        (QueryTarget::Disk, "used_percent" | "percent" | "usage" | "usage_percent") => {
+            "used_percent"
+        }
+        (QueryTarget::Disk, "file_system" | "fs") => "file_system",
+
+        (
+            QueryTarget::Battery,
+            "percentage" | "percent" | "level" | "charge" | "charge_percent",
+        ) => "percentage",
+        (QueryTarget::Battery, "state" | "status") => "state",
+        (QueryTarget::Battery, "time_to_empty" | "remaining") => "time_to_empty",
+        (QueryTarget::Battery, "time_to_full") => "time_to_full",
+
+        (QueryTarget::System, "hostname" | "name") => "hostname",
+        (QueryTarget::System, "os_name" | "os") => "os_name",
+        (QueryTarget::System, "os_version" | "version") => "os_version",
+        (QueryTarget::System, "kernel_version" | "kernel") => "kernel_version",
+        (QueryTarget::System, "uptime" | "uptime_secs") => "uptime",
+        (QueryTarget::System, "uptime_human") => "uptime_human",
+
+        (QueryTarget::Network, "name") => "name",
+        (QueryTarget::Network, "received" | "recv" | "bytes_recv") => "received",
+        (QueryTarget::Network, "transmitted" | "sent" | "bytes_sent") => "transmitted",
+        (QueryTarget::Network, "packets_received") => "packets_received",
+        (QueryTarget::Network, "packets_transmitted") => "packets_transmitted",
+        (QueryTarget::Network, "received_delta" | "recv_delta") => "received_delta",
+        (QueryTarget::Network, "transmitted_delta" | "sent_delta") => "transmitted_delta",
+        (QueryTarget::Network, "up") => "up",
+
+        (QueryTarget::Process, "pid") => "pid",
+        (QueryTarget::Process, "name") => "name",
+        (QueryTarget::Process, "name_full") => "name_full",
+        (QueryTarget::Process, "cpu" | "usage" | "percent" | "cpu_percent") => "cpu",
+        (QueryTarget::Process, "memory" | "memory_bytes" | "rss") => "memory",
+        (QueryTarget::Process, "status" | "state") => "status",
+        (QueryTarget::Process, "threads") => "threads",
+        (QueryTarget::Process, "open_files") => "open_files",
+        (QueryTarget::Process, "user") => "user",
+
+        (QueryTarget::SelfProc, "pid") => "pid",
+        (QueryTarget::SelfProc, "name") => "name",
+        (QueryTarget::SelfProc, "cpu" | "usage" | "percent" | "cpu_percent") => "cpu",
+        (QueryTarget::SelfProc, "memory" | "memory_bytes" | "rss") => "memory",
+        (QueryTarget::SelfProc, "virtual_memory" | "virtual_memory_bytes" | "virtual") => {
+            "virtual_memory"
+        }
+        (QueryTarget::SelfProc, "uptime" | "uptime_secs") => "uptime",
+
+        (QueryTarget::Files, "name") => "name",
+        (QueryTarget::Files, "path") => "path",
+        (QueryTarget::Files, "size" | "size_bytes") => "size",
+        (QueryTarget::Files, "is_dir") => "is_dir",
+        (QueryTarget::Files, "is_symlink") => "is_symlink",
+        (QueryTarget::Files, "symlink_target") => "symlink_target",
+        (QueryTarget::Files, "modified") => "modified",
+        (QueryTarget::Files, "extension" | "ext") => "extension",
+        (QueryTarget::Files, "dir_size") => "dir_size",
+        (QueryTarget::Files, "owner") => "owner",
+        (QueryTarget::Files, "group") => "group",
+        (QueryTarget::Files, "mode") => "mode",
+
+        (QueryTarget::Services, "name") => "name",
+        (QueryTarget::Services, "active_state" | "state" | "active") => "active_state",
+        (QueryTarget::Services, "sub_state") => "sub_state",
+        (QueryTarget::Services, "enabled") => "enabled",
+        (QueryTarget::Services, "pid") => "pid",
+        (QueryTarget::Services, "description") => "description",
+
+        _ => return None,
+    };
+    Some(canonical)
+}
+
+/// Every field name and alias accepted for a target, for suggestions
+pub fn known_fields(target: QueryTarget) -> &'static [&'static str] {
+    match target {
+        QueryTarget::Memory => &[
+            "total",
+            "used",
+            "free",
+            "available",
+            "used_percent",
+            "percent",
+            "usage",
+            "usage_percent",
+            "swap_total",
+            "swap_used",
+            "swap",
+            "pressure",
+        ],
+        QueryTarget::Cpu => &[
+            "usage",
+            "percent",
+            "used_percent",
+            "usage_percent",
+            "cores",
+            "frequency",
+            "brand",
+            "name",
+        ],
+        QueryTarget::Disk => &[
+            "name",
+            "mount_point",
+            "mount",
+            "total",
+            "used",
+            "free",
+            "available",
+            "used_percent",
+            "percent",
+            "usage",
+            "file_system",
+        ],
+        QueryTarget::Battery => &[
+            "percentage",
+            "percent",
+            "level",
+            "charge",
+            "state",
+            "status",
+            "time_to_empty",
+            "remaining",
+            "time_to_full",
+        ],
+        QueryTarget::System => &[
+            "hostname",
+            "name",
+            "os_name",
+            "os",
+            "os_version",
+            "version",
+            "kernel_version",
+            "kernel",
+            "uptime",
+            "uptime_human",
+        ],
+        QueryTarget::Network => &[
+            "name",
+            "received",
+            "recv",
+            "transmitted",
+            "sent",
+            "packets_received",
+            "packets_transmitted",
+            "received_delta",
+            "recv_delta",
+            "transmitted_delta",
+            "sent_delta",
+            "up",
+        ],
+        QueryTarget::Process => &[
+            "pid",
+            "name",
+            "name_full",
+            "cpu",
+            "usage",
+            "percent",
+            "cpu_percent",
+            "memory",
+            "memory_bytes",
+            "rss",
+            "status",
+            "state",
+            "threads",
+            "open_files",
+            "user",
+        ],
+        QueryTarget::SelfProc => &[
+            "pid",
+            "name",
+            "cpu",
+            "usage",
+            "percent",
+            "memory",
+            "rss",
+            "virtual_memory",
+            "virtual",
+            "uptime",
+        ],
+        QueryTarget::Files => &[
+            "name",
+            "path",
+            "size",
+            "is_dir",
+            "is_symlink",
+            "symlink_target",
+            "modified",
+            "extension",
+            "ext",
+            "dir_size",
+            "owner",
+            "group",
+            "mode",
+        ],
+        QueryTarget::Services => &[
+            "name",
+            "active_state",
+            "state",
+            "active",
+            "sub_state",
+            "enabled",
+            "pid",
+            "description",
+        ],
+        _ => &[],
+    }
+}
+
+fn unknown_field(target: QueryTarget, field: &str) -> String {
+    format!("{}{}", field, did_you_mean(field, known_fields(target)))
+}
+
+/// A query result whose fields can be looked up by canonical name
+pub trait FieldSource {
+    /// The query target the fields belong to
+    const TARGET: QueryTarget;
+
+    /// Value of a canonical field
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue>;
+}
+
+/// Look up a field (or alias) on a query result
+pub fn field_value<T: FieldSource>(source: &T, field: &str) -> Result<FieldValue> {
+    canonical_field(T::TARGET, field)
+        .and_then(|canonical| source.canonical_value(canonical))
+        .ok_or_else(|| {
+            ArtaError::ExecutionError(format!(
+                "Unknown {} field: {}",
+                T::TARGET,
+                unknown_field(T::TARGET, field)
+            ))
+        })
+}
+
+/// Look up a numeric field, for use in comparisons
+pub fn numeric_field_value<T: FieldSource>(source: &T, field: &str) -> Result<f64> {
+    field_value(source, field)?.as_number().ok_or_else(|| {
+        ArtaError::ExecutionError(format!("{} field '{}' is not numeric", T::TARGET, field))
+    })
+}
+
+impl FieldSource for MemoryInfo {
+    const TARGET: QueryTarget = QueryTarget::Memory;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        Some(match canonical {
+            "total" => FieldValue::Bytes(self.total),
+            "used" => FieldValue::Bytes(self.used),
+            "free" => FieldValue::Bytes(self.free),
+            "available" => FieldValue::Bytes(self.available),
+            "used_percent" => FieldValue::Percent(self.usage_percent),
+            "swap_total" => FieldValue::Bytes(self.swap_total),
+            "swap_used" => FieldValue::Bytes(self.swap_used),
+            "pressure" => FieldValue::Text(self.pressure.to_string()),
+            _ => return None,
+        })
+    }
+}
+
+impl FieldSource for CpuInfo {
+    const TARGET: QueryTarget = QueryTarget::Cpu;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        Some(match canonical {
+            "usage" => FieldValue::Percent(self.usage as f64),
+            "cores" => FieldValue::Count(self.cores as u64),
+            "frequency" => FieldValue::Megahertz(self.frequency),
+            "brand" => FieldValue::Text(self.brand.clone()),
+            _ => return None,
+        })
+    }
+}
+
+impl FieldSource for DiskEntry {
+    const TARGET: QueryTarget = QueryTarget::Disk;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        Some(match canonical {
+            "name" => FieldValue::Text(self.name.clone()),
+            "mount_point" => FieldValue::Text(self.mount_point.clone()),
+            "total" => FieldValue::Bytes(self.total),
+            "used" => FieldValue::Bytes(self.used),
+            "free" => FieldValue::Bytes(self.free),
+            "used_percent" => FieldValue::Percent(self.usage_percent),
+            "file_system" => FieldValue::Text(self.file_system.clone()),
+            _ => return None,
+        })
+    }
+}
+
+impl FieldSource for BatteryEntry {
+    const TARGET: QueryTarget = QueryTarget::Battery;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        let or_na = |v: &Option<String>| v.clone().unwrap_or_else(|| "N/A".to_string());
+        Some(match canonical {
+            "percentage" => FieldValue::Percent(self.percentage as f64),
+            "state" => FieldValue::Text(self.state.clone()),
+            "time_to_empty" => FieldValue::Text(or_na(&self.time_to_empty)),
+            "time_to_full" => FieldValue::Text(or_na(&self.time_to_full)),
+            _ => return None,
+        })
+    }
+}
+
+/// Across all batteries: `percentage` is aggregated, other fields come from
+/// the first battery
+impl FieldSource for BatteryInfo {
+    const TARGET: QueryTarget = QueryTarget::Battery;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        match canonical {
+            "percentage" => self.aggregate_percentage().map(FieldValue::Percent),
+            _ => self.batteries.first()?.canonical_value(canonical),
+        }
+    }
+}
+
+impl FieldSource for SystemInfo {
+    const TARGET: QueryTarget = QueryTarget::System;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        Some(match canonical {
+            "hostname" => FieldValue::Text(self.hostname.clone()),
+            "os_name" => FieldValue::Text(self.os_name.clone()),
+            "os_version" => FieldValue::Text(self.os_version.clone()),
+            "kernel_version" => FieldValue::Text(self.kernel_version.clone()),
+            "uptime" => FieldValue::Seconds(self.uptime),
+            "uptime_human" => FieldValue::Text(fmt_duration(self.uptime)),
+            _ => return None,
+        })
+    }
+}
+
+impl FieldSource for NetworkInterface {
+    const TARGET: QueryTarget = QueryTarget::Network;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        Some(match canonical {
+            "name" => FieldValue::Text(self.name.clone()),
+            "received" => FieldValue::Bytes(self.received),
+            "transmitted" => FieldValue::Bytes(self.transmitted),
+            "packets_received" => FieldValue::Count(self.packets_received),
+            "packets_transmitted" => FieldValue::Count(self.packets_transmitted),
+            "received_delta" => FieldValue::Bytes(self.received_delta.unwrap_or(0)),
+            "transmitted_delta" => FieldValue::Bytes(self.transmitted_delta.unwrap_or(0)),
+            "up" => FieldValue::Bool(self.up),
+            _ => return None,
+        })
+    }
+}
+
+impl FieldSource for ServiceInfo {
+    const TARGET: QueryTarget = QueryTarget::Services;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        Some(match canonical {
+            "name" => FieldValue::Text(self.name.clone()),
+            "active_state" => FieldValue::Text(self.active_state.clone()),
+            "sub_state" => FieldValue::Text(self.sub_state.clone()),
+            "enabled" => FieldValue::Text(self.enabled.clone()),
+            // systemd's own convention: 0 when there is no main process
+            "pid" => FieldValue::Count(self.pid.unwrap_or(0) as u64),
+            "description" => FieldValue::Text(self.description.clone()),
+            _ => return None,
+        })
+    }
+}
+
+impl FieldSource for ProcessInfo {
+    const TARGET: QueryTarget = QueryTarget::Process;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        // Counts a platform does not report sort as 0
+        let count = |v: Option<u32>| FieldValue::Count(v.unwrap_or_default().into());
+        Some(match canonical {
+            "pid" => FieldValue::Count(self.pid as u64),
+            "name" => FieldValue::Text(self.name.clone()),
+            "name_full" => FieldValue::Text(self.name_full.clone().unwrap_or(self.name.clone())),
+            "cpu" => FieldValue::Percent(self.cpu as f64),
+            "memory" => FieldValue::Bytes(self.memory),
+            "status" => FieldValue::Text(self.status.clone()),
+            "threads" => count(self.threads),
+            "open_files" => count(self.open_files),
+            "user" => FieldValue::Text(self.user.clone().unwrap_or_default()),
+            _ => return None,
+        })
+    }
+}
+
+impl FieldSource for SelfInfo {
+    const TARGET: QueryTarget = QueryTarget::SelfProc;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        Some(match canonical {
+            "pid" => FieldValue::Count(self.pid as u64),
+            "name" => FieldValue::Text(self.name.clone()),
+            "cpu" => FieldValue::Percent(self.cpu as f64),
+            "memory" => FieldValue::Bytes(self.memory),
+            "virtual_memory" => FieldValue::Bytes(self.virtual_memory),
+            "uptime" => FieldValue::Seconds(self.uptime),
+            _ => return None,
+        })
+    }
+}
+
+impl FieldSource for FileEntry {
+    const TARGET: QueryTarget = QueryTarget::Files;
+
+    fn canonical_value(&self, canonical: &str) -> Option<FieldValue> {
+        let text = |v: &Option<String>| FieldValue::Text(v.clone().unwrap_or_default());
+        Some(match canonical {
+            "name" => FieldValue::Text(self.name.clone()),
+            "path" => FieldValue::Text(self.path.clone()),
+            "size" => FieldValue::Bytes(self.size),
+            "is_dir" => FieldValue::Bool(self.is_dir),
+            "is_symlink" => FieldValue::Bool(self.is_symlink),
+            "symlink_target" => text(&self.symlink_target),
+            "modified" => FieldValue::Time(self.modified?),
+            "extension" => text(&self.extension),
+            "dir_size" => FieldValue::Bytes(self.dir_size?),
+            "owner" => text(&self.owner),
+            "group" => text(&self.group),
+            "mode" => text(&self.mode),
+            _ => return None,
+        })
+    }
+}
+
+/// Sort rows by a field (or alias), as for ORDER BY
+///
+/// Numbers and times compare by value, everything else as case-insensitive text.
+pub fn sort_by_field<T: FieldSource>(rows: &mut Vec<T>, order: &OrderBy) -> Result<()> {
+    if canonical_field(T::TARGET, &order.field).is_none() {
+        return Err(ArtaError::ExecutionError(format!(
+            "Cannot ORDER BY unknown {} field: {}",
+            T::TARGET,
+            unknown_field(T::TARGET, &order.field)
+        )));
+    }
+
+    let mut keyed = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        keyed.push((field_value(row, &order.field)?, i));
+    }
+    keyed.sort_by(|(a, _), (b, _)| {
+        let ordering = match (a, b, a.as_number(), b.as_number()) {
+            (FieldValue::Time(x), FieldValue::Time(y), _, _) => x.cmp(y),
+            (_, _, Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => a
+                .to_string()
+                .to_lowercase()
+                .cmp(&b.to_string().to_lowercase()),
+        };
+        match order.direction {
+            SortDirection::Asc => ordering,
+            SortDirection::Desc => ordering.reverse(),
+        }
+    });
+
+    let mut taken: Vec<Option<T>> = rows.drain(..).map(Some).collect();
+    rows.extend(keyed.into_iter().filter_map(|(_, i)| taken[i].take()));
+    Ok(())
+}
+
+/// One row of a projected SELECT, keyed by canonical field name
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectedRow(pub Vec<(String, FieldValue)>);
+
+impl ProjectedRow {
+    /// Project the requested fields of a query result
+    pub fn project<T: FieldSource>(source: &T, fields: &[String]) -> Result<Self> {
+        fields
+            .iter()
+            .map(|field| {
+                let canonical = canonical_field(T::TARGET, field).unwrap_or(field.as_str());
+                Ok((canonical.to_string(), field_value(source, field)?))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(ProjectedRow)
+    }
+}
+
+impl Serialize for ProjectedRow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Result of a SELECT with an explicit field list
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(untagged)]
+pub enum Projection {
+    /// Targets with a single record (MEMORY, CPU, SYSTEM, SELF)
+    Single(ProjectedRow),
+    /// Targets with one record per device (DISK, BATTERY, NETWORK)
+    Rows(Vec<ProjectedRow>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::queries::memory::MemoryPressure;
+
+    fn memory() -> MemoryInfo {
+        MemoryInfo {
+            total: 16 * 1024 * 1024 * 1024,
+            used: 4 * 1024 * 1024 * 1024,
+            free: 12 * 1024 * 1024 * 1024,
+            available: 12 * 1024 * 1024 * 1024,
+            usage_percent: 25.0,
+            swap_total: 0,
+            swap_used: 0,
+            pressure: MemoryPressure::Low,
+        }
+    }
+
+    #[test]
+    fn test_aliases_resolve_to_same_field() {
+        for alias in [
+            "used_percent",
+            "percent",
+            "usage",
+            "usage_percent",
+            "USED_PERCENT",
+        ] {
+            assert_eq!(
+                canonical_field(QueryTarget::Memory, alias),
+                Some("used_percent")
+            );
+            assert_eq!(
+                canonical_field(QueryTarget::Disk, alias),
+                Some("used_percent")
+            );
+        }
+        assert_eq!(canonical_field(QueryTarget::Memory, "bogus"), None);
+    }
+
+    #[test]
+    fn test_field_value_display_and_number() {
+        let info = memory();
+        let value = field_value(&info, "percent").unwrap();
+        assert_eq!(value.to_string(), "25.0%");
+        assert_eq!(numeric_field_value(&info, "used_percent").unwrap(), 25.0);
+        assert!(field_value(&info, "bogus").is_err());
+    }
+
+    #[test]
+    fn test_uptime_fields() {
+        let uptime = 5 * 86_400 + 3 * 3600 + 12 * 60;
+        let info = SystemInfo {
+            hostname: "web1".to_string(),
+            os_name: "Linux".to_string(),
+            os_version: "6.1".to_string(),
+            kernel_version: "6.1.0".to_string(),
+            uptime,
+            uptime_human: fmt_duration(uptime),
+        };
+        assert_eq!(
+            field_value(&info, "uptime").unwrap().to_string(),
+            "5d 3h 12m"
+        );
+        assert_eq!(numeric_field_value(&info, "uptime").unwrap(), uptime as f64);
+        assert_eq!(
+            field_value(&info, "uptime_human").unwrap(),
+            FieldValue::Text("5d 3h 12m".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_field_suggests_alias() {
+        let err = field_value(&memory(), "usge").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Execution error: Unknown MEMORY field: usge (did you mean usage?)"
+        );
+        assert!(!field_value(&memory(), "bogus")
+            .unwrap_err()
+            .to_string()
+            .contains("did you mean"));
+    }
+
+    #[test]
+    fn test_known_fields_resolve() {
+        for target in [
+            QueryTarget::Memory,
+            QueryTarget::Cpu,
+            QueryTarget::Disk,
+            QueryTarget::Battery,
+            QueryTarget::System,
+            QueryTarget::Network,
+            QueryTarget::Process,
+            QueryTarget::SelfProc,
+        ] {
+            for field in known_fields(target) {
+                assert!(canonical_field(target, field).is_some(), "{}", field);
+            }
+        }
+    }
+
+    #[test]
+    fn test_projection_uses_canonical_names() {
+        let row =
+            ProjectedRow::project(&memory(), &["usage".to_string(), "total".to_string()]).unwrap();
+        let json = serde_json::to_value(Projection::Single(row)).unwrap();
+        assert_eq!(json["used_percent"], 25.0);
+        assert_eq!(json["total"], 16u64 * 1024 * 1024 * 1024);
+    }
+}
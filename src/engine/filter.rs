@@ -0,0 +1,497 @@
+//! WHERE clause evaluation
+//!
+//! Each target knows how to test a single `Condition` against its items; this
+//! module combines those tests according to the clause's AND/OR chain. AND
+//! binds tighter than OR, so `a OR b AND c` means `a OR (b AND c)`.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use regex::Regex;
+
+use crate::engine::fields::canonical_field;
+use crate::error::{did_you_mean, ArtaError, Result};
+use crate::parser::{
+    CompareOp, Condition, ConditionExpr, LogicalOp, QueryTarget, Value, WhereClause,
+};
+
+/// Compiled patterns kept around before the cache is cleared
+const REGEX_CACHE_SIZE: usize = 64;
+
+/// Field suffix that measures a string field, e.g. `name.length`
+pub const LENGTH_SUFFIX: &str = ".length";
+
+/// Outcome of evaluating a WHERE clause against one item
+#[derive(Debug)]
+pub struct WhereTrace<'a> {
+    /// Whether the item matched the whole clause
+    pub matched: bool,
+    /// Conditions that evaluated true, in clause order
+    pub satisfied: Vec<&'a Condition>,
+}
+
+/// Check whether an item matches a WHERE clause
+pub fn matches<F>(where_clause: &WhereClause, test: F) -> bool
+where
+    F: FnMut(&Condition) -> bool,
+{
+    evaluate_with_trace(where_clause, test).matched
+}
+
+/// Check whether an item matches, with a test that returns `None` for unknown fields
+///
+/// Unknown fields count as matching, which is what queries do without
+/// `--strict-fields`. Actions and pipelines reject them up front with
+/// [`check_fields`], so a typo can never widen what they act on.
+pub fn matches_lenient<F>(where_clause: &WhereClause, mut test: F) -> bool
+where
+    F: FnMut(&Condition) -> Option<bool>,
+{
+    matches(where_clause, |c| test(c).unwrap_or(true))
+}
+
+/// Evaluate a WHERE clause, recording which conditions were satisfied
+pub fn evaluate_with_trace<F>(where_clause: &WhereClause, mut test: F) -> WhereTrace<'_>
+where
+    F: FnMut(&Condition) -> bool,
+{
+    let mut satisfied = Vec::new();
+    let mut matched = true;
+
+    for expr in &where_clause.conditions {
+        matched &= evaluate_expr(expr, &mut test, &mut satisfied);
+    }
+
+    WhereTrace { matched, satisfied }
+}
+
+fn evaluate_expr<'a, F>(
+    expr: &'a ConditionExpr,
+    test: &mut F,
+    satisfied: &mut Vec<&'a Condition>,
+) -> bool
+where
+    F: FnMut(&Condition) -> bool,
+{
+    // Split the chain into OR-separated groups of AND-ed conditions
+    let mut any_group = false;
+    let mut group = true;
+    let mut node = Some(expr);
+
+    while let Some(current) = node {
+        let result = test(&current.condition);
+        if result {
+            satisfied.push(&current.condition);
+        }
+        group &= result;
+
+        match &current.next {
+            Some((LogicalOp::Or, next)) => {
+                any_group |= group;
+                group = true;
+                node = Some(next);
+            }
+            Some((LogicalOp::And, next)) => node = Some(next),
+            None => node = None,
+        }
+    }
+
+    any_group || group
+}
+
+/// Evaluate a `field.length` condition, or `None` for any other condition
+///
+/// `text` looks up the string field being measured; the length is counted in
+/// characters. Fields it does not know, and non-numeric values, never match.
+pub fn length_condition<'t>(
+    condition: &Condition,
+    text: impl FnOnce(&str) -> Option<&'t str>,
+) -> Option<bool> {
+    let field = condition.field.to_lowercase();
+    let base = field.strip_suffix(LENGTH_SUFFIX)?;
+    Some(match (text(base), &condition.value) {
+        (Some(s), Value::Number(n)) => {
+            compare_numbers(s.chars().count() as f64, *n, &condition.operator)
+        }
+        _ => false,
+    })
+}
+
+/// Check that every MATCHES pattern in a WHERE clause is a valid regex
+///
+/// Run before filtering, so a bad pattern fails the query instead of
+/// silently matching nothing.
+pub fn check_patterns(where_clause: &WhereClause) -> Result<()> {
+    for expr in &where_clause.conditions {
+        let mut node = Some(expr);
+        while let Some(current) = node {
+            if let (CompareOp::Matches, Value::String(pattern)) =
+                (&current.condition.operator, &current.condition.value)
+            {
+                compiled_regex(pattern).map_err(|e| {
+                    ArtaError::ExecutionError(format!(
+                        "Invalid MATCHES pattern for '{}': {}",
+                        current.condition.field, e
+                    ))
+                })?;
+            }
+            node = current.next.as_ref().map(|(_, next)| next.as_ref());
+        }
+    }
+    Ok(())
+}
+
+/// Check that every field in a WHERE clause is one of `known`
+///
+/// `known` is the target's [`known_fields`](crate::engine::fields::known_fields),
+/// all of which its matcher handles. Lenient matching treats unknown fields as
+/// matching, so without this a typo such as `cpy > 10` selects everything.
+/// Actions and pipelines always check; queries do with `--strict-fields`.
+pub fn check_fields(where_clause: &WhereClause, target: &str, known: &[&str]) -> Result<()> {
+    for expr in &where_clause.conditions {
+        let mut node = Some(expr);
+        while let Some(current) = node {
+            let field = current.condition.field.to_lowercase();
+            let base = field.strip_suffix(LENGTH_SUFFIX).unwrap_or(&field);
+            if !known.contains(&base) {
+                return Err(ArtaError::ExecutionError(format!(
+                    "Unknown {} field in WHERE: {}{}",
+                    target,
+                    current.condition.field,
+                    did_you_mean(base, known)
+                )));
+            }
+            node = current.next.as_ref().map(|(_, next)| next.as_ref());
+        }
+    }
+    Ok(())
+}
+
+/// Check that conditions on a time field of `target` compare against a time
+/// [`parse_time`] understands, so a typo fails instead of matching nothing
+pub fn check_times(where_clause: &WhereClause, target: QueryTarget, field: &str) -> Result<()> {
+    for expr in &where_clause.conditions {
+        let mut node = Some(expr);
+        while let Some(current) = node {
+            let condition = &current.condition;
+            let parsed = match &condition.value {
+                Value::String(s) => parse_time(s),
+                _ => None,
+            };
+            if canonical_field(target, &condition.field) == canonical_field(target, field)
+                && parsed.is_none()
+            {
+                return Err(ArtaError::ExecutionError(format!(
+                    "Invalid time for '{}': {} (expected \"YYYY-MM-DD\", \"YYYY-MM-DD HH:MM\" or RFC 3339)",
+                    condition.field, condition.value
+                )));
+            }
+            node = current.next.as_ref().map(|(_, next)| next.as_ref());
+        }
+    }
+    Ok(())
+}
+
+/// A time given in WHERE: `2024-05-01` or `2024-05-01 12:30[:45]` in local
+/// time, or RFC 3339 with an offset
+pub fn parse_time(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(instant) = DateTime::parse_from_rfc3339(text) {
+        return Some(instant.with_timezone(&Utc));
+    }
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::MIN))
+        })
+        .ok()?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|instant| instant.with_timezone(&Utc))
+}
+
+/// Compare instants to the second, as `modified < "2024-05-01"` does
+pub(crate) fn compare_times(left: DateTime<Utc>, right: DateTime<Utc>, op: &CompareOp) -> bool {
+    compare_numbers(left.timestamp() as f64, right.timestamp() as f64, op)
+}
+
+/// Whether any condition in a WHERE clause tests `field` of `target`, under
+/// any of its aliases or measured with `.length`
+pub fn references_field(where_clause: &WhereClause, target: QueryTarget, field: &str) -> bool {
+    let wanted = canonical_field(target, field);
+    where_clause.conditions.iter().any(|expr| {
+        let mut node = Some(expr);
+        while let Some(current) = node {
+            let tested = current.condition.field.to_lowercase();
+            let base = tested.strip_suffix(LENGTH_SUFFIX).unwrap_or(&tested);
+            if wanted.is_some() && canonical_field(target, base) == wanted {
+                return true;
+            }
+            node = current.next.as_ref().map(|(_, next)| next.as_ref());
+        }
+        false
+    })
+}
+
+/// Turn `N%` values on byte fields into that share of a total
+///
+/// `size > 1%` on FILES means 1% of the filesystem, for instance. `total` is
+/// only called when the clause has a percentage on one of `fields`. A
+/// percentage on any other field is an error rather than matching nothing.
+pub fn resolve_percentages(
+    where_clause: &WhereClause,
+    fields: &[&str],
+    total: impl FnOnce() -> Result<u64>,
+) -> Result<WhereClause> {
+    let mut resolved = where_clause.clone();
+    let mut total_fn = Some(total);
+    let mut total = None;
+
+    for expr in &mut resolved.conditions {
+        let mut node = Some(expr);
+        while let Some(current) = node {
+            let condition = &mut current.condition;
+            if let Value::Percent(percent) = condition.value {
+                if !fields.contains(&condition.field.to_lowercase().as_str()) {
+                    return Err(ArtaError::ExecutionError(format!(
+                        "'{}' cannot be compared with a percentage (only {})",
+                        condition.field,
+                        fields.join(", ")
+                    )));
+                }
+                if let Some(total_fn) = total_fn.take() {
+                    total = Some(total_fn()?);
+                }
+                condition.value = Value::Size(percent_of(total.unwrap_or(0), percent));
+            }
+            node = current.next.as_mut().map(|(_, next)| next.as_mut());
+        }
+    }
+    Ok(resolved)
+}
+
+/// `percent` of `total`, rounded to whole bytes
+pub fn percent_of(total: u64, percent: f64) -> u64 {
+    (total as f64 * percent / 100.0).round() as u64
+}
+
+/// `part` as a percentage of `total`; 0 when `total` is 0 rather than NaN
+pub fn percent_share(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64 * 100.0
+    }
+}
+
+pub(crate) fn compare_numbers(left: f64, right: f64, op: &CompareOp) -> bool {
+    match op {
+        CompareOp::Equal => (left - right).abs() < f64::EPSILON,
+        CompareOp::NotEqual => (left - right).abs() >= f64::EPSILON,
+        CompareOp::GreaterThan => left > right,
+        CompareOp::GreaterThanOrEqual => left >= right,
+        CompareOp::LessThan => left < right,
+        CompareOp::LessThanOrEqual => left <= right,
+        _ => false,
+    }
+}
+
+/// String comparison shared by every WHERE matcher
+///
+/// `=`, `!=`, `LIKE` and `CONTAINS` ignore case. `LIKE` matches the whole
+/// value: `%` stands for any run of characters and everything else is
+/// literal, so `LIKE "log"` is an exact match, `LIKE "%log"` a suffix match
+/// and `LIKE "%log%"` the same as `CONTAINS "log"`. `MATCHES` is an unanchored
+/// regex and is case-sensitive unless the pattern says `(?i)`.
+pub(crate) fn compare_strings(left: &str, right: &str, op: &CompareOp) -> bool {
+    match op {
+        CompareOp::Equal => left.to_lowercase() == right.to_lowercase(),
+        CompareOp::NotEqual => left.to_lowercase() != right.to_lowercase(),
+        CompareOp::Like => {
+            let pattern: Vec<String> = right.split('%').map(regex::escape).collect();
+            regex::Regex::new(&format!("(?is)^{}$", pattern.join(".*")))
+                .map(|r| r.is_match(left))
+                .unwrap_or(false)
+        }
+        CompareOp::Contains => left.to_lowercase().contains(&right.to_lowercase()),
+        CompareOp::Matches => regex_is_match(right, left),
+        _ => false,
+    }
+}
+
+pub(crate) fn compare_bools(left: bool, right: bool, op: &CompareOp) -> bool {
+    match op {
+        CompareOp::Equal => left == right,
+        CompareOp::NotEqual => left != right,
+        _ => false,
+    }
+}
+
+/// Test `text` against a MATCHES pattern; invalid patterns never match
+pub(crate) fn regex_is_match(pattern: &str, text: &str) -> bool {
+    compiled_regex(pattern).is_ok_and(|re| re.is_match(text))
+}
+
+/// Compile a pattern once and reuse it across rows
+fn compiled_regex(pattern: &str) -> std::result::Result<Regex, regex::Error> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Regex>>> = OnceLock::new();
+
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(re) = cache.lock().ok().and_then(|c| c.get(pattern).cloned()) {
+        return Ok(re);
+    }
+
+    let re = Regex::new(pattern)?;
+    if let Ok(mut cache) = cache.lock() {
+        if cache.len() >= REGEX_CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(pattern.to_string(), re.clone());
+    }
+    Ok(re)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_command, Command};
+
+    #[test]
+    fn test_percent_share() {
+        assert_eq!(percent_share(0, 0), 0.0);
+        assert_eq!(percent_share(5, 0), 0.0);
+        assert_eq!(percent_share(0, 10), 0.0);
+        assert_eq!(percent_share(1, 4), 25.0);
+        assert_eq!(percent_share(u64::MAX, u64::MAX), 100.0);
+    }
+
+    fn where_of(query: &str) -> WhereClause {
+        match parse_command(query).unwrap() {
+            Command::Query(q) => q.where_clause.unwrap(),
+            _ => panic!("Expected Query command"),
+        }
+    }
+
+    fn truthy(fields: &'static [&'static str]) -> impl FnMut(&Condition) -> bool {
+        move |c| fields.contains(&c.field.as_str())
+    }
+
+    #[test]
+    fn test_check_fields() {
+        let known = &["cpu", "memory", "name"];
+        assert!(check_fields(
+            &where_of("SELECT PROCESS * WHERE CPU > 10"),
+            "PROCESS",
+            known
+        )
+        .is_ok());
+        assert!(check_fields(
+            &where_of("SELECT PROCESS * WHERE name.length > 3"),
+            "PROCESS",
+            known
+        )
+        .is_ok());
+
+        let wc = where_of("SELECT PROCESS * WHERE memory > 1MB AND cpy > 10");
+        assert_eq!(
+            check_fields(&wc, "PROCESS", known).unwrap_err().to_string(),
+            "Execution error: Unknown PROCESS field in WHERE: cpy (did you mean cpu?)"
+        );
+    }
+
+    #[test]
+    fn test_compare_strings() {
+        use CompareOp::*;
+        // LIKE without % is exact, % anchors nothing on its side
+        assert!(compare_strings("log", "LOG", &Like));
+        assert!(!compare_strings("logs", "log", &Like));
+        assert!(compare_strings("app.log", "%.log", &Like));
+        assert!(!compare_strings("app_log", "%.log", &Like));
+        assert!(compare_strings("log.old", "log%", &Like));
+        assert!(compare_strings("catalog.txt", "%LOG%", &Like));
+        assert!(compare_strings("file(1).txt", "file(1)%", &Like));
+        // CONTAINS is a substring test, = and != ignore case
+        assert!(compare_strings("catalog", "LOG", &Contains));
+        assert!(compare_strings("Nginx", "nginx", &Equal));
+        assert!(!compare_strings("Nginx", "NGINX", &NotEqual));
+        // MATCHES is an unanchored regex that keeps its own case rules
+        assert!(compare_strings("python3", "th.n", &Matches));
+        assert!(!compare_strings("Python", "^python", &Matches));
+        assert!(compare_strings("Python", "(?i)^python", &Matches));
+    }
+
+    #[test]
+    fn test_or_matches_either_side() {
+        let wc = where_of("SELECT FILES * WHERE a = 1 OR b = 2");
+        assert!(matches(&wc, truthy(&["a"])));
+        assert!(matches(&wc, truthy(&["b"])));
+        assert!(!matches(&wc, truthy(&[])));
+    }
+
+    #[test]
+    fn test_and_binds_tighter_than_or() {
+        let wc = where_of("SELECT FILES * WHERE a = 1 OR b = 2 AND c = 3");
+        assert!(matches(&wc, truthy(&["a"])));
+        assert!(!matches(&wc, truthy(&["b"])));
+        assert!(matches(&wc, truthy(&["b", "c"])));
+    }
+
+    #[test]
+    fn test_check_patterns() {
+        let wc = where_of(r#"SELECT FILES * WHERE size > 1 AND name MATCHES "^py.*3$""#);
+        assert!(check_patterns(&wc).is_ok());
+        assert!(regex_is_match("^py.*3$", "python3"));
+        assert!(!regex_is_match("^py.*3$", "python2"));
+
+        let wc = where_of(r#"SELECT FILES * WHERE size > 1 OR name MATCHES "py(""#);
+        let err = check_patterns(&wc).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid MATCHES pattern for 'name'"));
+        assert!(!regex_is_match("py(", "py("));
+    }
+
+    #[test]
+    fn test_resolve_percentages() {
+        let wc = where_of("SELECT FILES * WHERE size > 1% AND name = \"a\" OR dir_size < 50%");
+        let resolved = resolve_percentages(&wc, &["size", "dir_size"], || Ok(200_000)).unwrap();
+        let values: Vec<_> = resolved
+            .conditions
+            .iter()
+            .flat_map(|expr| {
+                std::iter::successors(Some(expr), |e| e.next.as_ref().map(|(_, n)| n.as_ref()))
+            })
+            .map(|e| e.condition.value.clone())
+            .collect();
+        assert!(matches!(values[0], Value::Size(2_000)));
+        assert!(matches!(values[2], Value::Size(100_000)));
+
+        // The total is not needed (or computed) without a percentage
+        let wc = where_of("SELECT FILES * WHERE size > 1MB");
+        let failing = || Err(ArtaError::ExecutionError("no total".to_string()));
+        assert!(resolve_percentages(&wc, &["size"], failing).is_ok());
+        let wc = where_of("SELECT FILES * WHERE size > 1%");
+        assert!(resolve_percentages(&wc, &["size"], failing).is_err());
+
+        // Other fields fail by name instead of silently matching nothing
+        let wc = where_of("SELECT PROCESS * WHERE cpu > 50%");
+        let err = resolve_percentages(&wc, &["memory"], || Ok(1_000))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("'cpu' cannot be compared with a percentage"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_trace_lists_satisfied_conditions() {
+        let wc = where_of("SELECT FILES * WHERE a = 1 OR b = 2 OR c = 3");
+        let trace = evaluate_with_trace(&wc, truthy(&["b"]));
+        assert!(trace.matched);
+        let fields: Vec<_> = trace.satisfied.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, vec!["b"]);
+    }
+}
@@ -0,0 +1,139 @@
+//! Reading lines appended to a file, for `SELECT CONTENT ... FOLLOW`
+//!
+//! `FileTail` remembers how far a file has been read and returns only the
+//! complete lines written since. A shrinking file (truncation) or a new file
+//! at the same path (rotation) is read again from the start.
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::error::{ArtaError, Result};
+
+/// Read position in a followed file
+#[derive(Debug)]
+pub struct FileTail {
+    path: PathBuf,
+    offset: u64,
+    identity: Option<u64>,
+    /// Bytes of a line whose newline has not been written yet
+    pending: Vec<u8>,
+}
+
+impl FileTail {
+    /// Start following `path` from its current end
+    pub fn at_end(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path).map_err(ArtaError::IoError)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            offset: metadata.len(),
+            identity: identity(&metadata),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Byte offset the next read starts from
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Complete lines appended since the last call
+    ///
+    /// A missing file (rotated away, not yet recreated) yields no lines.
+    pub fn read_new_lines(&mut self) -> Result<Vec<String>> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(ArtaError::IoError(e)),
+        };
+
+        let current = identity(&metadata);
+        if current != self.identity || metadata.len() < self.offset {
+            self.identity = current;
+            self.offset = 0;
+            self.pending.clear();
+        }
+        if metadata.len() == self.offset {
+            return Ok(Vec::new());
+        }
+
+        let mut file = File::open(&self.path).map_err(ArtaError::IoError)?;
+        file.seek(SeekFrom::Start(self.offset))
+            .map_err(ArtaError::IoError)?;
+        let read = file
+            .read_to_end(&mut self.pending)
+            .map_err(ArtaError::IoError)?;
+        self.offset += read as u64;
+
+        let Some(end) = self.pending.iter().rposition(|&b| b == b'\n') else {
+            return Ok(Vec::new());
+        };
+        let rest = self.pending.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.pending, rest);
+
+        Ok(complete[..end]
+            .split(|&b| b == b'\n')
+            .map(|line| {
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                String::from_utf8_lossy(line).into_owned()
+            })
+            .collect())
+    }
+}
+
+/// Identifies the file behind a path, so rotation can be told from appends
+#[cfg(unix)]
+fn identity(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn identity(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn append(path: &Path, text: &str) {
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_read_new_lines() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "old\n").unwrap();
+
+        let mut tail = FileTail::at_end(&path).unwrap();
+        assert!(tail.read_new_lines().unwrap().is_empty());
+
+        append(&path, "first\nsecond\r\npart");
+        assert_eq!(tail.read_new_lines().unwrap(), ["first", "second"]);
+
+        append(&path, "ial\n");
+        assert_eq!(tail.read_new_lines().unwrap(), ["partial"]);
+
+        // Truncated in place
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!(tail.read_new_lines().unwrap(), ["new"]);
+    }
+
+    #[test]
+    fn test_read_new_lines_after_rotation() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "before rotation, quite a long line\n").unwrap();
+        let mut tail = FileTail::at_end(&path).unwrap();
+
+        fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        assert!(tail.read_new_lines().unwrap().is_empty());
+
+        fs::write(&path, "rotated\n").unwrap();
+        assert_eq!(tail.read_new_lines().unwrap(), ["rotated"]);
+    }
+}
@@ -1,9 +1,26 @@
 //! Execution engine for Arta commands
 
 pub mod actions;
+pub mod audit;
+pub mod cancel;
+pub mod confirm;
+pub mod diff;
 pub mod executor;
+pub mod fields;
+pub mod filter;
+pub mod follow;
+pub mod ownership;
+pub mod provider;
 pub mod queries;
+pub mod registry;
+pub mod repeat;
 
+pub use cancel::CancellationToken;
+pub use confirm::Confirmer;
 pub use executor::{
-    execute_command, execute_command_with_context, ExecutionContext, ExecutionResult, ResultData,
+    check_alert, execute_command, execute_command_with_context, ExecutionContext, ExecutionResult,
+    ResultData, ALERT_EXIT_CODE,
 };
+pub use provider::{RefreshStrategy, SysinfoProvider, SystemHandle, SystemProvider};
+pub use registry::TargetRegistry;
+pub use repeat::{run_repeated, RepeatPlan};
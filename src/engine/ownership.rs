@@ -0,0 +1,150 @@
+//! File owner, group and permission bits for FILES queries
+//!
+//! Only filled in when a query asks for them (selected, filtered or sorted
+//! on, or `--verbose`), since resolving names costs a lookup per new id.
+//! Other platforms report no ownership.
+
+use std::collections::HashMap;
+use std::fs::Metadata;
+
+/// Owner and mode of one file; each part is `None` where unavailable
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ownership {
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    /// Permission bits as `ls` shows them, e.g. `rwxr-xr-x`
+    pub mode: Option<String>,
+}
+
+/// User and group names by id, resolved once per query
+#[derive(Debug, Default)]
+pub struct OwnerNames {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl OwnerNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ownership of a file from its metadata
+    #[cfg(unix)]
+    pub fn ownership(&mut self, metadata: &Metadata) -> Ownership {
+        use std::os::unix::fs::MetadataExt;
+
+        let (uid, gid) = (metadata.uid(), metadata.gid());
+        let owner = self
+            .users
+            .entry(uid)
+            .or_insert_with(|| user_name(uid).unwrap_or_else(|| uid.to_string()))
+            .clone();
+        let group = self
+            .groups
+            .entry(gid)
+            .or_insert_with(|| group_name(gid).unwrap_or_else(|| gid.to_string()))
+            .clone();
+
+        Ownership {
+            owner: Some(owner),
+            group: Some(group),
+            mode: Some(mode_string(metadata.mode())),
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn ownership(&mut self, _metadata: &Metadata) -> Ownership {
+        Ownership::default()
+    }
+}
+
+/// `rwxr-xr-x`-style rendering of the permission bits of a mode
+pub fn mode_string(mode: u32) -> String {
+    const BITS: [(u32, char); 9] = [
+        (0o400, 'r'),
+        (0o200, 'w'),
+        (0o100, 'x'),
+        (0o040, 'r'),
+        (0o020, 'w'),
+        (0o010, 'x'),
+        (0o004, 'r'),
+        (0o002, 'w'),
+        (0o001, 'x'),
+    ];
+    BITS.iter()
+        .map(|&(bit, c)| if mode & bit != 0 { c } else { '-' })
+        .collect()
+}
+
+/// Name of a user id, from the password database
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    lookup_name(|buf, len| {
+        // SAFETY: `passwd` is plain data; getpwuid_r fills it and points its
+        // strings into `buf`, which outlives every read of them below
+        let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        let rc = unsafe { libc::getpwuid_r(uid, &mut entry, buf, len, &mut result) };
+        (rc, (!result.is_null()).then_some(entry.pw_name))
+    })
+}
+
+/// Name of a group id, from the group database
+#[cfg(unix)]
+pub fn group_name(gid: u32) -> Option<String> {
+    lookup_name(|buf, len| {
+        // SAFETY: as in `user_name`, for the group entry
+        let mut entry: libc::group = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        let rc = unsafe { libc::getgrgid_r(gid, &mut entry, buf, len, &mut result) };
+        (rc, (!result.is_null()).then_some(entry.gr_name))
+    })
+}
+
+/// Run a `get*_r` lookup, growing its buffer while it reports `ERANGE`
+#[cfg(unix)]
+fn lookup_name(
+    mut lookup: impl FnMut(*mut libc::c_char, usize) -> (libc::c_int, Option<*mut libc::c_char>),
+) -> Option<String> {
+    let mut buf: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        match lookup(buf.as_mut_ptr(), buf.len()) {
+            (libc::ERANGE, _) if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            (0, Some(name)) if !name.is_null() => {
+                // SAFETY: the name is a NUL-terminated string inside `buf`
+                let name = unsafe { std::ffi::CStr::from_ptr(name) };
+                return Some(name.to_string_lossy().into_owned());
+            }
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_string() {
+        assert_eq!(mode_string(0o755), "rwxr-xr-x");
+        assert_eq!(mode_string(0o100644), "rw-r--r--");
+        assert_eq!(mode_string(0o600), "rw-------");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ownership_of_new_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("owned.txt");
+        std::fs::write(&path, "x").unwrap();
+
+        let metadata = std::fs::metadata(&path).unwrap();
+        let ownership = OwnerNames::new().ownership(&metadata);
+        // SAFETY: geteuid has no preconditions
+        let uid = unsafe { libc::geteuid() };
+        let expected = user_name(uid).unwrap_or_else(|| uid.to_string());
+        assert_eq!(ownership.owner, Some(expected));
+        assert!(ownership.group.is_some());
+        assert!(ownership.mode.unwrap().starts_with("rw"));
+    }
+}
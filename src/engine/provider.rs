@@ -0,0 +1,326 @@
+//! Reusable sysinfo access for queries
+//!
+//! Creating a `System` and calling `refresh_all` for every query is expensive.
+//! A `SystemProvider` keeps one `System` alive for a whole session and only
+//! refreshes the parts a query actually needs.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::engine::queries::network::NetworkCounters;
+use crate::engine::queries::process::{count_open_files, resolve_user_names};
+use crate::engine::queries::{CpuInfo, MemoryInfo, ProcessInfo};
+use sysinfo::{ProcessRefreshKind, System, UpdateKind, MINIMUM_CPU_UPDATE_INTERVAL};
+
+/// Oldest previous sample CPU usage is measured against
+///
+/// Usage is averaged between two samples, so a sample older than this is
+/// replaced by a fresh one instead of reporting a long-past average.
+pub const MAX_SAMPLE_AGE: Duration = Duration::from_secs(10);
+
+/// How a provider refreshes its `System` before handing it to a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RefreshStrategy {
+    /// Refresh everything on every request (slow, but always complete)
+    Full,
+    /// Refresh only the data the query needs
+    #[default]
+    Selective,
+}
+
+/// Counters for the refreshes a provider has performed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshStats {
+    /// Number of `refresh_all` calls
+    pub full_refreshes: usize,
+    /// Number of targeted refreshes (CPU, memory, processes)
+    pub selective_refreshes: usize,
+    /// Number of processes whose user name was looked up
+    pub user_lookups: usize,
+    /// Number of processes whose open files were counted
+    pub open_file_scans: usize,
+}
+
+/// Source of refreshed `System` snapshots for queries
+///
+/// Queries read their figures through `cpu_info`, `memory_info` and
+/// `process_list`, so a provider can override those to serve fixed data.
+pub trait SystemProvider: Send {
+    /// Get a system with up-to-date CPU usage
+    fn cpu(&mut self) -> &System;
+    /// Get a system with up-to-date memory figures
+    fn memory(&mut self) -> &System;
+    /// Get a system with an up-to-date process list
+    fn processes(&mut self) -> &System;
+
+    /// Current CPU figures
+    fn cpu_info(&mut self) -> CpuInfo {
+        CpuInfo::from_system(self.cpu())
+    }
+    /// Current memory figures
+    fn memory_info(&mut self) -> MemoryInfo {
+        MemoryInfo::from_system(self.memory())
+    }
+    /// Current process list
+    fn process_list(&mut self) -> Vec<ProcessInfo> {
+        ProcessInfo::list(self.processes())
+    }
+    /// Current process list without sampling CPU usage
+    ///
+    /// Skips the wait between two samples; `cpu` may be stale or zero.
+    fn process_list_without_cpu(&mut self) -> Vec<ProcessInfo> {
+        self.process_list()
+    }
+    /// Fill in the `user` name of the given processes
+    ///
+    /// Looking names up is comparatively slow, so queries call this only for
+    /// the rows they keep.
+    fn resolve_users(&mut self, processes: &mut [ProcessInfo]) {
+        resolve_user_names(processes);
+    }
+    /// Fill in the `open_files` count of the given processes
+    ///
+    /// Reads a directory per process, so queries call this only when
+    /// `open_files` is selected or filtered on.
+    fn count_open_files(&mut self, processes: &mut [ProcessInfo]) {
+        count_open_files(processes);
+    }
+    /// Store the latest network counters, returning the previous ones and
+    /// how long ago they were stored
+    ///
+    /// Backs the NETWORK `*_delta` fields; the default keeps no history.
+    fn swap_network_counters(
+        &mut self,
+        _current: NetworkCounters,
+    ) -> (NetworkCounters, Option<Duration>) {
+        (NetworkCounters::new(), None)
+    }
+    /// Refresh counters, mainly for diagnostics and tests
+    fn stats(&self) -> RefreshStats;
+}
+
+/// Default provider backed by a single long-lived `System`
+pub struct SysinfoProvider {
+    sys: System,
+    strategy: RefreshStrategy,
+    last_cpu_refresh: Option<Instant>,
+    last_process_refresh: Option<Instant>,
+    network_counters: NetworkCounters,
+    last_network_query: Option<Instant>,
+    stats: RefreshStats,
+}
+
+impl SysinfoProvider {
+    /// Create a provider with the given refresh strategy
+    pub fn new(strategy: RefreshStrategy) -> Self {
+        Self {
+            sys: System::new(),
+            strategy,
+            last_cpu_refresh: None,
+            last_process_refresh: None,
+            network_counters: NetworkCounters::new(),
+            last_network_query: None,
+            stats: RefreshStats::default(),
+        }
+    }
+
+    fn refresh_all_twice(&mut self) {
+        // CPU usage is computed from the difference between two samples
+        self.sys.refresh_all();
+        std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+        self.sys.refresh_all();
+        self.stats.full_refreshes += 2;
+    }
+
+    /// Wait until a second sample is meaningful, based on the previous one
+    ///
+    /// Returns true when there is no recent previous sample, in which case
+    /// the caller takes one and waits before sampling again.
+    fn wait_for_sample(last: Option<Instant>) -> bool {
+        match last {
+            Some(t) if t.elapsed() <= MAX_SAMPLE_AGE => {
+                let elapsed = t.elapsed();
+                if elapsed < MINIMUM_CPU_UPDATE_INTERVAL {
+                    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL - elapsed);
+                }
+                false
+            }
+            _ => true,
+        }
+    }
+
+    fn refresh_process_list(&mut self) {
+        self.sys.refresh_processes_specifics(
+            ProcessRefreshKind::new()
+                .with_cpu()
+                .with_memory()
+                .with_user(UpdateKind::OnlyIfNotSet),
+        );
+    }
+}
+
+impl Default for SysinfoProvider {
+    fn default() -> Self {
+        Self::new(RefreshStrategy::default())
+    }
+}
+
+impl SystemProvider for SysinfoProvider {
+    fn cpu(&mut self) -> &System {
+        match self.strategy {
+            RefreshStrategy::Full => self.refresh_all_twice(),
+            RefreshStrategy::Selective => {
+                if Self::wait_for_sample(self.last_cpu_refresh) {
+                    self.sys.refresh_cpu();
+                    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+                    self.stats.selective_refreshes += 1;
+                }
+                self.sys.refresh_cpu();
+                self.stats.selective_refreshes += 1;
+                self.last_cpu_refresh = Some(Instant::now());
+            }
+        }
+        &self.sys
+    }
+
+    fn memory(&mut self) -> &System {
+        match self.strategy {
+            RefreshStrategy::Full => {
+                self.sys.refresh_all();
+                self.stats.full_refreshes += 1;
+            }
+            RefreshStrategy::Selective => {
+                self.sys.refresh_memory();
+                self.stats.selective_refreshes += 1;
+            }
+        }
+        &self.sys
+    }
+
+    fn processes(&mut self) -> &System {
+        match self.strategy {
+            RefreshStrategy::Full => self.refresh_all_twice(),
+            RefreshStrategy::Selective => {
+                if Self::wait_for_sample(self.last_process_refresh) {
+                    self.refresh_process_list();
+                    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+                    self.stats.selective_refreshes += 1;
+                }
+                self.refresh_process_list();
+                self.stats.selective_refreshes += 1;
+                self.last_process_refresh = Some(Instant::now());
+            }
+        }
+        &self.sys
+    }
+
+    fn process_list_without_cpu(&mut self) -> Vec<ProcessInfo> {
+        match self.strategy {
+            RefreshStrategy::Full => {
+                self.sys.refresh_all();
+                self.stats.full_refreshes += 1;
+            }
+            RefreshStrategy::Selective => {
+                self.sys.refresh_processes_specifics(
+                    ProcessRefreshKind::new()
+                        .with_memory()
+                        .with_user(UpdateKind::OnlyIfNotSet),
+                );
+                self.stats.selective_refreshes += 1;
+            }
+        }
+        ProcessInfo::list(&self.sys)
+    }
+
+    fn resolve_users(&mut self, processes: &mut [ProcessInfo]) {
+        resolve_user_names(processes);
+        self.stats.user_lookups += processes.len();
+    }
+
+    fn count_open_files(&mut self, processes: &mut [ProcessInfo]) {
+        count_open_files(processes);
+        self.stats.open_file_scans += processes.len();
+    }
+
+    fn swap_network_counters(
+        &mut self,
+        current: NetworkCounters,
+    ) -> (NetworkCounters, Option<Duration>) {
+        let elapsed = self.last_network_query.replace(Instant::now());
+        (
+            std::mem::replace(&mut self.network_counters, current),
+            elapsed.map(|since| since.elapsed()),
+        )
+    }
+
+    fn stats(&self) -> RefreshStats {
+        self.stats
+    }
+}
+
+/// Shared handle to a session's system provider
+#[derive(Clone)]
+pub struct SystemHandle(Arc<Mutex<Box<dyn SystemProvider>>>);
+
+impl SystemHandle {
+    /// Wrap a provider so it can be shared across a session
+    pub fn new(provider: impl SystemProvider + 'static) -> Self {
+        Self(Arc::new(Mutex::new(Box::new(provider))))
+    }
+
+    /// Lock the provider for use by a query
+    pub fn lock(&self) -> MutexGuard<'_, Box<dyn SystemProvider>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for SystemHandle {
+    fn default() -> Self {
+        Self::new(SysinfoProvider::default())
+    }
+}
+
+impl std::fmt::Debug for SystemHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SystemHandle")
+            .field(&self.lock().stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selective_memory_avoids_full_refresh() {
+        let mut provider = SysinfoProvider::new(RefreshStrategy::Selective);
+        assert!(provider.memory().total_memory() > 0);
+        assert_eq!(provider.stats().full_refreshes, 0);
+        assert_eq!(provider.stats().selective_refreshes, 1);
+    }
+
+    #[test]
+    fn test_cpu_resamples_after_old_sample() {
+        let mut provider = SysinfoProvider::new(RefreshStrategy::Selective);
+        provider.cpu();
+        assert_eq!(provider.stats().selective_refreshes, 2);
+
+        // A recent sample is measured against
+        provider.cpu();
+        assert_eq!(provider.stats().selective_refreshes, 3);
+
+        // An old one is replaced by a fresh pair of samples
+        provider.last_cpu_refresh = Instant::now().checked_sub(MAX_SAMPLE_AGE * 2);
+        provider.cpu();
+        assert_eq!(provider.stats().selective_refreshes, 5);
+    }
+
+    #[test]
+    fn test_full_strategy_counts_full_refreshes() {
+        let mut provider = SysinfoProvider::new(RefreshStrategy::Full);
+        provider.memory();
+        provider.memory();
+        assert_eq!(provider.stats().full_refreshes, 2);
+    }
+}
@@ -1,6 +1,7 @@
 //! Battery query implementation
 
 use crate::error::Result;
+use crate::output::format_util::fmt_duration;
 use crate::parser::FieldList;
 use serde::{Deserialize, Serialize};
 
@@ -15,6 +16,44 @@ pub struct BatteryEntry {
     pub percentage: f32,
     pub time_to_empty: Option<String>,
     pub time_to_full: Option<String>,
+    /// Full-charge capacity in watt-hours, when reported
+    #[serde(default)]
+    pub capacity_wh: Option<f64>,
+}
+
+impl BatteryInfo {
+    /// Combined charge percentage across all batteries
+    ///
+    /// Weighted by capacity when every battery reports one, otherwise a
+    /// plain average. `None` if there are no batteries.
+    pub fn aggregate_percentage(&self) -> Option<f64> {
+        if self.batteries.is_empty() {
+            return None;
+        }
+
+        let capacities: Option<Vec<f64>> = self
+            .batteries
+            .iter()
+            .map(|b| b.capacity_wh.filter(|c| *c > 0.0))
+            .collect();
+
+        match capacities {
+            Some(capacities) => {
+                let total: f64 = capacities.iter().sum();
+                let charged: f64 = self
+                    .batteries
+                    .iter()
+                    .zip(&capacities)
+                    .map(|(b, c)| b.percentage as f64 * c)
+                    .sum();
+                Some(charged / total)
+            }
+            None => {
+                let sum: f64 = self.batteries.iter().map(|b| b.percentage as f64).sum();
+                Some(sum / self.batteries.len() as f64)
+            }
+        }
+    }
 }
 
 pub fn query_battery(_fields: &FieldList) -> Result<BatteryInfo> {
@@ -41,17 +80,19 @@ pub fn query_battery(_fields: &FieldList) -> Result<BatteryInfo> {
 
             let time_to_empty = battery
                 .time_to_empty()
-                .map(|t| format_duration(t.value as u64));
+                .map(|t| fmt_duration(t.value as u64));
+
+            let time_to_full = battery.time_to_full().map(|t| fmt_duration(t.value as u64));
 
-            let time_to_full = battery
-                .time_to_full()
-                .map(|t| format_duration(t.value as u64));
+            // Energy is reported in joules
+            let capacity_wh = Some(battery.energy_full().value as f64 / 3600.0);
 
             BatteryEntry {
                 state,
                 percentage,
                 time_to_empty,
                 time_to_full,
+                capacity_wh,
             }
         })
         .collect();
@@ -59,16 +100,6 @@ pub fn query_battery(_fields: &FieldList) -> Result<BatteryInfo> {
     Ok(BatteryInfo { batteries })
 }
 
-fn format_duration(seconds: u64) -> String {
-    let hours = seconds / 3600;
-    let minutes = (seconds % 3600) / 60;
-    if hours > 0 {
-        format!("{}h {}m", hours, minutes)
-    } else {
-        format!("{}m", minutes)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,5 +1,6 @@
 //! CPU query implementation
 
+use crate::engine::provider::{SysinfoProvider, SystemProvider};
 use crate::error::Result;
 use crate::parser::FieldList;
 use serde::{Deserialize, Serialize};
@@ -13,34 +14,39 @@ pub struct CpuInfo {
     pub frequency: u64,
 }
 
-pub fn query_cpu(_fields: &FieldList) -> Result<CpuInfo> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-
-    // Give CPU time to collect usage data
-    std::thread::sleep(std::time::Duration::from_millis(200));
-    sys.refresh_all();
-
-    let cpus = sys.cpus();
-    let usage: f32 = if !cpus.is_empty() {
-        cpus.iter().map(|cpu| cpu.cpu_usage()).sum::<f32>() / cpus.len() as f32
-    } else {
-        0.0
-    };
-
-    let brand = cpus
-        .first()
-        .map(|cpu| cpu.brand().to_string())
-        .unwrap_or_else(|| "Unknown".to_string());
-
-    let frequency = cpus.first().map(|cpu| cpu.frequency()).unwrap_or(0);
-
-    Ok(CpuInfo {
-        cores: cpus.len(),
-        usage,
-        brand,
-        frequency,
-    })
+impl CpuInfo {
+    /// Summarize the CPUs of a refreshed system
+    pub fn from_system(sys: &System) -> Self {
+        let cpus = sys.cpus();
+        let usage: f32 = if !cpus.is_empty() {
+            cpus.iter().map(|cpu| cpu.cpu_usage()).sum::<f32>() / cpus.len() as f32
+        } else {
+            0.0
+        };
+
+        let brand = cpus
+            .first()
+            .map(|cpu| cpu.brand().to_string())
+            .unwrap_or_else(|| "Unknown".to_string());
+
+        let frequency = cpus.first().map(|cpu| cpu.frequency()).unwrap_or(0);
+
+        CpuInfo {
+            cores: cpus.len(),
+            usage,
+            brand,
+            frequency,
+        }
+    }
+}
+
+pub fn query_cpu(fields: &FieldList) -> Result<CpuInfo> {
+    query_cpu_with(&mut SysinfoProvider::default(), fields)
+}
+
+/// Query CPU information using a (possibly shared) system provider
+pub fn query_cpu_with(provider: &mut dyn SystemProvider, _fields: &FieldList) -> Result<CpuInfo> {
+    Ok(provider.cpu_info())
 }
 
 #[cfg(test)]
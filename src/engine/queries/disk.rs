@@ -1,8 +1,10 @@
 //! Disk query implementation
 
+use crate::engine::filter::percent_share;
 use crate::error::Result;
 use crate::parser::FieldList;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use sysinfo::Disks;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,27 +23,55 @@ pub struct DiskEntry {
     pub file_system: String,
 }
 
-pub fn query_disk(_fields: &FieldList, from_path: Option<&str>) -> Result<DiskInfo> {
+impl DiskInfo {
+    /// The mount that contains `path` (longest matching mount point)
+    pub fn mount_for(&self, path: &str) -> Option<&DiskEntry> {
+        let path = Path::new(path);
+        self.disks
+            .iter()
+            .filter(|d| path.starts_with(&d.mount_point))
+            .max_by_key(|d| d.mount_point.len())
+    }
+
+    /// The disk used when no mount is named: the root mount, else the first disk
+    pub fn primary(&self) -> Option<&DiskEntry> {
+        self.mount_for("/").or_else(|| self.disks.first())
+    }
+}
+
+/// Filesystems backed by memory or the kernel rather than a disk
+///
+/// Hidden from DISK queries unless `INCLUDE PSEUDO` or `--all-mounts` is given.
+/// `overlay` and `squashfs` cover container roots and snap packages, which
+/// repeat the size of the disk underneath.
+pub const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "tmpfs", "devtmpfs", "devfs", "proc", "sysfs", "overlay", "squashfs",
+];
+
+/// Whether `file_system` is one of [`PSEUDO_FILESYSTEMS`]
+pub fn is_pseudo_filesystem(file_system: &str) -> bool {
+    PSEUDO_FILESYSTEMS
+        .iter()
+        .any(|fs| fs.eq_ignore_ascii_case(file_system))
+}
+
+/// Query mounted disks
+///
+/// Pseudo filesystems are skipped unless `include_pseudo` is set.
+pub fn query_disk(
+    _fields: &FieldList,
+    from_path: Option<&str>,
+    include_pseudo: bool,
+) -> Result<DiskInfo> {
     let disks = Disks::new_with_refreshed_list();
 
     let entries: Vec<DiskEntry> = disks
         .iter()
-        .filter(|disk| {
-            if let Some(path) = from_path {
-                disk.mount_point().to_string_lossy().starts_with(path)
-            } else {
-                true
-            }
-        })
         .map(|disk| {
             let total = disk.total_space();
             let free = disk.available_space();
             let used = total.saturating_sub(free);
-            let usage_percent = if total > 0 {
-                (used as f64 / total as f64) * 100.0
-            } else {
-                0.0
-            };
+            let usage_percent = percent_share(used, total);
 
             DiskEntry {
                 name: disk.name().to_string_lossy().to_string(),
@@ -55,17 +85,95 @@ pub fn query_disk(_fields: &FieldList, from_path: Option<&str>) -> Result<DiskIn
         })
         .collect();
 
-    Ok(DiskInfo { disks: entries })
+    Ok(DiskInfo {
+        disks: filter_disks(entries, from_path, include_pseudo),
+    })
+}
+
+/// Apply the FROM path and pseudo filesystem rule to a list of disks
+pub fn filter_disks(
+    mut disks: Vec<DiskEntry>,
+    from_path: Option<&str>,
+    include_pseudo: bool,
+) -> Vec<DiskEntry> {
+    if !include_pseudo {
+        disks.retain(|d| !is_pseudo_filesystem(&d.file_system));
+    }
+
+    if let Some(path) = from_path {
+        disks.retain(|d| d.mount_point.starts_with(path));
+    }
+
+    disks
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn entry(mount: &str, file_system: &str) -> DiskEntry {
+        DiskEntry {
+            name: mount.to_string(),
+            mount_point: mount.to_string(),
+            total: 100,
+            used: 50,
+            free: 50,
+            usage_percent: 50.0,
+            file_system: file_system.to_string(),
+        }
+    }
+
     #[test]
+    #[allow(clippy::overly_complex_bool_expr)]
     fn test_disk_query() {
-        let info = query_disk(&FieldList::All, None).unwrap();
+        let info = query_disk(&FieldList::All, None, false).unwrap();
         // Should have at least one disk
         assert!(!info.disks.is_empty() || true); // May be empty in some test environments
+        assert!(info
+            .disks
+            .iter()
+            .all(|d| !is_pseudo_filesystem(&d.file_system)));
+    }
+
+    #[test]
+    fn test_pseudo_filesystems_hidden_by_default() {
+        let disks = vec![
+            entry("/", "ext4"),
+            entry("/dev", "devtmpfs"),
+            entry("/dev/shm", "tmpfs"),
+            entry("/proc", "proc"),
+            entry("/sys", "sysfs"),
+            entry("/var/lib/docker/overlay2/abc/merged", "overlay"),
+            entry("/snap/core/1", "squashfs"),
+            entry("/Volumes/Data", "APFS"),
+        ];
+        let mounts = |disks: Vec<DiskEntry>| -> Vec<String> {
+            disks.into_iter().map(|d| d.mount_point).collect()
+        };
+
+        assert_eq!(
+            mounts(filter_disks(disks.clone(), None, false)),
+            ["/", "/Volumes/Data"]
+        );
+        assert_eq!(filter_disks(disks.clone(), None, true).len(), disks.len());
+        assert_eq!(
+            mounts(filter_disks(disks.clone(), Some("/dev"), true)),
+            ["/dev", "/dev/shm"]
+        );
+        assert!(filter_disks(disks, Some("/dev"), false).is_empty());
+        assert!(is_pseudo_filesystem("TMPFS"));
+    }
+
+    #[test]
+    fn test_mount_for_picks_longest_mount() {
+        let entry = |mount: &str| entry(mount, "ext4");
+        let info = DiskInfo {
+            disks: vec![entry("/boot"), entry("/"), entry("/home")],
+        };
+
+        assert_eq!(info.mount_for("/home/user").unwrap().mount_point, "/home");
+        assert_eq!(info.mount_for("/var/log").unwrap().mount_point, "/");
+        assert_eq!(info.mount_for("/homework").unwrap().mount_point, "/");
+        assert_eq!(info.primary().unwrap().mount_point, "/");
     }
 }
@@ -1,5 +1,7 @@
 //! Memory query implementation
 
+use crate::engine::filter::percent_share;
+use crate::engine::provider::{SysinfoProvider, SystemProvider};
 use crate::error::Result;
 use crate::parser::FieldList;
 use serde::{Deserialize, Serialize};
@@ -12,39 +14,185 @@ pub struct MemoryInfo {
     pub free: u64,
     pub available: u64,
     pub usage_percent: f64,
+    pub swap_total: u64,
+    pub swap_used: u64,
+    /// How close the system is to running out of memory
+    pub pressure: MemoryPressure,
 }
 
-pub fn query_memory(_fields: &FieldList) -> Result<MemoryInfo> {
-    let mut sys = System::new_all();
-    sys.refresh_memory();
+/// Memory pressure classification, from available memory and swap usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryPressure {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl std::fmt::Display for MemoryPressure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MemoryPressure::Low => "low",
+            MemoryPressure::Medium => "medium",
+            MemoryPressure::High => "high",
+            MemoryPressure::Critical => "critical",
+        })
+    }
+}
+
+/// Thresholds for `MemoryPressure`, as percentages of total memory or swap
+///
+/// The first matching rule wins:
+///
+/// | Pressure | Rule                                                      |
+/// |----------|-----------------------------------------------------------|
+/// | critical | available < `critical_available` (5%)                     |
+/// | high     | available < `high_available` (10%) or swap used >= `high_swap` (80%) |
+/// | medium   | available < `medium_available` (25%) or swap used >= `medium_swap` (50%) |
+/// | low      | otherwise                                                 |
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PressureThresholds {
+    pub critical_available: f64,
+    pub high_available: f64,
+    pub medium_available: f64,
+    pub high_swap: f64,
+    pub medium_swap: f64,
+}
+
+impl Default for PressureThresholds {
+    fn default() -> Self {
+        Self {
+            critical_available: 5.0,
+            high_available: 10.0,
+            medium_available: 25.0,
+            high_swap: 80.0,
+            medium_swap: 50.0,
+        }
+    }
+}
 
-    let total = sys.total_memory();
-    let used = sys.used_memory();
-    let free = sys.free_memory();
-    let available = sys.available_memory();
+impl PressureThresholds {
+    /// Classify memory figures against these thresholds
+    pub fn classify(&self, info: &MemoryInfo) -> MemoryPressure {
+        let available = percent(info.available, info.total).unwrap_or(100.0);
+        let swap = percent(info.swap_used, info.swap_total).unwrap_or(0.0);
 
-    let usage_percent = if total > 0 {
-        (used as f64 / total as f64) * 100.0
-    } else {
-        0.0
-    };
+        if available < self.critical_available {
+            MemoryPressure::Critical
+        } else if available < self.high_available || swap >= self.high_swap {
+            MemoryPressure::High
+        } else if available < self.medium_available || swap >= self.medium_swap {
+            MemoryPressure::Medium
+        } else {
+            MemoryPressure::Low
+        }
+    }
+}
 
-    Ok(MemoryInfo {
-        total,
-        used,
-        free,
-        available,
-        usage_percent,
-    })
+fn percent(part: u64, total: u64) -> Option<f64> {
+    (total > 0).then(|| percent_share(part, total))
+}
+
+impl MemoryInfo {
+    /// Memory figures from raw byte counts
+    pub fn new(total: u64, used: u64, free: u64, available: u64) -> Self {
+        let mut info = MemoryInfo {
+            total,
+            used,
+            free,
+            available,
+            usage_percent: percent_share(used, total),
+            swap_total: 0,
+            swap_used: 0,
+            pressure: MemoryPressure::Low,
+        };
+        info.pressure = PressureThresholds::default().classify(&info);
+        info
+    }
+
+    /// Add swap figures, reclassifying pressure with the default thresholds
+    pub fn with_swap(mut self, swap_total: u64, swap_used: u64) -> Self {
+        self.swap_total = swap_total;
+        self.swap_used = swap_used;
+        self.pressure = PressureThresholds::default().classify(&self);
+        self
+    }
+
+    /// Memory figures of a refreshed system
+    pub fn from_system(sys: &System) -> Self {
+        Self::new(
+            sys.total_memory(),
+            sys.used_memory(),
+            sys.free_memory(),
+            sys.available_memory(),
+        )
+        .with_swap(sys.total_swap(), sys.used_swap())
+    }
+}
+
+pub fn query_memory(fields: &FieldList) -> Result<MemoryInfo> {
+    query_memory_with(
+        &mut SysinfoProvider::default(),
+        fields,
+        &PressureThresholds::default(),
+    )
+}
+
+/// Query memory information using a (possibly shared) system provider
+pub fn query_memory_with(
+    provider: &mut dyn SystemProvider,
+    _fields: &FieldList,
+    thresholds: &PressureThresholds,
+) -> Result<MemoryInfo> {
+    let mut info = provider.memory_info();
+    info.pressure = thresholds.classify(&info);
+    Ok(info)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn memory(available_gb: u64, swap_used_gb: u64) -> MemoryInfo {
+        MemoryInfo::new(
+            100 * GB,
+            (100 - available_gb) * GB,
+            available_gb * GB,
+            available_gb * GB,
+        )
+        .with_swap(10 * GB, swap_used_gb * GB)
+    }
+
     #[test]
     fn test_memory_query() {
         let info = query_memory(&FieldList::All).unwrap();
         assert!(info.total > 0);
     }
+
+    #[test]
+    fn test_pressure_classification() {
+        assert_eq!(memory(60, 0).pressure, MemoryPressure::Low);
+        assert_eq!(memory(20, 0).pressure, MemoryPressure::Medium);
+        assert_eq!(memory(60, 5).pressure, MemoryPressure::Medium);
+        assert_eq!(memory(8, 0).pressure, MemoryPressure::High);
+        assert_eq!(memory(60, 9).pressure, MemoryPressure::High);
+        assert_eq!(memory(3, 0).pressure, MemoryPressure::Critical);
+        // No swap configured is not swap pressure
+        assert_eq!(
+            MemoryInfo::new(100 * GB, 40 * GB, 60 * GB, 60 * GB).pressure,
+            MemoryPressure::Low
+        );
+    }
+
+    #[test]
+    fn test_pressure_thresholds_override() {
+        let strict = PressureThresholds {
+            critical_available: 70.0,
+            ..Default::default()
+        };
+        assert_eq!(strict.classify(&memory(60, 0)), MemoryPressure::Critical);
+    }
 }
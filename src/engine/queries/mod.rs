@@ -6,12 +6,16 @@ pub mod disk;
 pub mod memory;
 pub mod network;
 pub mod process;
+pub mod self_proc;
+pub mod services;
 pub mod system;
 
 pub use battery::{query_battery, BatteryInfo};
-pub use cpu::{query_cpu, CpuInfo};
+pub use cpu::{query_cpu, query_cpu_with, CpuInfo};
 pub use disk::{query_disk, DiskInfo};
-pub use memory::{query_memory, MemoryInfo};
-pub use network::{query_network, NetworkInfo};
-pub use process::{query_processes, ProcessInfo};
+pub use memory::{query_memory, query_memory_with, MemoryInfo};
+pub use network::{query_network, query_network_with, NetworkInfo};
+pub use process::{full_process_name, query_processes, query_processes_with, ProcessInfo};
+pub use self_proc::{query_self, query_self_with, SelfInfo};
+pub use services::{query_services, ServiceInfo};
 pub use system::{query_system, SystemInfo};
@@ -1,49 +1,286 @@
 //! Network query implementation
 
+use crate::engine::filter::{self, compare_bools, compare_numbers, compare_strings};
+use crate::engine::provider::SystemProvider;
 use crate::error::Result;
-use crate::parser::FieldList;
+use crate::parser::{FieldList, Value, WhereClause};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use sysinfo::Networks;
 
+/// Cumulative (received, transmitted) bytes per interface name
+pub type NetworkCounters = HashMap<String, (u64, u64)>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkInfo {
     pub interfaces: Vec<NetworkInterface>,
+    /// Seconds since the previous NETWORK query, which the deltas cover
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta_secs: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkInterface {
     pub name: String,
+    /// Total bytes received since boot
     pub received: u64,
+    /// Total bytes transmitted since boot
     pub transmitted: u64,
+    /// Bytes received since the previous NETWORK query of the session
+    #[serde(default)]
+    pub received_delta: Option<u64>,
+    /// Bytes transmitted since the previous NETWORK query of the session
+    #[serde(default)]
+    pub transmitted_delta: Option<u64>,
     pub packets_received: u64,
     pub packets_transmitted: u64,
+    /// Whether the interface is administratively up (assumed up if unknown)
+    pub up: bool,
+}
+
+/// Query network interfaces, optionally filtered by a WHERE clause
+///
+/// Interfaces that are administratively down are skipped unless
+/// `include_down` is set.
+pub fn query_network(
+    _fields: &FieldList,
+    where_clause: Option<&WhereClause>,
+    include_down: bool,
+) -> Result<NetworkInfo> {
+    Ok(NetworkInfo {
+        interfaces: filter_interfaces(list_interfaces(), where_clause, include_down),
+        delta_secs: None,
+    })
 }
 
-pub fn query_network(_fields: &FieldList) -> Result<NetworkInfo> {
-    let networks = Networks::new_with_refreshed_list();
+/// Query network interfaces, with deltas against the session's previous query
+pub fn query_network_with(
+    provider: &mut dyn SystemProvider,
+    _fields: &FieldList,
+    where_clause: Option<&WhereClause>,
+    include_down: bool,
+) -> Result<NetworkInfo> {
+    let mut interfaces = list_interfaces();
+    let (previous, elapsed) = provider.swap_network_counters(counters_of(&interfaces));
+    apply_deltas(&mut interfaces, &previous);
 
-    let interfaces: Vec<NetworkInterface> = networks
+    Ok(NetworkInfo {
+        interfaces: filter_interfaces(interfaces, where_clause, include_down),
+        delta_secs: elapsed.map(|elapsed| elapsed.as_secs_f64()),
+    })
+}
+
+fn list_interfaces() -> Vec<NetworkInterface> {
+    Networks::new_with_refreshed_list()
         .iter()
         .map(|(name, data)| NetworkInterface {
             name: name.to_string(),
-            received: data.received(),
-            transmitted: data.transmitted(),
-            packets_received: data.packets_received(),
-            packets_transmitted: data.packets_transmitted(),
+            received: data.total_received(),
+            transmitted: data.total_transmitted(),
+            received_delta: None,
+            transmitted_delta: None,
+            packets_received: data.total_packets_received(),
+            packets_transmitted: data.total_packets_transmitted(),
+            up: interface_is_up(name),
         })
-        .collect();
+        .collect()
+}
+
+/// Counters to remember for the next delta computation
+pub fn counters_of(interfaces: &[NetworkInterface]) -> NetworkCounters {
+    interfaces
+        .iter()
+        .map(|i| (i.name.clone(), (i.received, i.transmitted)))
+        .collect()
+}
+
+/// Fill in `*_delta` from an earlier snapshot
+///
+/// New interfaces and counters that went backwards (e.g. after the interface
+/// was recreated) get no delta.
+pub fn apply_deltas(interfaces: &mut [NetworkInterface], previous: &NetworkCounters) {
+    for iface in interfaces {
+        if let Some(&(received, transmitted)) = previous.get(&iface.name) {
+            iface.received_delta = iface.received.checked_sub(received);
+            iface.transmitted_delta = iface.transmitted.checked_sub(transmitted);
+        }
+    }
+}
+
+/// Apply the down-interface rule and WHERE clause to a list of interfaces
+pub fn filter_interfaces(
+    mut interfaces: Vec<NetworkInterface>,
+    where_clause: Option<&WhereClause>,
+    include_down: bool,
+) -> Vec<NetworkInterface> {
+    if !include_down {
+        interfaces.retain(|i| i.up);
+    }
+
+    if let Some(where_clause) = where_clause {
+        interfaces.retain(|i| matches_where_clause(i, where_clause));
+    }
 
-    Ok(NetworkInfo { interfaces })
+    interfaces
+}
+
+/// Check the IFF_UP flag of an interface (Linux only)
+#[cfg(target_os = "linux")]
+fn interface_is_up(name: &str) -> bool {
+    const IFF_UP: u32 = 0x1;
+
+    std::fs::read_to_string(format!("/sys/class/net/{}/flags", name))
+        .ok()
+        .and_then(|flags| u32::from_str_radix(flags.trim().trim_start_matches("0x"), 16).ok())
+        .map(|flags| flags & IFF_UP != 0)
+        .unwrap_or(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn interface_is_up(_name: &str) -> bool {
+    true
+}
+
+fn matches_where_clause(iface: &NetworkInterface, where_clause: &WhereClause) -> bool {
+    filter::matches(where_clause, |c| matches_condition(iface, c))
+}
+
+fn matches_condition(iface: &NetworkInterface, condition: &crate::parser::Condition) -> bool {
+    let length = filter::length_condition(condition, |field| match field {
+        "name" => Some(&iface.name),
+        _ => None,
+    });
+    if let Some(matched) = length {
+        return matched;
+    }
+
+    let field = condition.field.to_lowercase();
+
+    let counter = match field.as_str() {
+        "name" => {
+            return match &condition.value {
+                Value::String(s) | Value::Identifier(s) => {
+                    compare_strings(&iface.name, s, &condition.operator)
+                }
+                _ => false,
+            };
+        }
+        "up" => {
+            return match &condition.value {
+                Value::Boolean(b) => compare_bools(iface.up, *b, &condition.operator),
+                _ => false,
+            };
+        }
+        "received" | "recv" | "bytes_recv" => iface.received,
+        "transmitted" | "sent" | "bytes_sent" => iface.transmitted,
+        "packets_received" => iface.packets_received,
+        "packets_transmitted" => iface.packets_transmitted,
+        "received_delta" | "recv_delta" => match iface.received_delta {
+            Some(delta) => delta,
+            None => return false,
+        },
+        "transmitted_delta" | "sent_delta" => match iface.transmitted_delta {
+            Some(delta) => delta,
+            None => return false,
+        },
+        _ => return true, // Unknown field - don't filter
+    };
+
+    let target = match &condition.value {
+        Value::Number(n) => *n,
+        Value::Size(s) => *s as f64,
+        _ => return false,
+    };
+    compare_numbers(counter as f64, target, &condition.operator)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::{parse_command, Command};
+
+    fn iface(name: &str, received: u64, up: bool) -> NetworkInterface {
+        NetworkInterface {
+            name: name.to_string(),
+            received,
+            transmitted: 0,
+            received_delta: None,
+            transmitted_delta: None,
+            packets_received: 0,
+            packets_transmitted: 0,
+            up,
+        }
+    }
+
+    fn synthetic() -> Vec<NetworkInterface> {
+        vec![
+            iface("lo", 1024, true),
+            iface("eth0", 2 * 1024 * 1024 * 1024, true),
+            iface("wlan0", 0, false),
+        ]
+    }
+
+    fn where_of(query: &str) -> WhereClause {
+        match parse_command(query).unwrap() {
+            Command::Query(q) => q.where_clause.unwrap(),
+            _ => panic!("Expected query"),
+        }
+    }
 
     #[test]
+    #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
     fn test_network_query() {
-        let info = query_network(&FieldList::All).unwrap();
+        let info = query_network(&FieldList::All, None, true).unwrap();
         // Network info should be queryable
         assert!(info.interfaces.len() >= 0);
     }
+
+    #[test]
+    fn test_filter_by_name() {
+        let wc = where_of("SELECT NETWORK * WHERE name = \"eth0\"");
+        let result = filter_interfaces(synthetic(), Some(&wc), false);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "eth0");
+    }
+
+    #[test]
+    fn test_filter_by_received_bytes() {
+        let wc = where_of("SELECT NETWORK * WHERE received > 1GB");
+        let result = filter_interfaces(synthetic(), Some(&wc), true);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "eth0");
+    }
+
+    #[test]
+    fn test_deltas_from_consecutive_snapshots() {
+        let first = vec![iface("lo", 1000, true), iface("eth0", 5000, true)];
+        let mut second = vec![
+            iface("lo", 1500, true),
+            iface("eth0", 4000, true),
+            iface("wlan0", 10, true),
+        ];
+        second[0].transmitted = 300;
+
+        let mut initial = first.clone();
+        apply_deltas(&mut initial, &NetworkCounters::new());
+        assert!(initial.iter().all(|i| i.received_delta.is_none()));
+
+        apply_deltas(&mut second, &counters_of(&first));
+        assert_eq!(second[0].received_delta, Some(500));
+        assert_eq!(second[0].transmitted_delta, Some(300));
+        // Counter reset and new interface
+        assert_eq!(second[1].received_delta, None);
+        assert_eq!(second[2].received_delta, None);
+
+        let wc = where_of("SELECT NETWORK * WHERE received_delta > 100");
+        let busy = filter_interfaces(second, Some(&wc), true);
+        assert_eq!(busy.len(), 1);
+        assert_eq!(busy[0].name, "lo");
+    }
+
+    #[test]
+    fn test_down_interfaces_excluded_by_default() {
+        assert_eq!(filter_interfaces(synthetic(), None, false).len(), 2);
+        assert_eq!(filter_interfaces(synthetic(), None, true).len(), 3);
+    }
 }
@@ -1,135 +1,360 @@
 //! Process query implementation
 
+use crate::engine::fields::{canonical_field, sort_by_field};
+use crate::engine::filter::{self, compare_numbers, compare_strings};
+use crate::engine::provider::{SysinfoProvider, SystemProvider};
 use crate::error::Result;
-use crate::parser::{CompareOp, FieldList, Value, WhereClause};
+use crate::parser::{CompareOp, FieldList, OrderBy, QueryTarget, Value, WhereClause};
 use serde::{Deserialize, Serialize};
-use sysinfo::System;
+use std::path::Path;
+use sysinfo::{ProcessStatus, System, Uid, Users};
+
+/// Length at which Linux (`comm`) and macOS cut process names
+const TRUNCATED_NAME_LEN: usize = 15;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessInfo {
     pub pid: u32,
     pub name: String,
+    /// Untruncated name from the executable path, when `name` is cut short
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_full: Option<String>,
     pub cpu: f32,
     pub memory: u64,
+    /// Lowercase state name, e.g. `running`, `sleeping` or `zombie`
     pub status: String,
+    /// Number of threads, on platforms that report it (Linux)
+    #[serde(default)]
+    pub threads: Option<u32>,
+    /// Open file descriptors (Linux); only counted when selected or filtered on
+    #[serde(default)]
+    pub open_files: Option<u32>,
+    /// Owner's user name, or the raw id when it has no name
+    ///
+    /// Filled in by `SystemProvider::resolve_users`; `None` until then.
     pub user: Option<String>,
+    /// Owner's id, for user name lookup
+    #[serde(skip)]
+    pub uid: Option<Uid>,
+}
+
+impl ProcessInfo {
+    /// All processes of a refreshed system
+    pub fn list(sys: &System) -> Vec<Self> {
+        sys.processes()
+            .iter()
+            .map(|(pid, process)| ProcessInfo {
+                pid: pid.as_u32(),
+                name: process.name().to_string(),
+                name_full: full_process_name(process.name(), process.exe()),
+                cpu: process.cpu_usage(),
+                memory: process.memory(),
+                status: process_status_name(process.status()).to_string(),
+                threads: thread_count(process),
+                open_files: None,
+                user: None,
+                uid: process.user_id().cloned(),
+            })
+            .collect()
+    }
+}
+
+/// Threads in a process: its tasks plus the main thread, which sysinfo
+/// leaves out of `tasks()`
+///
+/// `None` where sysinfo has no task list, and for the per-thread entries it
+/// also reports as processes on Linux.
+fn thread_count(process: &sysinfo::Process) -> Option<u32> {
+    process.tasks().map(|tasks| tasks.len() as u32 + 1)
+}
+
+/// Fill in `open_files` by counting each process's `/proc/<pid>/fd` entries
+///
+/// Processes whose fd directory cannot be read (other users' processes
+/// without root, or exited ones) keep `None`, as do all processes on other
+/// platforms.
+pub fn count_open_files(processes: &mut [ProcessInfo]) {
+    for process in processes {
+        process.open_files = open_file_count(process.pid);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_file_count(pid: u32) -> Option<u32> {
+    std::fs::read_dir(format!("/proc/{}/fd", pid))
+        .ok()
+        .map(|entries| entries.count() as u32)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_file_count(_pid: u32) -> Option<u32> {
+    None
+}
+
+/// Fill in `user` from each process's uid, using the system's user list
+pub fn resolve_user_names(processes: &mut [ProcessInfo]) {
+    if processes.iter().all(|p| p.uid.is_none()) {
+        return;
+    }
+    let users = Users::new_with_refreshed_list();
+    for process in processes {
+        if let Some(uid) = &process.uid {
+            process.user = Some(match users.get_user_by_id(uid) {
+                Some(user) => user.name().to_string(),
+                None => format!("{:?}", uid),
+            });
+        }
+    }
+}
+
+/// Lowercase name of a process state, as shown and matched by `status`
+pub fn process_status_name(status: ProcessStatus) -> &'static str {
+    match status {
+        ProcessStatus::Idle => "idle",
+        ProcessStatus::Run => "running",
+        ProcessStatus::Sleep => "sleeping",
+        ProcessStatus::Stop => "stopped",
+        ProcessStatus::Zombie => "zombie",
+        ProcessStatus::Tracing => "tracing",
+        ProcessStatus::Dead => "dead",
+        ProcessStatus::Wakekill => "wakekill",
+        ProcessStatus::Waking => "waking",
+        ProcessStatus::Parked => "parked",
+        ProcessStatus::LockBlocked => "lock_blocked",
+        ProcessStatus::UninterruptibleDiskSleep => "disk_sleep",
+        ProcessStatus::Unknown(_) => "unknown",
+    }
+}
+
+/// Untruncated process name, if the reported one is cut short or a path
+///
+/// Names that are exactly `TRUNCATED_NAME_LEN` long are replaced by the
+/// executable's file name when that starts with them. Names reported as a
+/// path are reduced to their file name. Otherwise the name is kept as is.
+pub fn full_process_name(name: &str, exe: Option<&Path>) -> Option<String> {
+    if name.contains('/') {
+        return Path::new(name)
+            .file_name()
+            .map(|base| base.to_string_lossy().into_owned());
+    }
+
+    let base = exe?.file_name()?.to_string_lossy();
+    (name.chars().count() == TRUNCATED_NAME_LEN
+        && base.len() > name.len()
+        && base.starts_with(name))
+    .then(|| base.into_owned())
+}
+
+/// Match a `name` condition against the reported name, then `name_full`
+///
+/// A process matches `=`, `LIKE` and `CONTAINS` when either name does, and
+/// `!=` only when neither name is equal to the value.
+pub(crate) fn match_process_name(
+    name: &str,
+    name_full: Option<&str>,
+    op: &CompareOp,
+    compare: impl Fn(&str) -> bool,
+) -> bool {
+    let mut candidates = std::iter::once(name).chain(name_full);
+    match op {
+        CompareOp::NotEqual => candidates.all(compare),
+        _ => candidates.any(compare),
+    }
 }
 
 pub fn query_processes(
-    _fields: &FieldList,
+    fields: &FieldList,
     where_clause: Option<&WhereClause>,
 ) -> Result<Vec<ProcessInfo>> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-
-    // Give it time to collect CPU usage
-    std::thread::sleep(std::time::Duration::from_millis(200));
-    sys.refresh_all();
-
-    let mut processes: Vec<ProcessInfo> = sys
-        .processes()
-        .iter()
-        .map(|(pid, process)| ProcessInfo {
-            pid: pid.as_u32(),
-            name: process.name().to_string(),
-            cpu: process.cpu_usage(),
-            memory: process.memory(),
-            status: format!("{:?}", process.status()),
-            user: process.user_id().map(|u| format!("{:?}", u)),
-        })
-        .collect();
+    query_processes_with(
+        &mut SysinfoProvider::default(),
+        fields,
+        where_clause,
+        true,
+        None,
+        None,
+    )
+}
+
+/// Query processes using a (possibly shared) system provider
+///
+/// With `full_names`, `name` conditions also match `name_full`.
+/// CPU usage is only sampled when `cpu` is selected, filtered or sorted on,
+/// or when `limit` is set without `order`, so the kept rows are the busiest;
+/// other queries skip the wait between samples. Without `order` the rows come
+/// back by CPU usage, highest first, or by PID when CPU was not sampled.
+/// User names and `open_files` are looked up after WHERE and `limit`, for the
+/// kept rows only, unless a condition or the ordering needs them;
+/// `open_files` also only when it is selected by name.
+pub fn query_processes_with(
+    provider: &mut dyn SystemProvider,
+    fields: &FieldList,
+    where_clause: Option<&WhereClause>,
+    full_names: bool,
+    order: Option<&OrderBy>,
+    limit: Option<usize>,
+) -> Result<Vec<ProcessInfo>> {
+    if let Some(where_clause) = where_clause {
+        filter::check_patterns(where_clause)?;
+    }
+    // Unknown fields are reported by the sort, before any lookup on their behalf
+    let sort_field = order.and_then(|o| canonical_field(QueryTarget::Process, &o.field));
+    let sample_cpu = needs_cpu(fields, where_clause)
+        || sort_field == Some("cpu")
+        || (order.is_none() && limit.is_some());
+    let mut processes = if sample_cpu {
+        provider.process_list()
+    } else {
+        provider.process_list_without_cpu()
+    };
+
+    let filter_on_user = where_clause
+        .is_some_and(|wc| filter::references_field(wc, QueryTarget::Process, "user"))
+        || sort_field == Some("user");
+    if filter_on_user {
+        provider.resolve_users(&mut processes);
+    }
+    let filter_on_files = where_clause
+        .is_some_and(|wc| filter::references_field(wc, QueryTarget::Process, "open_files"))
+        || sort_field == Some("open_files");
+    if filter_on_files {
+        provider.count_open_files(&mut processes);
+    }
 
     // Apply WHERE clause filtering
     if let Some(where_clause) = where_clause {
-        processes.retain(|p| matches_where_clause(p, where_clause));
+        processes.retain(|p| matches_where_clause(p, where_clause, full_names));
     }
 
-    // Sort by CPU usage descending
-    processes.sort_by(|a, b| {
-        b.cpu
-            .partial_cmp(&a.cpu)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
+    if let Some(order) = order {
+        sort_by_field(&mut processes, order)?;
+    } else if sample_cpu {
+        // Sort by CPU usage descending
+        processes.sort_by(|a, b| {
+            b.cpu
+                .partial_cmp(&a.cpu)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    } else {
+        processes.sort_by_key(|p| p.pid);
+    }
+
+    if let Some(limit) = limit {
+        processes.truncate(limit);
+    }
+    if !filter_on_user {
+        provider.resolve_users(&mut processes);
+    }
+    if !filter_on_files && selects_field(fields, "open_files") {
+        provider.count_open_files(&mut processes);
+    }
 
     Ok(processes)
 }
 
-fn matches_where_clause(process: &ProcessInfo, where_clause: &WhereClause) -> bool {
-    for condition_expr in &where_clause.conditions {
-        if !matches_condition(process, &condition_expr.condition) {
-            return false;
-        }
-    }
-    true
+/// Whether a query selects or filters on CPU usage
+fn needs_cpu(fields: &FieldList, where_clause: Option<&WhereClause>) -> bool {
+    matches!(fields, FieldList::All)
+        || selects_field(fields, "cpu")
+        || where_clause.is_some_and(|wc| filter::references_field(wc, QueryTarget::Process, "cpu"))
 }
 
-fn matches_condition(process: &ProcessInfo, condition: &crate::parser::Condition) -> bool {
-    let field = condition.field.to_lowercase();
-
-    match field.as_str() {
-        "pid" => {
-            if let Value::Number(n) = &condition.value {
-                compare_numbers(process.pid as f64, *n, &condition.operator)
-            } else {
-                false
-            }
-        }
-        "name" => {
-            if let Value::String(s) = &condition.value {
-                compare_strings(&process.name, s, &condition.operator)
-            } else {
-                false
-            }
-        }
-        "cpu" => {
-            if let Value::Number(n) = &condition.value {
-                compare_numbers(process.cpu as f64, *n, &condition.operator)
-            } else {
-                false
-            }
-        }
-        "memory" => {
-            let target = match &condition.value {
-                Value::Number(n) => *n as u64,
-                Value::Size(s) => *s,
-                _ => return false,
-            };
-            compare_numbers(process.memory as f64, target as f64, &condition.operator)
-        }
-        _ => true, // Unknown field - don't filter
+/// Whether `field` is named in an explicit field list (`*` does not count)
+fn selects_field(fields: &FieldList, field: &str) -> bool {
+    match fields {
+        FieldList::All => false,
+        FieldList::Fields(names) => names.iter().any(|f| f.eq_ignore_ascii_case(field)),
     }
 }
 
-fn compare_numbers(left: f64, right: f64, op: &CompareOp) -> bool {
-    match op {
-        CompareOp::Equal => (left - right).abs() < f64::EPSILON,
-        CompareOp::NotEqual => (left - right).abs() >= f64::EPSILON,
-        CompareOp::GreaterThan => left > right,
-        CompareOp::GreaterThanOrEqual => left >= right,
-        CompareOp::LessThan => left < right,
-        CompareOp::LessThanOrEqual => left <= right,
-        _ => false,
-    }
+fn matches_where_clause(
+    process: &ProcessInfo,
+    where_clause: &WhereClause,
+    full_names: bool,
+) -> bool {
+    filter::matches_lenient(where_clause, |c| matches_condition(process, c, full_names))
 }
 
-fn compare_strings(left: &str, right: &str, op: &CompareOp) -> bool {
-    match op {
-        CompareOp::Equal => left == right,
-        CompareOp::NotEqual => left != right,
-        CompareOp::Like => {
-            let pattern = right.replace('%', ".*");
-            regex::Regex::new(&format!("^{}$", pattern))
-                .map(|r| r.is_match(left))
-                .unwrap_or(false)
-        }
-        CompareOp::Contains => left.contains(right),
-        _ => false,
+/// Test one condition against a process; `None` when PROCESS has no such field
+///
+/// Every field in [`known_fields`](crate::engine::fields::known_fields) is
+/// handled here, through its canonical name.
+pub(crate) fn matches_condition(
+    process: &ProcessInfo,
+    condition: &crate::parser::Condition,
+    full_names: bool,
+) -> Option<bool> {
+    let field = condition.field.to_lowercase();
+    let (base, measured) = match field.strip_suffix(filter::LENGTH_SUFFIX) {
+        Some(base) => (base, true),
+        None => (field.as_str(), false),
+    };
+    let canonical = canonical_field(QueryTarget::Process, base)?;
+    let name_full = || process.name_full.as_deref().unwrap_or(&process.name);
+
+    // Measure the untruncated name when full names are on
+    if measured {
+        let length = filter::length_condition(condition, |_| match canonical {
+            "name" => Some(
+                process
+                    .name_full
+                    .as_deref()
+                    .filter(|_| full_names)
+                    .unwrap_or(&process.name),
+            ),
+            "name_full" => Some(name_full()),
+            "status" => Some(&process.status),
+            "user" => process.user.as_deref(),
+            _ => None,
+        });
+        return length;
     }
+
+    let op = &condition.operator;
+    let number = |actual: f64| match &condition.value {
+        Value::Number(n) => compare_numbers(actual, *n, op),
+        _ => false,
+    };
+    Some(match canonical {
+        "pid" => number(process.pid as f64),
+        "name" => match &condition.value {
+            Value::String(s) => {
+                let full = process.name_full.as_deref().filter(|_| full_names);
+                match_process_name(&process.name, full, op, |name| compare_strings(name, s, op))
+            }
+            _ => false,
+        },
+        "name_full" => match &condition.value {
+            Value::String(s) => compare_strings(name_full(), s, op),
+            _ => false,
+        },
+        "status" => match &condition.value {
+            Value::String(s) | Value::Identifier(s) => {
+                compare_strings(&process.status, &s.to_lowercase(), op)
+            }
+            _ => false,
+        },
+        "user" => match (&condition.value, &process.user) {
+            (Value::String(s) | Value::Identifier(s), Some(user)) => compare_strings(user, s, op),
+            _ => false,
+        },
+        "cpu" => number(process.cpu as f64),
+        "memory" => match &condition.value {
+            Value::Number(n) => compare_numbers(process.memory as f64, *n, op),
+            Value::Size(s) => compare_numbers(process.memory as f64, *s as f64, op),
+            _ => false,
+        },
+        // Counts a platform does not report never match
+        "threads" => process.threads.is_some_and(|n| number(n as f64)),
+        "open_files" => process.open_files.is_some_and(|n| number(n as f64)),
+        _ => return None,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::engine::fields::known_fields;
 
     #[test]
     fn test_process_query() {
@@ -137,6 +362,303 @@ mod tests {
         assert!(!processes.is_empty());
     }
 
+    #[test]
+    fn test_cpu_free_query_skips_second_sample() {
+        use crate::engine::provider::RefreshStrategy;
+        use crate::parser::{parse_command, Command};
+
+        let query = |text: &str| match parse_command(text).unwrap() {
+            Command::Query(q) => q,
+            _ => panic!("Expected Query command"),
+        };
+
+        let q = query(r#"SELECT PROCESS pid, name WHERE memory > 0"#);
+        let mut provider = SysinfoProvider::new(RefreshStrategy::Selective);
+        let processes = query_processes_with(
+            &mut provider,
+            &q.fields,
+            q.where_clause.as_ref(),
+            true,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(!processes.is_empty());
+        assert_eq!(provider.stats().selective_refreshes, 1);
+
+        let q = query("SELECT PROCESS pid WHERE cpu >= 0");
+        let mut provider = SysinfoProvider::new(RefreshStrategy::Selective);
+        query_processes_with(
+            &mut provider,
+            &q.fields,
+            q.where_clause.as_ref(),
+            true,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(provider.stats().selective_refreshes, 2);
+
+        // A LIMIT without ORDER BY keeps the busiest processes, not the lowest PIDs
+        let q = query("SELECT PROCESS name LIMIT 5");
+        let mut provider = SysinfoProvider::new(RefreshStrategy::Selective);
+        query_processes_with(&mut provider, &q.fields, None, true, None, q.limit).unwrap();
+        assert_eq!(provider.stats().selective_refreshes, 2);
+
+        let q = query("SELECT PROCESS name ORDER BY pid LIMIT 5");
+        let mut provider = SysinfoProvider::new(RefreshStrategy::Selective);
+        query_processes_with(
+            &mut provider,
+            &q.fields,
+            None,
+            true,
+            q.order_by.as_ref(),
+            q.limit,
+        )
+        .unwrap();
+        assert_eq!(provider.stats().selective_refreshes, 1);
+    }
+
+    #[test]
+    fn test_full_process_name() {
+        let exe = Path::new("/opt/app/bin/my-long-service-worker");
+        assert_eq!(
+            full_process_name("my-long-service", Some(exe)),
+            Some("my-long-service-worker".to_string())
+        );
+        // Short names are never treated as truncated
+        assert_eq!(
+            full_process_name("node", Some(Path::new("/usr/bin/nodejs"))),
+            None
+        );
+        assert_eq!(full_process_name("my-long-service", None), None);
+        assert_eq!(
+            full_process_name("/usr/sbin/sshd", None),
+            Some("sshd".to_string())
+        );
+        assert_eq!(
+            full_process_name("(sd-pam)", Some(Path::new("/lib/systemd/systemd"))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_name_matches_full_name() {
+        let process = ProcessInfo {
+            pid: 7,
+            name: "my-long-service".to_string(),
+            name_full: Some("my-long-service-worker".to_string()),
+            cpu: 0.0,
+            memory: 0,
+            status: "running".to_string(),
+            threads: None,
+            open_files: None,
+            user: None,
+            uid: None,
+        };
+        let condition = |op, value: &str| crate::parser::Condition {
+            field: "name".to_string(),
+            operator: op,
+            value: Value::String(value.to_string()),
+        };
+
+        let full = condition(CompareOp::Equal, "my-long-service-worker");
+        assert_eq!(matches_condition(&process, &full, true), Some(true));
+        assert_eq!(matches_condition(&process, &full, false), Some(false));
+        assert_eq!(
+            matches_condition(
+                &process,
+                &condition(CompareOp::Equal, "my-long-service"),
+                true
+            ),
+            Some(true)
+        );
+        assert_eq!(
+            matches_condition(
+                &process,
+                &condition(CompareOp::NotEqual, "my-long-service-worker"),
+                true
+            ),
+            Some(false)
+        );
+        assert_eq!(
+            matches_condition(&process, &condition(CompareOp::NotEqual, "other"), true),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_filter_by_status() {
+        let process = |pid, status: ProcessStatus| ProcessInfo {
+            pid,
+            name: format!("proc{}", pid),
+            name_full: None,
+            cpu: 0.0,
+            memory: 0,
+            status: process_status_name(status).to_string(),
+            threads: None,
+            open_files: None,
+            user: None,
+            uid: None,
+        };
+        let processes = [
+            process(1, ProcessStatus::Run),
+            process(2, ProcessStatus::Zombie),
+            process(3, ProcessStatus::Stop),
+            process(4, ProcessStatus::Sleep),
+        ];
+        let pids = |query: &str| {
+            let where_clause = match crate::parser::parse_command(query).unwrap() {
+                crate::parser::Command::Query(q) => q.where_clause.unwrap(),
+                _ => panic!("Expected Query command"),
+            };
+            processes
+                .iter()
+                .filter(|p| matches_where_clause(p, &where_clause, true))
+                .map(|p| p.pid)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(pids(r#"SELECT PROCESS * WHERE status = "zombie""#), [2]);
+        assert_eq!(pids(r#"SELECT PROCESS * WHERE status = "Stopped""#), [3]);
+        assert_eq!(
+            pids(r#"SELECT PROCESS * WHERE status != "sleeping""#),
+            [1, 2, 3]
+        );
+        assert_eq!(processes[0].status, "running");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_current_process_reports_threads() {
+        let pid = std::process::id();
+        let processes = query_processes(&FieldList::All, None).unwrap();
+        let current = processes.iter().find(|p| p.pid == pid).unwrap();
+        assert!(current.threads.is_some_and(|n| n >= 1));
+
+        let query = format!("SELECT PROCESS * WHERE pid = {} AND threads >= 1", pid);
+        let where_clause = match crate::parser::parse_command(&query).unwrap() {
+            crate::parser::Command::Query(q) => q.where_clause.unwrap(),
+            _ => panic!("Expected Query command"),
+        };
+        assert!(matches_where_clause(current, &where_clause, true));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_current_process_reports_open_files() {
+        let pid = std::process::id();
+        let query = format!("SELECT PROCESS * WHERE pid = {} AND open_files >= 3", pid);
+        let where_clause = match crate::parser::parse_command(&query).unwrap() {
+            crate::parser::Command::Query(q) => q.where_clause.unwrap(),
+            _ => panic!("Expected Query command"),
+        };
+        let processes = query_processes(&FieldList::All, Some(&where_clause)).unwrap();
+
+        // stdin, stdout and stderr at least, well short of any fd limit
+        assert_eq!(processes.len(), 1);
+        assert!(processes[0]
+            .open_files
+            .is_some_and(|n| (3..100_000).contains(&n)));
+
+        // Not counted for `*`
+        let processes = query_processes(&FieldList::All, None).unwrap();
+        let current = processes.iter().find(|p| p.pid == pid).unwrap();
+        assert_eq!(current.open_files, None);
+    }
+
+    #[test]
+    fn test_matches_condition_handles_every_field() {
+        let process = ProcessInfo {
+            pid: 1,
+            name: "init".to_string(),
+            name_full: None,
+            cpu: 0.0,
+            memory: 0,
+            status: "running".to_string(),
+            threads: None,
+            open_files: None,
+            user: None,
+            uid: None,
+        };
+        for field in known_fields(QueryTarget::Process) {
+            let condition = crate::parser::Condition {
+                field: field.to_string(),
+                operator: CompareOp::Equal,
+                value: Value::Number(0.0),
+            };
+            assert!(
+                matches_condition(&process, &condition, false).is_some(),
+                "{}",
+                field
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_field_strictness() {
+        let process = ProcessInfo {
+            pid: 1,
+            name: "worker".to_string(),
+            name_full: None,
+            cpu: 0.0,
+            memory: 0,
+            status: "running".to_string(),
+            threads: None,
+            open_files: None,
+            user: None,
+            uid: None,
+        };
+        let where_clause =
+            match crate::parser::parse_command("SELECT PROCESS * WHERE cpy > 10").unwrap() {
+                crate::parser::Command::Query(q) => q.where_clause.unwrap(),
+                _ => panic!("Expected Query command"),
+            };
+
+        // Without strict fields the typo is ignored and everything matches
+        assert!(matches_where_clause(&process, &where_clause, true));
+
+        let err =
+            filter::check_fields(&where_clause, "PROCESS", known_fields(QueryTarget::Process))
+                .unwrap_err();
+        assert!(err.to_string().contains("cpy (did you mean cpu?)"));
+    }
+
+    #[test]
+    fn test_filter_by_threads() {
+        let process = |threads| ProcessInfo {
+            pid: 1,
+            name: "worker".to_string(),
+            name_full: None,
+            cpu: 0.0,
+            memory: 0,
+            status: "running".to_string(),
+            threads,
+            open_files: None,
+            user: None,
+            uid: None,
+        };
+        let condition = crate::parser::Condition {
+            field: "threads".to_string(),
+            operator: CompareOp::GreaterThan,
+            value: Value::Number(100.0),
+        };
+
+        assert_eq!(
+            matches_condition(&process(Some(250)), &condition, true),
+            Some(true)
+        );
+        assert_eq!(
+            matches_condition(&process(Some(8)), &condition, true),
+            Some(false)
+        );
+        // An unknown thread count never matches
+        assert_eq!(
+            matches_condition(&process(None), &condition, true),
+            Some(false)
+        );
+    }
+
     #[test]
     fn test_compare_numbers() {
         assert!(compare_numbers(10.0, 5.0, &CompareOp::GreaterThan));
@@ -152,5 +674,13 @@ mod tests {
             "world",
             &CompareOp::Contains
         ));
+        // Same case rules as DELETE and KILL
+        assert!(compare_strings("Hello", "hello", &CompareOp::Equal));
+        assert!(compare_strings(
+            "Hello World",
+            "WORLD",
+            &CompareOp::Contains
+        ));
+        assert!(compare_strings("Hello", "h%", &CompareOp::Like));
     }
 }
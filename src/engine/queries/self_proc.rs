@@ -0,0 +1,54 @@
+//! SELF query implementation (Arta's own process)
+
+use crate::engine::provider::{SysinfoProvider, SystemProvider};
+use crate::error::{ArtaError, Result};
+use crate::parser::FieldList;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfInfo {
+    pub pid: u32,
+    pub name: String,
+    pub cpu: f32,
+    pub memory: u64,
+    pub virtual_memory: u64,
+    /// Seconds since the process started
+    pub uptime: u64,
+}
+
+pub fn query_self(fields: &FieldList) -> Result<SelfInfo> {
+    query_self_with(&mut SysinfoProvider::default(), fields)
+}
+
+/// Query the current process using a (possibly shared) system provider
+pub fn query_self_with(provider: &mut dyn SystemProvider, _fields: &FieldList) -> Result<SelfInfo> {
+    let pid = sysinfo::get_current_pid().map_err(|e| {
+        ArtaError::ExecutionError(format!("Cannot determine current process id: {}", e))
+    })?;
+
+    let sys = provider.processes();
+    let process = sys
+        .process(pid)
+        .ok_or_else(|| ArtaError::ExecutionError(format!("Current process {} not found", pid)))?;
+
+    Ok(SelfInfo {
+        pid: pid.as_u32(),
+        name: process.name().to_string(),
+        cpu: process.cpu_usage(),
+        memory: process.memory(),
+        virtual_memory: process.virtual_memory(),
+        uptime: process.run_time(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_query_reports_current_pid() {
+        let info = query_self(&FieldList::All).unwrap();
+        assert_eq!(info.pid, std::process::id());
+        assert!(info.memory > 0);
+    }
+}
@@ -0,0 +1,247 @@
+//! Service query implementation
+//!
+//! Reads systemd unit state through `systemctl`. Only available on Linux
+//! builds with the `services` feature; elsewhere the query fails with a
+//! platform error.
+
+use crate::engine::filter::{self, compare_numbers, compare_strings};
+use crate::error::{ArtaError, Result};
+use crate::parser::{Condition, FieldList, Value, WhereClause};
+use serde::{Deserialize, Serialize};
+
+/// Properties requested from `systemctl show`, in output order
+#[cfg(all(feature = "services", target_os = "linux"))]
+const SHOW_PROPERTIES: &str = "Id,ActiveState,SubState,UnitFileState,MainPID,Description";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceInfo {
+    /// Unit name, e.g. `nginx.service`
+    pub name: String,
+    /// High-level state: active, inactive, failed, ...
+    pub active_state: String,
+    /// Low-level state: running, exited, dead, ...
+    pub sub_state: String,
+    /// Unit file state: enabled, disabled, static, ...
+    pub enabled: String,
+    /// Main process, if the service is running
+    pub pid: Option<u32>,
+    pub description: String,
+}
+
+/// Query systemd services, optionally filtered by a WHERE clause
+pub fn query_services(
+    _fields: &FieldList,
+    where_clause: Option<&WhereClause>,
+) -> Result<Vec<ServiceInfo>> {
+    if let Some(wc) = where_clause {
+        filter::check_patterns(wc)?;
+    }
+
+    let mut services = list_services()?;
+    if let Some(wc) = where_clause {
+        services.retain(|s| filter::matches(wc, |c| matches_condition(s, c)));
+    }
+    Ok(services)
+}
+
+#[cfg(all(feature = "services", target_os = "linux"))]
+fn list_services() -> Result<Vec<ServiceInfo>> {
+    // Same check as sd_booted(): no systemd means no services, not an error
+    if !std::path::Path::new("/run/systemd/system").exists() {
+        return Ok(Vec::new());
+    }
+
+    let units = systemctl(&[
+        "list-units",
+        "--type=service",
+        "--all",
+        "--plain",
+        "--no-legend",
+        "--no-pager",
+    ])?;
+    let names = parse_unit_list(&units);
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut args = vec!["show", "--no-pager", "-p", SHOW_PROPERTIES];
+    args.extend(names.iter().map(String::as_str));
+    Ok(parse_show_output(&systemctl(&args)?))
+}
+
+#[cfg(all(feature = "services", target_os = "linux"))]
+fn systemctl(args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("systemctl")
+        .args(args)
+        .output()
+        .map_err(|e| ArtaError::ExecutionError(format!("Failed to run systemctl: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(ArtaError::ExecutionError(format!(
+            "systemctl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(all(not(feature = "services"), target_os = "linux"))]
+fn list_services() -> Result<Vec<ServiceInfo>> {
+    Err(ArtaError::ExecutionError(
+        "SERVICES support not enabled. Rebuild with --features services".to_string(),
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn list_services() -> Result<Vec<ServiceInfo>> {
+    Err(ArtaError::ExecutionError(
+        "SERVICES queries are unsupported on this platform (Linux only)".to_string(),
+    ))
+}
+
+/// Extract unit names from `systemctl list-units --plain --no-legend`
+pub fn parse_unit_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|name| name.ends_with(".service"))
+        .map(String::from)
+        .collect()
+}
+
+/// Parse `systemctl show` output, one blank-line separated block per unit
+pub fn parse_show_output(output: &str) -> Vec<ServiceInfo> {
+    let mut services = Vec::new();
+    let mut current = ServiceInfo::default();
+
+    for line in output.lines().chain(std::iter::once("")) {
+        let Some((key, value)) = line.split_once('=') else {
+            if !current.name.is_empty() {
+                services.push(std::mem::take(&mut current));
+            }
+            continue;
+        };
+        match key {
+            "Id" => current.name = value.to_string(),
+            "ActiveState" => current.active_state = value.to_string(),
+            "SubState" => current.sub_state = value.to_string(),
+            "UnitFileState" => current.enabled = value.to_string(),
+            // systemd reports 0 when there is no main process
+            "MainPID" => current.pid = value.parse().ok().filter(|pid| *pid != 0),
+            "Description" => current.description = value.to_string(),
+            _ => {}
+        }
+    }
+
+    services
+}
+
+fn matches_condition(service: &ServiceInfo, condition: &Condition) -> bool {
+    let length = filter::length_condition(condition, |field| match field {
+        "name" => Some(&service.name),
+        "state" | "active_state" | "active" => Some(&service.active_state),
+        "sub_state" => Some(&service.sub_state),
+        "enabled" => Some(&service.enabled),
+        "description" => Some(&service.description),
+        _ => None,
+    });
+    if let Some(matched) = length {
+        return matched;
+    }
+
+    let text = match condition.field.to_lowercase().as_str() {
+        "name" => &service.name,
+        "state" | "active_state" | "active" => &service.active_state,
+        "sub_state" => &service.sub_state,
+        "enabled" => &service.enabled,
+        "description" => &service.description,
+        "pid" => {
+            return match (&condition.value, service.pid) {
+                (Value::Number(n), Some(pid)) => {
+                    compare_numbers(pid as f64, *n, &condition.operator)
+                }
+                _ => false,
+            };
+        }
+        _ => return true, // Unknown field - don't filter
+    };
+
+    match &condition.value {
+        Value::String(s) | Value::Identifier(s) => {
+            // `name = "nginx"` also matches `nginx.service`
+            compare_strings(text, s, &condition.operator)
+                || (condition.field.eq_ignore_ascii_case("name")
+                    && text
+                        .strip_suffix(".service")
+                        .is_some_and(|short| compare_strings(short, s, &condition.operator)))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_command, Command};
+
+    fn where_of(query: &str) -> WhereClause {
+        match parse_command(query).unwrap() {
+            Command::Query(q) => q.where_clause.unwrap(),
+            _ => panic!("Expected Query command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_unit_list() {
+        let output = "nginx.service loaded active running A high performance web server\n\
+                      cron.service  loaded active running Regular background program\n\
+                      dev-sda.device loaded active plugged Disk\n";
+        assert_eq!(
+            parse_unit_list(output),
+            vec!["nginx.service", "cron.service"]
+        );
+    }
+
+    #[test]
+    fn test_parse_show_output() {
+        let output = "Id=nginx.service\nActiveState=active\nSubState=running\n\
+                      UnitFileState=enabled\nMainPID=812\nDescription=Web server\n\n\
+                      Id=backup.service\nActiveState=inactive\nSubState=dead\n\
+                      UnitFileState=disabled\nMainPID=0\nDescription=Nightly backup\n";
+        let services = parse_show_output(output);
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].name, "nginx.service");
+        assert_eq!(services[0].enabled, "enabled");
+        assert_eq!(services[0].pid, Some(812));
+        assert_eq!(services[1].active_state, "inactive");
+        assert_eq!(services[1].pid, None);
+    }
+
+    #[test]
+    fn test_where_name_matches_without_suffix() {
+        let nginx = ServiceInfo {
+            name: "nginx.service".to_string(),
+            active_state: "active".to_string(),
+            pid: Some(812),
+            ..Default::default()
+        };
+        let wc = where_of(r#"SELECT SERVICE * WHERE name = "nginx""#);
+        assert!(filter::matches(&wc, |c| matches_condition(&nginx, c)));
+
+        let wc = where_of(r#"SELECT SERVICES * WHERE state = "failed""#);
+        assert!(!filter::matches(&wc, |c| matches_condition(&nginx, c)));
+
+        let wc = where_of("SELECT SERVICES * WHERE pid > 100");
+        assert!(filter::matches(&wc, |c| matches_condition(&nginx, c)));
+    }
+
+    #[test]
+    fn test_query_services_platform() {
+        let result = query_services(&FieldList::All, None);
+        if cfg!(all(feature = "services", target_os = "linux")) {
+            assert!(result.is_ok());
+        } else {
+            assert!(result.is_err());
+        }
+    }
+}
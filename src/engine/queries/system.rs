@@ -1,6 +1,7 @@
 //! System query implementation
 
 use crate::error::Result;
+use crate::output::format_util::fmt_duration;
 use crate::parser::FieldList;
 use serde::{Deserialize, Serialize};
 use sysinfo::System;
@@ -11,16 +12,22 @@ pub struct SystemInfo {
     pub os_name: String,
     pub os_version: String,
     pub kernel_version: String,
+    /// Seconds since boot
     pub uptime: u64,
+    /// `uptime` as days, hours and minutes, e.g. `5d 3h 12m`
+    #[serde(default)]
+    pub uptime_human: String,
 }
 
 pub fn query_system(_fields: &FieldList) -> Result<SystemInfo> {
+    let uptime = System::uptime();
     Ok(SystemInfo {
         hostname: System::host_name().unwrap_or_else(|| "Unknown".to_string()),
         os_name: System::name().unwrap_or_else(|| "Unknown".to_string()),
         os_version: System::os_version().unwrap_or_else(|| "Unknown".to_string()),
         kernel_version: System::kernel_version().unwrap_or_else(|| "Unknown".to_string()),
-        uptime: System::uptime(),
+        uptime,
+        uptime_human: fmt_duration(uptime),
     })
 }
 
@@ -32,5 +39,6 @@ mod tests {
     fn test_system_query() {
         let info = query_system(&FieldList::All).unwrap();
         assert!(!info.hostname.is_empty() || info.hostname == "Unknown");
+        assert_eq!(info.uptime_human, fmt_duration(info.uptime));
     }
 }
@@ -0,0 +1,72 @@
+//! Custom query targets registered by library users
+//!
+//! Embedders can add their own metrics (a GPU, an app-specific counter, ...)
+//! without touching the grammar: any `SELECT <name> ...` whose target is not
+//! built in is looked up here.
+//!
+//! ```
+//! use arta::{execute_command, parse_command, ExecutionContext};
+//!
+//! let ctx = ExecutionContext::default()
+//!     .with_target("gpu", || Ok(serde_json::json!({ "usage": 42 })));
+//! let cmd = parse_command("SELECT GPU *").unwrap();
+//! assert!(execute_command(&cmd, &ctx).is_ok());
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::Result;
+
+/// Function producing the current value of a custom target
+pub type TargetFn = Arc<dyn Fn() -> Result<serde_json::Value> + Send + Sync>;
+
+/// Custom query targets, keyed by case-insensitive name
+#[derive(Clone, Default)]
+pub struct TargetRegistry {
+    targets: HashMap<String, TargetFn>,
+}
+
+impl TargetRegistry {
+    /// Register (or replace) a custom target
+    pub fn register<F>(&mut self, name: &str, query: F)
+    where
+        F: Fn() -> Result<serde_json::Value> + Send + Sync + 'static,
+    {
+        self.targets.insert(name.to_uppercase(), Arc::new(query));
+    }
+
+    /// Look up a custom target by name
+    pub fn get(&self, name: &str) -> Option<&TargetFn> {
+        self.targets.get(&name.to_uppercase())
+    }
+
+    /// Registered target names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.targets.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl std::fmt::Debug for TargetRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_is_case_insensitive() {
+        let mut registry = TargetRegistry::default();
+        registry.register("Gpu", || Ok(serde_json::json!(1)));
+
+        assert!(registry.get("GPU").is_some());
+        assert!(registry.get("gpu").is_some());
+        assert!(registry.get("cpu").is_none());
+        assert_eq!(registry.names(), vec!["GPU"]);
+    }
+}
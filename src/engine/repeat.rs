@@ -0,0 +1,149 @@
+//! Repeated execution for `arta query --repeat` and `EVERY` queries
+
+use std::time::Duration;
+
+use crate::engine::cancel::CancellationToken;
+use crate::error::Result;
+
+/// Granularity of the sleep between runs, so Ctrl+C is noticed quickly
+const SLEEP_SLICE: Duration = Duration::from_millis(100);
+
+/// How many times to run a query and how long to wait between runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RepeatPlan {
+    pub count: u32,
+    pub delay: Duration,
+}
+
+impl RepeatPlan {
+    pub fn new(count: u32, delay: Duration) -> Self {
+        Self { count, delay }
+    }
+
+    /// Whether iteration `done + 1` should run after `done` completed runs
+    pub fn should_run(&self, done: u32, running: bool) -> bool {
+        running && done < self.count
+    }
+
+    /// Time to wait after the `done`-th run, or `None` after the last one
+    pub fn delay_after(&self, done: u32) -> Option<Duration> {
+        (done < self.count && !self.delay.is_zero()).then_some(self.delay)
+    }
+}
+
+/// Run `step` according to `plan`, stopping early once `cancel` is set
+///
+/// `step` receives the 1-based iteration number. `sleep` is called with
+/// short slices of the delay so an interrupt takes effect promptly.
+/// Returns the number of completed runs.
+pub fn run_repeated<S, W>(
+    plan: &RepeatPlan,
+    cancel: &CancellationToken,
+    mut step: S,
+    mut sleep: W,
+) -> Result<u32>
+where
+    S: FnMut(u32) -> Result<()>,
+    W: FnMut(Duration),
+{
+    let mut done = 0;
+
+    while plan.should_run(done, !cancel.is_cancelled()) {
+        step(done + 1)?;
+        done += 1;
+
+        if let Some(mut remaining) = plan.delay_after(done) {
+            while !remaining.is_zero() && !cancel.is_cancelled() {
+                let slice = remaining.min(SLEEP_SLICE);
+                sleep(slice);
+                remaining -= slice;
+            }
+        }
+    }
+
+    Ok(done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_decisions() {
+        let plan = RepeatPlan::new(3, Duration::from_secs(2));
+        assert!(plan.should_run(0, true));
+        assert!(plan.should_run(2, true));
+        assert!(!plan.should_run(3, true));
+        assert!(!plan.should_run(0, false));
+
+        assert_eq!(plan.delay_after(1), Some(Duration::from_secs(2)));
+        assert_eq!(plan.delay_after(3), None);
+        assert_eq!(RepeatPlan::new(3, Duration::ZERO).delay_after(1), None);
+    }
+
+    #[test]
+    fn test_run_repeated_counts_and_sleeps() {
+        let plan = RepeatPlan::new(3, Duration::from_millis(250));
+        let cancel = CancellationToken::new();
+        let mut iterations = Vec::new();
+        let mut slept = Duration::ZERO;
+
+        let done = run_repeated(
+            &plan,
+            &cancel,
+            |i| {
+                iterations.push(i);
+                Ok(())
+            },
+            |d| slept += d,
+        )
+        .unwrap();
+
+        assert_eq!(done, 3);
+        assert_eq!(iterations, vec![1, 2, 3]);
+        // Two gaps between three runs, none after the last
+        assert_eq!(slept, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_run_repeated_stops_when_interrupted() {
+        let plan = RepeatPlan::new(10, Duration::from_secs(1));
+        let cancel = CancellationToken::new();
+        let mut slices = 0;
+
+        let done = run_repeated(
+            &plan,
+            &cancel,
+            |_| Ok(()),
+            |_| {
+                slices += 1;
+                if slices == 3 {
+                    cancel.cancel();
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(done, 1);
+        assert_eq!(slices, 3);
+    }
+
+    #[test]
+    fn test_run_repeated_propagates_errors() {
+        let plan = RepeatPlan::new(5, Duration::ZERO);
+        let cancel = CancellationToken::new();
+        let result = run_repeated(
+            &plan,
+            &cancel,
+            |i| {
+                if i == 2 {
+                    Err(crate::error::ArtaError::ExecutionError("boom".to_string()))
+                } else {
+                    Ok(())
+                }
+            },
+            |_| {},
+        );
+        assert!(result.is_err());
+    }
+}
@@ -33,3 +33,66 @@ pub enum ArtaError {
 }
 
 pub type Result<T> = std::result::Result<T, ArtaError>;
+
+/// Closest candidate to a mistyped name, compared case-insensitively
+///
+/// Only candidates within roughly a third of the input's length in edits are
+/// considered, so unrelated names are not suggested.
+pub fn suggest<'a>(input: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let input = input.to_lowercase();
+    let max_distance = input.chars().count().div_ceil(3).max(1);
+
+    candidates
+        .iter()
+        .map(|c| (edit_distance(&input, &c.to_lowercase()), *c))
+        .filter(|(distance, _)| *distance > 0 && *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// " (did you mean X?)" for the closest candidate, or an empty string
+pub fn did_you_mean(input: &str, candidates: &[&str]) -> String {
+    suggest(input, candidates)
+        .map(|s| format!(" (did you mean {}?)", s))
+        .unwrap_or_default()
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "cpu"), 3);
+        assert_eq!(edit_distance("usage", "usage"), 0);
+    }
+
+    #[test]
+    fn test_suggest_closest_name() {
+        let targets = ["CPU", "MEMORY", "DISK"];
+        assert_eq!(suggest("MEMROY", &targets), Some("MEMORY"));
+        assert_eq!(suggest("dsk", &targets), Some("DISK"));
+        assert_eq!(suggest("battery", &targets), None);
+        assert_eq!(did_you_mean("cpus", &targets), " (did you mean CPU?)");
+        assert_eq!(did_you_mean("zzz", &targets), "");
+    }
+}
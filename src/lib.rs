@@ -22,16 +22,28 @@ pub mod error;
 pub mod life;
 pub mod output;
 pub mod parser;
+#[cfg(feature = "ssh")]
+pub mod remote;
 pub mod script;
 pub mod security;
 
 #[cfg(feature = "repl")]
 pub mod repl;
 
+#[cfg(any(test, feature = "testing"))]
+pub mod test_support;
+
 pub use container::{Container, ContainerManager};
 pub use context::Context;
-pub use engine::{execute_command, execute_command_with_context, ExecutionContext};
+pub use engine::{
+    execute_command, execute_command_with_context, CancellationToken, ExecutionContext,
+};
 pub use error::{ArtaError, Result};
-pub use output::{format_output, OutputFormat};
-pub use parser::{parse_command, parse_script, Command, Script};
-pub use script::{validate_script, ScriptResult, ScriptRunner};
+pub use output::{format_output, FormatterRegistry, OutputFormat, OutputSink};
+pub use parser::{
+    parse_command, parse_command_with_options, parse_script, parse_script_with_options, Command,
+    ParseOptions, Script,
+};
+pub use script::{
+    validate_script, validate_script_report, ScriptResult, ScriptRunner, ValidationReport,
+};
@@ -2,16 +2,28 @@
 //!
 //! Provides continuous monitoring of system resources with reactive updates.
 
+use std::collections::VecDeque;
+use std::io::{IsTerminal, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::context::Context;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Stylize;
+use crossterm::terminal;
+
+use crate::cli::duration::MIN_INTERVAL;
+use crate::context::{Context, VariableValue};
+use crate::engine::filter::percent_share;
+use crate::engine::queries::memory::PressureThresholds;
 use crate::engine::queries::*;
+use crate::engine::CancellationToken;
+use crate::engine::SystemHandle;
 use crate::engine::{execute_command_with_context, ExecutionContext, ResultData};
 use crate::error::{ArtaError, Result};
-use crate::output::{format_output, OutputFormat};
-use crate::parser::{Command, LifeTarget};
+use crate::output::format_util::{fmt_percent, fmt_size};
+use crate::output::{DisplayZone, OutputFormat, OutputSink};
+use crate::parser::{LifeMonitor, LifeTarget};
 
 /// State for tracking changes in monitored resources
 #[derive(Debug, Clone)]
@@ -66,6 +78,143 @@ impl MonitorState {
             _ => true, // Different types always considered changed
         }
     }
+
+    /// Expose the state to a LIFE body as `life_*` variables
+    fn set_variables(&self, context: &mut Context) {
+        let vars = match *self {
+            MonitorState::Battery {
+                percentage,
+                charging,
+            } => vec![
+                ("life_percentage", VariableValue::Number(percentage as f64)),
+                ("life_charging", VariableValue::Boolean(charging)),
+            ],
+            MonitorState::Memory { used, total } | MonitorState::Disk { used, total } => vec![
+                ("life_used", VariableValue::Size(used)),
+                ("life_total", VariableValue::Size(total)),
+            ],
+            MonitorState::Cpu { usage } => {
+                vec![("life_usage", VariableValue::Number(usage as f64))]
+            }
+            MonitorState::Network {
+                bytes_sent,
+                bytes_recv,
+            } => vec![
+                ("life_sent", VariableValue::Size(bytes_sent)),
+                ("life_received", VariableValue::Size(bytes_recv)),
+            ],
+            MonitorState::Processes { count } => {
+                vec![("life_count", VariableValue::Number(count as f64))]
+            }
+        };
+        for (name, value) in vars {
+            context.set_variable(name.to_string(), value);
+        }
+    }
+}
+
+/// Fixed-size buffer of samples that yields their average once per window
+///
+/// Numeric readings are averaged. Cumulative network counters and the
+/// battery's charging flag are not, and take the latest sample instead.
+#[derive(Debug, Clone)]
+pub struct WindowAverage {
+    samples: VecDeque<MonitorState>,
+    capacity: usize,
+}
+
+impl WindowAverage {
+    /// Average over `capacity` samples (at least one)
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Add a sample, returning the window's average when it is full
+    pub fn push(&mut self, state: MonitorState) -> Option<MonitorState> {
+        self.samples.push_back(state);
+        if self.samples.len() < self.capacity {
+            return None;
+        }
+        let average = self.average();
+        self.samples.clear();
+        average
+    }
+
+    fn average(&self) -> Option<MonitorState> {
+        let latest = self.samples.back()?.clone();
+        let n = self.samples.len() as f64;
+        let mean = |value: fn(&MonitorState) -> Option<f64>| {
+            self.samples.iter().filter_map(value).sum::<f64>() / n
+        };
+
+        Some(match latest {
+            MonitorState::Battery { charging, .. } => MonitorState::Battery {
+                percentage: mean(|s| match s {
+                    MonitorState::Battery { percentage, .. } => Some(*percentage as f64),
+                    _ => None,
+                }) as f32,
+                charging,
+            },
+            MonitorState::Memory { total, .. } => MonitorState::Memory {
+                used: mean(|s| match s {
+                    MonitorState::Memory { used, .. } => Some(*used as f64),
+                    _ => None,
+                })
+                .round() as u64,
+                total,
+            },
+            MonitorState::Cpu { .. } => MonitorState::Cpu {
+                usage: mean(|s| match s {
+                    MonitorState::Cpu { usage } => Some(*usage as f64),
+                    _ => None,
+                }) as f32,
+            },
+            MonitorState::Disk { total, .. } => MonitorState::Disk {
+                used: mean(|s| match s {
+                    MonitorState::Disk { used, .. } => Some(*used as f64),
+                    _ => None,
+                })
+                .round() as u64,
+                total,
+            },
+            MonitorState::Processes { .. } => MonitorState::Processes {
+                count: mean(|s| match s {
+                    MonitorState::Processes { count } => Some(*count as f64),
+                    _ => None,
+                })
+                .round() as usize,
+            },
+            network @ MonitorState::Network { .. } => network,
+        })
+    }
+}
+
+/// Stops a monitor once nothing has changed for a whole window
+#[derive(Debug, Clone, Copy)]
+pub struct IdleTimeout {
+    window: Duration,
+    last_change: Instant,
+}
+
+impl IdleTimeout {
+    pub fn new(window: Duration, now: Instant) -> Self {
+        Self {
+            window,
+            last_change: now,
+        }
+    }
+
+    /// Record a sample, returning true once the monitor has been idle for the window
+    pub fn expired(&mut self, changed: bool, now: Instant) -> bool {
+        if changed {
+            self.last_change = now;
+        }
+        now.saturating_duration_since(self.last_change) >= self.window
+    }
 }
 
 /// Live monitor that continuously watches system resources
@@ -73,18 +222,26 @@ pub struct LiveMonitor {
     target: LifeTarget,
     interval: Duration,
     running: Arc<AtomicBool>,
+    system: SystemHandle,
 }
 
 impl LiveMonitor {
     /// Create a new live monitor
-    pub fn new(target: LifeTarget, interval_secs: u64, _exec_ctx: ExecutionContext) -> Self {
+    pub fn new(target: LifeTarget, interval: Duration, _exec_ctx: ExecutionContext) -> Self {
         Self {
             target,
-            interval: Duration::from_secs(interval_secs),
+            interval,
             running: Arc::new(AtomicBool::new(false)),
+            system: SystemHandle::default(),
         }
     }
 
+    /// Sample through a shared provider, such as a script context's
+    pub fn with_system(mut self, system: SystemHandle) -> Self {
+        self.system = system;
+        self
+    }
+
     /// Start monitoring with a callback for each update
     pub fn start<F>(&self, mut on_update: F) -> Result<()>
     where
@@ -137,27 +294,29 @@ impl LiveMonitor {
                 }
             }
             LifeTarget::Memory => {
-                let info = query_memory(&crate::parser::FieldList::All)?;
+                let info = query_memory_with(
+                    self.system.lock().as_mut(),
+                    &crate::parser::FieldList::All,
+                    &PressureThresholds::default(),
+                )?;
                 Ok(MonitorState::Memory {
                     used: info.used,
                     total: info.total,
                 })
             }
             LifeTarget::Cpu => {
-                let info = query_cpu(&crate::parser::FieldList::All)?;
+                let info =
+                    query_cpu_with(self.system.lock().as_mut(), &crate::parser::FieldList::All)?;
                 Ok(MonitorState::Cpu { usage: info.usage })
             }
             LifeTarget::Disk => {
-                let info = query_disk(&crate::parser::FieldList::All, None)?;
-                let (used, total) = info
-                    .disks
-                    .first()
-                    .map(|d| (d.used, d.total))
-                    .unwrap_or((0, 0));
+                // The root mount, which may be on overlay in a container
+                let info = query_disk(&crate::parser::FieldList::All, None, true)?;
+                let (used, total) = info.primary().map(|d| (d.used, d.total)).unwrap_or((0, 0));
                 Ok(MonitorState::Disk { used, total })
             }
             LifeTarget::Network => {
-                let info = query_network(&crate::parser::FieldList::All)?;
+                let info = query_network(&crate::parser::FieldList::All, None, true)?;
                 let (sent, recv) = info.interfaces.iter().fold((0, 0), |(s, r), iface| {
                     (s + iface.transmitted, r + iface.received)
                 });
@@ -167,7 +326,16 @@ impl LiveMonitor {
                 })
             }
             LifeTarget::Processes => {
-                let procs = query_processes(&crate::parser::FieldList::All, None)?;
+                // Only the count matters, so skip the CPU sample
+                let fields = crate::parser::FieldList::Fields(vec!["pid".to_string()]);
+                let procs = query_processes_with(
+                    self.system.lock().as_mut(),
+                    &fields,
+                    None,
+                    true,
+                    None,
+                    None,
+                )?;
                 Ok(MonitorState::Processes { count: procs.len() })
             }
         }
@@ -179,71 +347,128 @@ impl LiveMonitor {
     }
 }
 
+/// Marks a LIFE block as running, so a nested one fails instead of blocking
+///
+/// Only one LIFE block can run at a time: the body of a running block is
+/// re-executed on every change, so a LIFE inside it would never return.
+struct ActiveLife;
+
+static LIFE_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+impl ActiveLife {
+    fn enter() -> Result<Self> {
+        LIFE_ACTIVE
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .map(|_| ActiveLife)
+            .map_err(|_| {
+                ArtaError::ExecutionError(
+                    "LIFE blocks cannot be nested; another LIFE monitor is already running"
+                        .to_string(),
+                )
+            })
+    }
+}
+
+impl Drop for ActiveLife {
+    fn drop(&mut self) {
+        LIFE_ACTIVE.store(false, Ordering::SeqCst);
+    }
+}
+
 /// Run a LIFE monitoring block from a script
+///
+/// The body runs when the state changes or, with AVERAGE OVER, once per
+/// window with the averaged state. Either way the state is available to the
+/// body as `life_*` variables.
 pub fn run_life_block(
-    target: LifeTarget,
-    body: &[Command],
+    life: &LifeMonitor,
     exec_ctx: &ExecutionContext,
     context: &mut Context,
-    interval_secs: u64,
 ) -> Result<()> {
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
-
-    // Set up Ctrl+C handler
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::SeqCst);
-    })
-    .map_err(|e| ArtaError::ExecutionError(format!("Failed to set Ctrl+C handler: {}", e)))?;
+    let _active = ActiveLife::enter()?;
+    let cancel = match &exec_ctx.cancellation {
+        Some(token) => token.clone(),
+        None => CancellationToken::ctrl_c()?,
+    };
 
+    let interval_secs = life.interval_secs.unwrap_or(1);
     let interval = Duration::from_secs(interval_secs);
     let mut last_state: Option<MonitorState> = None;
+    let mut window = life
+        .average_secs
+        .map(|secs| WindowAverage::new(secs.div_ceil(interval_secs) as usize));
 
-    println!(
+    let mut sink = OutputSink::for_context(exec_ctx);
+    sink.write_line(&format!(
         "Starting LIFE monitor for {}... (Press Ctrl+C to stop)",
-        target
-    );
+        life.target
+    ))?;
 
-    let monitor = LiveMonitor::new(target, interval_secs, exec_ctx.clone());
+    let monitor = LiveMonitor::new(life.target, interval, exec_ctx.clone())
+        .with_system(context.system().clone());
+    let idle = life.idle_secs.map(Duration::from_secs);
+    let mut idle_timeout = idle.map(|window| IdleTimeout::new(window, Instant::now()));
 
-    while running.load(Ordering::SeqCst) {
-        let current_state = monitor.get_current_state()?;
+    while !cancel.is_cancelled() {
+        let sample = monitor.get_current_state()?;
 
-        // Only execute body if state has changed
-        let should_execute = match &last_state {
-            None => true,
-            Some(prev) => current_state.has_changed(prev),
+        // With a window, only its average is considered
+        let current_state = match window.as_mut() {
+            Some(window) => window.push(sample),
+            None => Some(sample),
         };
 
-        if should_execute {
-            // Execute each command in the body
-            for cmd in body {
-                let result = execute_command_with_context(cmd, exec_ctx, context)?;
+        if let Some(current_state) = current_state {
+            let changed = match &last_state {
+                None => true,
+                Some(prev) => current_state.has_changed(prev),
+            };
+
+            // Only execute body if state has changed, or on every full window
+            if changed || window.is_some() {
+                current_state.set_variables(context);
 
-                // Print output for non-empty results
-                match &result.data {
-                    ResultData::Empty => {}
-                    _ => {
-                        println!("{}", format_output(&result, &exec_ctx.output_format));
+                // Execute each command in the body
+                for cmd in &life.body {
+                    let result = execute_command_with_context(cmd, exec_ctx, context)?;
+
+                    // Print output for non-empty results
+                    match &result.data {
+                        ResultData::Empty => {}
+                        _ => sink.write_result(&result)?,
                     }
                 }
             }
+            if changed {
+                last_state = Some(current_state);
+            }
 
-            last_state = Some(current_state);
+            if let Some(timeout) = idle_timeout.as_mut() {
+                if timeout.expired(changed, Instant::now()) {
+                    sink.write_line(&format!(
+                        "\nNo change for {}s, stopping.",
+                        timeout.window.as_secs()
+                    ))?;
+                    break;
+                }
+            }
         }
 
         std::thread::sleep(interval);
     }
 
-    println!("\nLIFE monitor stopped.");
+    sink.write_line("\nLIFE monitor stopped.")?;
     Ok(())
 }
 
 /// Simple CLI monitoring command (arta life battery)
 pub fn run_simple_monitor(
     target_str: &str,
-    interval_secs: u64,
+    interval: Duration,
     output_format: &OutputFormat,
+    idle: Option<Duration>,
+    timezone: Option<DisplayZone>,
+    timestamps: bool,
 ) -> Result<()> {
     let target = match target_str.to_lowercase().as_str() {
         "battery" => LifeTarget::Battery,
@@ -255,45 +480,285 @@ pub fn run_simple_monitor(
         _ => return Err(ArtaError::InvalidTarget(target_str.to_string())),
     };
 
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
-
-    // Set up Ctrl+C handler
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::SeqCst);
-    })
-    .map_err(|e| ArtaError::ExecutionError(format!("Failed to set Ctrl+C handler: {}", e)))?;
+    let cancel = CancellationToken::ctrl_c()?;
+    let mut interval = interval.max(MIN_INTERVAL);
 
-    let interval = Duration::from_secs(interval_secs);
+    // Keys are only read from an interactive terminal
+    let raw_mode = RawModeGuard::enable();
+    let keys_enabled = raw_mode.is_some();
 
-    println!("Monitoring {}... (Press Ctrl+C to stop)\n", target);
+    if keys_enabled {
+        emit(
+            &format!(
+                "Monitoring {}... (space: print now, +/-: adjust interval, q: quit)\n",
+                target
+            ),
+            keys_enabled,
+        );
+    } else {
+        println!("Monitoring {}... (Press Ctrl+C to stop)\n", target);
+    }
 
-    let exec_ctx = ExecutionContext::default();
-    let monitor = LiveMonitor::new(target, interval_secs, exec_ctx);
+    let exec_ctx = ExecutionContext {
+        output_format: output_format.clone(),
+        timezone,
+        timestamps,
+        ..Default::default()
+    };
+    let zone = exec_ctx.display_zone();
+    // JSON samples always carry a timestamp field
+    let stamp = timestamps && !matches!(output_format, OutputFormat::Json);
+    let monitor = LiveMonitor::new(target, interval, exec_ctx);
     let mut last_state: Option<MonitorState> = None;
+    let mut force_print = false;
+    let mut idle_timeout = idle.map(|window| IdleTimeout::new(window, Instant::now()));
+    let color = crate::output::color_enabled(std::io::stdout().is_terminal());
 
-    while running.load(Ordering::SeqCst) {
+    while !cancel.is_cancelled() {
         let current_state = monitor.get_current_state()?;
 
-        // Print state on change
-        let should_print = match &last_state {
+        let changed = match &last_state {
             None => true,
             Some(prev) => current_state.has_changed(prev),
         };
 
+        // Print state on change, or when explicitly requested
+        let should_print = force_print || changed;
+
         if should_print {
-            print_state(&current_state, output_format);
+            let rendered = format_state(
+                &current_state,
+                last_state.as_ref(),
+                output_format,
+                zone,
+                color,
+            );
+            let rendered = if stamp {
+                stamp_lines(&rendered, zone)
+            } else {
+                rendered
+            };
+            emit(&rendered, keys_enabled);
             last_state = Some(current_state);
+            force_print = false;
         }
 
-        std::thread::sleep(interval);
+        // A forced print is not a change, so it does not reset the idle window
+        if let Some(timeout) = idle_timeout.as_mut() {
+            if timeout.expired(changed, Instant::now()) {
+                emit(
+                    &format!("No change for {:?}, stopping.", timeout.window),
+                    keys_enabled,
+                );
+                break;
+            }
+        }
+
+        // Wait for the next sample, reacting to key presses in the meantime
+        let deadline = Instant::now() + interval;
+        while !cancel.is_cancelled() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            let wait = remaining.min(KEY_POLL_INTERVAL);
+
+            if !keys_enabled {
+                std::thread::sleep(wait);
+                continue;
+            }
+
+            if !event::poll(wait)? {
+                continue;
+            }
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+
+            match key_action(&key) {
+                Some(MonitorKeyAction::Quit) => cancel.cancel(),
+                Some(MonitorKeyAction::ForcePrint) => {
+                    force_print = true;
+                    break;
+                }
+                Some(MonitorKeyAction::IncreaseInterval) => {
+                    interval += INTERVAL_STEP;
+                    emit(&format!("Interval: {:?}", interval), keys_enabled);
+                }
+                Some(MonitorKeyAction::DecreaseInterval) => {
+                    interval = interval.saturating_sub(INTERVAL_STEP).max(MIN_INTERVAL);
+                    emit(&format!("Interval: {:?}", interval), keys_enabled);
+                }
+                None => {}
+            }
+        }
     }
 
+    drop(raw_mode);
     println!("\nMonitoring stopped.");
     Ok(())
 }
 
-fn print_state(state: &MonitorState, format: &OutputFormat) {
+/// How much +/- change the interval by
+const INTERVAL_STEP: Duration = Duration::from_secs(1);
+
+/// How often the monitor checks for key presses while waiting
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Action triggered by a key press in the interactive monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorKeyAction {
+    /// Sample and print immediately, even if nothing changed
+    ForcePrint,
+    /// Stop monitoring
+    Quit,
+    /// Sample less often
+    IncreaseInterval,
+    /// Sample more often
+    DecreaseInterval,
+}
+
+/// Map a key event to a monitor action
+///
+/// Ctrl+C is mapped to `Quit` because raw mode stops the terminal from
+/// turning it into a signal.
+pub fn key_action(key: &KeyEvent) -> Option<MonitorKeyAction> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(MonitorKeyAction::Quit)
+        }
+        KeyCode::Char(' ') => Some(MonitorKeyAction::ForcePrint),
+        KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => Some(MonitorKeyAction::Quit),
+        KeyCode::Char('+') | KeyCode::Char('=') => Some(MonitorKeyAction::IncreaseInterval),
+        KeyCode::Char('-') | KeyCode::Char('_') => Some(MonitorKeyAction::DecreaseInterval),
+        _ => None,
+    }
+}
+
+/// Keeps the terminal in raw mode and restores it when dropped
+struct RawModeGuard;
+
+impl RawModeGuard {
+    fn enable() -> Option<Self> {
+        if !std::io::stdin().is_terminal() {
+            return None;
+        }
+        terminal::enable_raw_mode().ok().map(|_| RawModeGuard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Prefix every line with the current time, as `--timestamps` does for queries
+fn stamp_lines(text: &str, zone: DisplayZone) -> String {
+    let now = zone.rfc3339(chrono::Utc::now());
+    text.lines()
+        .map(|line| format!("{} {}", now, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Print a line, adding carriage returns when the terminal is in raw mode
+fn emit(text: &str, raw: bool) {
+    let mut stdout = std::io::stdout();
+    if raw {
+        let _ = write!(stdout, "{}\r\n", text.replace('\n', "\r\n"));
+    } else {
+        let _ = writeln!(stdout, "{}", text);
+    }
+    let _ = stdout.flush();
+}
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+const MB: f64 = 1024.0 * 1024.0;
+
+/// Describe the change between two samples, e.g. "↑ +12.0" for CPU
+///
+/// Returns `None` when the samples are of different kinds or nothing
+/// changed at the displayed precision.
+pub fn state_delta(previous: &MonitorState, current: &MonitorState) -> Option<String> {
+    match (previous, current) {
+        (
+            MonitorState::Battery { percentage: p1, .. },
+            MonitorState::Battery { percentage: p2, .. },
+        ) => signed_delta(f64::from(*p2) - f64::from(*p1), "%", 1),
+        (MonitorState::Memory { used: u1, .. }, MonitorState::Memory { used: u2, .. })
+        | (MonitorState::Disk { used: u1, .. }, MonitorState::Disk { used: u2, .. }) => {
+            signed_delta((*u2 as f64 - *u1 as f64) / GB, " GB", 1)
+        }
+        (MonitorState::Cpu { usage: u1 }, MonitorState::Cpu { usage: u2 }) => {
+            signed_delta(f64::from(*u2) - f64::from(*u1), "", 1)
+        }
+        (
+            MonitorState::Network {
+                bytes_sent: s1,
+                bytes_recv: r1,
+            },
+            MonitorState::Network {
+                bytes_sent: s2,
+                bytes_recv: r2,
+            },
+        ) => {
+            let parts: Vec<String> = [
+                signed_delta((*s2 as f64 - *s1 as f64) / MB, " MB", 1)
+                    .map(|d| format!("sent {}", d)),
+                signed_delta((*r2 as f64 - *r1 as f64) / MB, " MB", 1)
+                    .map(|d| format!("recv {}", d)),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            (!parts.is_empty()).then(|| parts.join(", "))
+        }
+        (MonitorState::Processes { count: c1 }, MonitorState::Processes { count: c2 }) => {
+            signed_delta(*c2 as f64 - *c1 as f64, "", 0)
+        }
+        _ => None,
+    }
+}
+
+/// Format a signed difference with an arrow, or `None` if it rounds to zero
+fn signed_delta(diff: f64, unit: &str, precision: usize) -> Option<String> {
+    let scale = 10f64.powi(precision as i32);
+    if (diff * scale).round() == 0.0 {
+        return None;
+    }
+    let arrow = if diff > 0.0 { '↑' } else { '↓' };
+    Some(format!("{} {:+.*}{}", arrow, precision, diff, unit))
+}
+
+/// Color a delta: green when the change is good news, red otherwise
+fn colorize_delta(delta: &str, state: &MonitorState) -> String {
+    let rising = delta.starts_with('↑');
+    match state {
+        MonitorState::Network { .. } => delta.cyan().to_string(),
+        MonitorState::Battery { .. } if rising => delta.green().to_string(),
+        MonitorState::Battery { .. } => delta.red().to_string(),
+        _ if rising => delta.red().to_string(),
+        _ => delta.green().to_string(),
+    }
+}
+
+/// Render a monitor state in the requested output format
+///
+/// Human output includes the change since `previous`, if there is one.
+fn format_state(
+    state: &MonitorState,
+    previous: Option<&MonitorState>,
+    format: &OutputFormat,
+    zone: DisplayZone,
+    color: bool,
+) -> String {
+    let now = chrono::Utc::now();
     match format {
         OutputFormat::Json => {
             let json = match state {
@@ -305,7 +770,7 @@ fn print_state(state: &MonitorState, format: &OutputFormat) {
                         "type": "battery",
                         "percentage": percentage,
                         "charging": charging,
-                        "timestamp": chrono::Utc::now().to_rfc3339()
+                        "timestamp": zone.rfc3339(now)
                     })
                 }
                 MonitorState::Memory { used, total } => {
@@ -313,15 +778,15 @@ fn print_state(state: &MonitorState, format: &OutputFormat) {
                         "type": "memory",
                         "used": used,
                         "total": total,
-                        "used_percent": (*used as f64 / *total as f64) * 100.0,
-                        "timestamp": chrono::Utc::now().to_rfc3339()
+                        "used_percent": percent_share(*used, *total),
+                        "timestamp": zone.rfc3339(now)
                     })
                 }
                 MonitorState::Cpu { usage } => {
                     serde_json::json!({
                         "type": "cpu",
                         "usage": usage,
-                        "timestamp": chrono::Utc::now().to_rfc3339()
+                        "timestamp": zone.rfc3339(now)
                     })
                 }
                 MonitorState::Disk { used, total } => {
@@ -329,8 +794,8 @@ fn print_state(state: &MonitorState, format: &OutputFormat) {
                         "type": "disk",
                         "used": used,
                         "total": total,
-                        "used_percent": (*used as f64 / *total as f64) * 100.0,
-                        "timestamp": chrono::Utc::now().to_rfc3339()
+                        "used_percent": percent_share(*used, *total),
+                        "timestamp": zone.rfc3339(now)
                     })
                 }
                 MonitorState::Network {
@@ -341,67 +806,74 @@ fn print_state(state: &MonitorState, format: &OutputFormat) {
                         "type": "network",
                         "bytes_sent": bytes_sent,
                         "bytes_recv": bytes_recv,
-                        "timestamp": chrono::Utc::now().to_rfc3339()
+                        "timestamp": zone.rfc3339(now)
                     })
                 }
                 MonitorState::Processes { count } => {
                     serde_json::json!({
                         "type": "processes",
                         "count": count,
-                        "timestamp": chrono::Utc::now().to_rfc3339()
+                        "timestamp": zone.rfc3339(now)
                     })
                 }
             };
-            println!(
-                "{}",
-                serde_json::to_string_pretty(&json).unwrap_or_default()
-            );
+            serde_json::to_string_pretty(&json).unwrap_or_default()
         }
-        OutputFormat::Human => {
-            let time = chrono::Local::now().format("%H:%M:%S");
-            match state {
+        // Monitor states are not query results, so custom, props, CSV and TSV don't apply
+        OutputFormat::Human
+        | OutputFormat::Properties
+        | OutputFormat::Csv
+        | OutputFormat::Tsv
+        | OutputFormat::Custom(_) => {
+            let time = zone.format(now, "%H:%M:%S");
+            let line = match state {
                 MonitorState::Battery {
                     percentage,
                     charging,
                 } => {
                     let status = if *charging { "Charging" } else { "Discharging" };
-                    println!("[{}] Battery: {:.0}% ({})", time, percentage, status);
-                }
-                MonitorState::Memory { used, total } => {
-                    let used_gb = *used as f64 / (1024.0 * 1024.0 * 1024.0);
-                    let total_gb = *total as f64 / (1024.0 * 1024.0 * 1024.0);
-                    let percent = (*used as f64 / *total as f64) * 100.0;
-                    println!(
-                        "[{}] Memory: {:.1} GB / {:.1} GB ({:.1}%)",
-                        time, used_gb, total_gb, percent
-                    );
+                    format!(
+                        "[{}] Battery: {} ({})",
+                        time,
+                        fmt_percent((*percentage).into()),
+                        status
+                    )
                 }
+                MonitorState::Memory { used, total } => format!(
+                    "[{}] Memory: {} / {} ({})",
+                    time,
+                    fmt_size(*used),
+                    fmt_size(*total),
+                    fmt_percent(percent_share(*used, *total))
+                ),
                 MonitorState::Cpu { usage } => {
-                    println!("[{}] CPU: {:.1}%", time, usage);
-                }
-                MonitorState::Disk { used, total } => {
-                    let used_gb = *used as f64 / (1024.0 * 1024.0 * 1024.0);
-                    let total_gb = *total as f64 / (1024.0 * 1024.0 * 1024.0);
-                    let percent = (*used as f64 / *total as f64) * 100.0;
-                    println!(
-                        "[{}] Disk: {:.1} GB / {:.1} GB ({:.1}%)",
-                        time, used_gb, total_gb, percent
-                    );
+                    format!("[{}] CPU: {}", time, fmt_percent((*usage).into()))
                 }
+                MonitorState::Disk { used, total } => format!(
+                    "[{}] Disk: {} / {} ({})",
+                    time,
+                    fmt_size(*used),
+                    fmt_size(*total),
+                    fmt_percent(percent_share(*used, *total))
+                ),
                 MonitorState::Network {
                     bytes_sent,
                     bytes_recv,
-                } => {
-                    let sent_mb = *bytes_sent as f64 / (1024.0 * 1024.0);
-                    let recv_mb = *bytes_recv as f64 / (1024.0 * 1024.0);
-                    println!(
-                        "[{}] Network: Sent {:.1} MB, Recv {:.1} MB",
-                        time, sent_mb, recv_mb
-                    );
-                }
+                } => format!(
+                    "[{}] Network: Sent {}, Recv {}",
+                    time,
+                    fmt_size(*bytes_sent),
+                    fmt_size(*bytes_recv)
+                ),
                 MonitorState::Processes { count } => {
-                    println!("[{}] Processes: {}", time, count);
+                    format!("[{}] Processes: {}", time, count)
                 }
+            };
+
+            match previous.and_then(|prev| state_delta(prev, state)) {
+                Some(delta) if color => format!("{} ({})", line, colorize_delta(&delta, state)),
+                Some(delta) => format!("{} ({})", line, delta),
+                None => line,
             }
         }
     }
@@ -411,6 +883,196 @@ fn print_state(state: &MonitorState, format: &OutputFormat) {
 mod tests {
     use super::*;
 
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_nested_life_is_rejected() {
+        let outer = ActiveLife::enter().unwrap();
+        assert!(ActiveLife::enter().is_err());
+        drop(outer);
+        assert!(ActiveLife::enter().is_ok());
+    }
+
+    #[test]
+    fn test_cpu_delta() {
+        let prev = MonitorState::Cpu { usage: 50.0 };
+        let curr = MonitorState::Cpu { usage: 62.0 };
+        assert_eq!(state_delta(&prev, &curr).as_deref(), Some("↑ +12.0"));
+        assert_eq!(state_delta(&curr, &prev).as_deref(), Some("↓ -12.0"));
+        assert_eq!(state_delta(&curr, &curr), None);
+    }
+
+    #[test]
+    fn test_memory_delta() {
+        let gb = 1024 * 1024 * 1024;
+        let prev = MonitorState::Memory {
+            used: 9 * gb,
+            total: 16 * gb,
+        };
+        let curr = MonitorState::Memory {
+            used: 8 * gb + gb / 2,
+            total: 16 * gb,
+        };
+        assert_eq!(state_delta(&prev, &curr).as_deref(), Some("↓ -0.5 GB"));
+    }
+
+    #[test]
+    fn test_first_sample_has_no_delta() {
+        let state = MonitorState::Cpu { usage: 62.0 };
+        let line = format_state(&state, None, &OutputFormat::Human, DisplayZone::Utc, false);
+        assert!(line.ends_with("CPU: 62.0%"));
+
+        let prev = MonitorState::Cpu { usage: 50.0 };
+        let line = format_state(
+            &state,
+            Some(&prev),
+            &OutputFormat::Human,
+            DisplayZone::Utc,
+            false,
+        );
+        assert!(line.ends_with("CPU: 62.0% (↑ +12.0)"));
+    }
+
+    #[test]
+    fn test_json_sample_timestamp_uses_zone() {
+        let state = MonitorState::Cpu { usage: 62.0 };
+        let tokyo = "Asia/Tokyo".parse().unwrap();
+        let json = format_state(&state, None, &OutputFormat::Json, tokyo, false);
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(json["timestamp"].as_str().unwrap().ends_with("+09:00"));
+    }
+
+    #[test]
+    fn test_stamp_lines_prefixes_every_line() {
+        let tokyo = "Asia/Tokyo".parse().unwrap();
+        let stamped = stamp_lines("first\nsecond", tokyo);
+        let lines: Vec<&str> = stamped.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for (line, text) in lines.iter().zip(["first", "second"]) {
+            let (time, rest) = line.split_once(' ').unwrap();
+            assert!(time.ends_with("+09:00"), "{}", line);
+            assert_eq!(rest, text);
+        }
+    }
+
+    #[test]
+    fn test_zero_total_is_zero_percent() {
+        // A disk or memory reporting no capacity must not produce NaN, which
+        // serde_json writes as null
+        for state in [
+            MonitorState::Memory { used: 0, total: 0 },
+            MonitorState::Disk { used: 0, total: 0 },
+        ] {
+            let json = format_state(&state, None, &OutputFormat::Json, DisplayZone::Utc, false);
+            let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(json["used_percent"].as_f64(), Some(0.0));
+
+            let line = format_state(&state, None, &OutputFormat::Human, DisplayZone::Utc, false);
+            assert!(line.ends_with("(0.0%)"), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_window_average() {
+        let mut window = WindowAverage::new(3);
+        assert!(window.push(MonitorState::Cpu { usage: 10.0 }).is_none());
+        assert!(window.push(MonitorState::Cpu { usage: 20.0 }).is_none());
+        match window.push(MonitorState::Cpu { usage: 60.0 }) {
+            Some(MonitorState::Cpu { usage }) => assert_eq!(usage, 30.0),
+            other => panic!("Expected CPU average, got {:?}", other),
+        }
+
+        // The next window starts empty
+        assert!(window.push(MonitorState::Cpu { usage: 90.0 }).is_none());
+
+        let mut window = WindowAverage::new(2);
+        window.push(MonitorState::Memory {
+            used: 100,
+            total: 1000,
+        });
+        match window.push(MonitorState::Memory {
+            used: 301,
+            total: 1000,
+        }) {
+            Some(MonitorState::Memory { used, total }) => assert_eq!((used, total), (201, 1000)),
+            other => panic!("Expected memory average, got {:?}", other),
+        }
+
+        // Cumulative counters are not averaged
+        let mut window = WindowAverage::new(2);
+        window.push(MonitorState::Network {
+            bytes_sent: 10,
+            bytes_recv: 20,
+        });
+        match window.push(MonitorState::Network {
+            bytes_sent: 50,
+            bytes_recv: 80,
+        }) {
+            Some(MonitorState::Network {
+                bytes_sent,
+                bytes_recv,
+            }) => assert_eq!((bytes_sent, bytes_recv), (50, 80)),
+            other => panic!("Expected latest network state, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_idle_timeout_after_quiet_samples() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut timeout = IdleTimeout::new(Duration::from_secs(10), start);
+
+        // (seconds since start, changed, expired)
+        let samples = [
+            (0, true, false),
+            (4, false, false),
+            (8, true, false),
+            (12, false, false),
+            (17, false, false),
+            (18, false, true),
+        ];
+        for (secs, changed, expected) in samples {
+            assert_eq!(timeout.expired(changed, at(secs)), expected, "at {}s", secs);
+        }
+    }
+
+    #[test]
+    fn test_key_action_mapping() {
+        assert_eq!(
+            key_action(&press(KeyCode::Char(' '))),
+            Some(MonitorKeyAction::ForcePrint)
+        );
+        assert_eq!(
+            key_action(&press(KeyCode::Char('q'))),
+            Some(MonitorKeyAction::Quit)
+        );
+        assert_eq!(
+            key_action(&press(KeyCode::Char('+'))),
+            Some(MonitorKeyAction::IncreaseInterval)
+        );
+        assert_eq!(
+            key_action(&press(KeyCode::Char('-'))),
+            Some(MonitorKeyAction::DecreaseInterval)
+        );
+        assert_eq!(key_action(&press(KeyCode::Char('x'))), None);
+    }
+
+    #[test]
+    fn test_key_action_ctrl_c_quits() {
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(key_action(&key), Some(MonitorKeyAction::Quit));
+        assert_eq!(key_action(&press(KeyCode::Char('c'))), None);
+    }
+
+    #[test]
+    fn test_key_action_ignores_release() {
+        let mut key = press(KeyCode::Char('q'));
+        key.kind = KeyEventKind::Release;
+        assert_eq!(key_action(&key), None);
+    }
+
     #[test]
     fn test_monitor_state_battery_change() {
         let s1 = MonitorState::Battery {
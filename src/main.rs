@@ -1,58 +1,211 @@
 //! Arta CLI - Query your system with SQL-like commands
 
-use arta::cli::Args;
+use arta::cli::{Args, LogLevel};
 use arta::container::ContainerManager;
+use arta::engine::{
+    check_alert, run_repeated, CancellationToken, RepeatPlan, ResultData, ALERT_EXIT_CODE,
+};
+use arta::output::color_enabled;
+use arta::output::trailer::Trailer;
 use arta::script::{
-    explain_script, has_errors, validate_script, ScriptRunner, ValidationOptions,
-    ValidationSeverity,
+    explain_script, validate_script, validate_script_report, ArgType, ScriptRunner,
+    ValidationOptions,
 };
 use arta::{
-    execute_command, format_output, parse_command, parse_script, ExecutionContext, OutputFormat,
+    execute_command, execute_command_with_context, format_output, parse_command, parse_script,
+    Command, Context, ExecutionContext, FormatterRegistry, OutputFormat, OutputSink,
 };
 use clap::Parser;
+use std::io::IsTerminal;
+use std::time::Duration;
 
 fn main() {
     let args = Args::parse();
 
-    if let Err(e) = run(args) {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    if let Some(level) = args.log_level {
+        init_logging(level);
+    }
+
+    let trailer = args.trailer.is_some();
+    match run(args) {
+        Ok(0) => {}
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            if trailer {
+                println!("{}", Trailer::failed(&e.to_string()).to_json_line());
+            }
+            std::process::exit(1);
+        }
     }
 }
 
-fn run(args: Args) -> arta::Result<()> {
+/// Send internal logs to stderr, keeping stdout for results
+fn init_logging(level: LogLevel) {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::from(level))
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .with_ansi(color_enabled(std::io::stderr().is_terminal()))
+        .init();
+}
+
+/// Run the subcommand, returning the process exit code
+fn run(args: Args) -> arta::Result<i32> {
+    #[cfg(feature = "ssh")]
+    if args.host.is_some() && !matches!(args.command, arta::cli::SubCommand::Query { .. }) {
+        return Err(arta::ArtaError::ExecutionError(
+            "--host is only supported by the query subcommand".to_string(),
+        ));
+    }
+
+    let output_format = args.output_format(&FormatterRegistry::default())?;
+
     match args.command {
-        arta::cli::SubCommand::Query { query } => {
+        arta::cli::SubCommand::Query {
+            query,
+            stream,
+            repeat,
+            delay,
+            alert,
+        } => {
             let cmd = parse_command(&query)?;
+            let alert_condition = match (&cmd, alert) {
+                (Command::Check(condition), true) => Some(condition.clone()),
+                (_, true) => {
+                    return Err(arta::ArtaError::ExecutionError(
+                        "--alert needs a condition, e.g. SELECT DISK used_percent > 90 FROM /"
+                            .to_string(),
+                    ))
+                }
+                (_, false) => None,
+            };
+            #[cfg(feature = "ssh")]
+            if alert_condition.is_some() && args.host.is_some() {
+                return Err(arta::ArtaError::ExecutionError(
+                    "--alert is not supported with --host".to_string(),
+                ));
+            }
             let ctx = ExecutionContext {
                 dry_run: args.dry_run,
                 allow_actions: args.allow_actions,
-                output_format: if args.json {
-                    OutputFormat::Json
-                } else {
-                    OutputFormat::Human
-                },
+                output_format,
                 verbose: args.verbose,
+                explain_matches: args.explain_matches,
+                timestamps: args.timestamps,
+                timezone: args.timezone,
+                json_units: args.json_units,
+                strict_fields: args.strict_fields,
+                all_mounts: args.all_mounts,
+                audit_log: args.audit_log.clone(),
+                precision: args.precision,
+                ..Default::default()
+            };
+            let delay = Duration::try_from_secs_f64(delay).map_err(|_| {
+                arta::ArtaError::ExecutionError(format!("Invalid --delay: {}", delay))
+            })?;
+            let plan = RepeatPlan::new(repeat, delay);
+
+            let cancel = if repeat > 1 {
+                CancellationToken::ctrl_c()?
+            } else {
+                CancellationToken::new()
             };
-            let result = execute_command(&cmd, &ctx)?;
-            println!("{}", format_output(&result, &ctx.output_format));
-            Ok(())
+
+            #[cfg(feature = "ssh")]
+            let remote = args
+                .host
+                .map(|host| arta::remote::RemoteHost::new(host).with_ssh_key(args.ssh_key));
+
+            // One context for all runs, so CPU usage is sampled between them
+            let mut context = Context::new();
+            let mut sink = OutputSink::stdout(&ctx).with_stream(stream);
+            let mut check_failed = false;
+            let mut alerted = false;
+            let mut last_result = None;
+            run_repeated(
+                &plan,
+                &cancel,
+                |_| {
+                    // Alerts print only on a breach, then stop any further runs
+                    if let Some(condition) = &alert_condition {
+                        if let Some(message) = check_alert(condition, &ctx, &context)? {
+                            sink.write_result(&arta::engine::ExecutionResult::new(
+                                ResultData::Message(message),
+                            ))?;
+                            alerted = true;
+                            cancel.cancel();
+                        }
+                        return Ok(());
+                    }
+
+                    #[cfg(feature = "ssh")]
+                    let result = match remote {
+                        Some(ref remote) => remote.run_query(&query, &ctx)?,
+                        None => execute_command_with_context(&cmd, &ctx, &mut context)?,
+                    };
+                    #[cfg(not(feature = "ssh"))]
+                    let result = execute_command_with_context(&cmd, &ctx, &mut context)?;
+
+                    sink.write_result(&result)?;
+                    check_failed = matches!(result.data, ResultData::Boolean(false));
+                    last_result = Some(result);
+                    Ok(())
+                },
+                std::thread::sleep,
+            )?;
+
+            if let (Some(_), Some(result)) = (args.trailer, &last_result) {
+                sink.write_trailer(&Trailer::for_result(result))?;
+            }
+
+            if alerted {
+                return Ok(ALERT_EXIT_CODE);
+            }
+            // A bare condition that is false exits non-zero, like `test`
+            Ok(i32::from(check_failed))
         }
 
         arta::cli::SubCommand::Run {
             file,
             args: script_args,
+            size_args,
+            num_args,
+            path_args,
+            str_args,
             container,
+            containers,
+            transactional,
+            only,
+            fail_on_warning,
         } => {
-            let ctx = ExecutionContext {
+            let mut ctx = ExecutionContext {
                 dry_run: args.dry_run,
                 allow_actions: args.allow_actions,
-                output_format: if args.json {
-                    OutputFormat::Json
-                } else {
-                    OutputFormat::Human
-                },
+                output_format: output_format.clone(),
                 verbose: args.verbose,
+                explain_matches: args.explain_matches,
+                timestamps: args.timestamps,
+                timezone: args.timezone,
+                json_units: args.json_units,
+                strict_fields: args.strict_fields,
+                all_mounts: args.all_mounts,
+                audit_log: args.audit_log.clone(),
+                precision: args.precision,
+                ..Default::default()
+            };
+
+            // Take over the container's context and narrow the permissions to its own
+            let container = match (&container, &containers) {
+                (Some(name), Some(path)) => {
+                    let container = ContainerManager::load_session(path, &ctx)?.take(name)?;
+                    container.restrict(&mut ctx);
+                    if args.verbose {
+                        println!("Running in container: {}", name);
+                    }
+                    Some(container)
+                }
+                _ => None,
             };
 
             // Read and parse the script first for validation
@@ -61,43 +214,53 @@ fn run(args: Args) -> arta::Result<()> {
 
             // Validate the script
             let validation_opts = ValidationOptions {
-                allow_actions: args.allow_actions,
+                allow_actions: ctx.allow_actions,
                 allow_life_actions: false,
                 max_nesting_depth: 10,
             };
-            let validation_errors = validate_script(&script, &validation_opts);
+            let report = validate_script_report(&script, &validation_opts);
 
             // Print warnings
-            for err in validation_errors
-                .iter()
-                .filter(|e| e.severity == ValidationSeverity::Warning)
-            {
-                eprintln!("Warning: {}", err);
+            let color = color_enabled(std::io::stderr().is_terminal());
+            for warning in &report.warnings {
+                eprintln!("Warning: {}", warning.render(color));
             }
 
-            // Abort on errors
-            if has_errors(&validation_errors) {
-                for err in validation_errors
-                    .iter()
-                    .filter(|e| e.severity == ValidationSeverity::Error)
-                {
-                    eprintln!("Error: {}", err);
+            // Abort on errors, and on warnings with --fail-on-warning
+            if report.exit_code(fail_on_warning) != 0 {
+                for err in &report.errors {
+                    eprintln!("Error: {}", err.render(color));
                 }
-                return Err(arta::ArtaError::ExecutionError(
-                    "Script validation failed. Fix errors or use --allow-actions if needed."
+                let denial = container.as_ref().and_then(|c| c.action_denial());
+                let reason = match denial {
+                    _ if report.is_ok() => {
+                        "Script has warnings and --fail-on-warning is set.".to_string()
+                    }
+                    Some(denial) if args.allow_actions => {
+                        format!(
+                            "Script validation failed. {}, so actions are disabled.",
+                            denial
+                        )
+                    }
+                    _ => "Script validation failed. Fix errors or use --allow-actions if needed."
                         .to_string(),
-                ));
-            }
-
-            // Log container if specified
-            if let Some(ref container_name) = container {
-                if args.verbose {
-                    println!("Running in container: {}", container_name);
-                }
+                };
+                return Err(arta::ArtaError::ExecutionError(reason));
             }
 
             // Run the script
-            let mut runner = ScriptRunner::new(ctx).with_args(script_args);
+            let mut sink = OutputSink::stdout(&ctx);
+            let mut runner = ScriptRunner::new(ctx)
+                .with_args(script_args)
+                .with_typed_args(ArgType::Size, size_args)?
+                .with_typed_args(ArgType::Number, num_args)?
+                .with_typed_args(ArgType::Path, path_args)?
+                .with_typed_args(ArgType::String, str_args)?
+                .with_transactional(transactional)
+                .with_only(only);
+            if let Some(container) = container {
+                runner = runner.with_context(container.context);
+            }
             let result = runner.run_file(&file)?;
 
             if !result.success {
@@ -107,23 +270,38 @@ fn run(args: Args) -> arta::Result<()> {
             }
 
             if args.verbose {
-                println!(
-                    "\n--- Script completed: {} statements executed ---",
-                    result.statements_executed
-                );
+                sink.write_line(&format!(
+                    "\n--- Script completed: {} statements executed in {}ms ---",
+                    result.statements_executed,
+                    result.total_duration.as_millis()
+                ))?;
+            }
+            if args.trailer.is_some() {
+                let trailer = Trailer {
+                    target: None,
+                    count: result.statements_executed,
+                    success: result.success,
+                    error: None,
+                };
+                sink.write_trailer(&trailer)?;
             }
 
-            Ok(())
+            Ok(0)
         }
 
-        arta::cli::SubCommand::Life { target, interval } => {
-            let output_format = if args.json {
-                OutputFormat::Json
-            } else {
-                OutputFormat::Human
-            };
-            arta::life::run_simple_monitor(&target, interval, &output_format)
-        }
+        arta::cli::SubCommand::Life {
+            target,
+            interval,
+            idle,
+        } => arta::life::run_simple_monitor(
+            &target,
+            interval,
+            &output_format,
+            idle,
+            args.timezone,
+            args.timestamps,
+        )
+        .map(|()| 0),
 
         arta::cli::SubCommand::Explain { input } => {
             // Check if input is a file path or a query
@@ -150,9 +328,10 @@ fn run(args: Args) -> arta::Result<()> {
                 let validation_errors = validate_script(&script, &validation_opts);
 
                 if !validation_errors.is_empty() {
+                    let color = color_enabled(std::io::stdout().is_terminal());
                     println!("\nValidation Notes:");
                     for err in &validation_errors {
-                        println!("  - {}", err);
+                        println!("  - {}", err.render(color));
                     }
                 }
             } else {
@@ -163,34 +342,55 @@ fn run(args: Args) -> arta::Result<()> {
                     allow_actions: false,
                     output_format: OutputFormat::Human,
                     verbose: args.verbose,
+                    explain_matches: false,
+                    ..Default::default()
                 };
                 let result = execute_command(&arta::parser::Command::Explain(Box::new(cmd)), &ctx)?;
                 println!("{}", format_output(&result, &ctx.output_format));
             }
 
-            Ok(())
+            Ok(0)
         }
 
-        arta::cli::SubCommand::Containers => {
-            let manager = ContainerManager::new();
-            println!("Containers:");
-            println!("-----------");
-            for name in manager.list() {
-                let container = manager.get(name).unwrap();
-                let active = if manager.active_name() == name {
-                    " (active)"
-                } else {
-                    ""
-                };
+        arta::cli::SubCommand::Validate { file, strict } => {
+            let content = std::fs::read_to_string(&file).map_err(arta::ArtaError::IoError)?;
+            let script = parse_script(&content)?;
+
+            let validation_opts = ValidationOptions {
+                allow_actions: args.allow_actions,
+                allow_life_actions: false,
+                max_nesting_depth: 10,
+            };
+            let report = validate_script_report(&script, &validation_opts);
+            let exit_code = report.exit_code(strict);
+
+            if output_format == OutputFormat::Json {
+                let json = serde_json::json!({
+                    "file": file.display().to_string(),
+                    "valid": exit_code == 0,
+                    "findings": report.iter().collect::<Vec<_>>(),
+                });
                 println!(
-                    "  {} - actions: {}, readonly: {}{}",
-                    name,
-                    if container.allow_actions { "yes" } else { "no" },
-                    if container.readonly { "yes" } else { "no" },
-                    active
+                    "{}",
+                    serde_json::to_string_pretty(&json).unwrap_or_default()
+                );
+            } else {
+                println!(
+                    "{}",
+                    report.render(color_enabled(std::io::stdout().is_terminal()))
                 );
             }
-            Ok(())
+
+            Ok(exit_code)
+        }
+
+        arta::cli::SubCommand::Containers => {
+            let manager = ContainerManager::new();
+            let result = arta::engine::ExecutionResult::new(ResultData::ContainerResult(
+                manager.list_info(None)?,
+            ));
+            println!("{}", format_output(&result, &output_format));
+            Ok(0)
         }
 
         #[cfg(feature = "repl")]
@@ -198,12 +398,12 @@ fn run(args: Args) -> arta::Result<()> {
             if let Some(ref container_name) = container {
                 println!("Starting REPL in container: {}", container_name);
             }
-            arta::repl::run_repl()
+            arta::repl::run_repl(args.timezone).map(|()| 0)
         }
         #[cfg(not(feature = "repl"))]
         arta::cli::SubCommand::Repl { .. } => {
             eprintln!("REPL support not enabled. Rebuild with --features repl");
-            std::process::exit(1);
+            Ok(1)
         }
     }
 }
@@ -0,0 +1,282 @@
+//! CSV and TSV output for spreadsheets and tools such as `cut` or `awk`
+//!
+//! A result becomes a header row of field names followed by one row per
+//! record: list results have a row per entry, single records one row.
+//! CONTENT is written as `line_number,text` rows. Nested values are written
+//! as compact JSON, and values are not rounded or annotated.
+//!
+//! CSV follows RFC 4180: a field containing a comma, quote or line break is
+//! quoted, with quotes doubled. TSV has no quoting, so tabs, line breaks and
+//! backslashes in a field are escaped as `\t`, `\n`, `\r` and `\\`.
+
+use crate::engine::executor::{ExecutionResult, ResultData};
+use crate::output::json::json_value;
+use serde_json::Value;
+
+/// Comma- or tab-separated output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimited {
+    Csv,
+    Tsv,
+}
+
+impl Delimited {
+    fn separator(self) -> &'static str {
+        match self {
+            Delimited::Csv => ",",
+            Delimited::Tsv => "\t",
+        }
+    }
+
+    /// A field as written in a record
+    fn field(self, value: &str) -> String {
+        match self {
+            Delimited::Csv if value.contains([',', '"', '\n', '\r']) => {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            }
+            Delimited::Csv => value.to_string(),
+            Delimited::Tsv => {
+                let mut out = String::with_capacity(value.len());
+                for c in value.chars() {
+                    match c {
+                        '\\' => out.push_str("\\\\"),
+                        '\t' => out.push_str("\\t"),
+                        '\n' => out.push_str("\\n"),
+                        '\r' => out.push_str("\\r"),
+                        other => out.push(other),
+                    }
+                }
+                out
+            }
+        }
+    }
+
+    fn record(self, fields: &[String]) -> String {
+        fields
+            .iter()
+            .map(|field| self.field(field))
+            .collect::<Vec<_>>()
+            .join(self.separator())
+    }
+}
+
+pub fn format_csv(result: &ExecutionResult) -> String {
+    format_delimited(result, Delimited::Csv)
+}
+
+pub fn format_tsv(result: &ExecutionResult) -> String {
+    format_delimited(result, Delimited::Tsv)
+}
+
+/// A result as a header and records, one per line
+///
+/// Each result of a script or FOR loop gets its own table, separated by an
+/// empty line.
+pub fn format_delimited(result: &ExecutionResult, format: Delimited) -> String {
+    let (header, rows) = match &result.data {
+        ResultData::Multiple(results) => {
+            return results
+                .iter()
+                .map(|result| format_delimited(result, format))
+                .filter(|table| !table.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+        }
+        ResultData::Empty => return String::new(),
+        ResultData::Content(info) => (
+            vec!["line_number".to_string(), "text".to_string()],
+            info.rows()
+                .map(|(number, text)| vec![number.to_string(), text.to_string()])
+                .collect(),
+        ),
+        _ => table(json_value(result)),
+    };
+
+    std::iter::once(format.record(&header))
+        .chain(rows.iter().map(|row| format.record(row)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Header and rows of a result's JSON
+fn table(value: Value) -> (Vec<String>, Vec<Vec<String>>) {
+    let records = match value {
+        // DISK, NETWORK and BATTERY wrap their rows in a single field
+        Value::Object(object) if object.len() == 1 && object.values().all(Value::is_array) => {
+            match object.into_iter().next() {
+                Some((_, Value::Array(rows))) => rows,
+                _ => Vec::new(),
+            }
+        }
+        Value::Array(rows) => rows,
+        record => vec![record],
+    };
+
+    let mut header: Vec<String> = Vec::new();
+    for record in &records {
+        match record {
+            Value::Object(object) => {
+                for key in object.keys() {
+                    if !header.contains(key) {
+                        header.push(key.clone());
+                    }
+                }
+            }
+            _ if !header.iter().any(|key| key == "value") => header.push("value".to_string()),
+            _ => {}
+        }
+    }
+
+    let rows = records
+        .iter()
+        .map(|record| {
+            header
+                .iter()
+                .map(|key| match record {
+                    Value::Object(object) => object.get(key).map(cell).unwrap_or_default(),
+                    value if key == "value" => cell(value),
+                    _ => String::new(),
+                })
+                .collect()
+        })
+        .collect();
+    (header, rows)
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Bool(_) | Value::Number(_) => value.to_string(),
+        nested => serde_json::to_string(nested).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::executor::ContentInfo;
+
+    /// Lines with the characters CSV and TSV have to escape
+    const LINES: [&str; 4] = ["plain", "a, b, c", r#"say "hi""#, "multi\nline\twith\\tab"];
+
+    fn content(numbered: bool) -> ExecutionResult {
+        let line_numbers = vec![1, 5, 9, 12];
+        let lines = LINES
+            .iter()
+            .zip(&line_numbers)
+            .map(|(line, number)| match numbered {
+                true => format!("{:>4}: {}", number, line),
+                false => line.to_string(),
+            })
+            .collect();
+        ExecutionResult::new(ResultData::Content(ContentInfo {
+            file_path: "/tmp/app.log".to_string(),
+            lines,
+            total_lines: 12,
+            file_size: 100,
+            scan_truncated: false,
+            line_numbers,
+            numbered,
+        }))
+    }
+
+    /// Minimal RFC 4180 reader, to check the output parses back
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let mut records = vec![vec![String::new()]];
+        let mut chars = text.chars().peekable();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            let record = records.last_mut().unwrap();
+            let field = record.last_mut().unwrap();
+            match (quoted, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                (true, '"') => quoted = false,
+                (true, c) => field.push(c),
+                (false, '"') => quoted = true,
+                (false, ',') => record.push(String::new()),
+                (false, '\n') => records.push(vec![String::new()]),
+                (false, c) => field.push(c),
+            }
+        }
+        records
+    }
+
+    fn unescape_tsv(field: &str) -> String {
+        let mut out = String::new();
+        let mut chars = field.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('t') => out.push('\t'),
+                Some('n') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_content_csv_round_trip() {
+        for numbered in [false, true] {
+            let records = parse_csv(&format_csv(&content(numbered)));
+            assert_eq!(records[0], ["line_number", "text"]);
+            let rows: Vec<(&str, &str)> = records[1..]
+                .iter()
+                .map(|r| (r[0].as_str(), r[1].as_str()))
+                .collect();
+            assert_eq!(
+                rows,
+                [
+                    ("1", LINES[0]),
+                    ("5", LINES[1]),
+                    ("9", LINES[2]),
+                    ("12", LINES[3])
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_content_tsv_round_trip() {
+        let output = format_tsv(&content(true));
+        let lines: Vec<&str> = output.lines().collect();
+        // One record per line, however many line breaks the text has
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "line_number\ttext");
+        assert_eq!(lines[4], r"12	multi\nline\twith\\tab");
+
+        for (line, expected) in lines[1..].iter().zip(LINES) {
+            let (_, text) = line.split_once('\t').unwrap();
+            assert_eq!(unescape_tsv(text), expected);
+        }
+    }
+
+    #[test]
+    fn test_list_results_have_a_row_each() {
+        let result =
+            ExecutionResult::new(ResultData::Custom(crate::engine::executor::CustomResult {
+                target: "gpu".to_string(),
+                value: serde_json::json!([
+                    { "name": "gpu0", "temp": 61.5 },
+                    { "name": "gpu, 1", "extra": { "fan": 2 } }
+                ]),
+            }));
+        assert_eq!(
+            format_csv(&result),
+            "name,temp,extra\ngpu0,61.5,\n\"gpu, 1\",,\"{\"\"fan\"\":2}\""
+        );
+
+        let message = ExecutionResult::new(ResultData::Message("done".to_string()));
+        assert_eq!(format_tsv(&message), "message\ndone");
+        assert_eq!(format_csv(&ExecutionResult::new(ResultData::Empty)), "");
+    }
+}
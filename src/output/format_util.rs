@@ -0,0 +1,128 @@
+//! Formatting for sizes, durations, rates and percentages
+//!
+//! Every human-readable rendering of these goes through here, so a memory
+//! size or an uptime looks the same in query output, LIFE lines and action
+//! summaries.
+
+use crate::output::timezone::DisplayZone;
+use bytesize::ByteSize;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// Size in decimal units, e.g. `1.5 GB`
+pub fn fmt_size(bytes: u64) -> String {
+    ByteSize(bytes).to_string()
+}
+
+/// Duration in seconds as `5d 3h 12m`, dropping leading zero units
+///
+/// Durations under a minute are shown in seconds.
+pub fn fmt_duration(secs: u64) -> String {
+    if secs < 60 {
+        return format!("{}s", secs);
+    }
+    let days = secs / 86_400;
+    let hours = (secs % 86_400) / 3600;
+    let minutes = (secs % 3600) / 60;
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Rate of `bytes` transferred over `elapsed`, e.g. `1.2 MB/s`
+///
+/// An empty interval has no meaningful rate and is shown as `0 B/s`.
+pub fn fmt_throughput(bytes: u64, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 {
+        (bytes as f64 / secs).round() as u64
+    } else {
+        0
+    };
+    format!("{}/s", fmt_size(rate))
+}
+
+/// File time in `zone` to the minute, e.g. `2024-05-01 12:30`
+pub fn fmt_file_time(instant: DateTime<Utc>, zone: DisplayZone) -> String {
+    zone.format(instant, "%Y-%m-%d %H:%M")
+}
+
+/// Decimal places of displayed percentages unless `--precision` says otherwise
+pub const DEFAULT_PRECISION: usize = 1;
+
+/// Percentage with one decimal, e.g. `42.5%`
+pub fn fmt_percent(percent: f64) -> String {
+    fmt_percent_with(percent, DEFAULT_PRECISION)
+}
+
+/// Percentage with `precision` decimals, e.g. `42%` or `42.460%`
+pub fn fmt_percent_with(percent: f64, precision: usize) -> String {
+    format!("{:.*}%", precision, percent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fmt_size() {
+        assert_eq!(fmt_size(0), "0 B");
+        assert_eq!(fmt_size(999), "999 B");
+        assert_eq!(fmt_size(1_500_000_000), "1.5 GB");
+        assert_eq!(fmt_size(u64::MAX), "18.4 EB");
+    }
+
+    #[test]
+    fn test_fmt_duration() {
+        assert_eq!(fmt_duration(0), "0s");
+        assert_eq!(fmt_duration(59), "59s");
+        assert_eq!(fmt_duration(600), "10m");
+        assert_eq!(fmt_duration(2 * 3600 + 60), "2h 1m");
+        assert_eq!(
+            fmt_duration(5 * 86_400 + 3 * 3600 + 12 * 60 + 59),
+            "5d 3h 12m"
+        );
+        // A year reads as days rather than 8760h
+        assert_eq!(fmt_duration(365 * 86_400), "365d 0h 0m");
+        assert_eq!(fmt_duration(u64::MAX), "213503982334601d 7h 0m");
+    }
+
+    #[test]
+    fn test_fmt_throughput() {
+        assert_eq!(fmt_throughput(0, Duration::from_secs(1)), "0 B/s");
+        assert_eq!(fmt_throughput(1_000, Duration::ZERO), "0 B/s");
+        assert_eq!(
+            fmt_throughput(3_000_000, Duration::from_secs(2)),
+            "1.5 MB/s"
+        );
+        assert_eq!(fmt_throughput(500, Duration::from_millis(250)), "2.0 KB/s");
+        assert_eq!(
+            fmt_throughput(u64::MAX, Duration::from_secs(1)),
+            fmt_size(u64::MAX) + "/s"
+        );
+    }
+
+    #[test]
+    fn test_fmt_file_time() {
+        use chrono::TimeZone;
+        let instant = Utc.with_ymd_and_hms(2024, 1, 15, 12, 30, 59).unwrap();
+        assert_eq!(fmt_file_time(instant, DisplayZone::Utc), "2024-01-15 12:30");
+        let tokyo = "Asia/Tokyo".parse().unwrap();
+        assert_eq!(fmt_file_time(instant, tokyo), "2024-01-15 21:30");
+    }
+
+    #[test]
+    fn test_fmt_percent() {
+        assert_eq!(fmt_percent(0.0), "0.0%");
+        assert_eq!(fmt_percent(42.46), "42.5%");
+        assert_eq!(fmt_percent(100.0), "100.0%");
+        assert_eq!(fmt_percent(0.04), "0.0%");
+        assert_eq!(fmt_percent(12345.678), "12345.7%");
+        assert_eq!(fmt_percent_with(42.46, 0), "42%");
+        assert_eq!(fmt_percent_with(42.46, 3), "42.460%");
+    }
+}
@@ -1,18 +1,64 @@
 //! Output formatting
 
 use crate::engine::executor::ExecutionResult;
-use crate::output::human::format_human;
+use crate::output::delimited::{format_csv, format_tsv};
+use crate::output::format_util::DEFAULT_PRECISION;
+use crate::output::human::format_human_with;
 use crate::output::json::format_json;
+use crate::output::properties::format_properties_with;
+use crate::output::registry::CustomFormatter;
+use crate::output::timezone::DisplayZone;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum OutputFormat {
     Human,
     Json,
+    /// `KEY=value` lines for shell `eval` and `.env` files
+    Properties,
+    /// Comma-separated records with a header row
+    Csv,
+    /// Tab-separated records with a header row
+    Tsv,
+    /// A formatter registered by the embedding application
+    Custom(CustomFormatter),
+}
+
+/// How human and properties output render values (`--precision`, `--timezone`)
+///
+/// JSON ignores these: it keeps full precision and UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// Decimal places of percentages
+    pub precision: usize,
+    /// Zone file times are shown in
+    pub zone: DisplayZone,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            precision: DEFAULT_PRECISION,
+            zone: DisplayZone::Local,
+        }
+    }
 }
 
 pub fn format_output(result: &ExecutionResult, format: &OutputFormat) -> String {
+    format_output_with(result, format, &DisplayOptions::default())
+}
+
+/// Like [`format_output`], with human and properties values rendered per `options`
+pub fn format_output_with(
+    result: &ExecutionResult,
+    format: &OutputFormat,
+    options: &DisplayOptions,
+) -> String {
     match format {
-        OutputFormat::Human => format_human(result),
+        OutputFormat::Human => format_human_with(result, options),
         OutputFormat::Json => format_json(result),
+        OutputFormat::Properties => format_properties_with(result, options),
+        OutputFormat::Csv => format_csv(result),
+        OutputFormat::Tsv => format_tsv(result),
+        OutputFormat::Custom(formatter) => formatter.format(result),
     }
 }
@@ -1,19 +1,43 @@
 //! Human-readable output formatting
 
+use crate::engine::diff::{DiffInfo, DiffLine, DiffLineKind};
 use crate::engine::executor::{ExecutionResult, ResultData};
-use bytesize::ByteSize;
+use crate::engine::fields::{FieldValue, Projection};
+use crate::engine::queries::memory::MemoryPressure;
+use crate::output::color_enabled;
+use crate::output::format_util::{
+    fmt_duration, fmt_file_time, fmt_percent_with, fmt_size, fmt_throughput,
+};
+use crate::output::formatter::DisplayOptions;
+use crossterm::style::Stylize;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+/// Processes listed before "... and N more" when the query has no LIMIT
+const PROCESS_DISPLAY_ROWS: usize = 20;
+/// Files listed before "... and N more" when the query has no LIMIT
+const FILE_DISPLAY_ROWS: usize = 50;
 
 pub fn format_human(result: &ExecutionResult) -> String {
+    format_human_with(result, &DisplayOptions::default())
+}
+
+/// Human output with percentages and file times rendered per `options`
+pub fn format_human_with(result: &ExecutionResult, options: &DisplayOptions) -> String {
+    let precision = options.precision;
     match &result.data {
         ResultData::Cpu(info) => {
             format!(
                 "CPU Information\n\
                  ---------------\n\
                  Cores:     {}\n\
-                 Usage:     {:.1}%\n\
+                 Usage:     {}\n\
                  Brand:     {}\n\
                  Frequency: {} MHz",
-                info.cores, info.usage, info.brand, info.frequency
+                info.cores,
+                fmt_percent_with(info.usage.into(), precision),
+                info.brand,
+                info.frequency
             )
         }
         ResultData::Memory(info) => {
@@ -24,25 +48,30 @@ pub fn format_human(result: &ExecutionResult) -> String {
                  Used:      {}\n\
                  Free:      {}\n\
                  Available: {}\n\
-                 Usage:     {:.1}%",
-                ByteSize(info.total),
-                ByteSize(info.used),
-                ByteSize(info.free),
-                ByteSize(info.available),
-                info.usage_percent
+                 Usage:     {}\n\
+                 Swap:      {} / {}\n\
+                 Pressure:  {}",
+                fmt_size(info.total),
+                fmt_size(info.used),
+                fmt_size(info.free),
+                fmt_size(info.available),
+                fmt_percent_with(info.usage_percent, precision),
+                fmt_size(info.swap_used),
+                fmt_size(info.swap_total),
+                styled_pressure(info.pressure)
             )
         }
         ResultData::Disk(info) => {
             let mut output = String::from("Disk Information\n----------------\n");
             for disk in &info.disks {
                 output.push_str(&format!(
-                    "\n{} ({})\n  Total: {} | Used: {} | Free: {} | Usage: {:.1}%\n",
+                    "\n{} ({})\n  Total: {} | Used: {} | Free: {} | Usage: {}\n",
                     disk.mount_point,
                     disk.file_system,
-                    ByteSize(disk.total),
-                    ByteSize(disk.used),
-                    ByteSize(disk.free),
-                    disk.usage_percent
+                    fmt_size(disk.total),
+                    fmt_size(disk.used),
+                    fmt_size(disk.free),
+                    fmt_percent_with(disk.usage_percent, precision)
                 ));
             }
             output
@@ -51,30 +80,44 @@ pub fn format_human(result: &ExecutionResult) -> String {
             let mut output = String::from("Network Interfaces\n------------------\n");
             for iface in &info.interfaces {
                 output.push_str(&format!(
-                    "\n{}\n  Received: {} | Transmitted: {}\n",
+                    "\n{}{}\n  Received: {} | Transmitted: {}\n",
                     iface.name,
-                    ByteSize(iface.received),
-                    ByteSize(iface.transmitted)
+                    if iface.up { "" } else { " (down)" },
+                    fmt_size(iface.received),
+                    fmt_size(iface.transmitted)
                 ));
+                if let (Some(rx), Some(tx)) = (iface.received_delta, iface.transmitted_delta) {
+                    let rate = |bytes| match info.delta_secs {
+                        Some(secs) => format!(
+                            " ({})",
+                            fmt_throughput(bytes, Duration::from_secs_f64(secs))
+                        ),
+                        None => String::new(),
+                    };
+                    output.push_str(&format!(
+                        "  Since last: +{}{} received | +{}{} transmitted\n",
+                        fmt_size(rx),
+                        rate(rx),
+                        fmt_size(tx),
+                        rate(tx)
+                    ));
+                }
             }
             output
         }
         ResultData::System(info) => {
-            let uptime_hours = info.uptime / 3600;
-            let uptime_mins = (info.uptime % 3600) / 60;
             format!(
                 "System Information\n\
                  ------------------\n\
                  Hostname:       {}\n\
                  OS:             {} {}\n\
                  Kernel:         {}\n\
-                 Uptime:         {}h {}m",
+                 Uptime:         {}",
                 info.hostname,
                 info.os_name,
                 info.os_version,
                 info.kernel_version,
-                uptime_hours,
-                uptime_mins
+                fmt_duration(info.uptime)
             )
         }
         ResultData::Battery(info) => {
@@ -84,10 +127,10 @@ pub fn format_human(result: &ExecutionResult) -> String {
             let mut output = String::from("Battery Information\n-------------------\n");
             for (i, battery) in info.batteries.iter().enumerate() {
                 output.push_str(&format!(
-                    "\nBattery {}\n  State: {} | Charge: {:.1}%",
+                    "\nBattery {}\n  State: {} | Charge: {}",
                     i + 1,
                     battery.state,
-                    battery.percentage
+                    fmt_percent_with(battery.percentage.into(), precision)
                 ));
                 if let Some(ref time) = battery.time_to_empty {
                     output.push_str(&format!(" | Time to empty: {}", time));
@@ -103,69 +146,173 @@ pub fn format_human(result: &ExecutionResult) -> String {
             if processes.is_empty() {
                 return "No matching processes found".to_string();
             }
+            // Only counted when asked for, so the column is too
+            let open_files = processes.iter().any(|p| p.open_files.is_some());
+            let count = |n: Option<u32>| n.map_or_else(|| "-".to_string(), |n| n.to_string());
+
             let mut output = String::from("Processes\n---------\n");
             output.push_str(&format!(
-                "{:<8} {:<20} {:>8} {:>12}\n",
-                "PID", "NAME", "CPU%", "MEMORY"
+                "{:<8} {:<20} {:>8} {:>12} {:>8}",
+                "PID", "NAME", "CPU%", "MEMORY", "THREADS"
             ));
-            output.push_str(&"-".repeat(52));
+            if open_files {
+                output.push_str(&format!(" {:>10}", "OPEN FILES"));
+            }
             output.push('\n');
-            for proc in processes.iter().take(20) {
+            output.push_str(&"-".repeat(if open_files { 72 } else { 61 }));
+            output.push('\n');
+            let shown = result
+                .limit
+                .map_or(PROCESS_DISPLAY_ROWS, |_| processes.len());
+            for proc in processes.iter().take(shown) {
                 output.push_str(&format!(
-                    "{:<8} {:<20} {:>7.1}% {:>12}\n",
+                    "{:<8} {:<20} {:>8} {:>12} {:>8}",
                     proc.pid,
                     truncate(&proc.name, 20),
-                    proc.cpu,
-                    ByteSize(proc.memory)
+                    fmt_percent_with(proc.cpu.into(), precision),
+                    fmt_size(proc.memory),
+                    count(proc.threads)
                 ));
+                if open_files {
+                    output.push_str(&format!(" {:>10}", count(proc.open_files)));
+                }
+                output.push('\n');
             }
-            if processes.len() > 20 {
+            if processes.len() > shown {
                 output.push_str(&format!(
                     "\n... and {} more processes\n",
-                    processes.len() - 20
+                    processes.len() - shown
                 ));
             }
             output
         }
+        ResultData::SelfProc(info) => {
+            format!(
+                "Arta Process\n\
+                 ------------\n\
+                 PID:            {}\n\
+                 Name:           {}\n\
+                 CPU:            {}\n\
+                 Memory:         {}\n\
+                 Virtual Memory: {}\n\
+                 Uptime:         {}",
+                info.pid,
+                info.name,
+                fmt_percent_with(info.cpu.into(), precision),
+                fmt_size(info.memory),
+                fmt_size(info.virtual_memory),
+                fmt_duration(info.uptime)
+            )
+        }
+        ResultData::Projection(projection) => {
+            let rows = match projection {
+                Projection::Single(row) => std::slice::from_ref(row),
+                Projection::Rows(rows) => rows.as_slice(),
+            };
+            let value = |value: &FieldValue| match value {
+                FieldValue::Time(t) => fmt_file_time(*t, options.zone),
+                value => format!("{:.*}", precision, value),
+            };
+            // A single value is printed bare, for `$(arta query "SELECT CPU usage")`
+            if let [row] = rows {
+                if let [(_, single)] = row.0.as_slice() {
+                    return value(single);
+                }
+            }
+            rows.iter()
+                .map(|row| {
+                    row.0
+                        .iter()
+                        .map(|(name, field)| {
+                            format!("{:<16} {}", format!("{}:", name), value(field))
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        }
         ResultData::Files(files) => {
             if files.is_empty() {
                 return "No files found".to_string();
             }
+            // Ownership columns only when the query looked them up
+            let verbose = files.iter().any(|f| f.mode.is_some());
             let mut output = String::from("Files\n-----\n");
-            output.push_str(&format!(
-                "{:<30} {:>12} {:<20}\n",
-                "NAME", "SIZE", "MODIFIED"
-            ));
-            output.push_str(&"-".repeat(64));
+            output.push_str(&format!("{:<30} {:>12} {:<20}", "NAME", "SIZE", "MODIFIED"));
+            if verbose {
+                output.push_str(&format!(" {:<10} {:<12} {:<12}", "MODE", "OWNER", "GROUP"));
+            }
             output.push('\n');
-            for file in files.iter().take(50) {
-                let name = if file.is_dir {
-                    format!("{}/", file.name)
-                } else {
-                    file.name.clone()
+            output.push_str(&"-".repeat(if verbose { 101 } else { 64 }));
+            output.push('\n');
+            let shown = result.limit.map_or(FILE_DISPLAY_ROWS, |_| files.len());
+            for file in files.iter().take(shown) {
+                let name = match (&file.symlink_target, file.is_dir) {
+                    (Some(target), _) => format!("{} -> {}", file.name, target),
+                    (None, true) => format!("{}/", file.name),
+                    (None, false) => file.name.clone(),
                 };
                 output.push_str(&format!(
-                    "{:<30} {:>12} {:<20}\n",
+                    "{:<30} {:>12} {:<20}",
                     truncate(&name, 30),
-                    if file.is_dir {
-                        "-".to_string()
-                    } else {
-                        ByteSize(file.size).to_string()
+                    match (file.is_dir, file.dir_size) {
+                        (true, Some(size)) => fmt_size(size),
+                        (true, None) => "-".to_string(),
+                        (false, _) => fmt_size(file.size),
                     },
-                    file.modified.as_deref().unwrap_or("-")
+                    file.modified
+                        .map(|t| fmt_file_time(t, options.zone))
+                        .unwrap_or_else(|| "-".to_string())
                 ));
+                if verbose {
+                    output.push_str(&format!(
+                        " {:<10} {:<12} {:<12}",
+                        file.mode.as_deref().unwrap_or("-"),
+                        truncate(file.owner.as_deref().unwrap_or("-"), 12),
+                        truncate(file.group.as_deref().unwrap_or("-"), 12)
+                    ));
+                }
+                output.push('\n');
+            }
+            if files.len() > shown {
+                output.push_str(&format!("\n... and {} more files\n", files.len() - shown));
+            }
+            output
+        }
+        ResultData::Services(services) => {
+            if services.is_empty() {
+                return "No matching services found".to_string();
             }
-            if files.len() > 50 {
-                output.push_str(&format!("\n... and {} more files\n", files.len() - 50));
+            let mut output = String::from("Services\n--------\n");
+            output.push_str(&format!(
+                "{:<32} {:<10} {:<10} {:<10} {:>8}\n",
+                "NAME", "ACTIVE", "SUB", "ENABLED", "PID"
+            ));
+            output.push_str(&"-".repeat(74));
+            output.push('\n');
+            for service in services {
+                output.push_str(&format!(
+                    "{:<32} {:<10} {:<10} {:<10} {:>8}\n",
+                    truncate(&service.name, 32),
+                    service.active_state,
+                    service.sub_state,
+                    service.enabled,
+                    service
+                        .pid
+                        .map_or_else(|| "-".to_string(), |pid| pid.to_string())
+                ));
             }
             output
         }
         ResultData::Content(content) => {
             let mut output = format!(
-                "File: {}\nSize: {} | Lines: {}\n{}\n",
+                "File: {}\nSize: {} | Lines: {}{}\n{}\n",
                 content.file_path,
-                ByteSize(content.file_size),
+                fmt_size(content.file_size),
                 content.total_lines,
+                // Reading stopped early, so there are at least this many lines
+                if content.scan_truncated { "+" } else { "" },
                 "-".repeat(60)
             );
             for line in &content.lines {
@@ -180,6 +327,29 @@ pub fn format_human(result: &ExecutionResult) -> String {
             }
             output
         }
+        ResultData::ContentStats(stats) => format!(
+            "File: {}\nLines: {}{}\nWords: {}\nChars: {}\nBytes: {}\n",
+            stats.file_path,
+            stats.lines,
+            // Reading stopped early, so these are lower bounds
+            if stats.scan_truncated { "+" } else { "" },
+            stats.words,
+            stats.chars,
+            stats.bytes
+        ),
+        ResultData::Diff(diff) => format_diff(diff),
+        ResultData::Custom(result) => {
+            let mut output = format!("{}\n{}\n", result.target, "-".repeat(result.target.len()));
+            match &result.value {
+                serde_json::Value::Object(object) => {
+                    for (key, value) in object {
+                        output.push_str(&format!("{}: {}\n", key, format_json_scalar(value)));
+                    }
+                }
+                value => output.push_str(&format_json_scalar(value)),
+            }
+            output
+        }
         ResultData::ActionResult(action) => {
             let mut output = format!(
                 "{} Result\n{}\n",
@@ -195,6 +365,97 @@ pub fn format_human(result: &ExecutionResult) -> String {
             }
             output
         }
+        ResultData::Audit(log) => {
+            if log.entries.is_empty() {
+                return format!("No actions recorded in {}", log.path);
+            }
+            let title = format!("Audit Log ({})", log.path);
+            let mut output = format!("{}\n{}\n", title, "-".repeat(title.len()));
+            for entry in &log.entries {
+                let mut line = format!(
+                    "{}  {:<12}  {} items",
+                    entry.timestamp, entry.action, entry.affected_count
+                );
+                if entry.affected_bytes > 0 {
+                    line.push_str(&format!(" ({})", fmt_size(entry.affected_bytes)));
+                }
+                if entry.dry_run {
+                    line.push_str("  [DRY RUN]");
+                }
+                if let Some(error) = &entry.error {
+                    line.push_str(&format!("  FAILED: {}", error));
+                }
+                output.push_str(&line);
+                output.push('\n');
+            }
+            output
+        }
+        ResultData::Version(info) => {
+            let or_unknown =
+                |v: &Option<String>| v.clone().unwrap_or_else(|| "unknown".to_string());
+            format!(
+                "Arta {}\n\
+                 Git SHA:    {}\n\
+                 Build date: {}\n\
+                 Features:   {}",
+                info.version,
+                or_unknown(&info.git_sha),
+                or_unknown(&info.build_date),
+                if info.features.is_empty() {
+                    "none".to_string()
+                } else {
+                    info.features.join(", ")
+                }
+            )
+        }
+        ResultData::Config(info) => {
+            let on_off = |v: bool| if v { "on" } else { "off" };
+            let list = |names: &[String]| {
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            };
+            format!(
+                "Configuration\n\
+                 -------------\n\
+                 Dry run:            {}\n\
+                 Allow actions:      {}\n\
+                 Output format:      {}\n\
+                 Verbose:            {}\n\
+                 Explain matches:    {}\n\
+                 Timestamps:         {}\n\
+                 Full process names: {}\n\
+                 Time zone:          {}\n\
+                 JSON units:         {}\n\
+                 Strict fields:      {}\n\
+                 All mounts:         {}\n\
+                 Audit log:          {}\n\
+                 Precision:          {}\n\
+                 Max line size:      {}\n\
+                 Max scan size:      {}\n\
+                 Custom targets:     {}\n\
+                 Custom formatters:  {}",
+                on_off(info.dry_run),
+                on_off(info.allow_actions),
+                info.output_format,
+                on_off(info.verbose),
+                on_off(info.explain_matches),
+                on_off(info.timestamps),
+                on_off(info.full_process_names),
+                info.timezone,
+                on_off(info.json_units),
+                on_off(info.strict_fields),
+                on_off(info.all_mounts),
+                info.audit_log.as_deref().unwrap_or("off"),
+                info.precision,
+                fmt_size(info.content_max_line_bytes as u64),
+                fmt_size(info.content_max_scan_bytes),
+                list(&info.targets),
+                list(&info.formatters)
+            )
+        }
         ResultData::ContextInfo(info) => {
             let mut output = String::new();
 
@@ -230,6 +491,19 @@ pub fn format_human(result: &ExecutionResult) -> String {
                 }
             }
 
+            if !info.stack.is_empty() {
+                if !output.is_empty() {
+                    output.push('\n');
+                }
+                output.push_str("Folder Stack\n");
+                output.push_str("------------\n");
+                let current = info.stack.len() - 1;
+                for (index, folder) in info.stack.iter().enumerate() {
+                    let marker = if index == current { "  (current)" } else { "" };
+                    output.push_str(&format!("  {}: {}{}\n", index, folder, marker));
+                }
+            }
+
             if output.is_empty() {
                 output = "No context information".to_string();
             }
@@ -238,13 +512,19 @@ pub fn format_human(result: &ExecutionResult) -> String {
         }
         ResultData::Explanation(explanation) => explanation.clone(),
         ResultData::Message(msg) => msg.clone(),
+        ResultData::Print(values) => values
+            .iter()
+            .map(format_json_scalar)
+            .collect::<Vec<_>>()
+            .join(" "),
+        ResultData::Boolean(value) => value.to_string(),
         ResultData::Multiple(results) => {
             let mut output = String::new();
             for (i, res) in results.iter().enumerate() {
                 if i > 0 {
                     output.push_str("\n---\n\n");
                 }
-                output.push_str(&format_human(res));
+                output.push_str(&format_human_with(res, options));
             }
             output
         }
@@ -284,6 +564,14 @@ pub fn format_human(result: &ExecutionResult) -> String {
     }
 }
 
+/// Show strings without quotes, everything else as compact JSON
+fn format_json_scalar(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
 fn truncate(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         s.to_string()
@@ -291,3 +579,51 @@ fn truncate(s: &str, max_len: usize) -> String {
         format!("{}...", &s[..max_len.saturating_sub(3)])
     }
 }
+
+/// Unified diff layout, with +/- lines colored when stdout is a terminal
+fn format_diff(diff: &DiffInfo) -> String {
+    if diff.is_identical() {
+        return format!("{} and {} are identical\n", diff.old_path, diff.new_path);
+    }
+
+    let color = color_enabled(std::io::stdout().is_terminal());
+    let mut output = format!("--- {}\n+++ {}\n", diff.old_path, diff.new_path);
+    for hunk in &diff.hunks {
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+        ));
+        for line in &hunk.lines {
+            output.push_str(&styled_diff_line(line, color));
+            output.push('\n');
+        }
+    }
+    output.push_str(&format!(
+        "\n{} added, {} removed\n",
+        diff.added, diff.removed
+    ));
+    output
+}
+
+fn styled_diff_line(line: &DiffLine, color: bool) -> String {
+    let text = format!("{}{}", line.kind.marker(), line.text);
+    match line.kind {
+        DiffLineKind::Added if color => text.green().to_string(),
+        DiffLineKind::Removed if color => text.red().to_string(),
+        _ => text,
+    }
+}
+
+/// Memory pressure, colored by severity when stdout is a terminal
+fn styled_pressure(pressure: MemoryPressure) -> String {
+    let text = pressure.to_string();
+    if !color_enabled(std::io::stdout().is_terminal()) {
+        return text;
+    }
+    match pressure {
+        MemoryPressure::Low => text.green().to_string(),
+        MemoryPressure::Medium => text.yellow().to_string(),
+        MemoryPressure::High => text.red().to_string(),
+        MemoryPressure::Critical => text.red().bold().to_string(),
+    }
+}
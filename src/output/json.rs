@@ -1,10 +1,31 @@
 //! JSON output formatting
 
 use crate::engine::executor::{ExecutionResult, ResultData};
+use crate::engine::fields::{ProjectedRow, Projection};
+use serde::Serialize;
 use serde_json::{json, Value};
+use std::io::{self, Write};
+
+/// List results longer than this are streamed even without `--stream`
+pub const STREAM_ROW_THRESHOLD: usize = 1000;
 
 pub fn format_json(result: &ExecutionResult) -> String {
-    let data: Value = match &result.data {
+    serde_json::to_string_pretty(&json_value(result)).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Format a result on a single line, for embedding in PRINT output
+///
+/// Messages and explanations are printed as plain text; everything else is
+/// written as compact JSON.
+pub fn format_json_compact(result: &ExecutionResult) -> String {
+    match &result.data {
+        ResultData::Message(s) | ResultData::Explanation(s) => s.clone(),
+        _ => serde_json::to_string(&json_value(result)).unwrap_or_else(|_| "{}".to_string()),
+    }
+}
+
+pub(crate) fn json_value(result: &ExecutionResult) -> Value {
+    match &result.data {
         ResultData::Cpu(info) => serde_json::to_value(info).unwrap_or(json!(null)),
         ResultData::Memory(info) => serde_json::to_value(info).unwrap_or(json!(null)),
         ResultData::Disk(info) => serde_json::to_value(info).unwrap_or(json!(null)),
@@ -12,22 +33,287 @@ pub fn format_json(result: &ExecutionResult) -> String {
         ResultData::System(info) => serde_json::to_value(info).unwrap_or(json!(null)),
         ResultData::Battery(info) => serde_json::to_value(info).unwrap_or(json!(null)),
         ResultData::Processes(info) => serde_json::to_value(info).unwrap_or(json!(null)),
+        ResultData::SelfProc(info) => serde_json::to_value(info).unwrap_or(json!(null)),
         ResultData::Files(info) => serde_json::to_value(info).unwrap_or(json!(null)),
+        ResultData::Services(info) => serde_json::to_value(info).unwrap_or(json!(null)),
+        ResultData::Projection(info) => serde_json::to_value(info).unwrap_or(json!(null)),
         ResultData::Content(info) => serde_json::to_value(info).unwrap_or(json!(null)),
+        ResultData::ContentStats(info) => serde_json::to_value(info).unwrap_or(json!(null)),
+        ResultData::Diff(info) => serde_json::to_value(info).unwrap_or(json!(null)),
+        ResultData::Custom(result) => result.value.clone(),
         ResultData::ActionResult(info) => serde_json::to_value(info).unwrap_or(json!(null)),
         ResultData::ContextInfo(info) => serde_json::to_value(info).unwrap_or(json!(null)),
+        ResultData::Version(info) => serde_json::to_value(info).unwrap_or(json!(null)),
+        ResultData::Config(info) => serde_json::to_value(info).unwrap_or(json!(null)),
+        ResultData::Audit(info) => serde_json::to_value(info).unwrap_or(json!(null)),
         ResultData::Explanation(s) => json!({ "explanation": s }),
         ResultData::Message(s) => json!({ "message": s }),
+        ResultData::Print(values) => json!({ "print": values }),
+        ResultData::Boolean(value) => json!({ "result": value }),
         ResultData::Multiple(results) => {
-            let items: Vec<Value> = results
-                .iter()
-                .map(|r| serde_json::from_str(&format_json(r)).unwrap_or(json!(null)))
-                .collect();
+            let items: Vec<Value> = results.iter().map(json_value).collect();
             json!({ "results": items })
         }
         ResultData::Empty => json!({ "empty": true }),
         ResultData::ContainerResult(info) => serde_json::to_value(info).unwrap_or(json!(null)),
-    };
+    }
+}
+
+/// JSON for a result with sizes and percentages as `{"value": ..., "unit": ...}`
+///
+/// Used by `--json-units`. Full CPU, MEMORY, DISK, NETWORK, BATTERY, FILES,
+/// PROCESS and SELF results are annotated by field name, projections by the type of each
+/// value; other results and missing values are left as they are.
+pub fn json_value_with_units(result: &ExecutionResult) -> Value {
+    match &result.data {
+        ResultData::Multiple(results) => {
+            let items: Vec<Value> = results.iter().map(json_value_with_units).collect();
+            return json!({ "results": items });
+        }
+        ResultData::Projection(Projection::Single(row)) => return row_with_units(row),
+        ResultData::Projection(Projection::Rows(rows)) => {
+            return Value::Array(rows.iter().map(row_with_units).collect());
+        }
+        _ => {}
+    }
+
+    let mut value = json_value(result);
+    annotate_units(&mut value, field_units(&result.data));
+    value
+}
+
+/// Unit of each numeric field of a result, by JSON key
+pub(crate) fn field_units(data: &ResultData) -> &'static [(&'static str, &'static str)] {
+    match data {
+        ResultData::Cpu(_) => &[("usage", "percent")],
+        ResultData::Memory(_) => &[
+            ("total", "bytes"),
+            ("used", "bytes"),
+            ("free", "bytes"),
+            ("available", "bytes"),
+            ("swap_total", "bytes"),
+            ("swap_used", "bytes"),
+            ("usage_percent", "percent"),
+        ],
+        ResultData::Disk(_) => &[
+            ("total", "bytes"),
+            ("used", "bytes"),
+            ("free", "bytes"),
+            ("usage_percent", "percent"),
+        ],
+        ResultData::Network(_) => &[
+            ("received", "bytes"),
+            ("transmitted", "bytes"),
+            ("received_delta", "bytes"),
+            ("transmitted_delta", "bytes"),
+        ],
+        ResultData::Battery(_) => &[("percentage", "percent")],
+        ResultData::Files(_) => &[("size", "bytes"), ("dir_size", "bytes")],
+        ResultData::Processes(_) => &[("memory", "bytes"), ("cpu", "percent")],
+        ResultData::SelfProc(_) => &[
+            ("memory", "bytes"),
+            ("virtual_memory", "bytes"),
+            ("cpu", "percent"),
+        ],
+        _ => &[],
+    }
+}
+
+fn row_with_units(row: &ProjectedRow) -> Value {
+    let object = row
+        .0
+        .iter()
+        .map(|(name, value)| {
+            let json = serde_json::to_value(value).unwrap_or(json!(null));
+            let json = match value.unit() {
+                Some(unit) => json!({ "value": json, "unit": unit }),
+                None => json,
+            };
+            (name.clone(), json)
+        })
+        .collect();
+    Value::Object(object)
+}
+
+fn annotate_units(value: &mut Value, units: &[(&str, &str)]) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                annotate_units(item, units);
+            }
+        }
+        Value::Object(object) => {
+            for (key, field) in object.iter_mut() {
+                match units.iter().find(|(name, _)| name == key) {
+                    Some((_, unit)) if !field.is_null() => {
+                        *field = json!({ "value": field.take(), "unit": unit });
+                    }
+                    Some(_) => {}
+                    None => annotate_units(field, units),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Write a result as JSON, streaming large list results row by row
+///
+/// Process and file listings are written as a compact JSON array when
+/// `stream` is set or they exceed `STREAM_ROW_THRESHOLD` rows; everything
+/// else is pretty-printed as usual.
+pub fn write_json<W: Write>(result: &ExecutionResult, writer: W, stream: bool) -> io::Result<()> {
+    let should_stream = |rows: usize| stream || rows > STREAM_ROW_THRESHOLD;
+
+    match &result.data {
+        ResultData::Processes(rows) if should_stream(rows.len()) => {
+            write_json_array(writer, rows).map(|_| ())
+        }
+        ResultData::Files(rows) if should_stream(rows.len()) => {
+            write_json_array(writer, rows).map(|_| ())
+        }
+        _ => {
+            let mut writer = writer;
+            writeln!(writer, "{}", format_json(result))
+        }
+    }
+}
+
+/// Write rows as a JSON array with one compact object per line
+///
+/// Only one row is serialized at a time, so the full document is never
+/// held in memory. Returns the number of rows written.
+pub fn write_json_array<W, I>(mut writer: W, rows: I) -> io::Result<usize>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    writer.write_all(b"[")?;
+
+    let mut count = 0;
+    for row in rows {
+        if count > 0 {
+            writer.write_all(b",")?;
+        }
+        writer.write_all(b"\n")?;
+        serde_json::to_writer(&mut writer, &row)?;
+        count += 1;
+    }
+
+    if count > 0 {
+        writer.write_all(b"\n")?;
+    }
+    writer.write_all(b"]\n")?;
+    writer.flush()?;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_array_round_trip() {
+        let rows: Vec<Value> = (0..2500)
+            .map(|i| json!({ "pid": i, "name": format!("proc{}", i) }))
+            .collect();
+
+        let mut buffer = Vec::new();
+        let written = write_json_array(&mut buffer, &rows).unwrap();
+        assert_eq!(written, 2500);
+
+        let parsed: Vec<Value> = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(parsed.len(), 2500);
+        assert_eq!(parsed[42]["name"], "proc42");
+    }
+
+    fn memory_result() -> ExecutionResult {
+        use crate::engine::queries::memory::MemoryPressure;
+        use crate::engine::queries::MemoryInfo;
+
+        ExecutionResult::new(ResultData::Memory(MemoryInfo {
+            total: 8589934592,
+            used: 4294967296,
+            free: 4294967296,
+            available: 4294967296,
+            usage_percent: 50.0,
+            swap_total: 0,
+            swap_used: 0,
+            pressure: MemoryPressure::Low,
+        }))
+    }
+
+    #[test]
+    fn test_memory_units() {
+        let json = json_value_with_units(&memory_result());
+        assert_eq!(
+            json["total"],
+            json!({ "value": 8589934592u64, "unit": "bytes" })
+        );
+        assert_eq!(
+            json["usage_percent"],
+            json!({ "value": 50.0, "unit": "percent" })
+        );
+        // Non-numeric fields are left alone
+        assert_eq!(json["pressure"], "low");
+
+        // The flat form stays the default
+        assert_eq!(json_value(&memory_result())["total"], 8589934592u64);
+    }
+
+    #[test]
+    fn test_projection_units() {
+        use crate::engine::fields::FieldValue;
+
+        let row = ProjectedRow(vec![
+            ("total".to_string(), FieldValue::Bytes(1024)),
+            ("used_percent".to_string(), FieldValue::Percent(25.0)),
+            ("pressure".to_string(), FieldValue::Text("low".to_string())),
+        ]);
+        let result = |projection| ExecutionResult::new(ResultData::Projection(projection));
+
+        let json = json_value_with_units(&result(Projection::Single(row.clone())));
+        assert_eq!(json["total"], json!({ "value": 1024, "unit": "bytes" }));
+        assert_eq!(
+            json["used_percent"],
+            json!({ "value": 25.0, "unit": "percent" })
+        );
+        assert_eq!(json["pressure"], "low");
+
+        let json = json_value_with_units(&result(Projection::Rows(vec![row])));
+        assert_eq!(json[0]["total"], json!({ "value": 1024, "unit": "bytes" }));
+    }
+
+    #[test]
+    fn test_units_skip_missing_values() {
+        use crate::engine::executor::FileEntry;
+
+        let result = ExecutionResult::new(ResultData::Files(vec![FileEntry {
+            name: "a.log".to_string(),
+            path: "/tmp/a.log".to_string(),
+            size: 12,
+            is_dir: false,
+            is_symlink: false,
+            symlink_target: None,
+            modified: None,
+            extension: Some("log".to_string()),
+            dir_size: None,
+            owner: None,
+            group: None,
+            mode: None,
+        }]));
+        let json = json_value_with_units(&result);
+        assert_eq!(json[0]["size"], json!({ "value": 12, "unit": "bytes" }));
+        assert!(json[0]["dir_size"].is_null());
+    }
 
-    serde_json::to_string_pretty(&data).unwrap_or_else(|_| "{}".to_string())
+    #[test]
+    fn test_stream_empty_array() {
+        let mut buffer = Vec::new();
+        write_json_array(&mut buffer, Vec::<Value>::new()).unwrap();
+        let parsed: Vec<Value> = serde_json::from_slice(&buffer).unwrap();
+        assert!(parsed.is_empty());
+    }
 }
@@ -1,7 +1,25 @@
 //! Output formatting module
 
+pub mod delimited;
+pub mod format_util;
 pub mod formatter;
 pub mod human;
 pub mod json;
+pub mod properties;
+pub mod registry;
+pub mod sink;
+pub mod timezone;
+pub mod trailer;
 
-pub use formatter::{format_output, OutputFormat};
+pub use formatter::{format_output, format_output_with, DisplayOptions, OutputFormat};
+pub use registry::FormatterRegistry;
+pub use sink::{OutputSink, SharedWriter};
+pub use timezone::DisplayZone;
+
+/// Whether to color output for a stream, honouring `NO_COLOR`
+///
+/// Color is only used on terminals, and never when `NO_COLOR` is set to a
+/// non-empty value (see <https://no-color.org>).
+pub fn color_enabled(is_terminal: bool) -> bool {
+    is_terminal && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
@@ -0,0 +1,319 @@
+//! `KEY=value` output for shell `eval` and `.env` files
+//!
+//! Keys are the JSON field paths of a result, uppercased and joined with `_`
+//! under the target name, e.g. `CPU_USAGE=42.1` or `MEMORY_USED=8589934592`.
+//! List rows are numbered from 0 (`PROCESS_0_PID=...`) and followed by a
+//! `<TARGET>_COUNT` line. Values that are not plain words are single-quoted.
+//! Percentages are rounded to the display precision; other numbers are
+//! written as they are.
+
+use crate::engine::executor::{ExecutionResult, ResultData};
+use crate::engine::fields::{FieldValue, Projection};
+use crate::output::formatter::DisplayOptions;
+use crate::output::json::{field_units, json_value};
+use serde_json::{Number, Value};
+
+pub fn format_properties(result: &ExecutionResult) -> String {
+    format_properties_with(result, &DisplayOptions::default())
+}
+
+/// Properties with percentages rounded to `options.precision` decimals
+pub fn format_properties_with(result: &ExecutionResult, options: &DisplayOptions) -> String {
+    let mut lines = Vec::new();
+    write_result(result, "", options.precision, &mut lines);
+    lines.join("\n")
+}
+
+fn write_result(result: &ExecutionResult, prefix: &str, precision: usize, lines: &mut Vec<String>) {
+    match &result.data {
+        ResultData::Multiple(results) => {
+            for (i, result) in results.iter().enumerate() {
+                let prefix = join(prefix, &format!("RESULT_{}", i));
+                write_result(result, &prefix, precision, lines);
+            }
+        }
+        ResultData::Empty => {}
+        data => {
+            let prefix = join(prefix, target_prefix(data));
+            let percents = Percents {
+                fields: percent_fields(data),
+                precision,
+            };
+            match json_value(result) {
+                // DISK, NETWORK and BATTERY wrap their rows in a single field
+                Value::Object(object)
+                    if object.len() == 1 && object.values().all(Value::is_array) =>
+                {
+                    let rows = object.into_iter().next().map(|(_, rows)| rows);
+                    write_rows(rows.unwrap_or_default(), &prefix, &percents, lines);
+                }
+                value @ Value::Array(_) => write_rows(value, &prefix, &percents, lines),
+                value => write_value(&value, &prefix, &percents, lines),
+            }
+        }
+    }
+}
+
+/// Fields holding percentages, which are rounded to `precision` decimals
+struct Percents<'a> {
+    fields: Vec<&'a str>,
+    precision: usize,
+}
+
+/// JSON keys of a result's percentage fields
+fn percent_fields(data: &ResultData) -> Vec<&str> {
+    let rows = match data {
+        ResultData::Projection(Projection::Single(row)) => std::slice::from_ref(row),
+        ResultData::Projection(Projection::Rows(rows)) => rows.as_slice(),
+        data => {
+            return field_units(data)
+                .iter()
+                .filter(|(_, unit)| *unit == "percent")
+                .map(|(field, _)| *field)
+                .collect();
+        }
+    };
+    rows.iter()
+        .flat_map(|row| &row.0)
+        .filter(|(_, value)| matches!(value, FieldValue::Percent(_)))
+        .map(|(field, _)| field.as_str())
+        .collect()
+}
+
+/// Key prefix for a result; empty where the JSON keys already say what it is
+pub(crate) fn target_prefix(data: &ResultData) -> &str {
+    match data {
+        ResultData::Cpu(_) => "CPU",
+        ResultData::Memory(_) => "MEMORY",
+        ResultData::Disk(_) => "DISK",
+        ResultData::Network(_) => "NETWORK",
+        ResultData::System(_) => "SYSTEM",
+        ResultData::Battery(_) => "BATTERY",
+        ResultData::Processes(_) => "PROCESS",
+        ResultData::SelfProc(_) => "SELF",
+        ResultData::Files(_) => "FILES",
+        ResultData::Services(_) => "SERVICE",
+        ResultData::Content(_) | ResultData::ContentStats(_) => "CONTENT",
+        ResultData::Diff(_) => "DIFF",
+        ResultData::Custom(custom) => &custom.target,
+        ResultData::ActionResult(_) => "ACTION",
+        ResultData::ContextInfo(_) => "CONTEXT",
+        ResultData::Version(_) => "VERSION",
+        ResultData::Config(_) => "CONFIG",
+        ResultData::Audit(_) => "AUDIT",
+        ResultData::ContainerResult(_) => "CONTAINER",
+        // Field lists, messages, PRINT and conditions: `USAGE=`, `MESSAGE=`, `RESULT=`
+        ResultData::Projection(_)
+        | ResultData::Explanation(_)
+        | ResultData::Message(_)
+        | ResultData::Print(_)
+        | ResultData::Boolean(_)
+        | ResultData::Multiple(_)
+        | ResultData::Empty => "",
+    }
+}
+
+fn write_rows(rows: Value, prefix: &str, percents: &Percents, lines: &mut Vec<String>) {
+    let count = rows.as_array().map_or(0, Vec::len);
+    write_value(&rows, prefix, percents, lines);
+    lines.push(format!("{}={}", key(&join(prefix, "COUNT")), count));
+}
+
+fn write_value(value: &Value, prefix: &str, percents: &Percents, lines: &mut Vec<String>) {
+    match value {
+        Value::Object(object) => {
+            for (field, value) in object {
+                let prefix = join(prefix, field);
+                match value {
+                    Value::Number(n) if percents.fields.contains(&field.as_str()) => lines.push(
+                        format!("{}={}", key(&prefix), percent(n, percents.precision)),
+                    ),
+                    value => write_value(value, &prefix, percents, lines),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                write_value(item, &join(prefix, &i.to_string()), percents, lines);
+            }
+        }
+        Value::Null => lines.push(format!("{}=", key(prefix))),
+        Value::Bool(b) => lines.push(format!("{}={}", key(prefix), b)),
+        Value::Number(n) => lines.push(format!("{}={}", key(prefix), n)),
+        Value::String(s) => lines.push(format!("{}={}", key(prefix), quote(s))),
+    }
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    match (prefix.is_empty(), name.is_empty()) {
+        (true, _) => name.to_string(),
+        (_, true) => prefix.to_string(),
+        _ => format!("{}_{}", prefix, name),
+    }
+}
+
+/// Uppercase, with anything but letters and digits replaced by `_`
+fn key(path: &str) -> String {
+    let key: String = path
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if key.is_empty() {
+        "VALUE".to_string()
+    } else {
+        key
+    }
+}
+
+/// A percentage rounded to `precision` decimals (`42.1`, not the widened
+/// `f32` value `42.099998474121094`)
+fn percent(n: &Number, precision: usize) -> String {
+    match n.as_f64() {
+        Some(f) => format!("{:.*}", precision, f),
+        None => n.to_string(),
+    }
+}
+
+/// Single-quote values a shell would split or expand
+fn quote(s: &str) -> String {
+    let plain = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.,:/@%+".contains(c));
+    if plain {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::queries::{CpuInfo, ProcessInfo};
+    use crate::output::format_util::DEFAULT_PRECISION;
+
+    fn result(data: ResultData) -> ExecutionResult {
+        ExecutionResult::new(data)
+    }
+
+    #[test]
+    fn test_cpu_properties() {
+        let cpu = CpuInfo {
+            cores: 8,
+            usage: 42.1,
+            brand: "Test CPU @ 3.0GHz".to_string(),
+            frequency: 3000,
+        };
+        let output = format_properties(&result(ResultData::Cpu(cpu)));
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(lines.contains(&"CPU_CORES=8"));
+        assert!(lines.contains(&"CPU_USAGE=42.1"));
+        assert!(lines.contains(&"CPU_FREQUENCY=3000"));
+        assert!(lines.contains(&"CPU_BRAND='Test CPU @ 3.0GHz'"));
+    }
+
+    #[test]
+    fn test_cpu_properties_precision() {
+        let cpu = ResultData::Cpu(CpuInfo {
+            cores: 8,
+            usage: 42.46,
+            brand: "Test CPU".to_string(),
+            frequency: 3000,
+        });
+        let usage = |precision| {
+            let options = DisplayOptions {
+                precision,
+                ..DisplayOptions::default()
+            };
+            format_properties_with(&result(cpu.clone()), &options)
+                .lines()
+                .find_map(|line| line.strip_prefix("CPU_USAGE="))
+                .map(str::to_string)
+        };
+
+        assert_eq!(usage(0).as_deref(), Some("42"));
+        assert_eq!(usage(3).as_deref(), Some("42.460"));
+        assert_eq!(usage(DEFAULT_PRECISION).as_deref(), Some("42.5"));
+    }
+
+    #[test]
+    fn test_process_list_properties() {
+        let process = |pid: u32, name: &str| ProcessInfo {
+            pid,
+            name: name.to_string(),
+            name_full: None,
+            cpu: 1.5,
+            memory: 8_589_934_592,
+            status: "running".to_string(),
+            threads: None,
+            open_files: None,
+            user: None,
+            uid: None,
+        };
+        let output = format_properties(&result(ResultData::Processes(vec![
+            process(10, "init"),
+            process(42, "it's"),
+        ])));
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(lines.contains(&"PROCESS_0_PID=10"));
+        assert!(lines.contains(&"PROCESS_0_NAME=init"));
+        assert!(lines.contains(&"PROCESS_0_CPU=1.5"));
+        assert!(lines.contains(&"PROCESS_0_MEMORY=8589934592"));
+        // Missing values keep their key, so every row has the same keys
+        assert!(lines.contains(&"PROCESS_0_THREADS="));
+        assert!(lines.contains(&"PROCESS_1_PID=42"));
+        assert!(lines.contains(&r"PROCESS_1_NAME='it'\''s'"));
+        assert_eq!(lines.last(), Some(&"PROCESS_COUNT=2"));
+    }
+
+    #[test]
+    fn test_only_percentages_are_rounded() {
+        use crate::engine::executor::CustomResult;
+        use crate::engine::fields::ProjectedRow;
+
+        let options = DisplayOptions {
+            precision: 0,
+            ..DisplayOptions::default()
+        };
+        let custom = result(ResultData::Custom(CustomResult {
+            target: "gpu".to_string(),
+            value: serde_json::json!({ "temperature": 61.75 }),
+        }));
+        assert_eq!(
+            format_properties_with(&custom, &options),
+            "GPU_TEMPERATURE=61.75"
+        );
+
+        let projected = result(ResultData::Projection(Projection::Single(ProjectedRow(
+            vec![
+                ("usage".to_string(), FieldValue::Percent(42.46)),
+                ("frequency".to_string(), FieldValue::Megahertz(3000)),
+            ],
+        ))));
+        assert_eq!(
+            format_properties_with(&projected, &options),
+            "FREQUENCY=3000\nUSAGE=42"
+        );
+    }
+
+    #[test]
+    fn test_unprefixed_results() {
+        assert_eq!(
+            format_properties(&result(ResultData::Boolean(true))),
+            "RESULT=true"
+        );
+        assert_eq!(
+            format_properties(&result(ResultData::Message("done".to_string()))),
+            "MESSAGE=done"
+        );
+        assert_eq!(format_properties(&result(ResultData::Empty)), "");
+    }
+}
@@ -0,0 +1,150 @@
+//! Custom output formatters registered by library users
+//!
+//! Embedders can render results in their own shape (an internal log format,
+//! say) without patching the built-in formatters. A registered formatter is
+//! selected with `--format custom:<name>`.
+//!
+//! ```
+//! use arta::{execute_command, format_output, parse_command, ExecutionContext};
+//!
+//! let mut ctx = ExecutionContext::default()
+//!     .with_formatter("short", |result| format!("{:?}", result.data).len().to_string());
+//! ctx.output_format = ctx.formatters.resolve("custom:short").unwrap();
+//!
+//! let result = execute_command(&parse_command("PRINT \"hi\"").unwrap(), &ctx).unwrap();
+//! println!("{}", format_output(&result, &ctx.output_format));
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::engine::executor::ExecutionResult;
+use crate::error::{ArtaError, Result};
+use crate::output::formatter::OutputFormat;
+
+/// Function rendering a result as text
+pub type FormatterFn = Arc<dyn Fn(&ExecutionResult) -> String + Send + Sync>;
+
+/// A registered formatter, carried by `OutputFormat::Custom`
+#[derive(Clone)]
+pub struct CustomFormatter {
+    pub name: String,
+    format: FormatterFn,
+}
+
+impl CustomFormatter {
+    pub fn format(&self, result: &ExecutionResult) -> String {
+        (self.format)(result)
+    }
+}
+
+impl PartialEq for CustomFormatter {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for CustomFormatter {}
+
+impl std::fmt::Debug for CustomFormatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "custom:{}", self.name)
+    }
+}
+
+/// Custom formatters, keyed by case-insensitive name
+#[derive(Clone, Default)]
+pub struct FormatterRegistry {
+    formatters: HashMap<String, CustomFormatter>,
+}
+
+impl FormatterRegistry {
+    /// Register (or replace) a custom formatter
+    pub fn register<F>(&mut self, name: &str, format: F)
+    where
+        F: Fn(&ExecutionResult) -> String + Send + Sync + 'static,
+    {
+        let name = name.to_lowercase();
+        self.formatters.insert(
+            name.clone(),
+            CustomFormatter {
+                name,
+                format: Arc::new(format),
+            },
+        );
+    }
+
+    /// Look up a custom formatter by name
+    pub fn get(&self, name: &str) -> Option<&CustomFormatter> {
+        self.formatters.get(&name.to_lowercase())
+    }
+
+    /// Registered formatter names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.formatters.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Turn a `--format` value (`human`, `json`, `props`, `csv`, `tsv` or
+    /// `custom:<name>`) into a format
+    pub fn resolve(&self, spec: &str) -> Result<OutputFormat> {
+        if let Some(name) = spec.strip_prefix("custom:") {
+            return self
+                .get(name)
+                .cloned()
+                .map(OutputFormat::Custom)
+                .ok_or_else(|| {
+                    ArtaError::ExecutionError(format!("Unknown output formatter '{}'", name))
+                });
+        }
+
+        match spec.to_lowercase().as_str() {
+            "human" => Ok(OutputFormat::Human),
+            "json" => Ok(OutputFormat::Json),
+            "props" | "properties" => Ok(OutputFormat::Properties),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(ArtaError::ExecutionError(format!(
+                "Unknown output format '{}' (expected human, json, props, csv, tsv or custom:<name>)",
+                spec
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Debug for FormatterRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::executor::ResultData;
+    use crate::output::format_output;
+
+    #[test]
+    fn test_custom_formatter_is_used() {
+        let mut registry = FormatterRegistry::default();
+        registry.register("Log", |result| match &result.data {
+            ResultData::Message(msg) => format!("level=info msg={:?}", msg),
+            _ => "level=info".to_string(),
+        });
+
+        let format = registry.resolve("custom:log").unwrap();
+        let result = ExecutionResult::new(ResultData::Message("hello".to_string()));
+        assert_eq!(format_output(&result, &format), "level=info msg=\"hello\"");
+    }
+
+    #[test]
+    fn test_resolve_format() {
+        let registry = FormatterRegistry::default();
+        assert_eq!(registry.resolve("json").unwrap(), OutputFormat::Json);
+        assert_eq!(registry.resolve("Human").unwrap(), OutputFormat::Human);
+        assert_eq!(registry.resolve("tsv").unwrap(), OutputFormat::Tsv);
+        assert!(registry.resolve("custom:missing").is_err());
+        assert!(registry.resolve("yaml").is_err());
+    }
+}
@@ -0,0 +1,350 @@
+//! Output sink shared by the CLI, script runner and LIFE monitor
+//!
+//! Everything Arta prints for a result goes through `OutputSink`, so options
+//! such as `--timestamps` are applied in one place instead of per formatter.
+
+use crate::engine::executor::{ExecutionContext, ExecutionResult};
+use crate::output::format_util::DEFAULT_PRECISION;
+use crate::output::formatter::{format_output_with, DisplayOptions, OutputFormat};
+use crate::output::json::{json_value, json_value_with_units, write_json};
+use crate::output::timezone::DisplayZone;
+use crate::output::trailer::Trailer;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// Writer shared by the clones of an `ExecutionContext`; stdout by default
+///
+/// Commands that print as they go (EVERY, FOLLOW, scripts, LIFE) write here,
+/// so an embedder can capture their output with `ExecutionContext::with_output`.
+#[derive(Clone)]
+pub struct SharedWriter(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl SharedWriter {
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        Self(Arc::new(Mutex::new(Box::new(writer))))
+    }
+
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl Default for SharedWriter {
+    fn default() -> Self {
+        Self::stdout()
+    }
+}
+
+impl std::fmt::Debug for SharedWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedWriter")
+    }
+}
+
+impl Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).flush()
+    }
+}
+
+/// Writes formatted results and messages to an underlying writer
+pub struct OutputSink<W: Write> {
+    writer: W,
+    format: OutputFormat,
+    timestamps: bool,
+    zone: DisplayZone,
+    stream: bool,
+    units: bool,
+    precision: usize,
+}
+
+impl OutputSink<io::Stdout> {
+    /// Sink writing to stdout with the context's format, timestamp and zone settings
+    pub fn stdout(ctx: &ExecutionContext) -> Self {
+        OutputSink::new(io::stdout(), ctx.output_format.clone()).configured(ctx)
+    }
+}
+
+impl OutputSink<SharedWriter> {
+    /// Sink writing to the context's output, with its format and other settings
+    pub fn for_context(ctx: &ExecutionContext) -> Self {
+        OutputSink::new(ctx.output.clone(), ctx.output_format.clone()).configured(ctx)
+    }
+}
+
+impl<W: Write> OutputSink<W> {
+    pub fn new(writer: W, format: OutputFormat) -> Self {
+        Self {
+            writer,
+            zone: DisplayZone::default_for(&format),
+            format,
+            timestamps: false,
+            stream: false,
+            units: false,
+            precision: DEFAULT_PRECISION,
+        }
+    }
+
+    /// Apply the context's timestamp, zone, unit and precision settings
+    fn configured(self, ctx: &ExecutionContext) -> Self {
+        self.with_timestamps(ctx.timestamps)
+            .with_timezone(ctx.display_zone())
+            .with_units(ctx.json_units)
+            .with_precision(ctx.precision)
+    }
+
+    /// Prefix human output lines with the time, or add a `timestamp` field to JSON
+    pub fn with_timestamps(mut self, timestamps: bool) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Render timestamps in this zone instead of the format's default
+    pub fn with_timezone(mut self, zone: DisplayZone) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    /// Stream list results as a compact JSON array (ignored with timestamps)
+    pub fn with_stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Annotate JSON sizes and percentages with their unit (disables streaming)
+    pub fn with_units(mut self, units: bool) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Decimal places of percentages in human and properties output
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Write a formatted result
+    pub fn write_result(&mut self, result: &ExecutionResult) -> io::Result<()> {
+        if !self.timestamps {
+            return match self.format {
+                OutputFormat::Json if self.units => {
+                    let value = json_value_with_units(result);
+                    self.write_value(&value)
+                }
+                OutputFormat::Json => write_json(result, &mut self.writer, self.stream),
+                _ => writeln!(
+                    self.writer,
+                    "{}",
+                    format_output_with(result, &self.format, &self.display_options())
+                ),
+            };
+        }
+
+        let now = self.timestamp();
+        match self.format {
+            OutputFormat::Json => {
+                let value = if self.units {
+                    json_value_with_units(result)
+                } else {
+                    json_value(result)
+                };
+                let stamped = match value {
+                    serde_json::Value::Object(mut object) => {
+                        object.insert("timestamp".to_string(), now.into());
+                        serde_json::Value::Object(object)
+                    }
+                    data => serde_json::json!({ "timestamp": now, "data": data }),
+                };
+                self.write_value(&stamped)
+            }
+            _ => {
+                let text = format_output_with(result, &self.format, &self.display_options());
+                self.write_stamped(&text, &now)
+            }
+        }
+    }
+
+    /// Write a plain message line, such as progress or timing notes
+    pub fn write_line(&mut self, text: &str) -> io::Result<()> {
+        if self.timestamps {
+            self.write_stamped(text, &self.timestamp())
+        } else {
+            writeln!(self.writer, "{}", text)
+        }
+    }
+
+    /// Write a `--trailer` summary; never timestamped, so it stays parseable
+    pub fn write_trailer(&mut self, trailer: &Trailer) -> io::Result<()> {
+        writeln!(self.writer, "{}", trailer.to_json_line())
+    }
+
+    /// Consume the sink and return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_value(&mut self, value: &serde_json::Value) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "{}",
+            serde_json::to_string_pretty(value).unwrap_or_else(|_| "{}".to_string())
+        )
+    }
+
+    fn display_options(&self) -> DisplayOptions {
+        DisplayOptions {
+            precision: self.precision,
+            zone: self.zone,
+        }
+    }
+
+    /// Current time as ISO-8601, e.g. `2024-05-01T12:30:00.123+02:00`
+    fn timestamp(&self) -> String {
+        self.zone.rfc3339(chrono::Utc::now())
+    }
+
+    fn write_stamped(&mut self, text: &str, now: &str) -> io::Result<()> {
+        if text.is_empty() {
+            return writeln!(self.writer, "{}", now);
+        }
+        for line in text.lines() {
+            writeln!(self.writer, "{} {}", now, line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::executor::ResultData;
+
+    fn message(text: &str) -> ExecutionResult {
+        ExecutionResult::new(ResultData::Message(text.to_string()))
+    }
+
+    fn render(format: OutputFormat, timestamps: bool) -> String {
+        let mut sink = OutputSink::new(Vec::new(), format).with_timestamps(timestamps);
+        sink.write_result(&message("first\nsecond")).unwrap();
+        sink.write_line("done").unwrap();
+        String::from_utf8(sink.into_inner()).unwrap()
+    }
+
+    fn is_timestamp(text: &str) -> bool {
+        chrono::DateTime::parse_from_rfc3339(text).is_ok()
+    }
+
+    #[test]
+    fn test_human_lines_are_prefixed_with_timestamps() {
+        let output = render(OutputFormat::Human, true);
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        for (line, text) in lines.iter().zip(["first", "second", "done"]) {
+            let (stamp, rest) = line.split_once(' ').unwrap();
+            assert!(is_timestamp(stamp), "{}", line);
+            assert_eq!(rest, text);
+        }
+
+        assert_eq!(render(OutputFormat::Human, false), "first\nsecond\ndone\n");
+    }
+
+    #[test]
+    fn test_json_gains_timestamp_field() {
+        let mut sink = OutputSink::new(Vec::new(), OutputFormat::Json).with_timestamps(true);
+        sink.write_result(&message("hello")).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&sink.into_inner()).unwrap();
+        assert_eq!(json["message"], "hello");
+        let stamp = json["timestamp"].as_str().unwrap();
+        assert!(is_timestamp(stamp));
+        // JSON defaults to UTC
+        assert!(stamp.ends_with("+00:00"), "{}", stamp);
+
+        let mut sink = OutputSink::new(Vec::new(), OutputFormat::Json)
+            .with_timestamps(true)
+            .with_timezone("Asia/Kolkata".parse().unwrap());
+        sink.write_result(&message("hello")).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&sink.into_inner()).unwrap();
+        assert!(json["timestamp"].as_str().unwrap().ends_with("+05:30"));
+
+        let mut sink = OutputSink::new(Vec::new(), OutputFormat::Json);
+        sink.write_result(&message("hello")).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&sink.into_inner()).unwrap();
+        assert!(json.get("timestamp").is_none());
+    }
+
+    #[test]
+    fn test_precision_applies_to_human_but_not_json() {
+        let cpu = ExecutionResult::new(ResultData::Cpu(crate::engine::queries::CpuInfo {
+            cores: 8,
+            usage: 42.4567,
+            brand: "Test CPU".to_string(),
+            frequency: 3000,
+        }));
+        let render = |format: OutputFormat, precision: usize| {
+            let mut sink = OutputSink::new(Vec::new(), format).with_precision(precision);
+            sink.write_result(&cpu).unwrap();
+            String::from_utf8(sink.into_inner()).unwrap()
+        };
+        let usage = |output: String| {
+            output
+                .lines()
+                .find_map(|line| line.strip_prefix("Usage:"))
+                .map(|usage| usage.trim().to_string())
+                .unwrap()
+        };
+
+        assert_eq!(usage(render(OutputFormat::Human, 0)), "42%");
+        assert_eq!(usage(render(OutputFormat::Human, 3)), "42.457%");
+        assert_eq!(
+            usage(render(OutputFormat::Human, DEFAULT_PRECISION)),
+            "42.5%"
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json, 0)).unwrap();
+        assert!(json["usage"].as_f64().unwrap().fract() > 0.0);
+    }
+
+    #[test]
+    fn test_network_deltas_show_rates() {
+        use crate::engine::queries::network::{NetworkInfo, NetworkInterface};
+
+        let network = |delta_secs| {
+            ExecutionResult::new(ResultData::Network(NetworkInfo {
+                interfaces: vec![NetworkInterface {
+                    name: "eth0".to_string(),
+                    received: 10_000_000,
+                    transmitted: 1_000_000,
+                    received_delta: Some(4_000_000),
+                    transmitted_delta: Some(0),
+                    packets_received: 0,
+                    packets_transmitted: 0,
+                    up: true,
+                }],
+                delta_secs,
+            }))
+        };
+        let render = |result: ExecutionResult| {
+            let mut sink = OutputSink::new(Vec::new(), OutputFormat::Human);
+            sink.write_result(&result).unwrap();
+            String::from_utf8(sink.into_inner()).unwrap()
+        };
+
+        let output = render(network(Some(2.0)));
+        assert!(
+            output.contains("Since last: +4.0 MB (2.0 MB/s) received | +0 B (0 B/s) transmitted"),
+            "{}",
+            output
+        );
+        // Without a previous query's time there is no rate to show
+        let output = render(network(None));
+        assert!(
+            output.contains("Since last: +4.0 MB received | +0 B transmitted"),
+            "{}",
+            output
+        );
+    }
+}
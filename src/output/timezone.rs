@@ -0,0 +1,143 @@
+//! Time zone used to render timestamps (`--timezone`)
+//!
+//! Instants are kept in UTC internally and only converted when printed, so
+//! history, file times, LIFE samples and `--timestamps` all agree.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use chrono_tz::Tz;
+
+use crate::error::{ArtaError, Result};
+use crate::output::OutputFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayZone {
+    Utc,
+    Local,
+    /// IANA zone such as `Europe/Berlin`
+    Named(Tz),
+}
+
+impl DisplayZone {
+    /// Zone used when `--timezone` is not given: UTC for JSON, local otherwise
+    pub fn default_for(format: &OutputFormat) -> Self {
+        match format {
+            OutputFormat::Json => DisplayZone::Utc,
+            _ => DisplayZone::Local,
+        }
+    }
+
+    /// Render an instant with a `strftime`-style format string
+    pub fn format(self, instant: DateTime<Utc>, fmt: &str) -> String {
+        match self {
+            DisplayZone::Utc => instant.format(fmt).to_string(),
+            DisplayZone::Local => instant.with_timezone(&Local).format(fmt).to_string(),
+            DisplayZone::Named(tz) => instant.with_timezone(&tz).format(fmt).to_string(),
+        }
+    }
+
+    /// Render an instant as ISO-8601 with milliseconds and the zone's offset
+    pub fn rfc3339(self, instant: DateTime<Utc>) -> String {
+        // `true` would print UTC as `Z`; an explicit offset is easier to parse
+        match self {
+            DisplayZone::Utc => instant.to_rfc3339_opts(SecondsFormat::Millis, false),
+            DisplayZone::Local => instant
+                .with_timezone(&Local)
+                .to_rfc3339_opts(SecondsFormat::Millis, false),
+            DisplayZone::Named(tz) => instant
+                .with_timezone(&tz)
+                .to_rfc3339_opts(SecondsFormat::Millis, false),
+        }
+    }
+}
+
+/// Serialize an instant as JSON output shows times: RFC 3339 in UTC with an offset
+pub fn serialize_rfc3339<S: serde::Serializer>(
+    instant: &DateTime<Utc>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&DisplayZone::Utc.rfc3339(*instant))
+}
+
+/// [`serialize_rfc3339`] for optional instants; `None` stays null
+pub fn serialize_rfc3339_opt<S: serde::Serializer>(
+    instant: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match instant {
+        Some(instant) => serialize_rfc3339(instant, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl FromStr for DisplayZone {
+    type Err = ArtaError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "utc" => Ok(DisplayZone::Utc),
+            "local" => Ok(DisplayZone::Local),
+            _ => s.parse::<Tz>().map(DisplayZone::Named).map_err(|_| {
+                ArtaError::ParseError(format!(
+                    "unknown time zone '{}': expected utc, local or a name such as Europe/Berlin",
+                    s
+                ))
+            }),
+        }
+    }
+}
+
+impl fmt::Display for DisplayZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayZone::Utc => write!(f, "utc"),
+            DisplayZone::Local => write!(f, "local"),
+            DisplayZone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn instant() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 15, 12, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn test_render_in_utc_and_named_zone() {
+        let utc: DisplayZone = "UTC".parse().unwrap();
+        assert_eq!(utc.format(instant(), "%Y-%m-%d %H:%M"), "2024-01-15 12:30");
+        assert_eq!(utc.rfc3339(instant()), "2024-01-15T12:30:00.000+00:00");
+
+        let tokyo: DisplayZone = "Asia/Tokyo".parse().unwrap();
+        assert_eq!(
+            tokyo.format(instant(), "%Y-%m-%d %H:%M"),
+            "2024-01-15 21:30"
+        );
+        assert_eq!(tokyo.rfc3339(instant()), "2024-01-15T21:30:00.000+09:00");
+        assert_eq!(tokyo.to_string(), "Asia/Tokyo");
+    }
+
+    #[test]
+    fn test_parse_zone() {
+        assert_eq!("local".parse::<DisplayZone>().unwrap(), DisplayZone::Local);
+        assert!("Mars/Olympus_Mons".parse::<DisplayZone>().is_err());
+    }
+
+    #[test]
+    fn test_default_zone_follows_format() {
+        assert_eq!(
+            DisplayZone::default_for(&OutputFormat::Json),
+            DisplayZone::Utc
+        );
+        assert_eq!(
+            DisplayZone::default_for(&OutputFormat::Human),
+            DisplayZone::Local
+        );
+    }
+}
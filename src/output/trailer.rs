@@ -0,0 +1,115 @@
+//! One-line JSON summary printed after human output (`--trailer json`)
+//!
+//! People read the body; scripts read the last line, e.g.
+//! `{"target":"PROCESS","count":12,"success":true}`.
+
+use crate::engine::executor::{ExecutionResult, ResultData};
+use crate::engine::fields::Projection;
+use crate::output::properties::target_prefix;
+use serde::Serialize;
+
+/// Summary of a command's result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Trailer {
+    /// Query target, e.g. `PROCESS`; null for field lists, messages and scripts
+    pub target: Option<String>,
+    /// Rows returned, items affected by an action, or statements run by a script
+    pub count: usize,
+    /// False for a failed command or a condition that is false
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Trailer {
+    /// Summary of one executed command
+    pub fn for_result(result: &ExecutionResult) -> Self {
+        let target = Some(target_prefix(&result.data))
+            .filter(|t| !t.is_empty())
+            .map(str::to_string);
+        Self {
+            target,
+            count: result_count(&result.data),
+            success: !matches!(result.data, ResultData::Boolean(false)),
+            error: None,
+        }
+    }
+
+    /// Summary of a command or script that stopped with an error
+    pub fn failed(error: &str) -> Self {
+        Self {
+            target: None,
+            count: 0,
+            success: false,
+            error: Some(error.to_string()),
+        }
+    }
+
+    /// The trailer as compact JSON, without a trailing newline
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+fn result_count(data: &ResultData) -> usize {
+    match data {
+        ResultData::Disk(info) => info.disks.len(),
+        ResultData::Network(info) => info.interfaces.len(),
+        ResultData::Battery(info) => info.batteries.len(),
+        ResultData::Processes(processes) => processes.len(),
+        ResultData::Files(files) => files.len(),
+        ResultData::Services(services) => services.len(),
+        ResultData::Projection(Projection::Rows(rows)) => rows.len(),
+        ResultData::Content(content) => content.lines.len(),
+        ResultData::ActionResult(action) => action.affected_count,
+        ResultData::Audit(log) => log.entries.len(),
+        ResultData::Print(values) => values.len(),
+        ResultData::Multiple(results) => results.len(),
+        ResultData::Empty => 0,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::queries::ProcessInfo;
+
+    fn result(data: ResultData) -> ExecutionResult {
+        ExecutionResult::new(data)
+    }
+
+    #[test]
+    fn test_list_trailer() {
+        let process = |pid| ProcessInfo {
+            pid,
+            name: "node".to_string(),
+            name_full: None,
+            cpu: 0.0,
+            memory: 0,
+            status: "running".to_string(),
+            threads: None,
+            open_files: None,
+            user: None,
+            uid: None,
+        };
+        let trailer =
+            Trailer::for_result(&result(ResultData::Processes(vec![process(1), process(2)])));
+        assert_eq!(
+            trailer.to_json_line(),
+            r#"{"target":"PROCESS","count":2,"success":true}"#
+        );
+    }
+
+    #[test]
+    fn test_condition_and_error_trailers() {
+        let trailer = Trailer::for_result(&result(ResultData::Boolean(false)));
+        assert_eq!(trailer.target, None);
+        assert!(!trailer.success);
+
+        assert_eq!(
+            Trailer::failed("boom").to_json_line(),
+            r#"{"target":null,"count":0,"success":false,"error":"boom"}"#
+        );
+    }
+}
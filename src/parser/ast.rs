@@ -15,6 +15,16 @@ pub enum Command {
     Print(PrintCommand),
     Container(ContainerCommand),
     Explain(Box<Command>),
+    /// A bare condition evaluated to true or false (e.g., SELECT CPU usage > 90)
+    Check(IfCondition),
+    Label(LabelBlock),
+    Diff(DiffCommand),
+    /// Named command sequence (DEFINE name AS ...), expanded by the script runner
+    Define(MacroDefinition),
+    /// Inline a macro defined earlier in the script (RUN name)
+    RunMacro(String),
+    /// A query piped into a follow-up step (`SELECT FILES ... | DELETE`)
+    Pipeline(PipelineCommand),
 }
 
 /// A script is a sequence of commands
@@ -32,6 +42,15 @@ pub struct Script {
 pub struct LifeMonitor {
     /// What to monitor (BATTERY, CPU, MEMORY, etc.)
     pub target: LifeTarget,
+    /// Seconds between samples (EVERY clause, default 1)
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+    /// Average samples over windows of this many seconds (AVERAGE OVER clause)
+    #[serde(default)]
+    pub average_secs: Option<u64>,
+    /// Stop after this many seconds without a change (STOP AFTER ... IDLE)
+    #[serde(default)]
+    pub idle_secs: Option<u64>,
     /// Commands to execute when changes are detected
     pub body: Vec<Command>,
 }
@@ -60,6 +79,36 @@ impl std::fmt::Display for LifeTarget {
     }
 }
 
+// ============================================================================
+// DIFF Command
+// ============================================================================
+
+/// Line diff between two files (DIFF /etc/hosts.bak /etc/hosts)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffCommand {
+    pub old: String,
+    pub new: String,
+}
+
+// ============================================================================
+// LABEL Sections
+// ============================================================================
+
+/// Named section of a script (LABEL "backup": ... END LABEL)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelBlock {
+    /// Section name; nested sections are addressed as `parent.child`
+    pub name: String,
+    pub body: Vec<Command>,
+}
+
+/// Macro definition (DEFINE cleanup AS DELETE FILES FROM /tmp ...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MacroDefinition {
+    pub name: String,
+    pub body: Vec<Command>,
+}
+
 // ============================================================================
 // PRINT Command
 // ============================================================================
@@ -73,8 +122,15 @@ pub struct PrintCommand {
 /// Expression in a PRINT command
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PrintExpr {
-    /// Query a specific field (e.g., BATTERY LEVEL)
-    QueryField { target: QueryTarget, field: String },
+    /// Query a specific field (e.g., BATTERY LEVEL or BATTERY[1] LEVEL)
+    QueryField {
+        target: QueryTarget,
+        #[serde(default)]
+        index: Option<usize>,
+        field: String,
+    },
+    /// A full query whose result is printed inline (e.g., SELECT MEMORY *)
+    Query(QueryCommand),
     /// A literal string
     String(String),
     /// A variable reference
@@ -112,12 +168,42 @@ pub struct IfStatement {
 pub struct IfCondition {
     /// The query target (CPU, MEMORY, etc.)
     pub target: QueryTarget,
+    /// Device index for multi-device targets (e.g., BATTERY[1])
+    #[serde(default)]
+    pub index: Option<usize>,
     /// The field to compare
     pub field: String,
     /// The comparison operator
     pub operator: CompareOp,
     /// The value to compare against
     pub value: Value,
+    /// Check every device instead of one (e.g., ANY DISK)
+    #[serde(default)]
+    pub quantifier: Option<Quantifier>,
+    /// Mount path to check (DISK), or folder to count in (FILES)
+    #[serde(default)]
+    pub from_path: Option<String>,
+    /// Rows to count (PROCESS and FILES only)
+    #[serde(default)]
+    pub where_clause: Option<WhereClause>,
+}
+
+/// How a multi-device IF condition combines per-device results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quantifier {
+    /// True if the comparison holds for at least one device
+    Any,
+    /// True if the comparison holds for every device
+    All,
+}
+
+impl std::fmt::Display for Quantifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Quantifier::Any => write!(f, "ANY"),
+            Quantifier::All => write!(f, "ALL"),
+        }
+    }
 }
 
 // ============================================================================
@@ -151,16 +237,45 @@ pub enum ContextCommand {
     EnterFolder(String),
     EnterFile(String),
     Exit,
-    Reset,
+    Up,
+    Reset(ResetTarget),
     Show(ShowTarget),
 }
 
+/// What to clear with RESET; plain RESET means the whole context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ResetTarget {
+    #[default]
+    Context,
+    Variables,
+    History,
+}
+
+impl std::fmt::Display for ResetTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResetTarget::Context => write!(f, "CONTEXT"),
+            ResetTarget::Variables => write!(f, "VARIABLES"),
+            ResetTarget::History => write!(f, "HISTORY"),
+        }
+    }
+}
+
 /// What to show with SHOW command
+/// Entries shown by `SHOW AUDIT` without a count
+pub const DEFAULT_AUDIT_ENTRIES: usize = 10;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ShowTarget {
     Context,
     Variables,
     History,
+    Version,
+    Stack,
+    /// Settings of the running `ExecutionContext`
+    Config,
+    /// Last entries of the audit log
+    Audit(usize),
 }
 
 impl std::fmt::Display for ShowTarget {
@@ -169,6 +284,10 @@ impl std::fmt::Display for ShowTarget {
             ShowTarget::Context => write!(f, "CONTEXT"),
             ShowTarget::Variables => write!(f, "VARIABLES"),
             ShowTarget::History => write!(f, "HISTORY"),
+            ShowTarget::Version => write!(f, "VERSION"),
+            ShowTarget::Stack => write!(f, "STACK"),
+            ShowTarget::Config => write!(f, "CONFIG"),
+            ShowTarget::Audit(count) => write!(f, "AUDIT {}", count),
         }
     }
 }
@@ -184,6 +303,63 @@ pub struct QueryCommand {
     pub fields: FieldList,
     pub from_path: Option<String>,
     pub where_clause: Option<WhereClause>,
+    /// Include interfaces that are down (NETWORK queries)
+    #[serde(default)]
+    pub include_down: bool,
+    /// Include pseudo filesystems such as tmpfs and proc (DISK queries)
+    #[serde(default)]
+    pub include_pseudo: bool,
+    /// Force line numbers on or off (CONTENT queries); `None` keeps the default
+    #[serde(default)]
+    pub line_numbers: Option<bool>,
+    /// Compute recursive directory sizes (FILES queries, `WITH SIZES`)
+    #[serde(default)]
+    pub with_sizes: bool,
+    /// Name of a registered custom target (when `target` is `Custom`)
+    #[serde(default)]
+    pub custom_target: Option<String>,
+    /// ORDER BY clause
+    #[serde(default)]
+    pub order_by: Option<OrderBy>,
+    /// LIMIT clause
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Re-run the query every this many seconds (EVERY clause)
+    #[serde(default)]
+    pub every: Option<u64>,
+    /// Stop an EVERY query after this many samples (FOR n TIMES)
+    #[serde(default)]
+    pub every_count: Option<u64>,
+    /// Keep printing appended lines until Ctrl+C (CONTENT queries, FOLLOW)
+    #[serde(default)]
+    pub follow: bool,
+    /// Count lines, words, characters and bytes (CONTENT queries, COUNT)
+    #[serde(default)]
+    pub count: bool,
+}
+
+/// ORDER BY clause: sort rows by a field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderBy {
+    pub field: String,
+    pub direction: SortDirection,
+}
+
+/// Sort direction for ORDER BY
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl std::fmt::Display for SortDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortDirection::Asc => write!(f, "ASC"),
+            SortDirection::Desc => write!(f, "DESC"),
+        }
+    }
 }
 
 /// Available query targets
@@ -196,8 +372,33 @@ pub enum QueryTarget {
     System,
     Battery,
     Process,
+    /// Arta's own process
+    SelfProc,
     Files,
     Content,
+    /// systemd service units (Linux)
+    Services,
+    /// A target registered by the embedding application
+    Custom,
+}
+
+impl QueryTarget {
+    /// Names of the built-in targets, as written in queries
+    pub const NAMES: &'static [&'static str] = &[
+        "CPU",
+        "MEMORY",
+        "DISK",
+        "NETWORK",
+        "SYSTEM",
+        "BATTERY",
+        "PROCESS",
+        "PROCESSES",
+        "SELF",
+        "FILES",
+        "CONTENT",
+        "SERVICE",
+        "SERVICES",
+    ];
 }
 
 impl std::fmt::Display for QueryTarget {
@@ -210,8 +411,11 @@ impl std::fmt::Display for QueryTarget {
             QueryTarget::System => write!(f, "SYSTEM"),
             QueryTarget::Battery => write!(f, "BATTERY"),
             QueryTarget::Process => write!(f, "PROCESS"),
+            QueryTarget::SelfProc => write!(f, "SELF"),
             QueryTarget::Files => write!(f, "FILES"),
             QueryTarget::Content => write!(f, "CONTENT"),
+            QueryTarget::Services => write!(f, "SERVICES"),
+            QueryTarget::Custom => write!(f, "CUSTOM"),
         }
     }
 }
@@ -244,6 +448,12 @@ pub struct Condition {
     pub value: Value,
 }
 
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.field, self.operator, self.value)
+    }
+}
+
 /// Logical operators
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LogicalOp {
@@ -291,6 +501,8 @@ pub enum Value {
     String(String),
     Number(f64),
     Size(u64), // Size in bytes
+    /// Share of a total, e.g. `50%` of total memory
+    Percent(f64),
     Boolean(bool),
     Identifier(String), // For variable references
 }
@@ -301,6 +513,7 @@ impl std::fmt::Display for Value {
             Value::String(s) => write!(f, "\"{}\"", s),
             Value::Number(n) => write!(f, "{}", n),
             Value::Size(s) => write!(f, "{}", bytesize::ByteSize(*s)),
+            Value::Percent(p) => write!(f, "{}%", p),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Identifier(id) => write!(f, "{}", id),
         }
@@ -316,6 +529,18 @@ impl std::fmt::Display for Value {
 pub enum ActionCommand {
     DeleteFiles(DeleteFilesCommand),
     KillProcess(KillProcessCommand),
+    WriteFile(WriteFileCommand),
+}
+
+impl ActionCommand {
+    /// Keyword(s) that start the command
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActionCommand::DeleteFiles(_) => "DELETE FILES",
+            ActionCommand::KillProcess(_) => "KILL PROCESS",
+            ActionCommand::WriteFile(_) => "WRITE",
+        }
+    }
 }
 
 /// DELETE FILES command
@@ -329,6 +554,82 @@ pub struct DeleteFilesCommand {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KillProcessCommand {
     pub where_clause: WhereClause,
+    /// Ask before killing each matched process (`CONFIRM EACH`)
+    #[serde(default)]
+    pub confirm_each: bool,
+}
+
+/// WRITE command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteFileCommand {
+    pub path: String,
+    /// Text to write, with escapes resolved; `${var}` is substituted at run time
+    pub content: String,
+    /// Add to the end of the file instead of replacing it
+    pub append: bool,
+}
+
+// ============================================================================
+// Pipelines
+// ============================================================================
+
+/// A query whose rows feed a follow-up step, e.g. `SELECT FILES ... | COUNT`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineCommand {
+    pub source: QueryCommand,
+    pub sink: PipeSink,
+    /// `| KILL CONFIRM EACH`: ask before each process is killed
+    #[serde(default)]
+    pub confirm_each: bool,
+}
+
+/// Step after `|`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PipeSink {
+    /// Delete the files of a FILES query
+    Delete,
+    /// Kill the processes of a PROCESS query
+    Kill,
+    /// Count the rows of any listing query
+    Count,
+}
+
+impl PipeSink {
+    /// Name of the action the step performs, if it is one
+    pub fn action_name(&self) -> Option<&'static str> {
+        match self {
+            PipeSink::Delete => Some("DELETE FILES"),
+            PipeSink::Kill => Some("KILL PROCESS"),
+            PipeSink::Count => None,
+        }
+    }
+
+    /// Whether the step can take the rows of a `target` query
+    pub fn accepts(&self, target: QueryTarget) -> bool {
+        match self {
+            PipeSink::Delete => target == QueryTarget::Files,
+            PipeSink::Kill => target == QueryTarget::Process,
+            PipeSink::Count => matches!(
+                target,
+                QueryTarget::Files
+                    | QueryTarget::Process
+                    | QueryTarget::Disk
+                    | QueryTarget::Network
+                    | QueryTarget::Battery
+                    | QueryTarget::Services
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for PipeSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipeSink::Delete => write!(f, "DELETE"),
+            PipeSink::Kill => write!(f, "KILL"),
+            PipeSink::Count => write!(f, "COUNT"),
+        }
+    }
 }
 
 // ============================================================================
@@ -342,12 +643,14 @@ pub enum ContainerCommand {
     Create(CreateContainer),
     /// Switch to a different container
     Switch(String),
-    /// List all containers
-    List,
+    /// List containers, optionally filtered on name, allow_actions, readonly or active
+    List(Option<WhereClause>),
     /// Destroy a container
     Destroy(String),
     /// Export a container to a script file
     Export(ExportContainer),
+    /// Export every non-default container to one script file
+    ExportSession(ExportSession),
 }
 
 /// CREATE CONTAINER command
@@ -377,4 +680,17 @@ pub struct ExportContainer {
     pub name: String,
     /// Path to export the container script to
     pub path: String,
+    /// Replace an existing file even if it is not an `.arta` script
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// EXPORT SESSION command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSession {
+    /// Path to write the session script to
+    pub path: String,
+    /// Replace an existing file even if it is not an `.arta` script
+    #[serde(default)]
+    pub force: bool,
 }
@@ -2,9 +2,11 @@
 
 use pest::Parser;
 use pest_derive::Parser;
+use tracing::instrument;
 
-use crate::error::{ArtaError, Result};
+use crate::error::{did_you_mean, ArtaError, Result};
 use crate::parser::ast::*;
+use crate::parser::options::{strip_line_comments, ParseOptions};
 
 #[derive(Parser)]
 #[grammar = "../grammar/arta.pest"]
@@ -12,7 +14,14 @@ pub struct ArtaParser;
 
 /// Parse a command string into an AST
 pub fn parse_command(input: &str) -> Result<Command> {
-    let pairs = ArtaParser::parse(Rule::command, input)
+    parse_command_with_options(input, &ParseOptions::default())
+}
+
+/// Parse a command string, accepting only the enabled comment styles
+#[instrument(level = "debug", skip_all, fields(len = input.len()))]
+pub fn parse_command_with_options(input: &str, options: &ParseOptions) -> Result<Command> {
+    let input = strip_line_comments(input, options);
+    let pairs = ArtaParser::parse(Rule::command, &input)
         .map_err(|e| ArtaError::ParseError(e.to_string()))?;
 
     let pair = pairs
@@ -20,13 +29,24 @@ pub fn parse_command(input: &str) -> Result<Command> {
         .next()
         .ok_or_else(|| ArtaError::ParseError("Empty input".to_string()))?;
 
-    parse_command_inner(pair)
+    let command = parse_command_inner(pair)?;
+    if !options.allow_actions {
+        reject_actions(std::slice::from_ref(&command))?;
+    }
+    Ok(command)
 }
 
 /// Parse a script (multiple statements) into an AST
 pub fn parse_script(input: &str) -> Result<Script> {
-    let pairs =
-        ArtaParser::parse(Rule::script, input).map_err(|e| ArtaError::ParseError(e.to_string()))?;
+    parse_script_with_options(input, &ParseOptions::default())
+}
+
+/// Parse a script, accepting only the enabled comment styles
+#[instrument(level = "debug", skip_all, fields(len = input.len()))]
+pub fn parse_script_with_options(input: &str, options: &ParseOptions) -> Result<Script> {
+    let input = strip_line_comments(input, options);
+    let pairs = ArtaParser::parse(Rule::script, &input)
+        .map_err(|e| ArtaError::ParseError(e.to_string()))?;
 
     let pair = pairs
         .into_iter()
@@ -41,9 +61,47 @@ pub fn parse_script(input: &str) -> Result<Script> {
         }
     }
 
+    if !options.allow_actions {
+        reject_actions(&statements)?;
+    }
+
+    tracing::debug!(statements = statements.len(), "parsed script");
     Ok(Script { statements })
 }
 
+/// Fail on the first action command, including ones nested in blocks
+fn reject_actions(commands: &[Command]) -> Result<()> {
+    for cmd in commands {
+        let nested: &[Command] = match cmd {
+            Command::Action(action) => {
+                return Err(ArtaError::ParseError(format!(
+                    "{} is not allowed: action commands are disabled for this parser",
+                    action.name()
+                )));
+            }
+            Command::Pipeline(pipeline) if pipeline.sink.action_name().is_some() => {
+                return Err(ArtaError::ParseError(format!(
+                    "| {} is not allowed: action commands are disabled for this parser",
+                    pipeline.sink
+                )));
+            }
+            Command::Explain(inner) => std::slice::from_ref(inner.as_ref()),
+            Command::For(f) => &f.body,
+            Command::If(i) => {
+                reject_actions(&i.then_body)?;
+                i.else_body.as_deref().unwrap_or_default()
+            }
+            Command::Life(l) => &l.body,
+            Command::Label(l) => &l.body,
+            Command::Define(d) => &d.body,
+            Command::Container(ContainerCommand::Create(c)) => &c.body,
+            _ => &[],
+        };
+        reject_actions(nested)?;
+    }
+    Ok(())
+}
+
 fn parse_command_inner(pair: pest::iterators::Pair<Rule>) -> Result<Command> {
     // command -> statement
     let statement = pair
@@ -63,6 +121,8 @@ fn parse_statement(pair: pest::iterators::Pair<Rule>) -> Result<Command> {
     match inner.as_rule() {
         Rule::container_cmd => Ok(Command::Container(parse_container_cmd(inner)?)),
         Rule::life_cmd => Ok(Command::Life(parse_life_cmd(inner)?)),
+        Rule::label_cmd => Ok(Command::Label(parse_label_cmd(inner)?)),
+        Rule::define_cmd => Ok(Command::Define(parse_define_cmd(inner)?)),
         Rule::for_cmd => Ok(Command::For(parse_for_cmd(inner)?)),
         Rule::if_cmd => Ok(Command::If(parse_if_cmd(inner)?)),
         Rule::simple_cmd => parse_simple_cmd(inner),
@@ -93,7 +153,34 @@ fn parse_simple_cmd(pair: pest::iterators::Pair<Rule>) -> Result<Command> {
             Ok(Command::Explain(Box::new(cmd)))
         }
         Rule::let_cmd => Ok(Command::Let(parse_let_cmd(inner)?)),
+        Rule::run_macro_cmd => {
+            let name = inner.into_inner().next().ok_or_else(|| {
+                ArtaError::ParseError("Expected macro name after RUN".to_string())
+            })?;
+            Ok(Command::RunMacro(name.as_str().to_string()))
+        }
         Rule::context_cmd => Ok(Command::Context(parse_context_cmd(inner)?)),
+        Rule::diff_cmd => {
+            let mut paths = inner.into_inner();
+            let mut next_path = || {
+                paths
+                    .next()
+                    .ok_or_else(|| ArtaError::ParseError("DIFF expects two paths".to_string()))
+                    .and_then(parse_path_value)
+            };
+            Ok(Command::Diff(DiffCommand {
+                old: next_path()?,
+                new: next_path()?,
+            }))
+        }
+        Rule::check_cmd => {
+            let condition = inner
+                .into_inner()
+                .next()
+                .ok_or_else(|| ArtaError::ParseError("Expected condition".to_string()))?;
+            Ok(Command::Check(parse_if_condition(condition)?))
+        }
+        Rule::pipeline_cmd => Ok(Command::Pipeline(parse_pipeline_cmd(inner)?)),
         Rule::query_cmd => Ok(Command::Query(parse_query_cmd(inner)?)),
         Rule::action_cmd => Ok(Command::Action(parse_action_cmd(inner)?)),
         _ => Err(ArtaError::ParseError(format!(
@@ -116,13 +203,100 @@ fn parse_life_cmd(pair: pest::iterators::Pair<Rule>) -> Result<LifeMonitor> {
         .ok_or_else(|| ArtaError::ParseError("Expected target in LIFE".to_string()))?;
     let target = parse_life_target(target_pair)?;
 
-    // Parse statement block (body)
+    let mut interval_secs = None;
+    let mut average_secs = None;
+    let mut idle_secs = None;
+    let mut body = None;
+    for item in inner {
+        let rule = item.as_rule();
+        if rule == Rule::statement_block {
+            body = Some(parse_statement_block(item)?);
+            continue;
+        }
+
+        let (slot, what) = match rule {
+            Rule::life_every => (&mut interval_secs, "interval"),
+            Rule::life_average => (&mut average_secs, "averaging window"),
+            Rule::life_idle => (&mut idle_secs, "idle timeout"),
+            _ => continue,
+        };
+        let secs = item
+            .into_inner()
+            .next()
+            .map(|n| parse_u64(n.as_str()))
+            .transpose()?;
+        if secs == Some(0) {
+            return Err(ArtaError::ParseError(format!(
+                "LIFE {} must be at least 1 second",
+                what
+            )));
+        }
+        *slot = secs;
+    }
+    let body =
+        body.ok_or_else(|| ArtaError::ParseError("Expected statement block in LIFE".to_string()))?;
+
+    Ok(LifeMonitor {
+        target,
+        interval_secs,
+        average_secs,
+        idle_secs,
+        body,
+    })
+}
+
+fn parse_label_cmd(pair: pest::iterators::Pair<Rule>) -> Result<LabelBlock> {
+    let mut inner = pair.into_inner();
+
+    let name_pair = inner
+        .next()
+        .and_then(|p| p.into_inner().next())
+        .ok_or_else(|| ArtaError::ParseError("Expected name in LABEL".to_string()))?;
+    let name = match name_pair.as_rule() {
+        Rule::string_value => {
+            let s = name_pair.as_str();
+            s[1..s.len() - 1].to_string()
+        }
+        _ => name_pair.as_str().to_string(),
+    };
+    if name.is_empty() || name.contains('.') {
+        return Err(ArtaError::ParseError(format!(
+            "Invalid LABEL name '{}': names must be non-empty and cannot contain '.'",
+            name
+        )));
+    }
+
     let block_pair = inner
         .next()
-        .ok_or_else(|| ArtaError::ParseError("Expected statement block in LIFE".to_string()))?;
+        .ok_or_else(|| ArtaError::ParseError("Expected statement block in LABEL".to_string()))?;
     let body = parse_statement_block(block_pair)?;
 
-    Ok(LifeMonitor { target, body })
+    Ok(LabelBlock { name, body })
+}
+
+fn parse_define_cmd(pair: pest::iterators::Pair<Rule>) -> Result<MacroDefinition> {
+    let mut inner = pair.into_inner();
+
+    let name = inner
+        .next()
+        .ok_or_else(|| ArtaError::ParseError("Expected name in DEFINE".to_string()))?
+        .as_str()
+        .to_string();
+
+    let body_pair = inner
+        .next()
+        .ok_or_else(|| ArtaError::ParseError(format!("Expected body for DEFINE {}", name)))?;
+    let body = match body_pair.as_rule() {
+        Rule::define_block => {
+            let block = body_pair.into_inner().next().ok_or_else(|| {
+                ArtaError::ParseError(format!("Expected statements in DEFINE {}", name))
+            })?;
+            parse_statement_block(block)?
+        }
+        _ => vec![parse_statement(body_pair)?],
+    };
+
+    Ok(MacroDefinition { name, body })
 }
 
 fn parse_life_target(pair: pest::iterators::Pair<Rule>) -> Result<LifeTarget> {
@@ -154,9 +328,25 @@ fn parse_container_cmd(pair: pest::iterators::Pair<Rule>) -> Result<ContainerCom
     match inner.as_rule() {
         Rule::create_container => parse_create_container(inner),
         Rule::switch_container => parse_switch_container(inner),
-        Rule::list_containers => Ok(ContainerCommand::List),
+        Rule::list_containers => Ok(ContainerCommand::List(
+            inner
+                .into_inner()
+                .next()
+                .map(parse_where_clause)
+                .transpose()?,
+        )),
         Rule::destroy_container => parse_destroy_container(inner),
         Rule::export_container => parse_export_container(inner),
+        Rule::export_session => {
+            let mut inner = inner.into_inner();
+            let path_pair = inner.next().ok_or_else(|| {
+                ArtaError::ParseError("Expected path after EXPORT SESSION TO".to_string())
+            })?;
+            Ok(ContainerCommand::ExportSession(ExportSession {
+                path: parse_path_value(path_pair)?,
+                force: inner.next().is_some(),
+            }))
+        }
         _ => Err(ArtaError::ParseError(format!(
             "Unknown container command: {:?}",
             inner.as_rule()
@@ -259,8 +449,13 @@ fn parse_export_container(pair: pest::iterators::Pair<Rule>) -> Result<Container
         .next()
         .ok_or_else(|| ArtaError::ParseError("Expected path after TO".to_string()))?;
     let path = parse_path_value(path_pair)?;
+    let force = inner.next().is_some();
 
-    Ok(ContainerCommand::Export(ExportContainer { name, path }))
+    Ok(ContainerCommand::Export(ExportContainer {
+        name,
+        path,
+        force,
+    }))
 }
 
 // ============================================================================
@@ -287,17 +482,28 @@ fn parse_print_expr(pair: pest::iterators::Pair<Rule>) -> Result<PrintExpr> {
         .ok_or_else(|| ArtaError::ParseError("Expected print expression".to_string()))?;
 
     match first.as_rule() {
+        Rule::query_cmd => Ok(PrintExpr::Query(parse_query_cmd(first)?)),
         Rule::query_target => {
             // This is QueryTarget followed by field
             let target = parse_query_target(first)?;
-            let field = inner
-                .next()
-                .ok_or_else(|| {
-                    ArtaError::ParseError("Expected field after query target in PRINT".to_string())
-                })?
-                .as_str()
-                .to_string();
-            Ok(PrintExpr::QueryField { target, field })
+            let mut next = inner.next().ok_or_else(|| {
+                ArtaError::ParseError("Expected field after query target in PRINT".to_string())
+            })?;
+            let index = if next.as_rule() == Rule::target_index {
+                let index = parse_target_index(next)?;
+                next = inner.next().ok_or_else(|| {
+                    ArtaError::ParseError("Expected field after index in PRINT".to_string())
+                })?;
+                Some(index)
+            } else {
+                None
+            };
+            let field = next.as_str().to_string();
+            Ok(PrintExpr::QueryField {
+                target,
+                index,
+                field,
+            })
         }
         Rule::string_value => {
             let s = first.as_str();
@@ -311,6 +517,17 @@ fn parse_print_expr(pair: pest::iterators::Pair<Rule>) -> Result<PrintExpr> {
     }
 }
 
+fn parse_target_index(pair: pest::iterators::Pair<Rule>) -> Result<usize> {
+    let number = pair
+        .into_inner()
+        .next()
+        .ok_or_else(|| ArtaError::ParseError("Expected index number".to_string()))?;
+    number
+        .as_str()
+        .parse()
+        .map_err(|_| ArtaError::ParseError(format!("Invalid index: {}", number.as_str())))
+}
+
 // ============================================================================
 // Control Flow Parsing
 // ============================================================================
@@ -381,7 +598,20 @@ fn parse_if_cmd(pair: pest::iterators::Pair<Rule>) -> Result<IfStatement> {
 }
 
 fn parse_if_condition(pair: pest::iterators::Pair<Rule>) -> Result<IfCondition> {
-    let mut inner = pair.into_inner();
+    let mut inner = pair.into_inner().peekable();
+
+    // Parse optional ANY/ALL quantifier
+    let quantifier = match inner.peek() {
+        Some(p) if p.as_rule() == Rule::if_quantifier => {
+            let q = inner.next().unwrap();
+            Some(if q.as_str().eq_ignore_ascii_case("ANY") {
+                Quantifier::Any
+            } else {
+                Quantifier::All
+            })
+        }
+        _ => None,
+    };
 
     // Parse query target
     let target_pair = inner.next().ok_or_else(|| {
@@ -389,12 +619,20 @@ fn parse_if_condition(pair: pest::iterators::Pair<Rule>) -> Result<IfCondition>
     })?;
     let target = parse_query_target(target_pair)?;
 
-    // Parse field
-    let field = inner
+    // Parse optional device index and field
+    let mut field_pair = inner
         .next()
-        .ok_or_else(|| ArtaError::ParseError("Expected field in IF condition".to_string()))?
-        .as_str()
-        .to_string();
+        .ok_or_else(|| ArtaError::ParseError("Expected field in IF condition".to_string()))?;
+    let index = if field_pair.as_rule() == Rule::target_index {
+        let index = parse_target_index(field_pair)?;
+        field_pair = inner
+            .next()
+            .ok_or_else(|| ArtaError::ParseError("Expected field in IF condition".to_string()))?;
+        Some(index)
+    } else {
+        None
+    };
+    let field = field_pair.as_str().to_string();
 
     // Parse operator
     let op_pair = inner
@@ -408,11 +646,32 @@ fn parse_if_condition(pair: pest::iterators::Pair<Rule>) -> Result<IfCondition>
         .ok_or_else(|| ArtaError::ParseError("Expected value in IF condition".to_string()))?;
     let value = parse_value(value_pair)?;
 
+    // Parse optional FROM path and WHERE filter
+    let mut from_path = None;
+    let mut where_clause = None;
+    for pair in inner {
+        match pair.as_rule() {
+            Rule::from_clause => from_path = Some(parse_from_clause(pair)?),
+            Rule::where_clause => where_clause = Some(parse_where_clause(pair)?),
+            _ => {}
+        }
+    }
+    if where_clause.is_some() && !matches!(target, QueryTarget::Process | QueryTarget::Files) {
+        return Err(ArtaError::ParseError(format!(
+            "WHERE in a condition is only supported for PROCESS and FILES counts, not {}",
+            target
+        )));
+    }
+
     Ok(IfCondition {
         target,
+        index,
         field,
         operator,
         value,
+        quantifier,
+        from_path,
+        where_clause,
     })
 }
 
@@ -441,7 +700,8 @@ fn parse_context_cmd(pair: pest::iterators::Pair<Rule>) -> Result<ContextCommand
     match inner.as_rule() {
         Rule::enter_cmd => parse_enter_cmd(inner),
         Rule::exit_cmd => Ok(ContextCommand::Exit),
-        Rule::reset_cmd => Ok(ContextCommand::Reset),
+        Rule::up_cmd => Ok(ContextCommand::Up),
+        Rule::reset_cmd => parse_reset_cmd(inner),
         Rule::show_cmd => parse_show_cmd(inner),
         _ => Err(ArtaError::ParseError(format!(
             "Unknown context command: {:?}",
@@ -475,17 +735,47 @@ fn parse_enter_cmd(pair: pest::iterators::Pair<Rule>) -> Result<ContextCommand>
     }
 }
 
+fn parse_reset_cmd(pair: pest::iterators::Pair<Rule>) -> Result<ContextCommand> {
+    let target = match pair.into_inner().next() {
+        None => ResetTarget::Context,
+        Some(inner) => match inner.as_str().to_uppercase().as_str() {
+            "CONTEXT" => ResetTarget::Context,
+            "VARIABLES" => ResetTarget::Variables,
+            "HISTORY" => ResetTarget::History,
+            other => {
+                return Err(ArtaError::ParseError(format!(
+                    "Unknown RESET target: {}",
+                    other
+                )))
+            }
+        },
+    };
+    Ok(ContextCommand::Reset(target))
+}
+
 fn parse_show_cmd(pair: pest::iterators::Pair<Rule>) -> Result<ContextCommand> {
     let inner = pair
         .into_inner()
         .next()
         .ok_or_else(|| ArtaError::ParseError("Expected target after SHOW".to_string()))?;
 
+    if let Some(audit) = inner.clone().into_inner().next() {
+        let count = if audit.clone().into_inner().next().is_some() {
+            parse_target_index(audit)?
+        } else {
+            DEFAULT_AUDIT_ENTRIES
+        };
+        return Ok(ContextCommand::Show(ShowTarget::Audit(count)));
+    }
+
     let target_str = inner.as_str().to_uppercase();
     let target = match target_str.as_str() {
         "CONTEXT" => ShowTarget::Context,
         "VARIABLES" => ShowTarget::Variables,
         "HISTORY" => ShowTarget::History,
+        "VERSION" => ShowTarget::Version,
+        "STACK" => ShowTarget::Stack,
+        "CONFIG" => ShowTarget::Config,
         _ => {
             return Err(ArtaError::ParseError(format!(
                 "Unknown SHOW target: {}",
@@ -533,7 +823,7 @@ fn parse_let_value(pair: pest::iterators::Pair<Rule>) -> Result<LetValue> {
             match path_inner.as_rule() {
                 Rule::string_value => {
                     let s = path_inner.as_str();
-                    Ok(LetValue::Path(s[1..s.len() - 1].to_string()))
+                    Ok(LetValue::Path(unescape(&s[1..s.len() - 1])))
                 }
                 Rule::bare_path => Ok(LetValue::Path(path_inner.as_str().to_string())),
                 Rule::identifier => Ok(LetValue::String(path_inner.as_str().to_string())),
@@ -558,12 +848,12 @@ fn parse_let_value(pair: pest::iterators::Pair<Rule>) -> Result<LetValue> {
         }
         Rule::string_value => {
             let s = inner.as_str();
-            let content = &s[1..s.len() - 1];
+            let content = unescape(&s[1..s.len() - 1]);
             // Treat strings that look like paths as paths
             if content.starts_with('/') || content.starts_with("~/") {
-                Ok(LetValue::Path(content.to_string()))
+                Ok(LetValue::Path(content))
             } else {
-                Ok(LetValue::String(content.to_string()))
+                Ok(LetValue::String(content))
             }
         }
         _ => Err(ArtaError::ParseError(format!(
@@ -583,15 +873,45 @@ fn parse_query_cmd(pair: pest::iterators::Pair<Rule>) -> Result<QueryCommand> {
     let target = inner
         .next()
         .ok_or_else(|| ArtaError::ParseError("Expected query target".to_string()))?;
-    let target = parse_query_target(target)?;
+    let (target, custom_target) = if target.as_rule() == Rule::custom_target {
+        (QueryTarget::Custom, Some(target.as_str().to_uppercase()))
+    } else {
+        (parse_query_target(target)?, None)
+    };
 
     let fields = inner
         .next()
         .ok_or_else(|| ArtaError::ParseError("Expected field list".to_string()))?;
-    let fields = parse_field_list(fields)?;
+    let sugared = fields.as_rule() == Rule::recency;
+    let count = fields.as_rule() == Rule::content_count;
+    if count && target != QueryTarget::Content {
+        return Err(ArtaError::ParseError(
+            "COUNT only applies to SELECT CONTENT".to_string(),
+        ));
+    }
+    let (fields, mut order_by, mut limit) = if sugared {
+        if target != QueryTarget::Files {
+            return Err(ArtaError::ParseError(
+                "NEWEST/OLDEST only applies to SELECT FILES".to_string(),
+            ));
+        }
+        let (order, count) = parse_recency(fields)?;
+        (FieldList::All, Some(order), Some(count))
+    } else if count {
+        (FieldList::All, None, None)
+    } else {
+        (parse_field_list(fields)?, None, None)
+    };
 
     let mut from_path = None;
     let mut where_clause = None;
+    let mut include_down = false;
+    let mut include_pseudo = false;
+    let mut line_numbers = None;
+    let mut with_sizes = false;
+    let mut every = None;
+    let mut every_count = None;
+    let mut follow = false;
 
     for item in inner {
         match item.as_rule() {
@@ -601,6 +921,49 @@ fn parse_query_cmd(pair: pest::iterators::Pair<Rule>) -> Result<QueryCommand> {
             Rule::where_clause => {
                 where_clause = Some(parse_where_clause(item)?);
             }
+            Rule::include_down => {
+                include_down = true;
+            }
+            Rule::include_pseudo => {
+                include_pseudo = true;
+            }
+            Rule::with_sizes => {
+                with_sizes = true;
+            }
+            Rule::order_by | Rule::limit_clause if sugared => {
+                return Err(ArtaError::ParseError(
+                    "NEWEST/OLDEST cannot be combined with ORDER BY or LIMIT".to_string(),
+                ));
+            }
+            Rule::order_by => {
+                order_by = Some(parse_order_by(item)?);
+            }
+            Rule::limit_clause => {
+                limit = Some(parse_target_index(item)?);
+            }
+            Rule::every_clause => {
+                let mut parts = item.into_inner();
+                every = parts.next().map(|n| parse_u64(n.as_str())).transpose()?;
+                every_count = parts
+                    .next()
+                    .and_then(|times| times.into_inner().next())
+                    .map(|n| parse_u64(n.as_str()))
+                    .transpose()?;
+            }
+            Rule::follow_clause if count => {
+                return Err(ArtaError::ParseError(
+                    "COUNT cannot be combined with FOLLOW".to_string(),
+                ));
+            }
+            Rule::follow_clause => {
+                follow = true;
+            }
+            Rule::line_numbers => {
+                line_numbers = item
+                    .into_inner()
+                    .next()
+                    .map(|toggle| toggle.as_rule() == Rule::with_line_numbers);
+            }
             _ => {}
         }
     }
@@ -610,9 +973,53 @@ fn parse_query_cmd(pair: pest::iterators::Pair<Rule>) -> Result<QueryCommand> {
         fields,
         from_path,
         where_clause,
+        include_down,
+        include_pseudo,
+        line_numbers,
+        with_sizes,
+        custom_target,
+        order_by,
+        limit,
+        every,
+        every_count,
+        follow,
+        count,
     })
 }
 
+fn parse_u64(s: &str) -> Result<u64> {
+    s.parse()
+        .map_err(|_| ArtaError::ParseError(format!("Invalid number: {}", s)))
+}
+
+/// Desugar `NEWEST n` / `OLDEST n` into `ORDER BY modified DESC/ASC LIMIT n`
+fn parse_recency(pair: pest::iterators::Pair<Rule>) -> Result<(OrderBy, usize)> {
+    let direction = if pair.as_str().to_uppercase().starts_with("NEWEST") {
+        SortDirection::Desc
+    } else {
+        SortDirection::Asc
+    };
+    let order = OrderBy {
+        field: "modified".to_string(),
+        direction,
+    };
+    Ok((order, parse_target_index(pair)?))
+}
+
+fn parse_order_by(pair: pest::iterators::Pair<Rule>) -> Result<OrderBy> {
+    let mut inner = pair.into_inner();
+    let field = inner
+        .next()
+        .ok_or_else(|| ArtaError::ParseError("Expected field in ORDER BY".to_string()))?
+        .as_str()
+        .to_string();
+    let direction = match inner.next() {
+        Some(dir) if dir.as_str().eq_ignore_ascii_case("DESC") => SortDirection::Desc,
+        _ => SortDirection::Asc,
+    };
+    Ok(OrderBy { field, direction })
+}
+
 fn parse_query_target(pair: pest::iterators::Pair<Rule>) -> Result<QueryTarget> {
     let target_str = pair.as_str().to_uppercase();
     match target_str.as_str() {
@@ -623,9 +1030,15 @@ fn parse_query_target(pair: pest::iterators::Pair<Rule>) -> Result<QueryTarget>
         "SYSTEM" => Ok(QueryTarget::System),
         "BATTERY" => Ok(QueryTarget::Battery),
         "PROCESS" | "PROCESSES" => Ok(QueryTarget::Process),
+        "SELF" => Ok(QueryTarget::SelfProc),
         "FILES" => Ok(QueryTarget::Files),
         "CONTENT" => Ok(QueryTarget::Content),
-        _ => Err(ArtaError::InvalidTarget(target_str)),
+        "SERVICE" | "SERVICES" => Ok(QueryTarget::Services),
+        _ => Err(ArtaError::InvalidTarget(format!(
+            "{}{}",
+            target_str,
+            did_you_mean(&target_str, QueryTarget::NAMES)
+        ))),
     }
 }
 
@@ -672,7 +1085,7 @@ fn parse_path_value(pair: pest::iterators::Pair<Rule>) -> Result<String> {
         Rule::string_value => {
             let s = inner.as_str();
             // Remove quotes
-            Ok(s[1..s.len() - 1].to_string())
+            Ok(unescape(&s[1..s.len() - 1]))
         }
         Rule::bare_path => Ok(inner.as_str().to_string()),
         Rule::identifier => Ok(inner.as_str().to_string()),
@@ -704,7 +1117,7 @@ fn parse_condition_expr(pair: pest::iterators::Pair<Rule>) -> Result<ConditionEx
         .ok_or_else(|| ArtaError::ParseError("Expected condition".to_string()))?;
     let condition = parse_condition(first_condition)?;
 
-    let mut next = None;
+    let mut rest = Vec::new();
 
     while let Some(op_pair) = inner.next() {
         let logical_op = match op_pair.as_rule() {
@@ -714,17 +1127,20 @@ fn parse_condition_expr(pair: pest::iterators::Pair<Rule>) -> Result<ConditionEx
         };
 
         if let Some(next_cond) = inner.next() {
-            let next_condition = parse_condition(next_cond)?;
-            next = Some((
-                logical_op,
-                Box::new(ConditionExpr {
-                    condition: next_condition,
-                    next: None,
-                }),
-            ));
+            rest.push((logical_op, parse_condition(next_cond)?));
         }
     }
 
+    // Link the chain from the end so every condition keeps its successor
+    let mut next = None;
+    for (logical_op, next_condition) in rest.into_iter().rev() {
+        let node = ConditionExpr {
+            condition: next_condition,
+            next: next.take(),
+        };
+        next = Some((logical_op, Box::new(node)));
+    }
+
     Ok(ConditionExpr { condition, next })
 }
 
@@ -734,8 +1150,11 @@ fn parse_condition(pair: pest::iterators::Pair<Rule>) -> Result<Condition> {
     let field = inner
         .next()
         .ok_or_else(|| ArtaError::ParseError("Expected field in condition".to_string()))?
-        .as_str()
-        .to_string();
+        .as_str();
+    let field = match field.split_once('.') {
+        Some((base, _)) => format!("{}{}", base, crate::engine::filter::LENGTH_SUFFIX),
+        None => field.to_string(),
+    };
 
     let op_pair = inner
         .next()
@@ -796,6 +1215,13 @@ fn parse_value(pair: pest::iterators::Pair<Rule>) -> Result<Value> {
             let bytes = parse_size_value(s)?;
             Ok(Value::Size(bytes))
         }
+        Rule::percent_value => {
+            let s = inner.as_str();
+            let percent: f64 = s[..s.len() - 1]
+                .parse()
+                .map_err(|_| ArtaError::ParseError(format!("Invalid percentage: {}", s)))?;
+            Ok(Value::Percent(percent))
+        }
         Rule::boolean => {
             let b = inner.as_str().to_uppercase() == "TRUE";
             Ok(Value::Boolean(b))
@@ -805,7 +1231,7 @@ fn parse_value(pair: pest::iterators::Pair<Rule>) -> Result<Value> {
     }
 }
 
-fn parse_size_value(s: &str) -> Result<u64> {
+pub(crate) fn parse_size_value(s: &str) -> Result<u64> {
     let s_upper = s.to_uppercase();
 
     let (num_str, multiplier) = if s_upper.ends_with("TB") {
@@ -842,6 +1268,7 @@ fn parse_action_cmd(pair: pest::iterators::Pair<Rule>) -> Result<ActionCommand>
     match inner.as_rule() {
         Rule::delete_cmd => Ok(ActionCommand::DeleteFiles(parse_delete_cmd(inner)?)),
         Rule::kill_cmd => Ok(ActionCommand::KillProcess(parse_kill_cmd(inner)?)),
+        Rule::write_cmd => Ok(ActionCommand::WriteFile(parse_write_cmd(inner)?)),
         _ => Err(ArtaError::ParseError("Unknown action command".to_string())),
     }
 }
@@ -860,13 +1287,135 @@ fn parse_delete_cmd(pair: pest::iterators::Pair<Rule>) -> Result<DeleteFilesComm
 }
 
 fn parse_kill_cmd(pair: pest::iterators::Pair<Rule>) -> Result<KillProcessCommand> {
-    let where_pair = pair.into_inner().next().ok_or_else(|| {
+    let mut inner = pair.into_inner();
+    let where_pair = inner.next().ok_or_else(|| {
         ArtaError::ParseError("Expected WHERE clause in KILL command".to_string())
     })?;
 
     let where_clause = parse_where_clause(where_pair)?;
+    let confirm_each = inner.next().is_some();
+
+    Ok(KillProcessCommand {
+        where_clause,
+        confirm_each,
+    })
+}
+
+fn parse_write_cmd(pair: pest::iterators::Pair<Rule>) -> Result<WriteFileCommand> {
+    let mut inner = pair.into_inner();
 
-    Ok(KillProcessCommand { where_clause })
+    let content_pair = inner
+        .next()
+        .ok_or_else(|| ArtaError::ParseError("Expected text in WRITE command".to_string()))?;
+    let s = content_pair.as_str();
+    let content = unescape(&s[1..s.len() - 1]);
+
+    let path_pair = inner
+        .next()
+        .ok_or_else(|| ArtaError::ParseError("Expected path in WRITE command".to_string()))?;
+    let path = parse_path_value(path_pair)?;
+
+    let append = inner
+        .next()
+        .is_some_and(|mode| mode.as_str().eq_ignore_ascii_case("APPEND"));
+
+    Ok(WriteFileCommand {
+        path,
+        content,
+        append,
+    })
+}
+
+// ============================================================================
+// Pipeline Parsing
+// ============================================================================
+
+fn parse_pipeline_cmd(pair: pest::iterators::Pair<Rule>) -> Result<PipelineCommand> {
+    let mut inner = pair.into_inner();
+    let source = inner
+        .next()
+        .ok_or_else(|| ArtaError::ParseError("Expected query before |".to_string()))
+        .and_then(parse_query_cmd)?;
+    let sink = match inner.next().map(|p| p.as_str().to_uppercase()).as_deref() {
+        Some("DELETE") => PipeSink::Delete,
+        Some("KILL") => PipeSink::Kill,
+        Some("COUNT") => PipeSink::Count,
+        _ => {
+            return Err(ArtaError::ParseError(
+                "Expected DELETE, KILL or COUNT after |".to_string(),
+            ))
+        }
+    };
+
+    if !sink.accepts(source.target) {
+        let sources = match sink {
+            PipeSink::Delete => "a FILES query",
+            PipeSink::Kill => "a PROCESS query",
+            PipeSink::Count => "a FILES, PROCESS, DISK, NETWORK, BATTERY or SERVICE query",
+        };
+        return Err(ArtaError::ParseError(format!(
+            "| {} needs {}, not {}",
+            sink, sources, source.target
+        )));
+    }
+    if source.every.is_some() || source.follow || source.count {
+        return Err(ArtaError::ParseError(
+            "A piped query cannot use EVERY, FOLLOW or COUNT".to_string(),
+        ));
+    }
+    let confirm_each = inner.next().is_some();
+    if confirm_each && sink != PipeSink::Kill {
+        return Err(ArtaError::ParseError(format!(
+            "CONFIRM EACH only applies to | KILL, not | {}",
+            sink
+        )));
+    }
+
+    Ok(PipelineCommand {
+        source,
+        sink,
+        confirm_each,
+    })
+}
+
+/// Resolve `\n`, `\t`, `\r`, `\\` and `\"` in a string literal
+/// Escape `s` for a string literal, so [`unescape`] gives it back
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(other @ ('\\' | '"')) => out.push(other),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
 }
 
 // ============================================================================
@@ -924,6 +1473,204 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_length_condition() {
+        let cmd = parse_command("SELECT PROCESS * WHERE name.Length > 20").unwrap();
+        match cmd {
+            Command::Query(q) => {
+                let condition = &q.where_clause.unwrap().conditions[0].condition;
+                assert_eq!(condition.field, "name.length");
+                assert!(matches!(condition.value, Value::Number(n) if n == 20.0));
+            }
+            _ => panic!("Expected Query command"),
+        }
+        assert!(parse_command("SELECT FILES * WHERE name.size > 2").is_err());
+    }
+
+    #[test]
+    fn test_parse_where_keeps_full_condition_chain() {
+        let cmd = parse_command("SELECT PROCESS * WHERE cpu > 10 OR memory > 1GB AND name = \"x\"")
+            .unwrap();
+        match cmd {
+            Command::Query(q) => {
+                let expr = &q.where_clause.unwrap().conditions[0];
+                assert_eq!(expr.condition.field, "cpu");
+                let (op, second) = expr.next.as_ref().unwrap();
+                assert_eq!(*op, LogicalOp::Or);
+                assert_eq!(second.condition.field, "memory");
+                let (op, third) = second.next.as_ref().unwrap();
+                assert_eq!(*op, LogicalOp::And);
+                assert_eq!(third.condition.field, "name");
+                assert!(third.next.is_none());
+            }
+            _ => panic!("Expected Query command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_content_line_numbers() {
+        let cases = [
+            ("SELECT CONTENT * FROM /etc/hosts", None),
+            (
+                "SELECT CONTENT * FROM /etc/hosts WITH LINE NUMBERS",
+                Some(true),
+            ),
+            (
+                "SELECT CONTENT * WHERE line CONTAINS \"x\" WITHOUT LINE NUMBERS",
+                Some(false),
+            ),
+        ];
+        for (query, expected) in cases {
+            match parse_command(query).unwrap() {
+                Command::Query(q) => assert_eq!(q.line_numbers, expected, "{}", query),
+                _ => panic!("Expected Query command"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_custom_target() {
+        match parse_command("SELECT gpu usage").unwrap() {
+            Command::Query(q) => {
+                assert_eq!(q.target, QueryTarget::Custom);
+                assert_eq!(q.custom_target.as_deref(), Some("GPU"));
+            }
+            _ => panic!("Expected Query command"),
+        }
+
+        // Built-in keywords still win, but only as whole words
+        for (query, target, custom) in [
+            ("SELECT CPU *", QueryTarget::Cpu, None),
+            ("SELECT PROCESSES *", QueryTarget::Process, None),
+            ("SELECT CPUX *", QueryTarget::Custom, Some("CPUX")),
+        ] {
+            match parse_command(query).unwrap() {
+                Command::Query(q) => {
+                    assert_eq!(q.target, target, "{}", query);
+                    assert_eq!(q.custom_target.as_deref(), custom, "{}", query);
+                }
+                _ => panic!("Expected Query command"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_order_by_and_limit() {
+        match parse_command("SELECT DISK * ORDER BY used_percent DESC LIMIT 1").unwrap() {
+            Command::Query(q) => {
+                let order = q.order_by.unwrap();
+                assert_eq!(order.field, "used_percent");
+                assert_eq!(order.direction, SortDirection::Desc);
+                assert_eq!(q.limit, Some(1));
+            }
+            _ => panic!("Expected Query command"),
+        }
+
+        match parse_command("SELECT DISK name order by free").unwrap() {
+            Command::Query(q) => {
+                assert_eq!(q.order_by.unwrap().direction, SortDirection::Asc);
+                assert_eq!(q.limit, None);
+            }
+            _ => panic!("Expected Query command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_newest_oldest() {
+        match parse_command("SELECT FILES NEWEST 5 FROM /downloads").unwrap() {
+            Command::Query(q) => {
+                assert!(matches!(q.fields, FieldList::All));
+                let order = q.order_by.unwrap();
+                assert_eq!(order.field, "modified");
+                assert_eq!(order.direction, SortDirection::Desc);
+                assert_eq!(q.limit, Some(5));
+                assert_eq!(q.from_path.as_deref(), Some("/downloads"));
+            }
+            _ => panic!("Expected Query command"),
+        }
+
+        match parse_command("select files oldest 2 where size > 1MB").unwrap() {
+            Command::Query(q) => {
+                assert_eq!(q.order_by.unwrap().direction, SortDirection::Asc);
+                assert_eq!(q.limit, Some(2));
+                assert!(q.where_clause.is_some());
+            }
+            _ => panic!("Expected Query command"),
+        }
+
+        // A field that merely starts with the keyword is still a field
+        match parse_command("SELECT FILES newest_name").unwrap() {
+            Command::Query(q) => assert!(q.order_by.is_none()),
+            _ => panic!("Expected Query command"),
+        }
+
+        assert!(parse_command("SELECT PROCESS NEWEST 5").is_err());
+        assert!(parse_command("SELECT FILES NEWEST 5 LIMIT 2").is_err());
+        assert!(parse_command("SELECT FILES OLDEST 5 ORDER BY size").is_err());
+    }
+
+    #[test]
+    fn test_parse_content_count() {
+        match parse_command("SELECT CONTENT COUNT FROM /etc/hosts").unwrap() {
+            Command::Query(q) => {
+                assert!(q.count);
+                assert!(matches!(q.fields, FieldList::All));
+                assert_eq!(q.from_path.as_deref(), Some("/etc/hosts"));
+            }
+            _ => panic!("Expected Query command"),
+        }
+        match parse_command("SELECT CONTENT count_field").unwrap() {
+            Command::Query(q) => assert!(!q.count),
+            _ => panic!("Expected Query command"),
+        }
+        assert!(parse_command("SELECT MEMORY COUNT").is_err());
+        assert!(parse_command("SELECT CONTENT COUNT FROM /var/log/syslog FOLLOW").is_err());
+    }
+
+    #[test]
+    fn test_parse_with_sizes() {
+        match parse_command("SELECT FILES * FROM / WITH SIZES ORDER BY dir_size DESC").unwrap() {
+            Command::Query(q) => {
+                assert!(q.with_sizes);
+                assert_eq!(q.order_by.unwrap().field, "dir_size");
+            }
+            _ => panic!("Expected Query command"),
+        }
+
+        match parse_command("SELECT CONTENT * WITH LINE NUMBERS").unwrap() {
+            Command::Query(q) => assert!(!q.with_sizes),
+            _ => panic!("Expected Query command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_disk_query_include_pseudo() {
+        let cmd = parse_command("SELECT DISK * INCLUDE PSEUDO").unwrap();
+        match cmd {
+            Command::Query(q) => assert!(q.include_pseudo && !q.include_down),
+            _ => panic!("Expected Query command"),
+        }
+
+        let cmd = parse_command("SELECT DISK *").unwrap();
+        match cmd {
+            Command::Query(q) => assert!(!q.include_pseudo),
+            _ => panic!("Expected Query command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_network_query_include_down() {
+        let cmd = parse_command("SELECT NETWORK * WHERE name = \"eth0\" INCLUDE DOWN").unwrap();
+        match cmd {
+            Command::Query(q) => {
+                assert_eq!(q.target, QueryTarget::Network);
+                assert!(q.where_clause.is_some());
+                assert!(q.include_down);
+            }
+            _ => panic!("Expected Query command"),
+        }
+    }
+
     #[test]
     fn test_parse_delete_command() {
         let cmd = parse_command("DELETE FILES FROM /tmp WHERE size > 100MB").unwrap();
@@ -937,16 +1684,49 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::len_zero)]
     fn test_parse_kill_command() {
         let cmd = parse_command("KILL PROCESS WHERE name = \"node\"").unwrap();
         match cmd {
             Command::Action(ActionCommand::KillProcess(k)) => {
                 assert!(k.where_clause.conditions.len() > 0);
+                assert!(!k.confirm_each);
+            }
+            _ => panic!("Expected KillProcess command"),
+        }
+
+        let cmd = parse_command("KILL PROCESS WHERE name = node confirm each").unwrap();
+        match cmd {
+            Command::Action(ActionCommand::KillProcess(k)) => {
+                assert_eq!(k.where_clause.conditions.len(), 1);
+                assert!(k.confirm_each);
             }
             _ => panic!("Expected KillProcess command"),
         }
     }
 
+    #[test]
+    fn test_parse_write_command() {
+        let cmd = parse_command(r#"WRITE "done\n" TO /tmp/flag APPEND"#).unwrap();
+        match cmd {
+            Command::Action(ActionCommand::WriteFile(w)) => {
+                assert_eq!(w.path, "/tmp/flag");
+                assert_eq!(w.content, "done\n");
+                assert!(w.append);
+            }
+            _ => panic!("Expected WriteFile command"),
+        }
+
+        let cmd = parse_command(r#"WRITE "a \"b\"" TO "/tmp/my flag""#).unwrap();
+        match cmd {
+            Command::Action(ActionCommand::WriteFile(w)) => {
+                assert_eq!(w.content, "a \"b\"");
+                assert!(!w.append);
+            }
+            _ => panic!("Expected WriteFile command"),
+        }
+    }
+
     #[test]
     fn test_parse_explain() {
         let cmd = parse_command("EXPLAIN SELECT CPU *").unwrap();
@@ -966,6 +1746,21 @@ mod tests {
         assert_eq!(parse_size_value("500KB").unwrap(), 500 * 1024);
     }
 
+    #[test]
+    fn test_parse_percent_values() {
+        match parse_command("DELETE FILES FROM /cache WHERE size > 1.5%").unwrap() {
+            Command::Action(ActionCommand::DeleteFiles(d)) => {
+                let condition = &d.where_clause.unwrap().conditions[0].condition;
+                assert!(matches!(condition.value, Value::Percent(p) if p == 1.5));
+            }
+            _ => panic!("Expected Delete Files command"),
+        }
+        match parse_command("SELECT MEMORY used > 50%").unwrap() {
+            Command::Check(c) => assert!(matches!(c.value, Value::Percent(p) if p == 50.0)),
+            _ => panic!("Expected Check command"),
+        }
+    }
+
     #[test]
     fn test_case_insensitivity() {
         assert!(parse_command("select cpu *").is_ok());
@@ -1011,13 +1806,43 @@ mod tests {
         assert!(matches!(cmd2, Command::Context(ContextCommand::Exit)));
     }
 
+    #[test]
+    fn test_parse_up_context() {
+        let cmd = parse_command("UP").unwrap();
+        assert!(matches!(cmd, Command::Context(ContextCommand::Up)));
+
+        let cmd2 = parse_command("ENTER PARENT").unwrap();
+        assert!(matches!(cmd2, Command::Context(ContextCommand::Up)));
+    }
+
     #[test]
     fn test_parse_reset_context() {
         let cmd = parse_command("RESET CONTEXT").unwrap();
-        assert!(matches!(cmd, Command::Context(ContextCommand::Reset)));
+        assert!(matches!(
+            cmd,
+            Command::Context(ContextCommand::Reset(ResetTarget::Context))
+        ));
 
         let cmd2 = parse_command("RESET").unwrap();
-        assert!(matches!(cmd2, Command::Context(ContextCommand::Reset)));
+        assert!(matches!(
+            cmd2,
+            Command::Context(ContextCommand::Reset(ResetTarget::Context))
+        ));
+    }
+
+    #[test]
+    fn test_parse_reset_targets() {
+        let cmd = parse_command("RESET VARIABLES").unwrap();
+        assert!(matches!(
+            cmd,
+            Command::Context(ContextCommand::Reset(ResetTarget::Variables))
+        ));
+
+        let cmd = parse_command("reset history").unwrap();
+        assert!(matches!(
+            cmd,
+            Command::Context(ContextCommand::Reset(ResetTarget::History))
+        ));
     }
 
     #[test]
@@ -1053,6 +1878,101 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_show_version() {
+        let cmd = parse_command("SHOW VERSION").unwrap();
+        match cmd {
+            Command::Context(ContextCommand::Show(target)) => {
+                assert_eq!(target, ShowTarget::Version);
+            }
+            _ => panic!("Expected Show History command"),
+        }
+
+        match parse_command("show config").unwrap() {
+            Command::Context(ContextCommand::Show(target)) => {
+                assert_eq!(target, ShowTarget::Config);
+            }
+            other => panic!("Expected Show Config command, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_show_audit() {
+        for (input, expected) in [
+            ("SHOW AUDIT", ShowTarget::Audit(DEFAULT_AUDIT_ENTRIES)),
+            ("SHOW AUDIT 2", ShowTarget::Audit(2)),
+            ("show actions 5", ShowTarget::Audit(5)),
+        ] {
+            match parse_command(input).unwrap() {
+                Command::Context(ContextCommand::Show(target)) => assert_eq!(target, expected),
+                other => panic!("Expected Show Audit command for {}, got {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_pipeline() {
+        match parse_command(r#"SELECT FILES * FROM /tmp WHERE extension = "log" | DELETE"#).unwrap()
+        {
+            Command::Pipeline(p) => {
+                assert_eq!(p.sink, PipeSink::Delete);
+                assert_eq!(p.source.target, QueryTarget::Files);
+                assert_eq!(p.source.from_path.as_deref(), Some("/tmp"));
+                assert!(p.source.where_clause.is_some());
+            }
+            other => panic!("Expected Pipeline command, got {:?}", other),
+        }
+        for (input, sink) in [
+            (
+                "SELECT PROCESS * WHERE name = \"node\" | kill",
+                PipeSink::Kill,
+            ),
+            (
+                "SELECT DISK name ORDER BY total LIMIT 2 | count",
+                PipeSink::Count,
+            ),
+            ("SELECT SERVICES * | COUNT;", PipeSink::Count),
+        ] {
+            match parse_command(input).unwrap() {
+                Command::Pipeline(p) => assert_eq!(p.sink, sink, "{}", input),
+                other => panic!("Expected Pipeline command for {}, got {:?}", input, other),
+            }
+        }
+        match parse_command("SELECT PROCESS * WHERE name = \"node\" | KILL CONFIRM EACH").unwrap() {
+            Command::Pipeline(p) => assert!(p.confirm_each),
+            other => panic!("Expected Pipeline command, got {:?}", other),
+        }
+        // Without a pipe it is still a plain query
+        assert!(matches!(
+            parse_command("SELECT FILES * FROM /tmp").unwrap(),
+            Command::Query(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_pipeline_rejects_mismatched_steps() {
+        let err = |input: &str| parse_command(input).unwrap_err().to_string();
+        assert!(err("SELECT PROCESS * WHERE cpu > 50 | DELETE").contains("needs a FILES query"));
+        assert!(err("SELECT FILES * FROM /tmp | KILL").contains("needs a PROCESS query"));
+        assert!(err("SELECT CPU * | COUNT").contains("not CPU"));
+        assert!(
+            err("SELECT FILES * FROM /tmp EVERY 5 SECONDS | COUNT").contains("cannot use EVERY")
+        );
+        assert!(parse_command("SELECT FILES * FROM /tmp | SORT").is_err());
+        assert!(
+            err("SELECT FILES * FROM /tmp WHERE size > 1GB | DELETE CONFIRM EACH")
+                .contains("CONFIRM EACH only applies to | KILL")
+        );
+
+        let options = ParseOptions::read_only();
+        let input = r#"SELECT FILES * FROM /tmp WHERE size > 1GB"#;
+        let err = parse_command_with_options(&format!("{} | DELETE", input), &options)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("| DELETE is not allowed"), "{}", err);
+        assert!(parse_command_with_options(&format!("{} | COUNT", input), &options).is_ok());
+    }
+
     #[test]
     fn test_parse_content_query() {
         let cmd = parse_command("SELECT CONTENT *").unwrap();
@@ -1306,6 +2226,59 @@ mod tests {
     }
 
     // LIFE monitoring tests
+    #[test]
+    fn test_parse_label() {
+        let cmd = parse_command(
+            "LABEL \"backup\": LET x = 1; LABEL logs: SELECT CPU * END LABEL END LABEL",
+        )
+        .unwrap();
+        match cmd {
+            Command::Label(l) => {
+                assert_eq!(l.name, "backup");
+                assert_eq!(l.body.len(), 2);
+                assert!(matches!(&l.body[1], Command::Label(inner) if inner.name == "logs"));
+            }
+            _ => panic!("Expected Label command"),
+        }
+
+        assert!(parse_command("LABEL \"a.b\": SELECT CPU * END LABEL").is_err());
+        assert!(parse_command("LABEL backup SELECT CPU * END LABEL").is_err());
+    }
+
+    #[test]
+    fn test_parse_define_and_run() {
+        let script = parse_script(
+            r#"
+            DEFINE cleanup AS DELETE FILES FROM /tmp WHERE extension = "tmp";
+            DEFINE report AS DO
+                SELECT MEMORY *;
+                SELECT DISK *
+            END DEFINE
+            RUN cleanup;
+            run report
+            "#,
+        )
+        .unwrap();
+        assert_eq!(script.statements.len(), 4);
+
+        match &script.statements[0] {
+            Command::Define(d) => {
+                assert_eq!(d.name, "cleanup");
+                assert!(matches!(
+                    d.body.as_slice(),
+                    [Command::Action(ActionCommand::DeleteFiles(_))]
+                ));
+            }
+            other => panic!("Expected Define command, got {:?}", other),
+        }
+        assert!(matches!(&script.statements[1], Command::Define(d) if d.body.len() == 2));
+        assert!(matches!(&script.statements[2], Command::RunMacro(name) if name == "cleanup"));
+        assert!(matches!(&script.statements[3], Command::RunMacro(name) if name == "report"));
+
+        assert!(parse_command("DEFINE cleanup AS").is_err());
+        assert!(parse_command("RUN").is_err());
+    }
+
     #[test]
     fn test_parse_life_battery() {
         let cmd = parse_command("LIFE MONITOR BATTERY DO PRINT BATTERY level END LIFE").unwrap();
@@ -1318,6 +2291,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_life_idle_timeout() {
+        let cmd = parse_command(
+            "LIFE MONITOR CPU STOP AFTER 60 SECONDS IDLE DO PRINT CPU usage END LIFE",
+        )
+        .unwrap();
+        match cmd {
+            Command::Life(l) => {
+                assert_eq!(l.idle_secs, Some(60));
+                assert_eq!(l.body.len(), 1);
+            }
+            _ => panic!("Expected Life command"),
+        }
+
+        assert!(parse_command("LIFE MONITOR CPU STOP AFTER 0 SECONDS IDLE DO END LIFE").is_err());
+    }
+
+    #[test]
+    fn test_parse_life_average_window() {
+        let cmd = parse_command(
+            "LIFE MONITOR CPU EVERY 1 SECONDS AVERAGE OVER 10 SECONDS DO PRINT life_usage END LIFE",
+        )
+        .unwrap();
+        match cmd {
+            Command::Life(l) => {
+                assert_eq!(l.interval_secs, Some(1));
+                assert_eq!(l.average_secs, Some(10));
+                assert_eq!(l.idle_secs, None);
+            }
+            _ => panic!("Expected Life command"),
+        }
+    }
+
     #[test]
     fn test_parse_life_memory() {
         let cmd = parse_command("LIFE MONITOR MEMORY DO SELECT MEMORY * END LIFE").unwrap();
@@ -1367,8 +2373,13 @@ mod tests {
             Command::Print(p) => {
                 assert_eq!(p.expressions.len(), 1);
                 match &p.expressions[0] {
-                    PrintExpr::QueryField { target, field } => {
+                    PrintExpr::QueryField {
+                        target,
+                        index,
+                        field,
+                    } => {
                         assert_eq!(*target, QueryTarget::Battery);
+                        assert_eq!(*index, None);
                         assert_eq!(field, "level");
                     }
                     _ => panic!("Expected QueryField expression"),
@@ -1378,6 +2389,178 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_every() {
+        let query = |input: &str| match parse_command(input).unwrap() {
+            Command::Query(q) => q,
+            other => panic!("Expected Query command, got {:?}", other),
+        };
+
+        let q = query("SELECT CPU usage EVERY 2 SECONDS");
+        assert_eq!(q.every, Some(2));
+        assert_eq!(q.every_count, None);
+
+        let q = query("SELECT PROCESS * WHERE cpu > 5 LIMIT 3 EVERY 1 second FOR 10 TIMES");
+        assert_eq!(q.limit, Some(3));
+        assert_eq!(q.every, Some(1));
+        assert_eq!(q.every_count, Some(10));
+
+        assert_eq!(query("SELECT CPU usage").every, None);
+        assert!(parse_command("SELECT CPU usage EVERY 2").is_err());
+    }
+
+    #[test]
+    fn test_parse_follow() {
+        match parse_command(r#"SELECT CONTENT * FROM /var/log/app.log WHERE line = "ERROR" FOLLOW"#)
+            .unwrap()
+        {
+            Command::Query(q) => {
+                assert!(q.follow);
+                assert_eq!(q.from_path.as_deref(), Some("/var/log/app.log"));
+                assert!(q.where_clause.is_some());
+            }
+            other => panic!("Expected Query command, got {:?}", other),
+        }
+        match parse_command("SELECT CONTENT *").unwrap() {
+            Command::Query(q) => assert!(!q.follow),
+            other => panic!("Expected Query command, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_diff() {
+        match parse_command(r#"DIFF /etc/hosts.bak "hosts copy""#).unwrap() {
+            Command::Diff(d) => {
+                assert_eq!(d.old, "/etc/hosts.bak");
+                assert_eq!(d.new, "hosts copy");
+            }
+            other => panic!("Expected Diff command, got {:?}", other),
+        }
+        assert!(parse_command("DIFF /etc/hosts").is_err());
+    }
+
+    #[test]
+    fn test_parse_bare_condition() {
+        match parse_command("SELECT CPU usage > 90").unwrap() {
+            Command::Check(c) => {
+                assert_eq!(c.target, QueryTarget::Cpu);
+                assert_eq!(c.field, "usage");
+            }
+            other => panic!("Expected Check command, got {:?}", other),
+        }
+        assert!(matches!(
+            parse_command("ANY DISK used_percent > 90").unwrap(),
+            Command::Check(_)
+        ));
+        // Without a comparison it is still a plain query
+        assert!(matches!(
+            parse_command("SELECT CPU usage").unwrap(),
+            Command::Query(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_print_inline_query() {
+        match parse_command("PRINT \"cpu:\", SELECT CPU usage, cores, \"done\"").unwrap() {
+            Command::Print(p) => {
+                assert_eq!(p.expressions.len(), 3);
+                match &p.expressions[1] {
+                    PrintExpr::Query(q) => {
+                        assert_eq!(q.target, QueryTarget::Cpu);
+                        assert!(matches!(&q.fields, FieldList::Fields(f) if f.len() == 2));
+                    }
+                    _ => panic!("Expected Query expression"),
+                }
+                assert!(matches!(&p.expressions[2], PrintExpr::String(s) if s == "done"));
+            }
+            _ => panic!("Expected Print command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_indexed_battery_field() {
+        match parse_command("PRINT BATTERY[1] level").unwrap() {
+            Command::Print(p) => match &p.expressions[0] {
+                PrintExpr::QueryField { index, field, .. } => {
+                    assert_eq!(*index, Some(1));
+                    assert_eq!(field, "level");
+                }
+                _ => panic!("Expected QueryField expression"),
+            },
+            _ => panic!("Expected Print command"),
+        }
+
+        match parse_command("IF SELECT BATTERY[0] level < 20 THEN PRINT \"low\" END IF").unwrap() {
+            Command::If(i) => {
+                assert_eq!(i.condition.index, Some(0));
+                assert_eq!(i.condition.field, "level");
+            }
+            _ => panic!("Expected If command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_if_disk_from_and_quantifier() {
+        match parse_command(
+            "IF SELECT DISK used_percent > 90 FROM /home THEN PRINT \"full\" END IF",
+        )
+        .unwrap()
+        {
+            Command::If(i) => {
+                assert_eq!(i.condition.quantifier, None);
+                assert_eq!(i.condition.from_path.as_deref(), Some("/home"));
+            }
+            _ => panic!("Expected If command"),
+        }
+
+        match parse_command("IF ANY DISK used_percent > 90 THEN PRINT \"full\" END IF").unwrap() {
+            Command::If(i) => {
+                assert_eq!(i.condition.quantifier, Some(Quantifier::Any));
+                assert_eq!(i.condition.target, QueryTarget::Disk);
+                assert_eq!(i.condition.field, "used_percent");
+                assert!(i.condition.from_path.is_none());
+            }
+            _ => panic!("Expected If command"),
+        }
+
+        match parse_command("IF all disk free > 1GB THEN PRINT \"ok\" END IF").unwrap() {
+            Command::If(i) => assert_eq!(i.condition.quantifier, Some(Quantifier::All)),
+            _ => panic!("Expected If command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_if_count_with_where() {
+        match parse_command(
+            "IF SELECT PROCESS count > 5 WHERE name = \"node\" AND cpu > 10 THEN PRINT \"busy\" END IF",
+        )
+        .unwrap()
+        {
+            Command::If(i) => {
+                assert_eq!(i.condition.target, QueryTarget::Process);
+                assert_eq!(i.condition.field, "count");
+                let wc = i.condition.where_clause.expect("WHERE clause");
+                assert_eq!(wc.conditions.len(), 1);
+                assert_eq!(wc.conditions[0].condition.field, "name");
+                assert_eq!(i.then_body.len(), 1);
+            }
+            _ => panic!("Expected If command"),
+        }
+
+        match parse_command("SELECT FILES count >= 3 FROM /tmp WHERE size > 1MB").unwrap() {
+            Command::Check(c) => {
+                assert_eq!(c.from_path.as_deref(), Some("/tmp"));
+                assert!(c.where_clause.is_some());
+            }
+            other => panic!("Expected Check command, got {:?}", other),
+        }
+
+        let err = parse_command("SELECT CPU usage > 5 WHERE name = \"x\"").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("only supported for PROCESS and FILES"));
+    }
+
     #[test]
     fn test_parse_print_multiple() {
         let cmd = parse_command("PRINT BATTERY level, \"status:\", BATTERY state").unwrap();
@@ -1418,6 +2601,48 @@ mod tests {
         assert_eq!(script.statements.len(), 3);
     }
 
+    #[test]
+    fn test_parse_script_with_hash_comments_disabled() {
+        let options = ParseOptions {
+            allow_hash_comments: false,
+            ..Default::default()
+        };
+        let script = "-- still a comment\nSELECT CPU *;\n# not a comment\nSELECT MEMORY *";
+        assert!(parse_script(script).is_ok());
+        assert!(parse_script_with_options(script, &options).is_err());
+
+        let without_hash = "-- still a comment\nSELECT CPU *;\n// and this\nSELECT MEMORY *";
+        let parsed = parse_script_with_options(without_hash, &options).unwrap();
+        assert_eq!(parsed.statements.len(), 2);
+    }
+
+    #[test]
+    fn test_read_only_parse_rejects_actions() {
+        let options = ParseOptions::read_only();
+
+        let script = "SELECT CPU *;\nIF SELECT MEMORY used_percent > 90 THEN\n    DELETE FILES FROM /tmp WHERE size > 1GB\nEND IF";
+        let err = parse_script_with_options(script, &options).unwrap_err();
+        assert!(err.to_string().contains("DELETE FILES is not allowed"));
+        assert!(parse_command_with_options(r#"WRITE "x" TO /tmp/flag"#, &options).is_err());
+        assert!(
+            parse_command_with_options("EXPLAIN KILL PROCESS WHERE pid = 1", &options).is_err()
+        );
+
+        let queries = "SELECT CPU *;\nFOR f IN SELECT FILES * FROM /tmp DO\n    PRINT f\nEND FOR";
+        let parsed = parse_script_with_options(queries, &options).unwrap();
+        assert_eq!(parsed.statements.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_command_strict_keeps_strings() {
+        let cmd = parse_command_with_options(r##"PRINT "# -- //""##, &ParseOptions::strict());
+        assert!(matches!(cmd, Ok(Command::Print(_))));
+        assert!(
+            parse_command_with_options("SELECT CPU * -- note", &ParseOptions::strict()).is_err()
+        );
+        assert!(parse_command("SELECT CPU * /* block */").is_ok());
+    }
+
     #[test]
     fn test_parse_script_with_variables_and_loops() {
         let script = parse_script(
@@ -1505,11 +2730,27 @@ mod tests {
     fn test_parse_list_containers() {
         let cmd = parse_command("LIST CONTAINERS").unwrap();
         match cmd {
-            Command::Container(ContainerCommand::List) => {}
+            Command::Container(ContainerCommand::List(None)) => {}
             _ => panic!("Expected List Containers command"),
         }
     }
 
+    #[test]
+    fn test_parse_list_containers_where() {
+        let cmd = parse_command("LIST CONTAINERS WHERE allow_actions = true AND readonly = false")
+            .unwrap();
+        match cmd {
+            Command::Container(ContainerCommand::List(Some(wc))) => {
+                let first = &wc.conditions[0];
+                assert_eq!(first.condition.field, "allow_actions");
+                assert!(matches!(first.condition.value, Value::Boolean(true)));
+                let (_, next) = first.next.as_ref().unwrap();
+                assert_eq!(next.condition.field, "readonly");
+            }
+            _ => panic!("Expected filtered List Containers command"),
+        }
+    }
+
     #[test]
     fn test_parse_destroy_container() {
         let cmd = parse_command("DESTROY CONTAINER \"sandbox\"").unwrap();
@@ -1521,6 +2762,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_export_session() {
+        match parse_command("EXPORT SESSION TO /tmp/session.arta").unwrap() {
+            Command::Container(ContainerCommand::ExportSession(e)) => {
+                assert_eq!(e.path, "/tmp/session.arta");
+                assert!(!e.force);
+            }
+            _ => panic!("Expected Export Session command"),
+        }
+
+        match parse_command("EXPORT SESSION TO /tmp/session.txt FORCE").unwrap() {
+            Command::Container(ContainerCommand::ExportSession(e)) => assert!(e.force),
+            _ => panic!("Expected Export Session command"),
+        }
+    }
+
     #[test]
     fn test_parse_export_container() {
         let cmd = parse_command("EXPORT CONTAINER \"sandbox\" TO /tmp/sandbox.arta").unwrap();
@@ -1528,9 +2785,15 @@ mod tests {
             Command::Container(ContainerCommand::Export(e)) => {
                 assert_eq!(e.name, "sandbox");
                 assert_eq!(e.path, "/tmp/sandbox.arta");
+                assert!(!e.force);
             }
             _ => panic!("Expected Export Container command"),
         }
+
+        match parse_command("EXPORT CONTAINER sandbox TO /tmp/sandbox.txt FORCE").unwrap() {
+            Command::Container(ContainerCommand::Export(e)) => assert!(e.force),
+            _ => panic!("Expected Export Container command"),
+        }
     }
 
     #[test]
@@ -2,6 +2,10 @@
 
 pub mod ast;
 pub mod grammar;
+pub mod options;
 
 pub use ast::*;
-pub use grammar::{parse_command, parse_script};
+pub use grammar::{
+    escape, parse_command, parse_command_with_options, parse_script, parse_script_with_options,
+};
+pub use options::ParseOptions;
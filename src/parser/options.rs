@@ -0,0 +1,152 @@
+//! Parser options and comment handling
+//!
+//! Line comments (`--`, `#`, `//`) are removed before the pest grammar runs,
+//! so each style can be switched off. Block comments (`/* ... */`) are always
+//! handled by the grammar itself.
+
+/// Options controlling which line comment styles and commands the parser accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Accept `# comment`
+    pub allow_hash_comments: bool,
+    /// Accept `// comment`
+    pub allow_double_slash: bool,
+    /// Accept `-- comment`
+    pub allow_double_dash: bool,
+    /// Accept action commands (DELETE, KILL, WRITE), anywhere in the input
+    pub allow_actions: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            allow_hash_comments: true,
+            allow_double_slash: true,
+            allow_double_dash: true,
+            allow_actions: true,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Options with every line comment style disabled
+    pub fn strict() -> Self {
+        Self {
+            allow_hash_comments: false,
+            allow_double_slash: false,
+            allow_double_dash: false,
+            allow_actions: true,
+        }
+    }
+
+    /// Options that reject any action command at parse time
+    pub fn read_only() -> Self {
+        Self {
+            allow_actions: false,
+            ..Self::default()
+        }
+    }
+
+    /// Length of the enabled comment marker at the start of `rest`, if any
+    fn marker_len(&self, rest: &str) -> Option<usize> {
+        if (self.allow_double_dash && rest.starts_with("--"))
+            || (self.allow_double_slash && rest.starts_with("//"))
+        {
+            Some(2)
+        } else if self.allow_hash_comments && rest.starts_with('#') {
+            Some(1)
+        } else {
+            None
+        }
+    }
+}
+
+/// Characters that can continue a bare path, identifier or number, so a
+/// marker right after them is part of the token (e.g. `/tmp//x`)
+fn continues_token(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '.')
+}
+
+/// Blank out enabled line comments, keeping string literals, block comments
+/// and all byte offsets intact so parse errors still point at the right column
+pub(crate) fn strip_line_comments(input: &str, options: &ParseOptions) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut prev: Option<char> = None;
+    let mut rest = input;
+
+    while let Some(c) = rest.chars().next() {
+        let at_boundary = prev.is_none_or(|p| !continues_token(p));
+
+        // Copy string literals and block comments through unchanged
+        let verbatim_len = if c == '"' {
+            Some(string_literal_len(rest))
+        } else if at_boundary && rest.starts_with("/*") {
+            Some(rest.find("*/").map_or(rest.len(), |end| end + 2))
+        } else {
+            None
+        };
+        if let Some(len) = verbatim_len {
+            output.push_str(&rest[..len]);
+            prev = rest[..len].chars().last();
+            rest = &rest[len..];
+            continue;
+        }
+
+        if at_boundary && options.marker_len(rest).is_some() {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            output.extend(std::iter::repeat_n(' ', end));
+            prev = Some(' ');
+            rest = &rest[end..];
+            continue;
+        }
+
+        output.push(c);
+        prev = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    output
+}
+
+/// Length of the string literal at the start of `s` (unterminated: all of it)
+fn string_literal_len(s: &str) -> usize {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_keeps_offsets_and_strings() {
+        let input = "SELECT CPU * -- note\nPRINT \"a # b\" # trailing";
+        let stripped = strip_line_comments(input, &ParseOptions::default());
+        assert_eq!(stripped.len(), input.len());
+        assert_eq!(stripped, "SELECT CPU *        \nPRINT \"a # b\"           ");
+    }
+
+    #[test]
+    fn test_markers_inside_tokens_are_kept() {
+        let input = "ENTER FOLDER /tmp//cache--old";
+        assert_eq!(strip_line_comments(input, &ParseOptions::default()), input);
+    }
+
+    #[test]
+    fn test_disabled_style_is_kept() {
+        let options = ParseOptions {
+            allow_hash_comments: false,
+            ..Default::default()
+        };
+        let input = "# keep\n// drop";
+        assert_eq!(strip_line_comments(input, &options), "# keep\n       ");
+    }
+}
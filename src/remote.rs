@@ -0,0 +1,266 @@
+//! Remote queries over SSH
+//!
+//! Runs `arta --json query ...` on another machine through the system `ssh`
+//! client and turns the JSON it prints back into an `ExecutionResult`, so
+//! the output is rendered locally exactly like a local query.
+
+use crate::engine::executor::{CustomResult, ExecutionContext, ExecutionResult, ResultData};
+use crate::error::{ArtaError, Result};
+use crate::parser::{Command, FieldList, QueryTarget};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Name of the arta binary on the remote host's PATH
+const REMOTE_BINARY: &str = "arta";
+
+/// A host to run queries on, e.g. `user@server`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteHost {
+    pub host: String,
+    /// Private key passed to `ssh -i`
+    pub ssh_key: Option<PathBuf>,
+}
+
+impl RemoteHost {
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            ssh_key: None,
+        }
+    }
+
+    pub fn with_ssh_key(mut self, ssh_key: Option<PathBuf>) -> Self {
+        self.ssh_key = ssh_key;
+        self
+    }
+
+    /// Arguments for the local `ssh` process
+    ///
+    /// BatchMode stops ssh from prompting for a password, which would hang
+    /// a non-interactive run. Options end at `--`, and a host that looks like
+    /// an option is refused, so the host can't smuggle in ssh options.
+    pub fn ssh_args(&self, query: &str, ctx: &ExecutionContext) -> Result<Vec<String>> {
+        if self.host.is_empty() || self.host.starts_with('-') {
+            return Err(ArtaError::ExecutionError(format!(
+                "Invalid host '{}'",
+                self.host
+            )));
+        }
+
+        let mut args = vec!["-o".to_string(), "BatchMode=yes".to_string()];
+        if let Some(ref key) = self.ssh_key {
+            args.push("-i".to_string());
+            args.push(key.display().to_string());
+        }
+        args.push("--".to_string());
+        args.push(self.host.clone());
+        args.push(remote_command(query, ctx));
+        Ok(args)
+    }
+
+    /// Run a query on the host and decode its result
+    pub fn run_query(&self, query: &str, ctx: &ExecutionContext) -> Result<ExecutionResult> {
+        let output = std::process::Command::new("ssh")
+            .args(self.ssh_args(query, ctx)?)
+            .output()
+            .map_err(|e| ArtaError::ExecutionError(format!("Failed to run ssh: {}", e)))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() && !is_false_check(output.status.code(), &stdout) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(ArtaError::ExecutionError(format!(
+                "Remote query on {} failed: {}",
+                self.host,
+                stderr.trim()
+            )));
+        }
+
+        decode_result(&self.host, &crate::parser::parse_command(query)?, &stdout)
+    }
+}
+
+/// Whether a failed remote run is a CHECK whose condition was false
+///
+/// arta exits 1 for a false condition after printing its result, while
+/// errors (arta's or ssh's, which exits 255) leave stdout without a result.
+fn is_false_check(code: Option<i32>, stdout: &str) -> bool {
+    code == Some(1)
+        && serde_json::from_str::<Value>(stdout)
+            .is_ok_and(|value| value.get("result") == Some(&Value::Bool(false)))
+}
+
+/// The command line the remote shell runs
+fn remote_command(query: &str, ctx: &ExecutionContext) -> String {
+    let mut parts = vec![REMOTE_BINARY, "--json"];
+    if ctx.dry_run {
+        parts.push("--dry-run");
+    }
+    if ctx.allow_actions {
+        parts.push("--allow-actions");
+    }
+    let timezone = ctx.timezone.map(|zone| format!("--timezone {}", zone));
+    if let Some(ref timezone) = timezone {
+        parts.push(timezone);
+    }
+    parts.push("query");
+
+    let mut command = parts.join(" ");
+    command.push(' ');
+    command.push_str(&shell_quote(query));
+    command
+}
+
+/// Quote a string for a POSIX shell
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Rebuild a result from the JSON printed by the remote arta
+///
+/// Full `SELECT <target> *` results are decoded into their usual types so
+/// they render the same as local ones; anything else is shown as a generic
+/// result labelled with the host.
+pub fn decode_result(host: &str, cmd: &Command, json: &str) -> Result<ExecutionResult> {
+    let value: Value = serde_json::from_str(json)
+        .map_err(|e| ArtaError::ExecutionError(format!("Invalid JSON from {}: {}", host, e)))?;
+
+    let data = match (cmd, &value) {
+        (_, Value::Object(object)) if object.len() == 1 && object.contains_key("message") => {
+            ResultData::Message(object["message"].as_str().unwrap_or_default().to_string())
+        }
+        (Command::Check(_), Value::Object(object)) if object["result"].is_boolean() => {
+            ResultData::Boolean(object["result"].as_bool().unwrap_or_default())
+        }
+        (Command::Query(query), _) if query.count => ResultData::ContentStats(decode(host, value)?),
+        (Command::Query(query), _) if matches!(query.fields, FieldList::All) => {
+            match query.target {
+                QueryTarget::Cpu => ResultData::Cpu(decode(host, value)?),
+                QueryTarget::Memory => ResultData::Memory(decode(host, value)?),
+                QueryTarget::Disk => ResultData::Disk(decode(host, value)?),
+                QueryTarget::Network => ResultData::Network(decode(host, value)?),
+                QueryTarget::System => ResultData::System(decode(host, value)?),
+                QueryTarget::Battery => ResultData::Battery(decode(host, value)?),
+                QueryTarget::Process => ResultData::Processes(decode(host, value)?),
+                QueryTarget::SelfProc => ResultData::SelfProc(decode(host, value)?),
+                QueryTarget::Files => ResultData::Files(decode(host, value)?),
+                QueryTarget::Content => ResultData::Content(decode(host, value)?),
+                QueryTarget::Services => ResultData::Services(decode(host, value)?),
+                QueryTarget::Custom => generic(host, value),
+            }
+        }
+        _ => generic(host, value),
+    };
+
+    // LIMIT decides whether human output lists every row, as it does locally
+    let limit = match cmd {
+        Command::Query(query) => query.limit,
+        _ => None,
+    };
+    Ok(ExecutionResult::new(data).with_limit(limit))
+}
+
+fn decode<T: DeserializeOwned>(host: &str, value: Value) -> Result<T> {
+    serde_json::from_value(value)
+        .map_err(|e| ArtaError::ExecutionError(format!("Unexpected result from {}: {}", host, e)))
+}
+
+fn generic(host: &str, value: Value) -> ResultData {
+    ResultData::Custom(CustomResult {
+        target: host.to_string(),
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_command;
+
+    #[test]
+    fn test_ssh_args() {
+        let host = RemoteHost::new("ops@db1").with_ssh_key(Some(PathBuf::from("/keys/id")));
+        let ctx = ExecutionContext {
+            dry_run: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            host.ssh_args("SELECT CPU *", &ctx).unwrap(),
+            vec![
+                "-o",
+                "BatchMode=yes",
+                "-i",
+                "/keys/id",
+                "--",
+                "ops@db1",
+                "arta --json --dry-run query 'SELECT CPU *'",
+            ]
+        );
+
+        for host in ["-oProxyCommand=touch /tmp/x", ""] {
+            assert!(RemoteHost::new(host)
+                .ssh_args("SELECT CPU *", &ctx)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_false_check_is_not_a_failure() {
+        assert!(is_false_check(Some(1), "{\"result\": false}\n"));
+        assert!(!is_false_check(Some(1), ""));
+        assert!(!is_false_check(Some(255), "{\"result\": false}"));
+        assert!(!is_false_check(Some(1), "{\"result\": true}"));
+    }
+
+    #[test]
+    fn test_query_is_shell_quoted() {
+        let command = remote_command(
+            "SELECT PROCESS * WHERE name = 'x'; rm -rf ~",
+            &ExecutionContext::default(),
+        );
+        assert_eq!(
+            command,
+            r#"arta --json query 'SELECT PROCESS * WHERE name = '\''x'\''; rm -rf ~'"#
+        );
+    }
+
+    #[test]
+    fn test_decode_result() {
+        let cpu = r#"{"cores": 8, "usage": 12.5, "brand": "Remote CPU", "frequency": 3000}"#;
+        let result = decode_result("db1", &parse_command("SELECT CPU *").unwrap(), cpu).unwrap();
+        match result.data {
+            ResultData::Cpu(info) => {
+                assert_eq!(info.cores, 8);
+                assert_eq!(info.brand, "Remote CPU");
+            }
+            other => panic!("Expected CPU result, got {:?}", other),
+        }
+
+        let projected = r#"{"usage": 12.5}"#;
+        let cmd = parse_command("SELECT CPU usage").unwrap();
+        match decode_result("db1", &cmd, projected).unwrap().data {
+            ResultData::Custom(result) => {
+                assert_eq!(result.target, "db1");
+                assert_eq!(result.value["usage"], 12.5);
+            }
+            other => panic!("Expected generic result, got {:?}", other),
+        }
+
+        // The query's LIMIT carries over, so human output lists every row
+        let cmd = parse_command("SELECT PROCESS * LIMIT 100").unwrap();
+        let result = decode_result("db1", &cmd, "[]").unwrap();
+        assert_eq!(result.limit, Some(100));
+
+        let cmd = parse_command("PRINT \"hi\"").unwrap();
+        match decode_result("db1", &cmd, r#"{"message": "hi"}"#)
+            .unwrap()
+            .data
+        {
+            ResultData::Message(msg) => assert_eq!(msg, "hi"),
+            other => panic!("Expected message, got {:?}", other),
+        }
+
+        assert!(decode_result("db1", &cmd, "not json").is_err());
+    }
+}
@@ -2,23 +2,30 @@
 
 use crate::container::ContainerManager;
 use crate::engine::executor::execute_command_with_context;
+use crate::engine::CancellationToken;
 use crate::error::Result;
+use crate::output::{DisplayZone, FormatterRegistry};
 use crate::{format_output, parse_command, ExecutionContext, OutputFormat};
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
 
-pub fn run_repl() -> Result<()> {
+/// Run the REPL, rendering timestamps in `timezone` if given
+pub fn run_repl(timezone: Option<DisplayZone>) -> Result<()> {
     let mut rl =
         DefaultEditor::new().map_err(|e| crate::error::ArtaError::ExecutionError(e.to_string()))?;
 
     println!("Arta v{} - Interactive Mode", env!("CARGO_PKG_VERSION"));
     println!("Type 'help' for commands, 'exit' to quit\n");
 
-    let exec_ctx = ExecutionContext {
+    let mut exec_ctx = ExecutionContext {
         dry_run: false,
         allow_actions: false,
         output_format: OutputFormat::Human,
         verbose: false,
+        explain_matches: false,
+        cancellation: Some(CancellationToken::ctrl_c()?),
+        timezone,
+        ..Default::default()
     };
 
     // Create container manager for multi-container support
@@ -107,6 +114,19 @@ pub fn run_repl() -> Result<()> {
                         }
                         _ => {}
                     }
+
+                    if let Some(format) =
+                        format_command(line, &exec_ctx.output_format, &exec_ctx.formatters)
+                    {
+                        match format {
+                            Ok(format) => {
+                                println!("Output format: {}\n", format_name(&format));
+                                exec_ctx.output_format = format;
+                            }
+                            Err(e) => eprintln!("Error: {}\n", e),
+                        }
+                        continue;
+                    }
                 }
 
                 let _ = rl.add_history_entry(line);
@@ -128,8 +148,11 @@ pub fn run_repl() -> Result<()> {
                 if upper.contains(" THEN") || upper.contains(" THEN ") || upper.ends_with(" THEN") {
                     block_depth += 1;
                 }
+                if upper.trim_start().starts_with("LABEL ") {
+                    block_depth += 1;
+                }
 
-                // Count closing keywords (END FOR, END IF, END CONTAINER, END LIFE)
+                // Count closing keywords (END FOR, END IF, END CONTAINER, END LIFE, END LABEL)
                 if upper.contains("END FOR") {
                     block_depth = block_depth.saturating_sub(1);
                 }
@@ -142,6 +165,9 @@ pub fn run_repl() -> Result<()> {
                 if upper.contains("END LIFE") {
                     block_depth = block_depth.saturating_sub(1);
                 }
+                if upper.contains("END LABEL") {
+                    block_depth = block_depth.saturating_sub(1);
+                }
 
                 // Add to buffer
                 if !input_buffer.is_empty() {
@@ -195,30 +221,32 @@ pub fn run_repl() -> Result<()> {
                                         }
                                         continue;
                                     }
-                                    crate::parser::ContainerCommand::List => {
-                                        println!("Containers:");
-                                        for name in container_manager.list() {
-                                            let c = container_manager.get(name).unwrap();
-                                            let active = if container_manager.active_name() == name
-                                            {
-                                                " (active)"
-                                            } else {
-                                                ""
-                                            };
-                                            println!(
-                                                "  {} - actions: {}, readonly: {}{}",
-                                                name,
-                                                if c.allow_actions { "yes" } else { "no" },
-                                                if c.readonly { "yes" } else { "no" },
-                                                active
-                                            );
+                                    crate::parser::ContainerCommand::List(where_clause) => {
+                                        match container_manager.list_info(where_clause.as_ref()) {
+                                            Ok(info) => {
+                                                println!("Containers:");
+                                                for c in info.containers.unwrap_or_default() {
+                                                    println!(
+                                                        "  {} - actions: {}, readonly: {}{}",
+                                                        c.name,
+                                                        if c.allow_actions { "yes" } else { "no" },
+                                                        if c.readonly { "yes" } else { "no" },
+                                                        if c.is_active { " (active)" } else { "" }
+                                                    );
+                                                }
+                                                println!();
+                                            }
+                                            Err(e) => eprintln!("Error: {}\n", e),
                                         }
-                                        println!();
                                         continue;
                                     }
                                     crate::parser::ContainerCommand::Export(export) => {
                                         let path = std::path::Path::new(&export.path);
-                                        match container_manager.export(&export.name, path) {
+                                        match container_manager.export(
+                                            &export.name,
+                                            path,
+                                            export.force,
+                                        ) {
                                             Ok(()) => println!(
                                                 "Container '{}' exported to '{}'\n",
                                                 export.name, export.path
@@ -227,11 +255,26 @@ pub fn run_repl() -> Result<()> {
                                         }
                                         continue;
                                     }
+                                    crate::parser::ContainerCommand::ExportSession(export) => {
+                                        match container_manager.export_all(
+                                            std::path::Path::new(&export.path),
+                                            export.force,
+                                        ) {
+                                            Ok(()) => {
+                                                println!("Session exported to '{}'\n", export.path)
+                                            }
+                                            Err(e) => eprintln!("Error: {}\n", e),
+                                        }
+                                        continue;
+                                    }
                                 }
                             }
 
                             // Execute regular commands in active container's context
                             let container = container_manager.active_mut();
+                            if let Some(token) = &exec_ctx.cancellation {
+                                token.reset();
+                            }
                             match execute_command_with_context(
                                 &cmd,
                                 &exec_ctx,
@@ -279,6 +322,36 @@ pub fn run_repl() -> Result<()> {
     Ok(())
 }
 
+/// Handle `:format` and `:<format>` meta-commands, returning the format to use
+///
+/// `None` means the line is not a format command. `:table` is an alias for
+/// the human renderer, which already lays lists out as tables.
+fn format_command(
+    input: &str,
+    current: &OutputFormat,
+    formatters: &FormatterRegistry,
+) -> Option<Result<OutputFormat>> {
+    let spec = input.strip_prefix(':')?.trim();
+    if spec.eq_ignore_ascii_case("format") {
+        return Some(Ok(current.clone()));
+    }
+    if spec.eq_ignore_ascii_case("table") {
+        return Some(Ok(OutputFormat::Human));
+    }
+    Some(formatters.resolve(spec))
+}
+
+fn format_name(format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Human => "human".to_string(),
+        OutputFormat::Json => "json".to_string(),
+        OutputFormat::Properties => "props".to_string(),
+        OutputFormat::Csv => "csv".to_string(),
+        OutputFormat::Tsv => "tsv".to_string(),
+        OutputFormat::Custom(formatter) => format!("custom:{}", formatter.name),
+    }
+}
+
 /// Expand common shortcuts to full commands
 fn expand_shortcuts(input: &str) -> String {
     let lower = input.to_lowercase();
@@ -287,7 +360,10 @@ fn expand_shortcuts(input: &str) -> String {
     if lower.starts_with("cd ") {
         return format!("ENTER FOLDER {}", &input[3..]);
     }
-    if lower == "cd" || lower == ".." {
+    if lower == "cd .." || lower == ".." {
+        return "UP".to_string();
+    }
+    if lower == "cd" {
         return "EXIT".to_string();
     }
     if lower.starts_with("ls") {
@@ -348,17 +424,26 @@ CONTAINERS:
 
   SWITCH CONTAINER "name"         - Switch to a different container
   LIST CONTAINERS                 - List all containers
+  LIST CONTAINERS WHERE readonly = false - Filter on name, allow_actions, readonly, active
   DESTROY CONTAINER "name"        - Destroy a container
   EXPORT CONTAINER "name" TO /path - Export container to script file
+  EXPORT CONTAINER "name" TO /path FORCE - Also replace a file that is not .arta
+  EXPORT SESSION TO /path         - Export all containers to one script
 
 CONTEXT NAVIGATION:
   ENTER FOLDER /path              - Change to directory
   ENTER FILE /path                - Select file for inspection
   EXIT                            - Go back (exit file, then folder)
+  UP, ENTER PARENT                - Go to the parent folder (drops file)
   RESET                           - Reset to initial context
+  RESET VARIABLES, RESET HISTORY  - Clear variables or history only
   SHOW CONTEXT                    - Show current context
   SHOW VARIABLES                  - Show defined variables
   SHOW HISTORY                    - Show navigation history
+  SHOW STACK                      - List entered folders, root to current
+  SHOW VERSION                    - Show Arta version and build info
+  SHOW CONFIG                     - Show dry run, allow actions and other settings
+  SHOW AUDIT [n]                  - Show the last n actions from --audit-log
 
 QUERIES (read-only):
   SELECT CPU *                    - Show CPU information
@@ -368,21 +453,36 @@ QUERIES (read-only):
   SELECT SYSTEM *                 - Show system information  
   SELECT BATTERY *                - Show battery status
   SELECT PROCESS * WHERE cpu > 10 - Show processes with high CPU
+  SELECT SELF *                   - Show Arta's own process stats
+  SELECT SERVICES *               - Show systemd services (Linux)
   SELECT FILES * FROM /path       - List files in directory
   SELECT FILES * FROM my_var      - List files using variable
+  SELECT FILES NEWEST 5 FROM /path - Most recently modified files (OLDEST for least)
   SELECT CONTENT *                - Show content of current file
   SELECT CONTENT * FROM /path     - Show content of specific file
+  SELECT CONTENT COUNT FROM /path - Count lines, words, chars and bytes
+  SELECT CONTENT * FROM /path FOLLOW - Print new lines as they are appended (Ctrl+C to stop)
+  DIFF /old/path /new/path        - Show lines added and removed between two files
+  SELECT CPU usage > 90           - Check a condition (true/false)
+  SELECT CPU usage EVERY 2 SECONDS - Sample until Ctrl+C (FOR n TIMES to stop)
 
 ACTIONS (require --allow-actions at startup):
   DELETE FILES FROM /path WHERE size > 100MB
   KILL PROCESS WHERE name = "process"
+  KILL PROCESS WHERE cpu > 90 CONFIRM EACH - Ask y/N before killing each match
+  WRITE "text\n" TO /path [APPEND]  - Write a file (${{var}} is substituted)
+  SELECT FILES ... WHERE ... | DELETE   - Delete the files a query returned
+  SELECT PROCESS ... WHERE ... | KILL   - Kill the processes a query returned
 
 OTHER:
+  PRINT SELECT MEMORY *           - Print a query result inline as compact JSON
+  SELECT FILES * ... | COUNT      - Count the rows of a query
   EXPLAIN <command>               - Show what a command would do
 
 SHORTCUTS:
   cd /path                        - Same as ENTER FOLDER /path
-  cd or ..                        - Same as EXIT
+  cd                              - Same as EXIT
+  cd .. or ..                     - Same as UP
   ls                              - Same as SELECT FILES *
   ls /path                        - Same as SELECT FILES * FROM /path
   cat /path                       - Same as ENTER FILE /path
@@ -395,6 +495,9 @@ REPL Commands:
   pwd                             - Show current folder
   containers                      - List all containers
   clear, cls                      - Clear screen
+  :json, :human, :props, :csv     - Switch output format (also :tsv, :table)
+  :custom:<name>                  - Switch to a registered formatter
+  :format                         - Show current output format
   exit, quit, q                   - Exit REPL
 
 Note: FOR, IF, CONTAINER, and LIFE blocks can be entered across multiple lines.
@@ -402,3 +505,46 @@ Note: FOR, IF, CONTAINER, and LIFE blocks can be entered across multiple lines.
 "#
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_command_switches_format() {
+        let formatters = FormatterRegistry::default();
+        let human = OutputFormat::Human;
+
+        assert_eq!(
+            format_command(":json", &human, &formatters)
+                .unwrap()
+                .unwrap(),
+            OutputFormat::Json
+        );
+        assert_eq!(
+            format_command(":HUMAN", &OutputFormat::Json, &formatters)
+                .unwrap()
+                .unwrap(),
+            OutputFormat::Human
+        );
+        assert_eq!(
+            format_command(":format", &OutputFormat::Json, &formatters)
+                .unwrap()
+                .unwrap(),
+            OutputFormat::Json
+        );
+        assert_eq!(
+            format_command(":csv", &human, &formatters)
+                .unwrap()
+                .unwrap(),
+            OutputFormat::Csv
+        );
+        assert_eq!(
+            format_command(":table", &OutputFormat::Json, &formatters)
+                .unwrap()
+                .unwrap(),
+            OutputFormat::Human
+        );
+        assert!(format_command("pwd", &human, &formatters).is_none());
+    }
+}
@@ -7,7 +7,7 @@ pub mod interactive;
 pub use interactive::run_repl;
 
 #[cfg(not(feature = "repl"))]
-pub fn run_repl() -> crate::error::Result<()> {
+pub fn run_repl(_timezone: Option<crate::output::DisplayZone>) -> crate::error::Result<()> {
     Err(crate::error::ArtaError::ExecutionError(
         "REPL not enabled. Rebuild with --features repl".to_string(),
     ))
@@ -0,0 +1,165 @@
+//! Script macros: `DEFINE name AS ...` and `RUN name`
+//!
+//! Macros are expanded before a script runs. A definition's body is expanded
+//! when the definition is read, so a macro can only use macros defined above
+//! it, which also rules out recursion. A DEFINE inside a block (IF, FOR,
+//! LABEL, ... or another DEFINE) is only visible within that block.
+
+use std::collections::HashMap;
+
+use crate::error::{ArtaError, Result};
+use crate::parser::{Command, ContainerCommand};
+
+/// Macro bodies by name
+pub type Macros = HashMap<String, Vec<Command>>;
+
+/// Record the DEFINEs in `statements` and replace each `RUN` with its body
+pub fn expand_macros(statements: &[Command], macros: &mut Macros) -> Result<Vec<Command>> {
+    expand(statements, macros, None)
+}
+
+fn expand(
+    statements: &[Command],
+    macros: &mut Macros,
+    defining: Option<&str>,
+) -> Result<Vec<Command>> {
+    let mut expanded = Vec::with_capacity(statements.len());
+
+    for cmd in statements {
+        match cmd {
+            Command::Define(def) => {
+                if macros.contains_key(&def.name) {
+                    return Err(ArtaError::ExecutionError(format!(
+                        "Macro '{}' is already defined",
+                        def.name
+                    )));
+                }
+                let body = expand_scoped(&def.body, macros, Some(&def.name))?;
+                macros.insert(def.name.clone(), body);
+            }
+            Command::RunMacro(name) => {
+                if defining == Some(name.as_str()) {
+                    return Err(ArtaError::ExecutionError(format!(
+                        "Macro '{}' cannot RUN itself",
+                        name
+                    )));
+                }
+                let body = macros.get(name).ok_or_else(|| {
+                    ArtaError::ExecutionError(format!("Unknown macro '{}'", name))
+                })?;
+                expanded.extend(body.iter().cloned());
+            }
+            _ => expanded.push(expand_nested(cmd, macros, defining)?),
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Expand a block body, keeping the macros it defines to itself
+fn expand_scoped(
+    statements: &[Command],
+    macros: &Macros,
+    defining: Option<&str>,
+) -> Result<Vec<Command>> {
+    expand(statements, &mut macros.clone(), defining)
+}
+
+/// Expand RUNs inside the body of a block command
+fn expand_nested(cmd: &Command, macros: &Macros, defining: Option<&str>) -> Result<Command> {
+    let mut cmd = cmd.clone();
+    match &mut cmd {
+        Command::For(f) => f.body = expand_scoped(&f.body, macros, defining)?,
+        Command::If(i) => {
+            i.then_body = expand_scoped(&i.then_body, macros, defining)?;
+            if let Some(else_body) = &i.else_body {
+                i.else_body = Some(expand_scoped(else_body, macros, defining)?);
+            }
+        }
+        Command::Life(l) => l.body = expand_scoped(&l.body, macros, defining)?,
+        Command::Label(l) => l.body = expand_scoped(&l.body, macros, defining)?,
+        Command::Container(ContainerCommand::Create(c)) => {
+            c.body = expand_scoped(&c.body, macros, defining)?
+        }
+        _ => {}
+    }
+    Ok(cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_script;
+
+    fn expand_source(source: &str) -> Result<Vec<Command>> {
+        expand_macros(&parse_script(source)?.statements, &mut Macros::new())
+    }
+
+    #[test]
+    fn test_run_inlines_body() {
+        let statements = expand_source(
+            r#"
+            DEFINE status AS DO
+                PRINT "memory";
+                SELECT MEMORY *
+            END DEFINE
+            RUN status;
+            IF SELECT CPU usage >= 0 THEN
+                RUN status
+            END IF
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(statements.len(), 3);
+        assert!(matches!(statements[0], Command::Print(_)));
+        assert!(matches!(statements[1], Command::Query(_)));
+        match &statements[2] {
+            Command::If(i) => assert_eq!(i.then_body.len(), 2),
+            other => panic!("Expected If, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rejects_unknown_and_recursive_macros() {
+        let err = expand_source("RUN missing").unwrap_err();
+        assert!(err.to_string().contains("Unknown macro 'missing'"));
+
+        let err = expand_source("DEFINE loop AS RUN loop").unwrap_err();
+        assert!(err.to_string().contains("cannot RUN itself"));
+
+        // Macros only see definitions above them
+        let err = expand_source("DEFINE a AS RUN b\nDEFINE b AS RUN a").unwrap_err();
+        assert!(err.to_string().contains("Unknown macro 'b'"));
+
+        let err = expand_source("DEFINE a AS PRINT \"x\"\nDEFINE a AS PRINT \"y\"").unwrap_err();
+        assert!(err.to_string().contains("already defined"));
+    }
+
+    #[test]
+    fn test_block_macros_stay_in_their_block() {
+        let mut macros = Macros::new();
+        let source = r#"
+            IF SELECT CPU usage >= 0 THEN
+                DEFINE inner AS PRINT "inner";
+                RUN inner
+            END IF
+            "#;
+        let statements = expand_macros(&parse_script(source).unwrap().statements, &mut macros);
+        match &statements.unwrap()[0] {
+            Command::If(i) => assert!(matches!(i.then_body[..], [Command::Print(_)])),
+            other => panic!("Expected If, got {:?}", other),
+        }
+        assert!(macros.is_empty());
+
+        let err = expand_source(&format!("{}\nRUN inner", source)).unwrap_err();
+        assert!(err.to_string().contains("Unknown macro 'inner'"));
+
+        // Neither does a DEFINE inside a macro body
+        let err = expand_source(
+            "DEFINE outer AS DO\n DEFINE inner AS PRINT \"x\"\nEND DEFINE\nRUN inner",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Unknown macro 'inner'"));
+    }
+}
@@ -2,11 +2,12 @@
 //!
 //! Handles loading, validating, and executing .arta script files.
 
+pub mod macros;
 pub mod runner;
 pub mod validator;
 
-pub use runner::{explain_script, ScriptResult, ScriptRunner};
+pub use runner::{explain_script, ArgType, ScriptResult, ScriptRunner};
 pub use validator::{
-    has_errors, has_warnings, validate_script, ScriptValidationError, ValidationOptions,
-    ValidationSeverity,
+    has_errors, has_warnings, validate_script, validate_script_report, validation_exit_code,
+    ScriptValidationError, ValidationOptions, ValidationReport, ValidationSeverity,
 };
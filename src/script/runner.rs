@@ -3,12 +3,15 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 use crate::context::{Context, VariableValue};
+use crate::engine::actions::ActionSummary;
 use crate::engine::{execute_command_with_context, ExecutionContext, ExecutionResult, ResultData};
 use crate::error::{ArtaError, Result};
-use crate::output::{format_output, OutputFormat};
+use crate::output::{OutputFormat, OutputSink};
 use crate::parser::{parse_script, Command, Script};
+use crate::script::macros::{expand_macros, Macros};
 
 /// Result of script execution
 #[derive(Debug)]
@@ -21,6 +24,21 @@ pub struct ScriptResult {
     pub success: bool,
     /// Error message if script failed
     pub error: Option<String>,
+    /// Wall-clock duration of each statement, in execution order
+    pub statement_durations: Vec<Duration>,
+    /// Wall-clock duration of the whole run
+    pub total_duration: Duration,
+    /// What the actions would have changed, for dry runs
+    pub dry_run_summary: Option<ActionSummary>,
+}
+
+/// Explicit type for a script argument passed via `--arg-<type>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgType {
+    Size,
+    Number,
+    Path,
+    String,
 }
 
 /// Script runner that manages script execution
@@ -31,6 +49,14 @@ pub struct ScriptRunner {
     context: Context,
     /// Script arguments passed via --arg
     script_args: HashMap<String, String>,
+    /// Explicitly typed script arguments (take precedence over --arg)
+    typed_args: HashMap<String, VariableValue>,
+    /// Restore the context snapshot if the script fails
+    transactional: bool,
+    /// Run only this LABEL section (`parent.child` for nested ones)
+    only: Option<String>,
+    /// Macros from DEFINE statements, inlined at each RUN
+    macros: Macros,
 }
 
 impl ScriptRunner {
@@ -40,9 +66,19 @@ impl ScriptRunner {
             exec_ctx,
             context: Context::new(),
             script_args: HashMap::new(),
+            typed_args: HashMap::new(),
+            transactional: false,
+            only: None,
+            macros: Macros::new(),
         }
     }
 
+    /// Start from an existing context, e.g. a container's, instead of a fresh one
+    pub fn with_context(mut self, context: Context) -> Self {
+        self.context = context;
+        self
+    }
+
     /// Set script arguments
     pub fn with_args(mut self, args: Vec<String>) -> Self {
         for arg in args {
@@ -53,6 +89,43 @@ impl ScriptRunner {
         self
     }
 
+    /// Set script arguments with an explicit type
+    pub fn with_typed_args(mut self, arg_type: ArgType, args: Vec<String>) -> Result<Self> {
+        for arg in args {
+            let (key, value) = arg.split_once('=').ok_or_else(|| {
+                ArtaError::ExecutionError(format!("Expected KEY=VALUE argument, got '{}'", arg))
+            })?;
+
+            let var_value = match arg_type {
+                ArgType::Size => {
+                    VariableValue::Size(crate::parser::grammar::parse_size_value(value)?)
+                }
+                ArgType::Number => VariableValue::Number(value.parse().map_err(|_| {
+                    ArtaError::ExecutionError(format!("Invalid number for '{}': {}", key, value))
+                })?),
+                ArgType::Path => VariableValue::Path(std::path::PathBuf::from(value)),
+                ArgType::String => VariableValue::String(value.to_string()),
+            };
+
+            self.typed_args.insert(key.to_string(), var_value);
+        }
+        Ok(self)
+    }
+
+    /// Roll back the in-memory context when the script fails
+    pub fn with_transactional(mut self, transactional: bool) -> Self {
+        self.transactional = transactional;
+        self
+    }
+
+    /// Run only the named LABEL section instead of the whole script
+    ///
+    /// Top-level LET statements still run, so sections can share variables.
+    pub fn with_only(mut self, label: Option<String>) -> Self {
+        self.only = label;
+        self
+    }
+
     /// Load and run a script file
     pub fn run_file(&mut self, path: &Path) -> Result<ScriptResult> {
         // Validate file extension
@@ -81,8 +154,33 @@ impl ScriptRunner {
         let mut results = Vec::new();
         let mut statements_executed = 0;
 
-        for cmd in &script.statements {
-            match execute_command_with_context(cmd, &self.exec_ctx, &mut self.context) {
+        // Snapshot the context so a failed run can be rolled back
+        let snapshot = self.transactional.then(|| self.context.clone());
+        let mut actions_ran = false;
+
+        let run_started = Instant::now();
+        let mut statement_durations = Vec::new();
+        let mut sink = OutputSink::for_context(&self.exec_ctx);
+        let mut summary = ActionSummary::default();
+
+        self.macros.clear();
+        let expanded = expand_macros(&script.statements, &mut self.macros)?;
+        let statements: Vec<&Command> = match &self.only {
+            Some(path) => select_label(&expanded, path)?,
+            None => expanded.iter().collect(),
+        };
+
+        for (i, cmd) in statements.into_iter().enumerate() {
+            if !self.exec_ctx.dry_run && contains_action(cmd) {
+                actions_ran = true;
+            }
+
+            let started = Instant::now();
+            let outcome = execute_command_with_context(cmd, &self.exec_ctx, &mut self.context);
+            let elapsed = started.elapsed();
+            statement_durations.push(elapsed);
+
+            match outcome {
                 Ok(result) => {
                     statements_executed += 1;
 
@@ -90,31 +188,62 @@ impl ScriptRunner {
                     match &result.data {
                         ResultData::Empty => {}
                         ResultData::Message(msg) if self.exec_ctx.verbose => {
-                            println!("{}", msg);
-                        }
-                        _ => {
-                            println!("{}", format_output(&result, &self.exec_ctx.output_format));
+                            sink.write_line(msg)?;
                         }
+                        _ => sink.write_result(&result)?,
                     }
 
+                    if self.exec_ctx.verbose {
+                        sink.write_line(&format!(
+                            "[{}ms] {}. {}",
+                            elapsed.as_millis(),
+                            i + 1,
+                            explain_command(cmd).trim_end()
+                        ))?;
+                    }
+
+                    add_actions(&result.data, &mut summary);
                     results.push(result);
                 }
                 Err(e) => {
+                    if let Some(snapshot) = snapshot {
+                        self.context = snapshot;
+                        if actions_ran {
+                            eprintln!(
+                                "Warning: context rolled back, but file system and process actions cannot be undone"
+                            );
+                        }
+                    }
+
                     return Ok(ScriptResult {
                         results,
                         statements_executed,
                         success: false,
                         error: Some(e.to_string()),
+                        statement_durations,
+                        total_duration: run_started.elapsed(),
+                        dry_run_summary: None,
                     });
                 }
             }
         }
 
+        // One line totalling every action, after their individual reports
+        let dry_run_summary = (self.exec_ctx.dry_run && !summary.is_empty()).then_some(summary);
+        if let Some(summary) = &dry_run_summary {
+            sink.write_result(&ExecutionResult::new(ResultData::Message(
+                summary.to_string(),
+            )))?;
+        }
+
         Ok(ScriptResult {
             results,
             statements_executed,
             success: true,
             error: None,
+            statement_durations,
+            total_duration: run_started.elapsed(),
+            dry_run_summary,
         })
     }
 
@@ -136,6 +265,10 @@ impl ScriptRunner {
 
             self.context.set_variable(key.clone(), var_value);
         }
+
+        for (key, value) in &self.typed_args {
+            self.context.set_variable(key.clone(), value.clone());
+        }
     }
 
     /// Get the output format
@@ -144,6 +277,71 @@ impl ScriptRunner {
     }
 }
 
+/// Add the action results in `data`, including those nested in blocks
+fn add_actions(data: &ResultData, summary: &mut ActionSummary) {
+    match data {
+        ResultData::ActionResult(action) => summary.add(action),
+        ResultData::Multiple(results) => {
+            for result in results {
+                add_actions(&result.data, summary);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Statements to run for `--only`: the top-level LET and ENTER/EXIT
+/// statements before the section, then the section's body
+fn select_label<'a>(statements: &'a [Command], path: &str) -> Result<Vec<&'a Command>> {
+    let missing = || ArtaError::ExecutionError(format!("No LABEL '{}' in script", path));
+    let is_label = |cmd: &Command, name: &str| matches!(cmd, Command::Label(l) if l.name == name);
+
+    let first = path.split('.').next().unwrap_or_default();
+    let position = statements
+        .iter()
+        .position(|cmd| is_label(cmd, first))
+        .ok_or_else(missing)?;
+
+    let mut body = statements;
+    for name in path.split('.') {
+        body = body
+            .iter()
+            .find_map(|cmd| match cmd {
+                Command::Label(l) if l.name == name => Some(l.body.as_slice()),
+                _ => None,
+            })
+            .ok_or_else(missing)?;
+    }
+
+    Ok(statements[..position]
+        .iter()
+        .filter(|cmd| matches!(cmd, Command::Let(_) | Command::Context(_)))
+        .chain(body)
+        .collect())
+}
+
+/// Check whether a command (or any nested body) performs a system action
+fn contains_action(cmd: &Command) -> bool {
+    match cmd {
+        Command::Action(_) => true,
+        Command::Pipeline(p) => p.sink.action_name().is_some(),
+        Command::For(f) => f.body.iter().any(contains_action),
+        Command::If(i) => {
+            i.then_body.iter().any(contains_action)
+                || i.else_body
+                    .as_ref()
+                    .is_some_and(|e| e.iter().any(contains_action))
+        }
+        Command::Life(l) => l.body.iter().any(contains_action),
+        Command::Label(l) => l.body.iter().any(contains_action),
+        Command::Define(d) => d.body.iter().any(contains_action),
+        Command::Container(crate::parser::ContainerCommand::Create(c)) => {
+            c.body.iter().any(contains_action)
+        }
+        _ => false,
+    }
+}
+
 /// Explain a script without executing
 pub fn explain_script(script: &Script) -> Vec<String> {
     let mut explanations = Vec::new();
@@ -162,6 +360,7 @@ fn explain_command(cmd: &Command) -> String {
                 "SELECT {} {} {}{}",
                 q.target,
                 match &q.fields {
+                    _ if q.count => "COUNT".to_string(),
                     crate::parser::FieldList::All => "*".to_string(),
                     crate::parser::FieldList::Fields(f) => f.join(", "),
                 },
@@ -186,15 +385,27 @@ fn explain_command(cmd: &Command) -> String {
                         .unwrap_or("")
                 )
             }
-            crate::parser::ActionCommand::KillProcess(_) => {
-                "KILL PROCESS with filtering".to_string()
+            crate::parser::ActionCommand::KillProcess(k) => format!(
+                "KILL PROCESS with filtering{}",
+                if k.confirm_each { " CONFIRM EACH" } else { "" }
+            ),
+            crate::parser::ActionCommand::WriteFile(w) => {
+                format!(
+                    "WRITE TO {}{}",
+                    w.path,
+                    if w.append { " APPEND" } else { "" }
+                )
             }
         },
         Command::Context(c) => match c {
             crate::parser::ContextCommand::EnterFolder(p) => format!("ENTER FOLDER {}", p),
             crate::parser::ContextCommand::EnterFile(p) => format!("ENTER FILE {}", p),
             crate::parser::ContextCommand::Exit => "EXIT".to_string(),
-            crate::parser::ContextCommand::Reset => "RESET".to_string(),
+            crate::parser::ContextCommand::Up => "UP".to_string(),
+            crate::parser::ContextCommand::Reset(crate::parser::ResetTarget::Context) => {
+                "RESET".to_string()
+            }
+            crate::parser::ContextCommand::Reset(t) => format!("RESET {}", t),
             crate::parser::ContextCommand::Show(t) => format!("SHOW {}", t),
         },
         Command::Let(l) => format!("LET {} = {:?}", l.name, l.value),
@@ -220,6 +431,9 @@ fn explain_command(cmd: &Command) -> String {
         Command::Life(l) => {
             format!("LIFE MONITOR {} ({} statements)", l.target, l.body.len())
         }
+        Command::Label(l) => format!("LABEL \"{}\" ({} statements)", l.name, l.body.len()),
+        Command::Define(d) => format!("DEFINE {} ({} statements)", d.name, d.body.len()),
+        Command::RunMacro(name) => format!("RUN {}", name),
         Command::Print(p) => {
             format!("PRINT ({} expressions)", p.expressions.len())
         }
@@ -234,15 +448,37 @@ fn explain_command(cmd: &Command) -> String {
             crate::parser::ContainerCommand::Switch(name) => {
                 format!("SWITCH CONTAINER \"{}\"", name)
             }
-            crate::parser::ContainerCommand::List => "LIST CONTAINERS".to_string(),
+            crate::parser::ContainerCommand::List(None) => "LIST CONTAINERS".to_string(),
+            crate::parser::ContainerCommand::List(Some(_)) => {
+                "LIST CONTAINERS WHERE ...".to_string()
+            }
             crate::parser::ContainerCommand::Destroy(name) => {
                 format!("DESTROY CONTAINER \"{}\"", name)
             }
             crate::parser::ContainerCommand::Export(e) => {
-                format!("EXPORT CONTAINER \"{}\" TO \"{}\"", e.name, e.path)
+                format!(
+                    "EXPORT CONTAINER \"{}\" TO \"{}\"{}",
+                    e.name,
+                    e.path,
+                    if e.force { " FORCE" } else { "" }
+                )
+            }
+            crate::parser::ContainerCommand::ExportSession(e) => {
+                format!(
+                    "EXPORT SESSION TO \"{}\"{}",
+                    e.path,
+                    if e.force { " FORCE" } else { "" }
+                )
             }
         },
         Command::Explain(inner) => format!("EXPLAIN {}", explain_command(inner)),
+        Command::Diff(d) => format!("DIFF {} {}", d.old, d.new),
+        Command::Pipeline(p) => format!(
+            "{} | {}",
+            explain_command(&Command::Query(p.source.clone())),
+            p.sink
+        ),
+        Command::Check(c) => format!("CHECK {} {} {} {}", c.target, c.field, c.operator, c.value),
     }
 }
 
@@ -264,6 +500,192 @@ mod tests {
         assert_eq!(runner.script_args.get("threshold"), Some(&"80".to_string()));
     }
 
+    #[test]
+    fn test_typed_args() {
+        let mut runner = ScriptRunner::new(ExecutionContext::default())
+            .with_args(vec!["flag=true".to_string()])
+            .with_typed_args(ArgType::Size, vec!["max=100MB".to_string()])
+            .unwrap()
+            .with_typed_args(ArgType::Number, vec!["threshold=80".to_string()])
+            .unwrap()
+            .with_typed_args(ArgType::Path, vec!["dir=/tmp".to_string()])
+            .unwrap()
+            .with_typed_args(ArgType::String, vec!["flag=true".to_string()])
+            .unwrap();
+        runner.inject_script_args();
+
+        let var = |name| runner.context.get_variable(name).cloned();
+        assert!(matches!(var("max"), Some(VariableValue::Size(s)) if s == 100 * 1024 * 1024));
+        assert!(matches!(var("threshold"), Some(VariableValue::Number(n)) if n == 80.0));
+        assert!(matches!(var("dir"), Some(VariableValue::Path(p)) if p == Path::new("/tmp")));
+        // The typed string overrides the heuristic boolean
+        assert!(matches!(var("flag"), Some(VariableValue::String(s)) if s == "true"));
+    }
+
+    #[test]
+    fn test_typed_args_reject_bad_values() {
+        let runner = ScriptRunner::new(ExecutionContext::default());
+        assert!(runner
+            .with_typed_args(ArgType::Number, vec!["threshold=high".to_string()])
+            .is_err());
+
+        let runner = ScriptRunner::new(ExecutionContext::default());
+        assert!(runner
+            .with_typed_args(ArgType::Size, vec!["max".to_string()])
+            .is_err());
+    }
+
+    #[test]
+    fn test_transactional_rollback_on_failure() {
+        let script = parse_script(
+            "LET before = 1; LET during = 2; SELECT CONTENT * FROM /nonexistent_arta_file",
+        )
+        .unwrap();
+
+        let mut runner = ScriptRunner::new(ExecutionContext::default()).with_transactional(true);
+        let result = runner.run_script(&script).unwrap();
+
+        assert!(!result.success);
+        assert!(runner.context.get_variable("before").is_none());
+        assert!(runner.context.get_variable("during").is_none());
+    }
+
+    #[test]
+    fn test_non_transactional_keeps_partial_state() {
+        let script =
+            parse_script("LET before = 1; SELECT CONTENT * FROM /nonexistent_arta_file").unwrap();
+
+        let mut runner = ScriptRunner::new(ExecutionContext::default());
+        let result = runner.run_script(&script).unwrap();
+
+        assert!(!result.success);
+        assert!(runner.context.get_variable("before").is_some());
+    }
+
+    #[test]
+    fn test_statement_durations_recorded() {
+        let script = parse_script("LET a = 1; LET b = 2; PRINT \"done\"").unwrap();
+
+        let mut runner = ScriptRunner::new(ExecutionContext::default());
+        let result = runner.run_script(&script).unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.statement_durations.len(), 3);
+        let sum: Duration = result.statement_durations.iter().sum();
+        assert!(result.total_duration >= sum);
+    }
+
+    #[test]
+    fn test_dry_run_summary_totals_actions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("a.log"), vec![0u8; 1000]).unwrap();
+        fs::write(dir.path().join("b.log"), vec![0u8; 500]).unwrap();
+        let script = parse_script(&format!(
+            r#"DELETE FILES FROM "{}" WHERE extension = "log"; KILL PROCESS WHERE pid = {}"#,
+            dir.path().display(),
+            std::process::id()
+        ))
+        .unwrap();
+
+        let ctx = ExecutionContext {
+            dry_run: true,
+            ..Default::default()
+        };
+        let result = ScriptRunner::new(ctx).run_script(&script).unwrap();
+
+        let summary = result.dry_run_summary.unwrap();
+        assert_eq!(
+            summary,
+            ActionSummary {
+                files: 2,
+                bytes: 1500,
+                processes: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            summary.to_string(),
+            "Would delete 2 files (1.5 KB), would kill 1 process"
+        );
+        assert!(dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn test_only_runs_selected_label() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let script = parse_script(&format!(
+            r#"
+            LET shared = 1
+            ENTER FOLDER "{}"
+            LABEL backup:
+                LET backed_up = 1
+            END LABEL
+            LABEL "cleanup":
+                LET cleaned = 1
+                LABEL logs:
+                    LET logs_cleaned = 1
+                END LABEL
+            END LABEL
+            LET after = 1
+            "#,
+            dir.path().display()
+        ))
+        .unwrap();
+        let ran = |only: &str| {
+            let mut runner =
+                ScriptRunner::new(ExecutionContext::default()).with_only(Some(only.to_string()));
+            assert!(runner.run_script(&script).unwrap().success);
+            // The section runs in the folder entered before it
+            assert_eq!(runner.context.current_folder(), dir.path());
+            ["shared", "backed_up", "cleaned", "logs_cleaned", "after"]
+                .into_iter()
+                .filter(|v| runner.context.get_variable(v).is_some())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ran("backup"), ["shared", "backed_up"]);
+        assert_eq!(ran("cleanup"), ["shared", "cleaned", "logs_cleaned"]);
+        assert_eq!(ran("cleanup.logs"), ["shared", "logs_cleaned"]);
+
+        let mut runner =
+            ScriptRunner::new(ExecutionContext::default()).with_only(Some("logs".to_string()));
+        assert!(runner.run_script(&script).is_err());
+
+        let mut runner = ScriptRunner::new(ExecutionContext::default());
+        runner.run_script(&script).unwrap();
+        assert!(runner.context.get_variable("backed_up").is_some());
+    }
+
+    #[test]
+    fn test_macros_are_inlined() {
+        let script = parse_script(
+            r#"
+            DEFINE setup AS DO
+                LET a = 1;
+                LET b = 2
+            END DEFINE
+            RUN setup;
+            PRINT "done"
+            "#,
+        )
+        .unwrap();
+
+        let mut runner = ScriptRunner::new(ExecutionContext::default());
+        let result = runner.run_script(&script).unwrap();
+        assert!(result.success);
+        assert_eq!(result.statements_executed, 3);
+        assert!(runner.context.get_variable("a").is_some());
+        assert!(runner.context.get_variable("b").is_some());
+        assert_eq!(runner.macros["setup"].len(), 2);
+
+        // Running the script again starts from its own definitions
+        assert!(runner.run_script(&script).unwrap().success);
+
+        let script = parse_script("PRINT \"start\"; RUN missing").unwrap();
+        let err = runner.run_script(&script).unwrap_err();
+        assert!(err.to_string().contains("Unknown macro 'missing'"));
+    }
+
     #[test]
     fn test_explain_script() {
         let script = parse_script("SELECT CPU *; SELECT MEMORY *").unwrap();
@@ -2,10 +2,16 @@
 //!
 //! Validates scripts before execution for safety and correctness.
 
-use crate::parser::{ActionCommand, Command, Script};
+use crate::engine::actions::files::is_protected_path;
+use crate::error::ArtaError;
+use crate::parser::{ActionCommand, Command, ContainerCommand, Script};
+use crate::script::macros::{expand_macros, Macros};
+use crossterm::style::Stylize;
+use serde::Serialize;
+use std::collections::HashSet;
 
 /// Errors that can occur during script validation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ScriptValidationError {
     pub line: Option<usize>,
     pub message: String,
@@ -13,26 +19,35 @@ pub struct ScriptValidationError {
 }
 
 /// Severity level for validation issues
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ValidationSeverity {
     Error,
     Warning,
 }
 
-impl std::fmt::Display for ScriptValidationError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let prefix = match self.severity {
-            ValidationSeverity::Error => "ERROR",
-            ValidationSeverity::Warning => "WARNING",
+impl ScriptValidationError {
+    /// Same text as `Display`, with the severity in red or yellow when `color` is set
+    pub fn render(&self, color: bool) -> String {
+        let prefix = match (self.severity, color) {
+            (ValidationSeverity::Error, true) => "ERROR".red().bold().to_string(),
+            (ValidationSeverity::Warning, true) => "WARNING".yellow().bold().to_string(),
+            (ValidationSeverity::Error, false) => "ERROR".to_string(),
+            (ValidationSeverity::Warning, false) => "WARNING".to_string(),
         };
-        if let Some(line) = self.line {
-            write!(f, "{} (line {}): {}", prefix, line, self.message)
-        } else {
-            write!(f, "{}: {}", prefix, self.message)
+        match self.line {
+            Some(line) => format!("{} (line {}): {}", prefix, line, self.message),
+            None => format!("{}: {}", prefix, self.message),
         }
     }
 }
 
+impl std::fmt::Display for ScriptValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render(false))
+    }
+}
+
 /// Validation options
 #[derive(Debug, Clone)]
 pub struct ValidationOptions {
@@ -54,6 +69,77 @@ impl Default for ValidationOptions {
     }
 }
 
+/// Validation findings split by severity
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub errors: Vec<ScriptValidationError>,
+    pub warnings: Vec<ScriptValidationError>,
+}
+
+impl ValidationReport {
+    /// Group findings by severity, keeping their order within each group
+    pub fn from_findings(findings: Vec<ScriptValidationError>) -> Self {
+        let (errors, warnings) = findings
+            .into_iter()
+            .partition(|f| f.severity == ValidationSeverity::Error);
+        Self { errors, warnings }
+    }
+
+    /// True when there are no errors; warnings are allowed
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Errors first, then warnings
+    pub fn iter(&self) -> impl Iterator<Item = &ScriptValidationError> {
+        self.errors.iter().chain(&self.warnings)
+    }
+
+    /// Process exit code, see `validation_exit_code`
+    pub fn exit_code(&self, strict: bool) -> i32 {
+        if !self.is_ok() || (strict && !self.warnings.is_empty()) {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+impl ValidationReport {
+    /// One line per finding, errors first, then an error/warning count
+    pub fn render(&self, color: bool) -> String {
+        let mut output = String::new();
+        for finding in self.iter() {
+            output.push_str(&finding.render(color));
+            output.push('\n');
+        }
+        output.push_str(&format!(
+            "{} error(s), {} warning(s)",
+            self.errors.len(),
+            self.warnings.len()
+        ));
+        output
+    }
+}
+
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render(false))
+    }
+}
+
+/// Validate a script and group the findings by severity
+#[tracing::instrument(level = "debug", skip_all, fields(statements = script.statements.len()))]
+pub fn validate_script_report(script: &Script, options: &ValidationOptions) -> ValidationReport {
+    let report = ValidationReport::from_findings(validate_script(script, options));
+    tracing::debug!(
+        errors = report.errors.len(),
+        warnings = report.warnings.len(),
+        "validated script"
+    );
+    report
+}
+
 /// Validate a script for safety and correctness
 pub fn validate_script(script: &Script, options: &ValidationOptions) -> Vec<ScriptValidationError> {
     let mut errors = Vec::new();
@@ -61,10 +147,187 @@ pub fn validate_script(script: &Script, options: &ValidationOptions) -> Vec<Scri
     for (i, cmd) in script.statements.iter().enumerate() {
         validate_command(cmd, options, &mut errors, i + 1, 0);
     }
+    check_duplicate_lets(&script.statements, |i| i + 1, &mut errors);
+    check_life_blocks(&script.statements, &mut errors);
+    check_macros(&script.statements, &mut errors);
+    check_container_cycles(&script.statements, &mut errors);
 
     errors
 }
 
+/// Reject container bodies that create or switch into a container still
+/// being created, itself or one it is nested in
+fn check_container_cycles(statements: &[Command], errors: &mut Vec<ScriptValidationError>) {
+    fn walk<'a>(
+        block: &'a [Command],
+        parent_line: Option<usize>,
+        creating: &mut Vec<&'a str>,
+        errors: &mut Vec<ScriptValidationError>,
+    ) {
+        for (i, cmd) in block.iter().enumerate() {
+            let line = parent_line.unwrap_or(i + 1);
+            let body: &[Command] = match cmd {
+                Command::Container(ContainerCommand::Create(create)) => {
+                    if let Some(message) = cycle_message("creates", &create.name, creating) {
+                        errors.push(ScriptValidationError {
+                            line: Some(line),
+                            message,
+                            severity: ValidationSeverity::Error,
+                        });
+                        continue;
+                    }
+                    creating.push(&create.name);
+                    walk(&create.body, Some(line), creating, errors);
+                    creating.pop();
+                    continue;
+                }
+                Command::Container(ContainerCommand::Switch(name)) => {
+                    if let Some(message) = cycle_message("switches into", name, creating) {
+                        errors.push(ScriptValidationError {
+                            line: Some(line),
+                            message,
+                            severity: ValidationSeverity::Error,
+                        });
+                    }
+                    continue;
+                }
+                Command::For(f) => &f.body,
+                Command::Life(l) => &l.body,
+                Command::Label(l) => &l.body,
+                Command::Define(d) => &d.body,
+                Command::If(i) => {
+                    walk(&i.then_body, Some(line), creating, errors);
+                    i.else_body.as_deref().unwrap_or_default()
+                }
+                _ => continue,
+            };
+            walk(body, Some(line), creating, errors);
+        }
+    }
+
+    fn cycle_message(verb: &str, name: &str, creating: &[&str]) -> Option<String> {
+        let innermost = *creating.last()?;
+        if name == innermost {
+            Some(format!(
+                "Container '{}' {} itself in its CREATE body",
+                name, verb
+            ))
+        } else if creating.contains(&name) {
+            Some(format!(
+                "Container '{}' {} '{}', which is still being created",
+                innermost, verb, name
+            ))
+        } else {
+            None
+        }
+    }
+
+    walk(statements, None, &mut Vec::new(), errors);
+}
+
+/// Report RUNs of unknown or recursive macros before anything executes
+fn check_macros(statements: &[Command], errors: &mut Vec<ScriptValidationError>) {
+    if let Err(e) = expand_macros(statements, &mut Macros::new()) {
+        let message = match e {
+            ArtaError::ExecutionError(message) => message,
+            other => other.to_string(),
+        };
+        errors.push(ScriptValidationError {
+            line: None,
+            message,
+            severity: ValidationSeverity::Error,
+        });
+    }
+}
+
+/// Reject LIFE blocks that would run more than once or never be reached
+///
+/// A LIFE block without an idle timeout only returns on Ctrl+C, so a script
+/// can hold one, outside of loops and other LIFE blocks, and nothing runs
+/// after it. One with STOP AFTER ... IDLE returns by itself, like any other
+/// statement; its body runs on every change, like a loop body.
+fn check_life_blocks(statements: &[Command], errors: &mut Vec<ScriptValidationError>) {
+    fn walk(
+        block: &[Command],
+        parent_line: Option<usize>,
+        in_loop: bool,
+        first: &mut Option<usize>,
+        errors: &mut Vec<ScriptValidationError>,
+    ) {
+        for (i, cmd) in block.iter().enumerate() {
+            let line = parent_line.unwrap_or(i + 1);
+            match cmd {
+                Command::Life(l) => {
+                    let bounded = l.idle_secs.is_some();
+                    let message = match (*first, in_loop && !bounded) {
+                        (Some(first), _) => Some(format!(
+                            "Only one LIFE block can run per script; the one on line {} never returns",
+                            first
+                        )),
+                        (None, true) => Some(
+                            "LIFE blocks cannot run inside a loop without STOP AFTER ... IDLE"
+                                .to_string(),
+                        ),
+                        (None, false) => None,
+                    };
+                    if let Some(message) = message {
+                        errors.push(ScriptValidationError {
+                            line: Some(line),
+                            message,
+                            severity: ValidationSeverity::Error,
+                        });
+                    }
+                    if !bounded {
+                        first.get_or_insert(line);
+                    }
+                    walk(&l.body, Some(line), in_loop || bounded, first, errors);
+                }
+                Command::For(f) => walk(&f.body, Some(line), true, first, errors),
+                Command::Label(l) => walk(&l.body, Some(line), in_loop, first, errors),
+                Command::Define(d) => walk(&d.body, Some(line), in_loop, first, errors),
+                Command::If(i) => {
+                    walk(&i.then_body, Some(line), in_loop, first, errors);
+                    if let Some(else_body) = &i.else_body {
+                        walk(else_body, Some(line), in_loop, first, errors);
+                    }
+                }
+                Command::Container(crate::parser::ContainerCommand::Create(create)) => {
+                    walk(&create.body, Some(line), in_loop, first, errors)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    walk(statements, None, false, &mut None, errors);
+}
+
+/// Warn when a block assigns the same variable more than once
+///
+/// Each block (the script itself, or a FOR/IF/LIFE/CONTAINER body) is its
+/// own scope; nested blocks are checked when their parent is validated.
+fn check_duplicate_lets(
+    block: &[Command],
+    line_of: impl Fn(usize) -> usize,
+    errors: &mut Vec<ScriptValidationError>,
+) {
+    let mut assigned = HashSet::new();
+    for (i, cmd) in block.iter().enumerate() {
+        if let Command::Let(let_stmt) = cmd {
+            if !assigned.insert(let_stmt.name.as_str()) {
+                errors.push(ScriptValidationError {
+                    line: Some(line_of(i)),
+                    message: format!(
+                        "Variable '{}' is assigned more than once; the earlier value is overwritten",
+                        let_stmt.name
+                    ),
+                    severity: ValidationSeverity::Warning,
+                });
+            }
+        }
+    }
+}
+
 fn validate_command(
     cmd: &Command,
     options: &ValidationOptions,
@@ -86,12 +349,53 @@ fn validate_command(
     }
 
     match cmd {
+        Command::Query(query) if query.every.is_some() && query.every_count.is_none() => {
+            errors.push(ScriptValidationError {
+                line: Some(line),
+                message: "EVERY without FOR <n> TIMES never ends; add a sample count in scripts"
+                    .to_string(),
+                severity: ValidationSeverity::Error,
+            });
+        }
+
+        Command::Query(query) if query.follow => {
+            errors.push(ScriptValidationError {
+                line: Some(line),
+                message: "FOLLOW only stops on Ctrl+C, so a script using it never finishes"
+                    .to_string(),
+                severity: ValidationSeverity::Error,
+            });
+        }
+
+        Command::Pipeline(pipeline) => {
+            if let Some(action_name) = pipeline.sink.action_name() {
+                if !options.allow_actions {
+                    errors.push(ScriptValidationError {
+                        line: Some(line),
+                        message: format!(
+                            "{} action found. Use --allow-actions to enable destructive actions",
+                            action_name
+                        ),
+                        severity: ValidationSeverity::Error,
+                    });
+                }
+                // Refused at run time, so catch it before anything runs
+                if pipeline.source.where_clause.is_none() {
+                    errors.push(ScriptValidationError {
+                        line: Some(line),
+                        message: format!(
+                            "| {} without a WHERE clause would act on every {} row",
+                            pipeline.sink, pipeline.source.target
+                        ),
+                        severity: ValidationSeverity::Error,
+                    });
+                }
+            }
+        }
+
         Command::Action(action) => {
             if !options.allow_actions {
-                let action_name = match action {
-                    ActionCommand::DeleteFiles(_) => "DELETE FILES",
-                    ActionCommand::KillProcess(_) => "KILL PROCESS",
-                };
+                let action_name = action.name();
                 errors.push(ScriptValidationError {
                     line: Some(line),
                     message: format!(
@@ -123,6 +427,16 @@ fn validate_command(
                     });
                 }
             }
+
+            if let ActionCommand::WriteFile(w) = action {
+                if is_protected_path(std::path::Path::new(&w.path)) {
+                    errors.push(ScriptValidationError {
+                        line: Some(line),
+                        message: format!("WRITE targeting system path: {}", w.path),
+                        severity: ValidationSeverity::Warning,
+                    });
+                }
+            }
         }
 
         Command::For(f) => {
@@ -130,6 +444,20 @@ fn validate_command(
             for body_cmd in &f.body {
                 validate_command(body_cmd, options, errors, line, depth + 1);
             }
+            check_duplicate_lets(&f.body, |_| line, errors);
+        }
+
+        Command::Label(l) => {
+            for body_cmd in &l.body {
+                validate_command(body_cmd, options, errors, line, depth + 1);
+            }
+            check_duplicate_lets(&l.body, |_| line, errors);
+        }
+
+        Command::Define(d) => {
+            for body_cmd in &d.body {
+                validate_command(body_cmd, options, errors, line, depth + 1);
+            }
         }
 
         Command::If(i) => {
@@ -137,30 +465,31 @@ fn validate_command(
             for body_cmd in &i.then_body {
                 validate_command(body_cmd, options, errors, line, depth + 1);
             }
+            check_duplicate_lets(&i.then_body, |_| line, errors);
 
             // Validate else body
             if let Some(else_body) = &i.else_body {
                 for body_cmd in else_body {
                     validate_command(body_cmd, options, errors, line, depth + 1);
                 }
+                check_duplicate_lets(else_body, |_| line, errors);
             }
         }
 
         Command::Life(l) => {
             // LIFE blocks should not contain destructive actions by default
             for body_cmd in &l.body {
-                if let Command::Action(_) = body_cmd {
-                    if !options.allow_life_actions {
-                        errors.push(ScriptValidationError {
-                            line: Some(line),
-                            message: "LIFE blocks cannot contain destructive actions by default"
-                                .to_string(),
-                            severity: ValidationSeverity::Error,
-                        });
-                    }
+                if action_name(body_cmd).is_some() && !options.allow_life_actions {
+                    errors.push(ScriptValidationError {
+                        line: Some(line),
+                        message: "LIFE blocks cannot contain destructive actions by default"
+                            .to_string(),
+                        severity: ValidationSeverity::Error,
+                    });
                 }
                 validate_command(body_cmd, options, errors, line, depth + 1);
             }
+            check_duplicate_lets(&l.body, |_| line, errors);
         }
 
         Command::Container(crate::parser::ContainerCommand::Create(create)) => {
@@ -168,15 +497,12 @@ fn validate_command(
             for body_cmd in &create.body {
                 validate_command(body_cmd, options, errors, line, depth + 1);
             }
+            check_duplicate_lets(&create.body, |_| line, errors);
 
             // Check for actions in container without allow_actions
             if !create.options.allow_actions {
                 for body_cmd in &create.body {
-                    if let Command::Action(action) = body_cmd {
-                        let action_name = match action {
-                            ActionCommand::DeleteFiles(_) => "DELETE FILES",
-                            ActionCommand::KillProcess(_) => "KILL PROCESS",
-                        };
+                    if let Some(action_name) = action_name(body_cmd) {
                         errors.push(ScriptValidationError {
                             line: Some(line),
                             message: format!(
@@ -199,6 +525,15 @@ fn validate_command(
     }
 }
 
+/// Name of the destructive action a command performs, piped ones included
+fn action_name(cmd: &Command) -> Option<&'static str> {
+    match cmd {
+        Command::Action(action) => Some(action.name()),
+        Command::Pipeline(pipeline) => pipeline.sink.action_name(),
+        _ => None,
+    }
+}
+
 /// Check if a script has any validation errors (not just warnings)
 pub fn has_errors(errors: &[ScriptValidationError]) -> bool {
     errors
@@ -213,6 +548,17 @@ pub fn has_warnings(errors: &[ScriptValidationError]) -> bool {
         .any(|e| e.severity == ValidationSeverity::Warning)
 }
 
+/// Map validation findings to a process exit code
+///
+/// Errors always fail; warnings only fail in strict mode.
+pub fn validation_exit_code(errors: &[ScriptValidationError], strict: bool) -> i32 {
+    if has_errors(errors) || (strict && has_warnings(errors)) {
+        1
+    } else {
+        0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -254,6 +600,268 @@ mod tests {
         assert!(has_warnings(&errors));
     }
 
+    #[test]
+    fn test_validate_action_pipelines() {
+        let options = ValidationOptions {
+            allow_actions: true,
+            ..Default::default()
+        };
+        let script = parse_script(
+            "LIFE MONITOR CPU DO SELECT PROCESS * WHERE name = \"sleep\" | KILL END LIFE",
+        )
+        .unwrap();
+        let errors = validate_script(&script, &options);
+        assert!(errors.iter().any(|e| e
+            .message
+            .contains("LIFE blocks cannot contain destructive actions")));
+
+        let script = parse_script(
+            "CREATE CONTAINER \"tidy\" DO SELECT FILES * FROM /tmp WHERE size > 1GB | DELETE END CONTAINER",
+        )
+        .unwrap();
+        let errors = validate_script(&script, &options);
+        assert!(errors.iter().any(|e| e
+            .message
+            .contains("DELETE FILES action in container 'tidy'")));
+
+        let script = parse_script("SELECT FILES * FROM /tmp | DELETE").unwrap();
+        let errors = validate_script(&script, &options);
+        assert!(has_errors(&errors));
+        assert!(errors[0].message.contains("without a WHERE clause"));
+
+        let script = parse_script("SELECT FILES * FROM /tmp | COUNT").unwrap();
+        assert!(validate_script(&script, &ValidationOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_validation_exit_code_strict() {
+        let script = parse_script("DELETE FILES FROM /tmp/arta_test").unwrap();
+        let options = ValidationOptions {
+            allow_actions: true,
+            ..Default::default()
+        };
+        let errors = validate_script(&script, &options);
+
+        assert!(!has_errors(&errors));
+        assert_eq!(validation_exit_code(&errors, false), 0);
+        assert_eq!(validation_exit_code(&errors, true), 1);
+    }
+
+    #[test]
+    fn test_validate_duplicate_let() {
+        let script = parse_script("LET x = 1; PRINT x; LET x = 2; LET y = 3").unwrap();
+        let errors = validate_script(&script, &ValidationOptions::default());
+        assert!(has_warnings(&errors));
+        assert!(!has_errors(&errors));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, Some(3));
+        assert!(errors[0].message.contains("'x'"));
+
+        // A LET inside a block is a separate scope from the script itself
+        let script =
+            parse_script("LET x = 1; IF SELECT CPU usage > 0 THEN LET x = 2 ELSE LET x = 3 END IF")
+                .unwrap();
+        assert!(validate_script(&script, &ValidationOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_validate_multiple_life_blocks() {
+        let script = parse_script(
+            "LIFE MONITOR CPU DO PRINT \"cpu\" END LIFE; \
+             LIFE MONITOR MEMORY DO PRINT \"memory\" END LIFE",
+        )
+        .unwrap();
+        let errors = validate_script(&script, &ValidationOptions::default());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, Some(2));
+        assert!(errors[0].message.contains("line 1"), "{}", errors[0]);
+
+        let script = parse_script(
+            "LIFE MONITOR CPU DO LIFE MONITOR MEMORY DO PRINT \"x\" END LIFE END LIFE",
+        )
+        .unwrap();
+        assert!(has_errors(&validate_script(
+            &script,
+            &ValidationOptions::default()
+        )));
+
+        let script = parse_script(
+            "FOR f IN SELECT FILES * FROM /tmp DO LIFE MONITOR CPU DO PRINT \"x\" END LIFE END FOR",
+        )
+        .unwrap();
+        assert!(has_errors(&validate_script(
+            &script,
+            &ValidationOptions::default()
+        )));
+
+        let script = parse_script("LIFE MONITOR CPU DO PRINT \"cpu\" END LIFE").unwrap();
+        assert!(validate_script(&script, &ValidationOptions::default()).is_empty());
+
+        // An idle timeout ends the block, so more can follow or loop
+        let script = parse_script(
+            "LIFE MONITOR CPU STOP AFTER 60 SECONDS IDLE DO PRINT \"cpu\" END LIFE; \
+             FOR f IN SELECT FILES * FROM /tmp DO \
+                 LIFE MONITOR DISK STOP AFTER 5 SECONDS IDLE DO PRINT \"x\" END LIFE \
+             END FOR; \
+             LIFE MONITOR MEMORY DO PRINT \"memory\" END LIFE",
+        )
+        .unwrap();
+        assert!(validate_script(&script, &ValidationOptions::default()).is_empty());
+
+        // Its body runs on every change, so a never-ending LIFE can't go there
+        let script = parse_script(
+            "LIFE MONITOR CPU STOP AFTER 60 SECONDS IDLE DO \
+                 LIFE MONITOR MEMORY DO PRINT \"x\" END LIFE \
+             END LIFE",
+        )
+        .unwrap();
+        assert!(has_errors(&validate_script(
+            &script,
+            &ValidationOptions::default()
+        )));
+    }
+
+    #[test]
+    fn test_validate_unbounded_every() {
+        let script = parse_script("SELECT CPU usage EVERY 2 SECONDS").unwrap();
+        let errors = validate_script(&script, &ValidationOptions::default());
+        assert!(has_errors(&errors));
+
+        let script = parse_script("SELECT CONTENT * FROM /var/log/syslog FOLLOW").unwrap();
+        assert!(has_errors(&validate_script(
+            &script,
+            &ValidationOptions::default()
+        )));
+
+        let script = parse_script(
+            "SELECT CPU usage EVERY 2 SECONDS FOR 5 TIMES; \
+             IF SELECT CPU usage > 0 THEN SELECT MEMORY * EVERY 1 SECOND END IF",
+        )
+        .unwrap();
+        let errors = validate_script(&script, &ValidationOptions::default());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, Some(2));
+
+        // Bodies are checked however deep the query sits
+        let script = parse_script(
+            "LABEL watch: \
+                 FOR f IN SELECT FILES * FROM /tmp DO \
+                     IF SELECT CPU usage > 0 THEN SELECT CPU usage EVERY 1 SECOND END IF \
+                 END FOR \
+             END LABEL",
+        )
+        .unwrap();
+        assert!(has_errors(&validate_script(
+            &script,
+            &ValidationOptions::default()
+        )));
+    }
+
+    #[test]
+    fn test_validate_macros() {
+        let script = parse_script("DEFINE status AS SELECT MEMORY *; RUN status").unwrap();
+        assert!(validate_script(&script, &ValidationOptions::default()).is_empty());
+
+        let script = parse_script("RUN status").unwrap();
+        let errors = validate_script(&script, &ValidationOptions::default());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unknown macro 'status'");
+
+        // Actions inside a macro body are checked like any other block
+        let script = parse_script("DEFINE stop AS KILL PROCESS WHERE name = \"node\"").unwrap();
+        let options = ValidationOptions {
+            allow_actions: false,
+            ..Default::default()
+        };
+        assert!(has_errors(&validate_script(&script, &options)));
+    }
+
+    #[test]
+    fn test_validate_container_cycles() {
+        let script =
+            parse_script("CREATE CONTAINER \"dev\" DO SWITCH CONTAINER \"dev\" END CONTAINER")
+                .unwrap();
+        let errors = validate_script(&script, &ValidationOptions::default());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].severity, ValidationSeverity::Error);
+        assert_eq!(
+            errors[0].message,
+            "Container 'dev' switches into itself in its CREATE body"
+        );
+
+        let script = parse_script(
+            "CREATE CONTAINER \"outer\" DO \
+                 CREATE CONTAINER \"inner\" DO SWITCH CONTAINER \"outer\" END CONTAINER \
+             END CONTAINER",
+        )
+        .unwrap();
+        let errors = validate_script(&script, &ValidationOptions::default());
+        assert!(has_errors(&errors));
+        assert!(errors[0].message.contains("still being created"));
+
+        // Switching between containers once they exist is fine
+        let script = parse_script(
+            "CREATE CONTAINER \"dev\" DO LET x = 1 END CONTAINER; SWITCH CONTAINER \"dev\"",
+        )
+        .unwrap();
+        assert!(validate_script(&script, &ValidationOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_validation_report_groups_findings() {
+        let script = parse_script(
+            "LET x = 1; LET x = 2; DELETE FILES FROM /tmp WHERE size > 1MB; SELECT CPU usage EVERY 1 SECOND",
+        )
+        .unwrap();
+        let report = validate_script_report(&script, &ValidationOptions::default());
+
+        assert!(!report.is_ok());
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].line, Some(2));
+        assert_eq!(report.exit_code(false), 1);
+
+        let text = report.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("ERROR (line 3)"));
+        assert!(lines[1].starts_with("ERROR (line 4)"));
+        assert!(lines[2].starts_with("WARNING (line 2)"));
+        assert_eq!(lines[3], "2 error(s), 1 warning(s)");
+
+        let clean = validate_script_report(
+            &parse_script("SELECT CPU *").unwrap(),
+            &ValidationOptions::default(),
+        );
+        assert!(clean.is_ok());
+        assert_eq!(clean.exit_code(true), 0);
+        assert_eq!(clean.to_string(), "0 error(s), 0 warning(s)");
+    }
+
+    #[test]
+    fn test_render_colored_severity() {
+        let finding = |severity| ScriptValidationError {
+            line: Some(2),
+            message: "problem".to_string(),
+            severity,
+        };
+        let error = finding(ValidationSeverity::Error);
+        let warning = finding(ValidationSeverity::Warning);
+
+        let colored = error.render(true);
+        assert!(colored.contains("\u{1b}[38;5;9m"), "{:?}", colored);
+        assert!(colored.ends_with(" (line 2): problem"));
+        assert!(warning.render(true).contains("\u{1b}[38;5;11m"));
+
+        assert_eq!(error.render(false), "ERROR (line 2): problem");
+        assert_eq!(error.render(false), error.to_string());
+        assert!(!warning.render(false).contains('\u{1b}'));
+
+        let report = ValidationReport::from_findings(vec![error, warning]);
+        assert!(report.render(true).contains("\u{1b}["));
+        assert!(!report.to_string().contains('\u{1b}'));
+    }
+
     #[test]
     fn test_validate_dangerous_path() {
         let script = parse_script("DELETE FILES FROM / WHERE name = \"temp\"").unwrap();
@@ -0,0 +1,515 @@
+//! Deterministic end-to-end testing helpers (requires the `testing` feature)
+//!
+//! `MockProvider` serves fixed CPU, memory and process figures, and `run`
+//! drives a query through parsing, execution and formatting against it:
+//!
+//! ```
+//! use arta::test_support::{run, MockProvider};
+//! use arta::OutputFormat;
+//!
+//! let provider = MockProvider::new().with_cpu_usage(42.5);
+//! let output = run("SELECT CPU usage", provider, OutputFormat::Json).unwrap();
+//! assert!(output.contains("42.5"));
+//! ```
+
+use std::path::Path;
+use sysinfo::System;
+
+use crate::context::Context;
+use crate::engine::executor::execute_command_with_context;
+use crate::engine::provider::{RefreshStats, SystemProvider};
+use crate::engine::queries::{full_process_name, CpuInfo, MemoryInfo, ProcessInfo};
+use crate::engine::ExecutionContext;
+use crate::error::Result;
+use crate::output::{format_output, OutputFormat};
+use crate::parser::parse_command;
+
+const GB: u64 = 1024 * 1024 * 1024;
+
+/// System provider returning fixed figures instead of reading the host
+pub struct MockProvider {
+    /// Empty system for code that still reads sysinfo directly
+    sys: System,
+    cpu: CpuInfo,
+    memory: MemoryInfo,
+    processes: Vec<ProcessInfo>,
+    stats: RefreshStats,
+}
+
+impl MockProvider {
+    /// An idle 4-core machine with 16 GB of memory and no processes
+    pub fn new() -> Self {
+        Self {
+            sys: System::new(),
+            cpu: CpuInfo {
+                cores: 4,
+                usage: 0.0,
+                brand: "Mock CPU".to_string(),
+                frequency: 2400,
+            },
+            memory: MemoryInfo::new(16 * GB, 0, 16 * GB, 16 * GB),
+            processes: Vec::new(),
+            stats: RefreshStats::default(),
+        }
+    }
+
+    /// Replace the CPU figures
+    pub fn with_cpu(mut self, cpu: CpuInfo) -> Self {
+        self.cpu = cpu;
+        self
+    }
+
+    /// Set the overall CPU usage percentage
+    pub fn with_cpu_usage(mut self, usage: f32) -> Self {
+        self.cpu.usage = usage;
+        self
+    }
+
+    /// Set total and used memory in bytes
+    pub fn with_memory(mut self, total: u64, used: u64) -> Self {
+        let free = total.saturating_sub(used);
+        self.memory = MemoryInfo::new(total, used, free, free);
+        self
+    }
+
+    /// Add a running process
+    pub fn with_process(mut self, pid: u32, name: &str, cpu: f32, memory: u64) -> Self {
+        self.processes.push(ProcessInfo {
+            pid,
+            name: name.to_string(),
+            name_full: None,
+            cpu,
+            memory,
+            status: "running".to_string(),
+            threads: None,
+            open_files: None,
+            user: None,
+            uid: None,
+        });
+        self
+    }
+
+    /// Set the executable path of the last added process
+    ///
+    /// Its `name_full` is derived the same way as for real processes, so a
+    /// 15-character name followed by `with_exe` simulates a truncated name.
+    pub fn with_exe(mut self, exe: &str) -> Self {
+        if let Some(process) = self.processes.last_mut() {
+            process.name_full = full_process_name(&process.name, Some(Path::new(exe)));
+        }
+        self
+    }
+
+    /// Set the open file descriptor count of the last added process
+    pub fn with_open_files(mut self, count: u32) -> Self {
+        if let Some(process) = self.processes.last_mut() {
+            process.open_files = Some(count);
+        }
+        self
+    }
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemProvider for MockProvider {
+    fn cpu(&mut self) -> &System {
+        &self.sys
+    }
+
+    fn memory(&mut self) -> &System {
+        &self.sys
+    }
+
+    fn processes(&mut self) -> &System {
+        &self.sys
+    }
+
+    fn stats(&self) -> RefreshStats {
+        self.stats
+    }
+
+    fn cpu_info(&mut self) -> CpuInfo {
+        self.stats.selective_refreshes += 1;
+        self.cpu.clone()
+    }
+
+    fn memory_info(&mut self) -> MemoryInfo {
+        self.stats.selective_refreshes += 1;
+        self.memory.clone()
+    }
+
+    fn process_list(&mut self) -> Vec<ProcessInfo> {
+        self.stats.selective_refreshes += 1;
+        self.processes.clone()
+    }
+
+    fn resolve_users(&mut self, processes: &mut [ProcessInfo]) {
+        self.stats.user_lookups += processes.len();
+    }
+
+    /// Keeps the counts set with `with_open_files`
+    fn count_open_files(&mut self, processes: &mut [ProcessInfo]) {
+        self.stats.open_file_scans += processes.len();
+    }
+}
+
+/// Parse, execute and format a command against a mock provider
+pub fn run(query: &str, provider: MockProvider, format: OutputFormat) -> Result<String> {
+    let cmd = parse_command(query)?;
+    let ctx = ExecutionContext {
+        output_format: format,
+        ..Default::default()
+    };
+    let mut context = Context::new().with_system_provider(provider);
+
+    let result = execute_command_with_context(&cmd, &ctx, &mut context)?;
+    Ok(format_output(&result, &ctx.output_format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::executor::ResultData;
+    use crate::error::ArtaError;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    fn machine() -> MockProvider {
+        MockProvider::new()
+            .with_cpu_usage(42.5)
+            .with_memory(8 * GB, 2 * GB)
+            .with_process(1, "init", 0.1, 10 * 1024 * 1024)
+            .with_process(200, "node", 55.0, 512 * 1024 * 1024)
+            .with_process(300, "postgres", 12.0, GB)
+    }
+
+    #[test]
+    fn test_memory_pressure_condition() {
+        let tight = || MockProvider::new().with_memory(10 * GB, 97 * GB / 10);
+        let check = |query: &str| run(query, tight(), OutputFormat::Json).unwrap();
+
+        assert!(check("SELECT MEMORY pressure = \"critical\"").contains("true"));
+        assert!(check("SELECT MEMORY pressure = \"low\"").contains("false"));
+        assert!(check("SELECT MEMORY pressure").contains("\"critical\""));
+
+        let output = run("SELECT MEMORY pressure", machine(), OutputFormat::Human).unwrap();
+        assert!(output.contains("low"), "{}", output);
+    }
+
+    #[test]
+    fn test_pipeline_human_output() {
+        let cases: &[(&str, &[&str], &[&str])] = &[
+            ("SELECT CPU *", &["Cores:     4", "Usage:     42.5%"], &[]),
+            ("SELECT CPU usage", &["42.5%"], &["Cores", "usage:"]),
+            (
+                "SELECT MEMORY *",
+                &["Total:     8.6 GB", "Usage:     25.0%"],
+                &[],
+            ),
+            (
+                "SELECT PROCESS * WHERE cpu > 10",
+                &["node", "postgres"],
+                &["init"],
+            ),
+            (
+                "SELECT PROCESS * WHERE name = \"init\" OR memory > 1GB",
+                &["init"],
+                &["node", "postgres"],
+            ),
+            (
+                "SELECT PROCESS * WHERE name MATCHES \"^(no|po).*[es]$\"",
+                &["node", "postgres"],
+                &["init"],
+            ),
+        ];
+
+        for (query, present, absent) in cases {
+            let output = run(query, machine(), OutputFormat::Human).unwrap();
+            for text in *present {
+                assert!(
+                    output.contains(text),
+                    "{}: missing {:?} in\n{}",
+                    query,
+                    text,
+                    output
+                );
+            }
+            for text in *absent {
+                assert!(
+                    !output.contains(text),
+                    "{}: unexpected {:?} in\n{}",
+                    query,
+                    text,
+                    output
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_regex_fails_query() {
+        let err = run(
+            "SELECT PROCESS * WHERE name MATCHES \"[unclosed\"",
+            machine(),
+            OutputFormat::Human,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid MATCHES pattern"));
+    }
+
+    #[test]
+    fn test_pipeline_json_output() {
+        let json = |query: &str| -> serde_json::Value {
+            serde_json::from_str(&run(query, machine(), OutputFormat::Json).unwrap()).unwrap()
+        };
+
+        assert_eq!(json("SELECT MEMORY used_percent")["used_percent"], 25.0);
+        assert_eq!(json("SELECT CPU cores")["cores"], 4);
+
+        let processes = json("SELECT PROCESS * WHERE cpu > 10");
+        let pids: Vec<_> = processes
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["pid"].as_u64().unwrap())
+            .collect();
+        // Sorted by CPU usage, highest first
+        assert_eq!(pids, vec![200, 300]);
+    }
+
+    #[test]
+    fn test_truncated_process_names() {
+        let provider = || {
+            MockProvider::new()
+                .with_process(10, "my-long-service", 1.0, GB)
+                .with_exe("/opt/app/my-long-service-worker")
+                .with_process(11, "my-long-service", 1.0, GB)
+                .with_exe("/opt/app/my-long-service-indexer")
+        };
+        let pids = |query: &str| -> Vec<u64> {
+            let output = run(query, provider(), OutputFormat::Json).unwrap();
+            let rows: serde_json::Value = serde_json::from_str(&output).unwrap();
+            let mut pids: Vec<u64> = rows
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|p| p["pid"].as_u64().unwrap())
+                .collect();
+            pids.sort_unstable();
+            pids
+        };
+
+        assert_eq!(
+            pids("SELECT PROCESS * WHERE name = \"my-long-service-worker\""),
+            vec![10]
+        );
+        assert_eq!(
+            pids("SELECT PROCESS * WHERE name = \"my-long-service\""),
+            vec![10, 11]
+        );
+        assert_eq!(
+            pids("SELECT PROCESS * WHERE name_full = \"my-long-service-indexer\""),
+            vec![11]
+        );
+
+        let output = run("SELECT PROCESS *", provider(), OutputFormat::Json).unwrap();
+        assert!(output.contains("\"name_full\": \"my-long-service-worker\""));
+    }
+
+    #[test]
+    fn test_process_limit_before_user_lookup() {
+        let lookups = |query: &str| {
+            let cmd = parse_command(query).unwrap();
+            let mut context = Context::new().with_system_provider(machine());
+            execute_command_with_context(&cmd, &ExecutionContext::default(), &mut context).unwrap();
+            let stats = context.system().lock().stats();
+            stats.user_lookups
+        };
+
+        assert_eq!(lookups("SELECT PROCESS *"), 3);
+        assert_eq!(lookups("SELECT PROCESS * LIMIT 1"), 1);
+        assert_eq!(lookups("SELECT PROCESS * WHERE cpu > 10 LIMIT 5"), 2);
+        // A user condition needs names before filtering
+        assert_eq!(lookups("SELECT PROCESS * WHERE user = \"root\" LIMIT 1"), 3);
+    }
+
+    #[test]
+    fn test_open_files_counted_only_when_needed() {
+        let provider = || {
+            MockProvider::new()
+                .with_process(10, "nginx", 1.0, GB)
+                .with_open_files(2000)
+                .with_process(11, "bash", 1.0, GB)
+                .with_open_files(4)
+        };
+        let run_query = |query: &str| {
+            let cmd = parse_command(query).unwrap();
+            let mut context = Context::new().with_system_provider(provider());
+            let result =
+                execute_command_with_context(&cmd, &ExecutionContext::default(), &mut context)
+                    .unwrap();
+            let scans = context.system().lock().stats().open_file_scans;
+            (result, scans)
+        };
+
+        assert_eq!(run_query("SELECT PROCESS *").1, 0);
+        assert_eq!(run_query("SELECT PROCESS pid, open_files LIMIT 1").1, 1);
+
+        let (result, scans) = run_query("SELECT PROCESS * WHERE open_files > 1000");
+        assert_eq!(scans, 2);
+        match result.data {
+            ResultData::Processes(processes) => {
+                assert_eq!(processes.len(), 1);
+                assert_eq!(processes[0].pid, 10);
+            }
+            other => panic!("Expected processes, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_process_order_by() {
+        let run_query = |query: &str| {
+            let provider = MockProvider::new()
+                .with_process(30, "nginx", 5.0, GB)
+                .with_open_files(10)
+                .with_process(10, "bash", 1.0, 3 * GB)
+                .with_open_files(300)
+                .with_process(20, "Redis", 9.0, 2 * GB)
+                .with_open_files(20);
+            let cmd = parse_command(query)?;
+            let mut context = Context::new().with_system_provider(provider);
+            match execute_command_with_context(&cmd, &ExecutionContext::default(), &mut context)?
+                .data
+            {
+                ResultData::Processes(processes) => {
+                    Ok(processes.iter().map(|p| p.pid).collect::<Vec<_>>())
+                }
+                other => panic!("Expected processes, got {:?}", other),
+            }
+        };
+
+        // Default: highest CPU first
+        assert_eq!(run_query("SELECT PROCESS *").unwrap(), [20, 30, 10]);
+        assert_eq!(
+            run_query("SELECT PROCESS * ORDER BY memory DESC").unwrap(),
+            [10, 20, 30]
+        );
+        assert_eq!(
+            run_query("SELECT PROCESS * ORDER BY name").unwrap(),
+            [10, 30, 20]
+        );
+        assert_eq!(
+            run_query("SELECT PROCESS * ORDER BY pid LIMIT 2").unwrap(),
+            [10, 20]
+        );
+        assert_eq!(
+            run_query("SELECT PROCESS pid ORDER BY cpu ASC").unwrap(),
+            [10, 30, 20]
+        );
+        // open_files is counted for the sort even when not selected
+        assert_eq!(
+            run_query("SELECT PROCESS pid ORDER BY open_files DESC LIMIT 1").unwrap(),
+            [10]
+        );
+
+        let err = run_query("SELECT PROCESS * ORDER BY memroy").unwrap_err();
+        assert!(matches!(err, ArtaError::ExecutionError(_)));
+        assert!(err.to_string().contains("memory"), "{}", err);
+    }
+
+    #[test]
+    fn test_single_field_json_shape() {
+        let cases = [
+            ("SELECT CPU usage", serde_json::json!({ "usage": 42.5 })),
+            ("SELECT MEMORY used", serde_json::json!({ "used": 2 * GB })),
+            (
+                "SELECT MEMORY used_percent",
+                serde_json::json!({ "used_percent": 25.0 }),
+            ),
+        ];
+        for (query, expected) in cases {
+            let output = run(query, machine(), OutputFormat::Json).unwrap();
+            let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+            assert_eq!(value, expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_single_field_human_is_bare_value() {
+        let output = run("SELECT CPU usage", machine(), OutputFormat::Human).unwrap();
+        assert_eq!(output, "42.5%");
+        let output = run("SELECT MEMORY used_percent", machine(), OutputFormat::Human).unwrap();
+        assert_eq!(output, "25.0%");
+
+        // Several fields keep their labels
+        let output = run("SELECT CPU usage, cores", machine(), OutputFormat::Human).unwrap();
+        assert!(output.starts_with("usage:"), "{}", output);
+    }
+
+    #[test]
+    fn test_bare_condition() {
+        let cases = [
+            ("SELECT CPU usage > 40", "true", r#"{"result":true}"#),
+            ("SELECT CPU usage > 90", "false", r#"{"result":false}"#),
+            (
+                "SELECT MEMORY used_percent = 25",
+                "true",
+                r#"{"result":true}"#,
+            ),
+        ];
+        for (query, human, json) in cases {
+            assert_eq!(run(query, machine(), OutputFormat::Human).unwrap(), human);
+            let output = run(query, machine(), OutputFormat::Json).unwrap();
+            let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+            assert_eq!(
+                value,
+                serde_json::from_str::<serde_json::Value>(json).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_print_inline_query() {
+        let output = run(
+            "PRINT \"cpu:\", SELECT CPU usage, cores",
+            machine(),
+            OutputFormat::Human,
+        )
+        .unwrap();
+        assert_eq!(output, r#"cpu: {"cores":4,"usage":42.5}"#);
+
+        let output = run("PRINT SELECT CPU *", machine(), OutputFormat::Human).unwrap();
+        assert!(!output.contains('\n'), "{}", output);
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value["usage"], 42.5);
+
+        // Every non-JSON format embeds the same single line
+        let output = run(
+            "PRINT SELECT CPU usage",
+            machine(),
+            OutputFormat::Properties,
+        )
+        .unwrap();
+        assert_eq!(output, r#"MESSAGE='{"usage":42.5}'"#);
+    }
+
+    #[test]
+    fn test_pipeline_files() {
+        let dir = TempDir::new().unwrap();
+        File::create(dir.path().join("app.log")).unwrap();
+        File::create(dir.path().join("notes.txt")).unwrap();
+
+        let query = format!(
+            "SELECT FILES * FROM \"{}\" WHERE extension = \"log\"",
+            dir.path().display()
+        );
+        let output = run(&query, MockProvider::new(), OutputFormat::Human).unwrap();
+
+        assert!(output.contains("app.log"));
+        assert!(!output.contains("notes.txt"));
+    }
+}
@@ -0,0 +1,271 @@
+//! End-to-end tests for the arta binary
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::str::contains;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_validate_warning_fails_only_when_strict() {
+    let dir = TempDir::new().unwrap();
+    let script = dir.path().join("cleanup.arta");
+    // Allowed action without WHERE: a warning, not an error
+    fs::write(&script, "DELETE FILES FROM /tmp/arta_validate_test\n").unwrap();
+
+    cargo_bin_cmd!("arta")
+        .args(["--allow-actions", "validate"])
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(contains("0 error(s), 1 warning(s)"));
+
+    cargo_bin_cmd!("arta")
+        .args(["--allow-actions", "validate", "--strict"])
+        .arg(&script)
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn test_run_fail_on_warning() {
+    let dir = TempDir::new().unwrap();
+    let script = dir.path().join("warn.arta");
+    // Reassigning a variable is a warning, not an error
+    fs::write(&script, "LET x = 1;\nLET x = 2;\nPRINT \"ran\"\n").unwrap();
+
+    cargo_bin_cmd!("arta")
+        .arg("run")
+        .arg(&script)
+        .assert()
+        .success()
+        .stdout(contains("ran"));
+
+    let output = cargo_bin_cmd!("arta")
+        .args(["run", "--fail-on-warning"])
+        .arg(&script)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("ran"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--fail-on-warning"));
+}
+
+#[test]
+fn test_containers_json() {
+    let output = cargo_bin_cmd!("arta")
+        .args(["containers", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let containers = json["containers"].as_array().unwrap();
+    assert_eq!(containers[0]["name"], "default");
+    assert_eq!(containers[0]["is_active"], true);
+}
+
+#[test]
+fn test_run_in_readonly_container_refuses_actions() {
+    let dir = TempDir::new().unwrap();
+    let session = dir.path().join("session.arta");
+    fs::write(
+        &session,
+        format!(
+            "CREATE CONTAINER \"audit\" WITH READONLY DO\n\
+             \x20   LET who = \"auditor\";\n\
+             END CONTAINER\n\
+             CREATE CONTAINER \"ops\" WITH ALLOW ACTIONS DO\n\
+             \x20   ENTER FOLDER \"{}\";\n\
+             END CONTAINER\n",
+            dir.path().display()
+        ),
+    )
+    .unwrap();
+    let script = dir.path().join("write.arta");
+    let target = dir.path().join("out.txt");
+    fs::write(
+        &script,
+        format!("WRITE \"written\" TO \"{}\"\n", target.display()),
+    )
+    .unwrap();
+
+    let output = cargo_bin_cmd!("arta")
+        .args([
+            "--allow-actions",
+            "run",
+            "--container",
+            "audit",
+            "--containers",
+        ])
+        .arg(&session)
+        .arg(&script)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Container 'audit' is read-only"));
+    assert!(!target.exists());
+
+    cargo_bin_cmd!("arta")
+        .args([
+            "--allow-actions",
+            "run",
+            "--container",
+            "ops",
+            "--containers",
+        ])
+        .arg(&session)
+        .arg(&script)
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&target).unwrap(), "written");
+
+    // The script sees the container's variables
+    let print = dir.path().join("print.arta");
+    fs::write(&print, "PRINT who\n").unwrap();
+    cargo_bin_cmd!("arta")
+        .args(["run", "--container", "audit", "--containers"])
+        .arg(&session)
+        .arg(&print)
+        .assert()
+        .success()
+        .stdout(contains("auditor"));
+}
+
+#[test]
+fn test_timestamps_flag() {
+    let stdout = |args: &[&str]| {
+        let output = cargo_bin_cmd!("arta").args(args).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let plain = stdout(&["query", "PRINT \"hello\""]);
+    assert_eq!(plain, "hello\n");
+
+    let stamped = stdout(&["--timestamps", "query", "PRINT \"hello\""]);
+    let (stamp, rest) = stamped.trim_end().split_once(' ').unwrap();
+    assert!(
+        chrono::DateTime::parse_from_rfc3339(stamp).is_ok(),
+        "{}",
+        stamped
+    );
+    assert_eq!(rest, "hello");
+
+    let json: serde_json::Value = serde_json::from_str(&stdout(&[
+        "--timestamps",
+        "--json",
+        "query",
+        "PRINT \"hello\"",
+    ]))
+    .unwrap();
+    assert_eq!(json["print"], serde_json::json!(["hello"]));
+    assert!(json["timestamp"].is_string());
+
+    let json: serde_json::Value =
+        serde_json::from_str(&stdout(&["--json", "query", "PRINT \"hello\""])).unwrap();
+    assert!(json.get("timestamp").is_none());
+}
+
+#[test]
+fn test_bare_condition_exit_code() {
+    cargo_bin_cmd!("arta")
+        .args(["query", "SELECT MEMORY total > 0"])
+        .assert()
+        .success()
+        .stdout("true\n");
+
+    let output = cargo_bin_cmd!("arta")
+        .args(["--json", "query", "SELECT MEMORY total < 0"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["result"], false);
+}
+
+#[test]
+fn test_json_trailer_is_last_line() {
+    let output = cargo_bin_cmd!("arta")
+        .args(["--trailer", "json", "query", "SELECT MEMORY *"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    let last = lines.next_back().unwrap();
+    assert!(lines.any(|line| line.starts_with("Total:")));
+
+    // Compact: no spaces or indentation
+    assert!(!last.contains(' '));
+    let trailer: serde_json::Value = serde_json::from_str(last).unwrap();
+    assert_eq!(trailer["target"], "MEMORY");
+    assert_eq!(trailer["count"], 1);
+    assert_eq!(trailer["success"], true);
+}
+
+#[test]
+fn test_alert_exit_code() {
+    let output = cargo_bin_cmd!("arta")
+        .args(["query", "--alert", "SELECT DISK used_percent >= 0 FROM /"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("ALERT: DISK used_percent >= 0 FROM / (/: used_percent = "));
+
+    // The alert is output like any result, so output options apply
+    let output = cargo_bin_cmd!("arta")
+        .args([
+            "--json",
+            "--timestamps",
+            "query",
+            "--alert",
+            "SELECT DISK used_percent >= 0 FROM /",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["message"].as_str().unwrap().starts_with("ALERT: "));
+    assert!(json["timestamp"].is_string());
+
+    cargo_bin_cmd!("arta")
+        .args(["query", "--alert", "SELECT DISK used_percent > 100 FROM /"])
+        .assert()
+        .success()
+        .stdout("");
+
+    cargo_bin_cmd!("arta")
+        .args(["query", "--alert", "SELECT DISK *"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_files_limit_lists_every_row() {
+    let dir = TempDir::new().unwrap();
+    for i in 0..60 {
+        fs::write(dir.path().join(format!("f{:02}.log", i)), "x").unwrap();
+    }
+    let query = |suffix: &str| {
+        let output = cargo_bin_cmd!("arta")
+            .args([
+                "query",
+                &format!("SELECT FILES * FROM {} {}", dir.path().display(), suffix),
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let rows = |output: &str| output.matches(".log").count();
+
+    // Without LIMIT the list stops at 50 rows
+    let output = query("");
+    assert_eq!(rows(&output), 50, "{}", output);
+    assert!(output.contains("... and 10 more files"), "{}", output);
+
+    let output = query("LIMIT 55");
+    assert_eq!(rows(&output), 55, "{}", output);
+    assert!(!output.contains("more files"), "{}", output);
+}